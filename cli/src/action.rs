@@ -0,0 +1,197 @@
+use anchor_lang::prelude::Pubkey;
+use serde_json::{json, Value};
+use solana_sdk::signature::Signer;
+use staking::account::{FactoryRoles, QueuedAction};
+use staking::fee::OwnerFee;
+use staking::limits::StakeLimits;
+use staking::reward::Reward;
+use staking::timelock::AdminAction;
+use staking_client::account::fetch;
+use staking_client::audit;
+use staking_client::instruction;
+use staking_client::timelock::list_queued_actions;
+use crate::context::{Context, Result};
+use crate::pool::reward_view;
+
+pub fn action_view(action: &AdminAction) -> Value {
+    match action {
+        AdminAction::SetOwnerFee { stake_pool, owner_fee } => json!({
+            "type": "set_owner_fee",
+            "stake_pool": stake_pool.to_string(),
+            "fee_bps": owner_fee.bps,
+            "max_fee_per_claim": owner_fee.max_per_claim,
+        }),
+        AdminAction::SetReward { stake_pool, reward } => json!({
+            "type": "set_reward",
+            "stake_pool": stake_pool.to_string(),
+            "reward": reward_view(reward),
+        }),
+        AdminAction::Unpause => json!({ "type": "unpause" }),
+        AdminAction::TransferOwnership { owner } => json!({ "type": "transfer_ownership", "owner": owner.to_string() }),
+        AdminAction::SetTimelockDelay { delay } => json!({ "type": "set_timelock_delay", "delay": delay }),
+        AdminAction::SetFeeRecipient { stake_pool, fee_recipient } => json!({
+            "type": "set_fee_recipient",
+            "stake_pool": stake_pool.to_string(),
+            "fee_recipient": fee_recipient.to_string(),
+        }),
+        AdminAction::SetCrankTip { stake_pool, crank_tip_bps } => json!({
+            "type": "set_crank_tip",
+            "stake_pool": stake_pool.to_string(),
+            "crank_tip_bps": crank_tip_bps,
+        }),
+        AdminAction::SetPartialPayments { stake_pool, partial_payments } => json!({
+            "type": "set_partial_payments",
+            "stake_pool": stake_pool.to_string(),
+            "partial_payments": partial_payments,
+        }),
+        AdminAction::SetStakeLimits { stake_pool, limits } => json!({
+            "type": "set_stake_limits",
+            "stake_pool": stake_pool.to_string(),
+            "max_total_staked": limits.max_total_staked.map(|max| max.to_string()),
+            "max_per_member": limits.max_per_member,
+            "min_stake_amount": limits.min_stake_amount,
+        }),
+        AdminAction::SetRoles { pool_creator, reward_funder, pauser } => json!({
+            "type": "set_roles",
+            "pool_creator": pool_creator.to_string(),
+            "reward_funder": reward_funder.to_string(),
+            "pauser": pauser.to_string(),
+        }),
+        AdminAction::SetPoolManager { stake_pool, manager } => json!({
+            "type": "set_pool_manager",
+            "stake_pool": stake_pool.to_string(),
+            "manager": manager.map(|manager| manager.to_string()),
+        }),
+    }
+}
+
+fn queued_action_view(key: &Pubkey, queued_action: &QueuedAction, now: u64) -> Value {
+    json!({
+        "id": queued_action.id,
+        "queued_action": key.to_string(),
+        "action": action_view(&queued_action.action),
+        "proposer": queued_action.proposer.to_string(),
+        "queued_at": queued_action.queued_at,
+        "eta": queued_action.eta,
+        "executable": queued_action.eta <= now,
+    })
+}
+
+/// Lists the queued actions, whether their eta has come or not.
+pub fn list(ctx: &Context) -> Result<Value> {
+    let keys = ctx.factory_keys()?;
+    let now = ctx.rpc.get_block_time()?;
+    let queue = list_queued_actions(&ctx.rpc, &keys, ctx.factory()?.action_count)?;
+
+    Ok(Value::Array(queue.iter()
+        .map(|queued_action| queued_action_view(&keys.queued_action(queued_action.id), queued_action, now))
+        .collect()))
+}
+
+/// Queues the `action`, executable from the `eta`, the factory's timelock delay from now by default.
+pub fn queue(ctx: &Context, action: AdminAction, eta: Option<u64>) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let factory = ctx.factory()?;
+    let id = factory.action_count + 1;
+    let eta = match eta {
+        Some(eta) => eta,
+        None => ctx.rpc.get_block_time()? + factory.timelock_delay,
+    };
+
+    let signature = ctx.send(&signer, &[instruction::queue_action(&keys, &signer.pubkey(), id, action, eta)])?;
+
+    Ok(json!({
+        "signature": signature.to_string(),
+        "id": id,
+        "queued_action": keys.queued_action(id).to_string(),
+        "action": action_view(&action),
+        "eta": eta,
+    }))
+}
+
+pub fn set_owner_fee(ctx: &Context, name: &str, fee_bps: u16, max_fee_per_claim: Option<u64>, eta: Option<u64>) -> Result<Value> {
+    let stake_pool = ctx.factory_keys()?.stake_pool(name);
+    let owner_fee = OwnerFee::new(fee_bps, max_fee_per_claim)?;
+
+    queue(ctx, AdminAction::SetOwnerFee { stake_pool, owner_fee }, eta)
+}
+
+pub fn set_reward(ctx: &Context, name: &str, reward: Reward, eta: Option<u64>) -> Result<Value> {
+    let stake_pool = ctx.factory_keys()?.stake_pool(name);
+
+    queue(ctx, AdminAction::SetReward { stake_pool, reward }, eta)
+}
+
+pub fn set_fee_recipient(ctx: &Context, name: &str, fee_recipient: Pubkey, eta: Option<u64>) -> Result<Value> {
+    let stake_pool = ctx.factory_keys()?.stake_pool(name);
+
+    queue(ctx, AdminAction::SetFeeRecipient { stake_pool, fee_recipient }, eta)
+}
+
+pub fn set_crank_tip(ctx: &Context, name: &str, crank_tip_bps: u16, eta: Option<u64>) -> Result<Value> {
+    let stake_pool = ctx.factory_keys()?.stake_pool(name);
+
+    queue(ctx, AdminAction::SetCrankTip { stake_pool, crank_tip_bps }, eta)
+}
+
+pub fn set_partial_payments(ctx: &Context, name: &str, partial_payments: bool, eta: Option<u64>) -> Result<Value> {
+    let stake_pool = ctx.factory_keys()?.stake_pool(name);
+
+    queue(ctx, AdminAction::SetPartialPayments { stake_pool, partial_payments }, eta)
+}
+
+pub fn set_stake_limits(ctx: &Context, name: &str, limits: StakeLimits, eta: Option<u64>) -> Result<Value> {
+    limits.validate()?;
+    let stake_pool = ctx.factory_keys()?.stake_pool(name);
+
+    queue(ctx, AdminAction::SetStakeLimits { stake_pool, limits }, eta)
+}
+
+/// Queues the assignment of the roles of the factory, keeping the current holders of the roles not given.
+pub fn set_roles(
+    ctx: &Context,
+    pool_creator: Option<Pubkey>,
+    reward_funder: Option<Pubkey>,
+    pauser: Option<Pubkey>,
+    eta: Option<u64>,
+) -> Result<Value> {
+    let roles: FactoryRoles = fetch(&ctx.rpc, &ctx.factory_keys()?.roles())?;
+    let action = AdminAction::SetRoles {
+        pool_creator: pool_creator.unwrap_or(roles.pool_creator),
+        reward_funder: reward_funder.unwrap_or(roles.reward_funder),
+        pauser: pauser.unwrap_or(roles.pauser),
+    };
+
+    queue(ctx, action, eta)
+}
+
+pub fn set_pool_manager(ctx: &Context, name: &str, manager: Option<Pubkey>, eta: Option<u64>) -> Result<Value> {
+    let stake_pool = ctx.factory_keys()?.stake_pool(name);
+
+    queue(ctx, AdminAction::SetPoolManager { stake_pool, manager }, eta)
+}
+
+pub fn cancel(ctx: &Context, id: u64) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let queued_action: QueuedAction = fetch(&ctx.rpc, &keys.queued_action(id))?;
+
+    let signature = ctx.send(&signer, &[instruction::cancel_action(&keys, &signer.pubkey(), &queued_action)])?;
+
+    Ok(json!({ "signature": signature.to_string(), "id": id, "action": action_view(&queued_action.action) }))
+}
+
+pub fn execute(ctx: &Context, id: u64) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let queued_action: QueuedAction = fetch(&ctx.rpc, &keys.queued_action(id))?;
+    let positions = match queued_action.action.stake_pool() {
+        Some(stake_pool) if queued_action.action.settles_rewards() => audit::fetch_positions(&ctx.rpc, &keys, &stake_pool)?.keys(),
+        _ => vec![],
+    };
+
+    let signature = ctx.send(&signer, &[instruction::execute_action(&keys, &queued_action, &positions)])?;
+
+    Ok(json!({ "signature": signature.to_string(), "id": id, "action": action_view(&queued_action.action) }))
+}
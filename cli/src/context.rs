@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use serde_json::Value;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use staking::account::Factory;
+use staking_client::account::fetch_factory;
+use staking_client::{FactoryKeys, RpcClient};
+use crate::output::{self, OutputFormat};
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Everything a command needs to talk to the cluster.
+pub struct Context {
+    pub rpc: RpcClient,
+    pub program_id: Pubkey,
+    pub keypair_path: PathBuf,
+    pub output: OutputFormat,
+}
+
+impl Context {
+    /// The local keypair that signs and pays for the transactions.
+    pub fn signer(&self) -> Result<Keypair> {
+        read_keypair_file(&self.keypair_path)
+            .map_err(|err| format!("Failed to read the keypair {}: {}", self.keypair_path.display(), err).into())
+    }
+
+    pub fn factory(&self) -> Result<Factory> {
+        Ok(fetch_factory(&self.rpc, &self.program_id)?)
+    }
+
+    pub fn factory_keys(&self) -> Result<FactoryKeys> {
+        Ok(self.rpc.get_factory_keys(&self.program_id)?)
+    }
+
+    pub fn send(&self, signer: &Keypair, instructions: &[Instruction]) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signer.pubkey()),
+            &[signer],
+            blockhash,
+        );
+
+        Ok(self.rpc.send_and_confirm_transaction(&transaction)?)
+    }
+
+    pub fn print(&self, value: &Value) {
+        output::print(self.output, value)
+    }
+}
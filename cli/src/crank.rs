@@ -0,0 +1,34 @@
+use serde_json::{json, Value};
+use solana_sdk::signature::Signer;
+use staking_client::crank::{crankable_positions, fetch_auto_claim_members, fetch_member_positions};
+use staking_client::instruction;
+use crate::context::{Context, Result};
+
+/// Claims the rewards of every position opted in to the auto-claims,
+/// `batch_size` positions per transaction. The tips go to the signer's ATA.
+pub fn crank(ctx: &Context, batch_size: usize) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let cranker_reward_vault = keys.reward_token_account(&signer.pubkey());
+    let now = ctx.rpc.get_block_time()?;
+
+    let members = fetch_auto_claim_members(&ctx.rpc, &keys)?;
+    let mut positions = vec![];
+    for member in &members {
+        positions.extend(fetch_member_positions(&ctx.rpc, &keys, &member.beneficiary)?);
+    }
+    let crankable = crankable_positions(&ctx.rpc, &keys, &members, &positions, now)?;
+
+    let signatures = crankable.chunks(batch_size.max(1))
+        .map(|batch| {
+            let crank = instruction::crank_claims(&keys, &signer.pubkey(), &cranker_reward_vault, batch);
+            Ok(ctx.send(&signer, &[crank])?.to_string())
+        })
+        .collect::<Result<Vec<String>>>()?;
+
+    Ok(json!({
+        "members": members.len(),
+        "positions": crankable.len(),
+        "signatures": signatures,
+    }))
+}
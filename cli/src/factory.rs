@@ -0,0 +1,98 @@
+use anchor_lang::prelude::Pubkey;
+use serde_json::{json, Value};
+use solana_sdk::signature::Signer;
+use staking::account::FactoryRoles;
+use staking_client::account::{fetch_optional, fetch_token_account};
+use staking_client::migration::{factory_keys, find_v0_accounts};
+use staking_client::{instruction, FactoryKeys};
+use crate::context::{Context, Result};
+
+pub fn init(ctx: &Context, stake_token_mint: Pubkey, reward_token_mint: Pubkey, owner: Option<Pubkey>) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let owner = owner.unwrap_or_else(|| signer.pubkey());
+    let keys = FactoryKeys::new(ctx.program_id, stake_token_mint, reward_token_mint).with_token_programs(
+        ctx.rpc.get_account_owner(&stake_token_mint)?,
+        ctx.rpc.get_account_owner(&reward_token_mint)?,
+    );
+
+    let signature = ctx.send(&signer, &[instruction::initialize(&keys, &signer.pubkey(), &owner)])?;
+
+    Ok(json!({
+        "signature": signature.to_string(),
+        "factory": keys.factory.to_string(),
+        "owner": owner.to_string(),
+        "vault_reward": keys.vault_reward.to_string(),
+    }))
+}
+
+pub fn show(ctx: &Context) -> Result<Value> {
+    let factory = ctx.factory()?;
+    let vault_reward = fetch_token_account(&ctx.rpc, &factory.vault_reward)?;
+    let keys = ctx.factory_keys()?;
+    let roles: Option<FactoryRoles> = fetch_optional(&ctx.rpc, &keys.roles())?;
+
+    Ok(json!({
+        "factory": keys.factory.to_string(),
+        "owner": factory.owner.to_string(),
+        "roles": roles.map(|roles| json!({
+            "pool_creator": roles.pool_creator.to_string(),
+            "reward_funder": roles.reward_funder.to_string(),
+            "pauser": roles.pauser.to_string(),
+        })),
+        "paused": factory.paused,
+        "timelock_delay": factory.timelock_delay,
+        "stake_token_mint": factory.stake_token_mint.to_string(),
+        "reward_token_mint": factory.reward_token_mint.to_string(),
+        "vault_reward": factory.vault_reward.to_string(),
+        "vault_reward_amount": vault_reward.amount,
+        "committed_rewards": factory.committed_rewards.to_string(),
+        "funded_rewards": factory.funded_rewards,
+        "uncommitted_rewards": (vault_reward.amount.saturating_sub(factory.funded_rewards) as u128)
+            .saturating_sub(factory.committed_rewards)
+            .to_string(),
+    }))
+}
+
+/// Pauses the stakes and the claims, lifted by the queued `action unpause`.
+pub fn pause(ctx: &Context) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+
+    let signature = ctx.send(&signer, &[instruction::pause(&keys, &signer.pubkey())])?;
+
+    Ok(json!({ "signature": signature.to_string(), "paused": true }))
+}
+
+/// Deposits reward tokens from the signer's `from` account, its ATA by default.
+pub fn deposit_reward(ctx: &Context, amount: u64, from: Option<Pubkey>) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let from = from.unwrap_or_else(|| keys.reward_token_account(&signer.pubkey()));
+
+    let signature = ctx.send(&signer, &[instruction::deposit_reward(&keys, &signer.pubkey(), &from, amount)])?;
+
+    Ok(json!({ "signature": signature.to_string(), "amount": amount }))
+}
+
+/// Migrates the version 0 accounts of the program, `batch_size` accounts per transaction.
+pub fn migrate(ctx: &Context, batch_size: usize) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = factory_keys(&ctx.rpc, &ctx.program_id)?;
+    let accounts = find_v0_accounts(&ctx.rpc, &ctx.program_id)?;
+
+    let signatures = accounts.chunks(batch_size.max(1))
+        .map(|batch| {
+            let instructions: Vec<_> = batch.iter()
+                .map(|account| instruction::migrate(&keys, &signer.pubkey(), &account.key, account.kind, &account.remaining_accounts))
+                .collect();
+            Ok(ctx.send(&signer, &instructions)?.to_string())
+        })
+        .collect::<Result<Vec<String>>>()?;
+
+    Ok(json!({
+        "migrated": accounts.iter()
+            .map(|account| json!({ "account": account.key.to_string(), "kind": format!("{:?}", account.kind) }))
+            .collect::<Vec<_>>(),
+        "signatures": signatures,
+    }))
+}
@@ -0,0 +1,749 @@
+//! Command-line tool for the staking program.
+//!
+//! Operators initialise the factory, manage stake pools and fund rewards,
+//! and the managers of the pools describe them, set their allowlists and fund them.
+//! The fees, the rewards, the limits, the roles, the managers, the lifting of the pause and the admin change
+//! wait in the timelocked queue of actions.
+//! Members deposit, stake, claim, compound, unstake and withdraw their tokens or NFTs,
+//! and may let a delegate stake, claim and compound for them.
+//! Anyone may crank the claims of the members opted in to the auto-claims.
+mod action;
+mod context;
+mod crank;
+mod factory;
+mod member;
+mod output;
+mod pool;
+mod simulate;
+mod stats;
+
+use std::path::PathBuf;
+use anchor_lang::prelude::Pubkey;
+use clap::{Args, Parser, Subcommand};
+use staking::limits::StakeLimits;
+use staking::timelock::AdminAction;
+use staking_client::RpcClient;
+use context::{Context, Result};
+use output::OutputFormat;
+use pool::{NewStakePoolArgs, RewardArg};
+use simulate::{DraftPoolArgs, SimulateArgs};
+
+#[derive(Parser)]
+#[command(name = "staking", version, about = "Manage stake pools and stakes of the staking program")]
+struct Cli {
+    /// The JSON RPC URL of the cluster
+    #[arg(long, short = 'u', global = true, default_value = "http://localhost:8899")]
+    url: String,
+    /// The keypair file that signs and pays for the transactions
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<PathBuf>,
+    #[arg(long, global = true, default_value_t = staking::ID)]
+    program_id: Pubkey,
+    #[arg(long, short = 'o', global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the stake pool factory
+    Init {
+        #[arg(long)]
+        stake_token_mint: Pubkey,
+        #[arg(long)]
+        reward_token_mint: Pubkey,
+        /// The owner of the factory, the signer by default
+        #[arg(long)]
+        owner: Option<Pubkey>,
+    },
+    /// Show the factory and its reward vault
+    Factory,
+    /// Stop the stakes and the claims of every pool, signed by the pauser. Lifted by `action unpause`
+    Pause,
+    /// Queue, cancel and execute the timelocked admin actions
+    #[command(subcommand)]
+    Action(ActionCommand),
+    /// Deposit reward tokens to the factory's reward vault
+    DepositReward {
+        amount: u64,
+        /// The token account to take the tokens from, the signer's ATA by default
+        #[arg(long)]
+        from: Option<Pubkey>,
+    },
+    /// Manage stake pools
+    #[command(subcommand)]
+    Pool(PoolCommand),
+    /// Member's positions and actions
+    #[command(subcommand)]
+    Member(MemberCommand),
+    /// Claim the rewards of the members opted in to the auto-claims for the crank tips
+    Crank {
+        /// The positions claimed by a single transaction
+        #[arg(long, default_value_t = 3)]
+        batch_size: usize,
+    },
+    /// Upgrade the accounts created before the accounts were versioned to the current layout.
+    /// The signer pays the rent of the added space
+    Migrate {
+        /// The accounts migrated by a single transaction
+        #[arg(long, default_value_t = 5)]
+        batch_size: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum ActionCommand {
+    /// List the queued actions
+    List,
+    /// Queue the change of the pool's owner fee, signed by the admin
+    SetOwnerFee {
+        name: String,
+        /// The owner's fee in basis points of each claim
+        #[arg(long)]
+        fee_bps: u16,
+        /// The maximum fee of a single claim, uncapped by default
+        #[arg(long)]
+        max_fee_per_claim: Option<u64>,
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Queue the change of the pool's reward rate, signed by the admin or the pool's manager.
+    /// Fixed pools change their rate only with nothing staked
+    SetReward {
+        name: String,
+        /// fixed:<rate %> or unfixed:<tokens per period>, of the pool's reward type
+        #[arg(long)]
+        reward: RewardArg,
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Queue the lifting of the pause, signed by the admin or the pauser
+    Unpause {
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Queue the change of the factory's admin, signed by the admin
+    TransferOwnership {
+        owner: Pubkey,
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Queue the change of the seconds the actions wait, signed by the admin
+    SetTimelockDelay {
+        delay: u64,
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Queue the change of the wallet receiving the pool's fees, signed by the admin
+    SetFeeRecipient {
+        name: String,
+        fee_recipient: Pubkey,
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Queue the change of the share of the owner's fee paid to the cranker of the auto-claims, signed by the admin
+    SetCrankTip {
+        name: String,
+        /// Basis points of the owner's fee
+        bps: u16,
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Queue paying the claims pro rata and owing the rest when the reward vault is short, fixed pools only,
+    /// signed by the admin
+    SetPartialPayments {
+        name: String,
+        #[arg(action = clap::ArgAction::Set)]
+        enabled: bool,
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Queue the change of the amounts of tokens the pool accepts, signed by the admin or the pool's manager
+    SetLimits {
+        name: String,
+        #[command(flatten)]
+        limits: LimitsArgs,
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Queue the assignment of the roles of the factory, signed by the admin. The roles not given are kept
+    SetRoles {
+        /// Creates and registers the pools and assigns their managers
+        #[arg(long)]
+        pool_creator: Option<Pubkey>,
+        /// Deposits the reward tokens
+        #[arg(long)]
+        reward_funder: Option<Pubkey>,
+        /// Pauses the stakes and the claims and queues the lifting of the pause
+        #[arg(long)]
+        pauser: Option<Pubkey>,
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Queue letting the manager describe the pool, set its allowlist and limits and fund it,
+    /// signed by the pool creator
+    SetManager {
+        name: String,
+        /// The manager of the pool, removed without it
+        #[arg(long)]
+        manager: Option<Pubkey>,
+        #[command(flatten)]
+        eta: EtaArgs,
+    },
+    /// Cancel the queued action, signed by its proposer or the admin
+    Cancel {
+        id: u64,
+    },
+    /// Execute the queued action once its eta has come, signed by anyone.
+    /// The reward and manager changes settle the rewards of all the positions of the pool first
+    Execute {
+        id: u64,
+    },
+}
+
+#[derive(Args)]
+struct EtaArgs {
+    /// The UNIX time the action becomes executable, the timelock delay from now by default
+    #[arg(long)]
+    eta: Option<u64>,
+}
+
+#[derive(Args)]
+struct LimitsArgs {
+    /// The maximum tokens staked in the pool, unlimited by default
+    #[arg(long)]
+    max_total_staked: Option<u128>,
+    /// The maximum tokens a member stakes in the pool, unlimited by default
+    #[arg(long)]
+    max_per_member: Option<u64>,
+    /// The minimum tokens of a single stake
+    #[arg(long, default_value_t = 0)]
+    min_stake: u64,
+}
+
+impl From<LimitsArgs> for StakeLimits {
+    fn from(args: LimitsArgs) -> StakeLimits {
+        StakeLimits {
+            max_total_staked: args.max_total_staked,
+            max_per_member: args.max_per_member,
+            min_stake_amount: args.min_stake,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum PoolCommand {
+    /// Create a new stake pool
+    Create {
+        name: String,
+        /// fixed:<rate %> or unfixed:<tokens per period>
+        #[arg(long)]
+        reward: RewardArg,
+        /// The UNIX time the pool stops gaining rewards
+        #[arg(long)]
+        ends_at: u64,
+        /// The owner's fee in basis points of each claim
+        #[arg(long, default_value_t = 0)]
+        fee_bps: u16,
+        /// The maximum owner's fee of a single claim
+        #[arg(long)]
+        max_fee_per_claim: Option<u64>,
+        /// The wallet receiving the fees, the signer by default
+        #[arg(long)]
+        fee_recipient: Option<Pubkey>,
+        /// Seconds a member waits to finish unstaking
+        #[arg(long)]
+        unstake_delay: u64,
+        /// Seconds between the rewards
+        #[arg(long)]
+        reward_period: u64,
+        /// The CSV of the beneficiaries allowed to stake, everyone by default
+        #[arg(long)]
+        allowlist: Option<PathBuf>,
+        #[command(flatten)]
+        limits: LimitsArgs,
+    },
+    /// Show a stake pool
+    Show { name: String },
+    /// List the stake pools of the factory's registry with their states
+    List,
+    /// Add a pool created before the registry to it
+    Register { name: String },
+    /// Mark the ended pool with nothing staked closed
+    Close { name: String },
+    /// Show the pool's TVL and the reward runway of the factory
+    Stats { name: String },
+    /// Move the accrued fees to the fee recipient
+    HarvestFees {
+        name: String,
+        /// The fee recipient's token account, its ATA by default
+        #[arg(long)]
+        to: Option<Pubkey>,
+    },
+    /// Deposit reward tokens on behalf of the pool, signed by its manager
+    Fund {
+        name: String,
+        amount: u64,
+        /// The token account to take the tokens from, the signer's ATA by default
+        #[arg(long)]
+        from: Option<Pubkey>,
+    },
+    /// Make the pool accept the NFTs of a verified collection instead of the stake tokens, while it's empty
+    SetNftConfig {
+        name: String,
+        /// The collection mint of the NFTs
+        #[arg(long, required_unless_present = "fungible")]
+        collection: Option<Pubkey>,
+        /// The weight of every NFT without the weights CSV
+        #[arg(long, default_value_t = 1)]
+        base_weight: u64,
+        /// The CSV of mint,weight pairs of the NFTs
+        #[arg(long)]
+        weights: Option<PathBuf>,
+        /// Turn the pool back into a pool of the stake tokens
+        #[arg(long, conflicts_with_all = ["collection", "weights"])]
+        fungible: bool,
+    },
+    /// Describe the pool for the wallets and explorers
+    SetMetadata {
+        name: String,
+        /// The URI of the description of the pool
+        #[arg(long, default_value = "")]
+        description_uri: String,
+        /// The URI of the logo of the pool
+        #[arg(long, default_value = "")]
+        logo_uri: String,
+        /// A tag of the pool, repeated for more tags
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Show how many tokens a new member is able to stake in the pool
+    Capacity { name: String },
+    /// Show the merkle root of the allowlist CSV and the proof of a beneficiary
+    Allowlist {
+        csv: PathBuf,
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+    },
+    /// Replace the allowlist of the pool
+    SetAllowlist {
+        name: String,
+        /// The CSV of the beneficiaries allowed to stake
+        #[arg(required_unless_present = "open")]
+        csv: Option<PathBuf>,
+        /// Remove the allowlist and let everyone stake
+        #[arg(long, conflicts_with = "csv")]
+        open: bool,
+    },
+    /// Check the staked tokens and the reward vault of the pool against its positions
+    Audit {
+        name: String,
+        /// Also send the audit_pool instruction to check the pool on chain
+        #[arg(long)]
+        send: bool,
+    },
+    /// Replay a scenario CSV of member actions and report the rewards
+    Simulate {
+        /// Lines of time,member,action[,amount], the time in seconds since the pool started
+        scenario: PathBuf,
+        /// Replay against the existing pool and the factory's reward vault
+        #[arg(long, conflicts_with_all = ["reward", "duration", "reward_period"])]
+        pool: Option<String>,
+        /// fixed:<rate %> or unfixed:<tokens per period> of the draft pool
+        #[arg(long, required_unless_present = "pool")]
+        reward: Option<RewardArg>,
+        /// Seconds the draft pool gains rewards for
+        #[arg(long, required_unless_present = "pool")]
+        duration: Option<u64>,
+        #[arg(long, required_unless_present = "pool")]
+        reward_period: Option<u64>,
+        #[arg(long, default_value_t = 0)]
+        fee_bps: u16,
+        #[arg(long)]
+        max_fee_per_claim: Option<u64>,
+        #[arg(long, default_value_t = 0)]
+        unstake_delay: u64,
+        /// The reward tokens in the factory's vault at the start
+        #[arg(long, default_value_t = 0)]
+        vault_reward: u64,
+        /// Write the totals of each member to the CSV file
+        #[arg(long)]
+        members_csv: Option<PathBuf>,
+        /// Write the outcome of each action to the CSV file
+        #[arg(long)]
+        ledger_csv: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum MemberCommand {
+    /// Create the member account of the signer
+    Create,
+    /// Show the member with its stakes and pending unstakes, the signer's by default
+    Show { beneficiary: Option<Pubkey> },
+    /// List all members
+    List,
+    /// Deposit tokens to the member's free vault
+    Deposit {
+        amount: u64,
+        /// The token account to take the tokens from, the signer's ATA by default
+        #[arg(long)]
+        from: Option<Pubkey>,
+    },
+    /// Deposit SOL to the member's free vault, when the stake tokens are the wrapped SOL
+    DepositSol { lamports: u64 },
+    /// Allow the delegate to stake, claim and compound for the signer
+    SetDelegate {
+        #[arg(required_unless_present = "revoke")]
+        delegate: Option<Pubkey>,
+        /// Revoke the delegate
+        #[arg(long, conflicts_with = "delegate")]
+        revoke: bool,
+    },
+    /// Let anyone claim the signer's rewards for a tip paid by the pool owner
+    SetAutoClaim {
+        #[arg(action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Stake tokens from the free vault in the pool
+    Stake {
+        pool: String,
+        amount: u64,
+        /// The allowlist CSV of a permissioned pool to prove the beneficiary is in
+        #[arg(long)]
+        allowlist: Option<PathBuf>,
+        /// Stake as the delegate of the beneficiary, the signer's own member by default
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+    },
+    /// Stake tokens straight from the wallet, creating the member on the first use
+    DepositAndStake {
+        pool: String,
+        amount: u64,
+        /// The token account to take the tokens from, the signer's ATA by default
+        #[arg(long)]
+        from: Option<Pubkey>,
+        /// The allowlist CSV of a permissioned pool to prove the signer is in
+        #[arg(long)]
+        allowlist: Option<PathBuf>,
+    },
+    /// Allow the rewards to be claimed to another token account, e.g. a cold wallet's ATA
+    SetRewardDestination {
+        #[arg(required_unless_present = "clear")]
+        reward_destination: Option<Pubkey>,
+        /// Remove the reward destination
+        #[arg(long, conflicts_with = "reward_destination")]
+        clear: bool,
+    },
+    /// Claim the reward of the stake in the pool
+    Claim {
+        pool: String,
+        /// The reward token account, the reward destination or the beneficiary's ATA by default
+        #[arg(long)]
+        to: Option<Pubkey>,
+        /// Claim as the delegate of the beneficiary, the signer's own member by default
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+    },
+    /// Claim the rewards of all the stakes in one transaction
+    ClaimAll {
+        /// The reward token account, the reward destination or the beneficiary's ATA by default
+        #[arg(long)]
+        to: Option<Pubkey>,
+        /// Claim as the delegate of the beneficiary, the signer's own member by default
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+    },
+    /// Stake the reward of the stake in the pool in the same stake
+    Compound {
+        pool: String,
+        /// Compound as the delegate of the beneficiary, the signer's own member by default
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+    },
+    /// Show the reward that could be claimed now
+    Pending { pool: String, beneficiary: Option<Pubkey> },
+    /// Start unstaking all tokens of the stake in the pool
+    Unstake { pool: String },
+    /// Move the unstaked tokens to the free vault after the unstake delay
+    FinishUnstake { pool: String },
+    /// Move the unstaked tokens straight to the wallet after the unstake delay
+    UnstakeAndWithdraw {
+        pool: String,
+        /// The token account to send the tokens to, the signer's ATA by default
+        #[arg(long)]
+        to: Option<Pubkey>,
+    },
+    /// Stake the signer's NFT in an NFT pool
+    StakeNft {
+        pool: String,
+        mint: Pubkey,
+        /// The weights CSV of the pool to prove the weight of the NFT
+        #[arg(long)]
+        weights: Option<PathBuf>,
+        /// The allowlist CSV of a permissioned pool to prove the signer is in
+        #[arg(long)]
+        allowlist: Option<PathBuf>,
+    },
+    /// Claim the reward of the staked NFT
+    ClaimNft {
+        pool: String,
+        mint: Pubkey,
+        /// The reward token account, the reward destination or the beneficiary's ATA by default
+        #[arg(long)]
+        to: Option<Pubkey>,
+        /// Claim as the delegate of the beneficiary, the signer's own member by default
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+    },
+    /// Start unstaking the NFT
+    UnstakeNft { pool: String, mint: Pubkey },
+    /// Return the NFT to the signer's wallet after the unstake delay
+    FinishUnstakeNft { pool: String, mint: Pubkey },
+    /// Withdraw all tokens from the free vault
+    Withdraw {
+        /// The token account to send the tokens to, the signer's ATA by default
+        #[arg(long)]
+        to: Option<Pubkey>,
+    },
+    /// Close the signer's member without stakes, withdrawing the free vault and reclaiming the rent
+    Close {
+        /// The token account to send the free tokens to, the signer's ATA by default
+        #[arg(long)]
+        to: Option<Pubkey>,
+    },
+    /// Withdraw all the wrapped SOL from the free vault to the signer's wallet as SOL
+    WithdrawSol,
+    /// Create the position index of the signer's member opened before the index, from its positions on chain
+    IndexPositions,
+}
+
+fn default_keypair_path() -> PathBuf {
+    let home = std::env::var_os("HOME").unwrap_or_default();
+    PathBuf::from(home).join(".config").join("solana").join("id.json")
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let ctx = Context {
+        rpc: RpcClient::new(&cli.url),
+        program_id: cli.program_id,
+        keypair_path: cli.keypair.unwrap_or_else(default_keypair_path),
+        output: cli.output,
+    };
+
+    let result = match cli.command {
+        Command::Init { stake_token_mint, reward_token_mint, owner } => {
+            factory::init(&ctx, stake_token_mint, reward_token_mint, owner)
+        },
+        Command::Factory => factory::show(&ctx),
+        Command::Pause => factory::pause(&ctx),
+        Command::Action(command) => match command {
+            ActionCommand::List => action::list(&ctx),
+            ActionCommand::SetOwnerFee { name, fee_bps, max_fee_per_claim, eta } => {
+                action::set_owner_fee(&ctx, &name, fee_bps, max_fee_per_claim, eta.eta)
+            },
+            ActionCommand::SetReward { name, reward, eta } => action::set_reward(&ctx, &name, reward.0, eta.eta),
+            ActionCommand::Unpause { eta } => action::queue(&ctx, AdminAction::Unpause, eta.eta),
+            ActionCommand::TransferOwnership { owner, eta } => {
+                action::queue(&ctx, AdminAction::TransferOwnership { owner }, eta.eta)
+            },
+            ActionCommand::SetTimelockDelay { delay, eta } => {
+                action::queue(&ctx, AdminAction::SetTimelockDelay { delay }, eta.eta)
+            },
+            ActionCommand::SetFeeRecipient { name, fee_recipient, eta } => {
+                action::set_fee_recipient(&ctx, &name, fee_recipient, eta.eta)
+            },
+            ActionCommand::SetCrankTip { name, bps, eta } => action::set_crank_tip(&ctx, &name, bps, eta.eta),
+            ActionCommand::SetPartialPayments { name, enabled, eta } => {
+                action::set_partial_payments(&ctx, &name, enabled, eta.eta)
+            },
+            ActionCommand::SetLimits { name, limits, eta } => action::set_stake_limits(&ctx, &name, limits.into(), eta.eta),
+            ActionCommand::SetRoles { pool_creator, reward_funder, pauser, eta } => {
+                action::set_roles(&ctx, pool_creator, reward_funder, pauser, eta.eta)
+            },
+            ActionCommand::SetManager { name, manager, eta } => action::set_pool_manager(&ctx, &name, manager, eta.eta),
+            ActionCommand::Cancel { id } => action::cancel(&ctx, id),
+            ActionCommand::Execute { id } => action::execute(&ctx, id),
+        },
+        Command::DepositReward { amount, from } => factory::deposit_reward(&ctx, amount, from),
+        Command::Pool(command) => match command {
+            PoolCommand::Create {
+                name, reward, ends_at, fee_bps, max_fee_per_claim, fee_recipient, unstake_delay, reward_period, allowlist,
+                limits,
+            } => pool::create(&ctx, NewStakePoolArgs {
+                name,
+                reward: reward.0,
+                ends_at,
+                fee_bps,
+                max_fee_per_claim,
+                fee_recipient,
+                unstake_delay,
+                reward_period,
+                allowlist,
+                limits: limits.into(),
+            }),
+            PoolCommand::Show { name } => pool::show(&ctx, &name),
+            PoolCommand::List => pool::list(&ctx),
+            PoolCommand::Register { name } => pool::register(&ctx, &name),
+            PoolCommand::Close { name } => pool::close(&ctx, &name),
+            PoolCommand::Stats { name } => pool::stats(&ctx, &name),
+            PoolCommand::HarvestFees { name, to } => pool::harvest_fees(&ctx, &name, to),
+            PoolCommand::Fund { name, amount, from } => pool::fund(&ctx, &name, amount, from),
+            PoolCommand::SetNftConfig { name, collection, base_weight, weights, fungible: _ } => {
+                pool::set_nft_config(&ctx, &name, collection, base_weight, weights.as_deref())
+            },
+            PoolCommand::SetMetadata { name, description_uri, logo_uri, tags } => {
+                pool::set_metadata(&ctx, &name, description_uri, logo_uri, tags)
+            },
+            PoolCommand::Capacity { name } => pool::capacity(&ctx, &name),
+            PoolCommand::Allowlist { csv, beneficiary } => pool::allowlist(&csv, beneficiary),
+            PoolCommand::SetAllowlist { name, csv, open: _ } => pool::set_allowlist(&ctx, &name, csv.as_deref()),
+            PoolCommand::Audit { name, send } => pool::audit(&ctx, &name, send),
+            PoolCommand::Simulate {
+                scenario, pool, reward, duration, reward_period, fee_bps, max_fee_per_claim, unstake_delay, vault_reward,
+                members_csv, ledger_csv,
+            } => simulate::simulate(&ctx, SimulateArgs {
+                scenario,
+                draft: match (reward, duration, reward_period) {
+                    (Some(reward), Some(duration), Some(reward_period)) => Some(DraftPoolArgs {
+                        reward: reward.0,
+                        duration,
+                        fee_bps,
+                        max_fee_per_claim,
+                        unstake_delay,
+                        reward_period,
+                        vault_reward,
+                    }),
+                    _ => None,
+                },
+                pool,
+                members_csv,
+                ledger_csv,
+            }),
+        },
+        Command::Member(command) => match command {
+            MemberCommand::Create => member::create(&ctx),
+            MemberCommand::Show { beneficiary } => member::show(&ctx, beneficiary),
+            MemberCommand::List => member::list(&ctx),
+            MemberCommand::Deposit { amount, from } => member::deposit(&ctx, amount, from),
+            MemberCommand::DepositSol { lamports } => member::deposit_sol(&ctx, lamports),
+            MemberCommand::SetDelegate { delegate, revoke: _ } => member::set_delegate(&ctx, delegate),
+            MemberCommand::Stake { pool, amount, allowlist, beneficiary } => {
+                member::stake(&ctx, &pool, amount, allowlist.as_deref(), beneficiary)
+            },
+            MemberCommand::DepositAndStake { pool, amount, from, allowlist } => {
+                member::deposit_and_stake(&ctx, &pool, amount, from, allowlist.as_deref())
+            },
+            MemberCommand::SetAutoClaim { enabled } => member::set_auto_claim(&ctx, enabled),
+            MemberCommand::SetRewardDestination { reward_destination, clear: _ } => {
+                member::set_reward_destination(&ctx, reward_destination)
+            },
+            MemberCommand::Claim { pool, to, beneficiary } => member::claim(&ctx, &pool, to, beneficiary),
+            MemberCommand::ClaimAll { to, beneficiary } => member::claim_all(&ctx, to, beneficiary),
+            MemberCommand::Compound { pool, beneficiary } => member::compound(&ctx, &pool, beneficiary),
+            MemberCommand::Pending { pool, beneficiary } => member::pending(&ctx, &pool, beneficiary),
+            MemberCommand::Unstake { pool } => member::unstake(&ctx, &pool),
+            MemberCommand::FinishUnstake { pool } => member::finish_unstake(&ctx, &pool),
+            MemberCommand::UnstakeAndWithdraw { pool, to } => member::unstake_and_withdraw(&ctx, &pool, to),
+            MemberCommand::StakeNft { pool, mint, weights, allowlist } => {
+                member::stake_nft(&ctx, &pool, mint, weights.as_deref(), allowlist.as_deref())
+            },
+            MemberCommand::ClaimNft { pool, mint, to, beneficiary } => member::claim_nft(&ctx, &pool, mint, to, beneficiary),
+            MemberCommand::UnstakeNft { pool, mint } => member::unstake_nft(&ctx, &pool, mint),
+            MemberCommand::FinishUnstakeNft { pool, mint } => member::finish_unstake_nft(&ctx, &pool, mint),
+            MemberCommand::Withdraw { to } => member::withdraw(&ctx, to),
+            MemberCommand::WithdrawSol => member::withdraw_sol(&ctx),
+            MemberCommand::Close { to } => member::close(&ctx, to),
+            MemberCommand::IndexPositions => member::index_positions(&ctx),
+        },
+        Command::Crank { batch_size } => crank::crank(&ctx, batch_size),
+        Command::Migrate { batch_size } => factory::migrate(&ctx, batch_size),
+    }?;
+
+    ctx.print(&result);
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_pool_create() {
+        let cli = Cli::try_parse_from([
+            "staking", "-o", "json", "pool", "create", "fixed",
+            "--reward", "fixed:10", "--ends-at", "1700000000",
+            "--fee-bps", "250", "--unstake-delay", "60", "--reward-period", "3600",
+            "--max-total-staked", "1000000", "--min-stake", "10",
+        ]).unwrap();
+
+        assert_eq!(cli.output, OutputFormat::Json);
+        match cli.command {
+            Command::Pool(PoolCommand::Create { fee_bps: 250, reward_period: 3600, limits, .. }) => assert_eq!(
+                StakeLimits::from(limits),
+                StakeLimits { max_total_staked: Some(1_000_000), max_per_member: None, min_stake_amount: 10 },
+            ),
+            _ => panic!("expected pool create"),
+        }
+    }
+
+    #[test]
+    fn set_allowlist_requires_csv_or_open() {
+        assert!(Cli::try_parse_from(["staking", "pool", "set-allowlist", "partners"]).is_err());
+        assert!(Cli::try_parse_from(["staking", "pool", "set-allowlist", "partners", "allowlist.csv", "--open"]).is_err());
+        assert!(Cli::try_parse_from(["staking", "pool", "set-allowlist", "partners", "--open"]).is_ok());
+    }
+
+    #[test]
+    fn set_delegate_requires_delegate_or_revoke() {
+        let delegate = Pubkey::new_unique().to_string();
+        assert!(Cli::try_parse_from(["staking", "member", "set-delegate"]).is_err());
+        assert!(Cli::try_parse_from(["staking", "member", "set-delegate", &delegate, "--revoke"]).is_err());
+        assert!(Cli::try_parse_from(["staking", "member", "set-delegate", &delegate]).is_ok());
+        assert!(Cli::try_parse_from(["staking", "member", "set-delegate", "--revoke"]).is_ok());
+    }
+
+    #[test]
+    fn set_nft_config_requires_collection_or_fungible() {
+        let collection = Pubkey::new_unique().to_string();
+        assert!(Cli::try_parse_from(["staking", "pool", "set-nft-config", "apes"]).is_err());
+        assert!(Cli::try_parse_from(["staking", "pool", "set-nft-config", "apes", "--collection", &collection, "--fungible"]).is_err());
+        assert!(Cli::try_parse_from(["staking", "pool", "set-nft-config", "apes", "--collection", &collection]).is_ok());
+        assert!(Cli::try_parse_from(["staking", "pool", "set-nft-config", "apes", "--fungible"]).is_ok());
+    }
+
+    #[test]
+    fn parses_queued_actions() {
+        let cli = Cli::try_parse_from(["staking", "action", "set-owner-fee", "fixed", "--fee-bps", "300", "--eta", "1700000000"]).unwrap();
+        match cli.command {
+            Command::Action(ActionCommand::SetOwnerFee { fee_bps: 300, max_fee_per_claim: None, eta: EtaArgs { eta: Some(1_700_000_000) }, .. }) => {},
+            _ => panic!("expected action set-owner-fee"),
+        }
+        assert!(Cli::try_parse_from(["staking", "action", "unpause"]).is_ok());
+        assert!(Cli::try_parse_from(["staking", "action", "execute"]).is_err());
+    }
+
+    #[test]
+    fn simulate_requires_pool_or_its_parameters() {
+        assert!(Cli::try_parse_from(["staking", "pool", "simulate", "scenario.csv"]).is_err());
+        assert!(Cli::try_parse_from(["staking", "pool", "simulate", "scenario.csv", "--pool", "fixed"]).is_ok());
+        assert!(Cli::try_parse_from([
+            "staking", "pool", "simulate", "scenario.csv",
+            "--reward", "unfixed:1000", "--duration", "86400", "--reward-period", "3600",
+        ]).is_ok());
+    }
+}
@@ -0,0 +1,383 @@
+use std::path::Path;
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use serde_json::{json, Value};
+use solana_sdk::signature::{Keypair, Signer};
+use staking::account::{Member, MemberStake, PositionIndex, PositionKind};
+use staking_client::account::{deserialize, fetch_member, fetch_optional, fetch_stake_pool, fetch_token_account, offset};
+use staking_client::{estimate, instruction, portfolio, FactoryKeys};
+use crate::context::{Context, Result};
+use crate::pool::{read_allowlist, read_nft_weights};
+
+/// Sends the member's instruction signed by the local keypair.
+fn send_member_instruction(
+    ctx: &Context,
+    build: impl FnOnce(&FactoryKeys, &Keypair) -> anchor_lang::solana_program::instruction::Instruction,
+) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let signature = ctx.send(&signer, &[build(&keys, &signer)])?;
+
+    Ok(json!({ "signature": signature.to_string() }))
+}
+
+pub fn create(ctx: &Context) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| instruction::create_member(keys, &signer.pubkey()))
+}
+
+/// Deposits tokens from the signer's `from` account, its ATA by default.
+pub fn deposit(ctx: &Context, amount: u64, from: Option<Pubkey>) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| {
+        let from = from.unwrap_or_else(|| keys.stake_token_account(&signer.pubkey()));
+        instruction::deposit(keys, &signer.pubkey(), &from, amount)
+    })
+}
+
+/// Wraps the signer's `lamports` into the free vault.
+pub fn deposit_sol(ctx: &Context, lamports: u64) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| instruction::deposit_sol(keys, &signer.pubkey(), lamports))
+}
+
+/// Allows the `delegate` to stake, claim and compound for the signer, `None` revokes it.
+pub fn set_delegate(ctx: &Context, delegate: Option<Pubkey>) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| instruction::set_delegate(keys, &signer.pubkey(), delegate))
+}
+
+/// Stakes in the pool, proving the beneficiary is in the `allowlist` CSV of a permissioned pool.
+/// The signer acts as the delegate of the `beneficiary` if given.
+pub fn stake(ctx: &Context, pool: &str, amount: u64, allowlist: Option<&Path>, beneficiary: Option<Pubkey>) -> Result<Value> {
+    let beneficiary = beneficiary_or_signer(ctx, beneficiary)?;
+    let proof = match allowlist {
+        Some(path) => read_allowlist(path)?.proof(&beneficiary).ok_or_else(|| format!("{} isn't in the allowlist", beneficiary))?,
+        None => vec![],
+    };
+
+    send_member_instruction(ctx, |keys, signer| {
+        instruction::stake(keys, &signer.pubkey(), &beneficiary, &keys.stake_pool(pool), amount, proof)
+    })
+}
+
+/// Stakes straight from the signer's `from` account, its ATA by default, proving the signer is in
+/// the `allowlist` CSV of a permissioned pool. Creates the member on the first use.
+pub fn deposit_and_stake(ctx: &Context, pool: &str, amount: u64, from: Option<Pubkey>, allowlist: Option<&Path>) -> Result<Value> {
+    let signer = ctx.signer()?.pubkey();
+    let proof = match allowlist {
+        Some(path) => read_allowlist(path)?.proof(&signer).ok_or_else(|| format!("{} isn't in the allowlist", signer))?,
+        None => vec![],
+    };
+
+    send_member_instruction(ctx, |keys, signer| {
+        let from = from.unwrap_or_else(|| keys.stake_token_account(&signer.pubkey()));
+        instruction::deposit_and_stake(keys, &signer.pubkey(), &keys.stake_pool(pool), &from, amount, proof)
+    })
+}
+
+/// Lets anyone claim the signer's rewards by the crank, or stops it.
+pub fn set_auto_claim(ctx: &Context, enabled: bool) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| instruction::set_auto_claim(keys, &signer.pubkey(), enabled))
+}
+
+/// Allows the rewards to be claimed to the `reward_destination` token account, `None` removes it.
+pub fn set_reward_destination(ctx: &Context, reward_destination: Option<Pubkey>) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| {
+        instruction::set_reward_destination(keys, &signer.pubkey(), reward_destination)
+    })
+}
+
+/// The `to` account, or the member's reward destination, or the beneficiary's ATA.
+fn reward_vault_or_default(ctx: &Context, keys: &FactoryKeys, beneficiary: &Pubkey, to: Option<Pubkey>) -> Result<Pubkey> {
+    if let Some(to) = to {
+        return Ok(to);
+    }
+
+    let member = fetch_member(&ctx.rpc, &keys.member(beneficiary))?;
+    Ok(member.reward_destination.unwrap_or_else(|| keys.reward_token_account(beneficiary)))
+}
+
+/// Claims the reward to the `to` account, the member's reward destination or the beneficiary's ATA by default.
+/// The signer acts as the delegate of the `beneficiary` if given.
+pub fn claim(ctx: &Context, pool: &str, to: Option<Pubkey>, beneficiary: Option<Pubkey>) -> Result<Value> {
+    let keys = ctx.factory_keys()?;
+    let beneficiary = beneficiary_or_signer(ctx, beneficiary)?;
+    let to = reward_vault_or_default(ctx, &keys, &beneficiary, to)?;
+
+    send_member_instruction(ctx, |keys, signer| {
+        instruction::claim_reward(keys, &signer.pubkey(), &beneficiary, &keys.stake_pool(pool), &to)
+    })
+}
+
+/// Claims the rewards of all the stakes of the beneficiary in one transaction,
+/// to the `to` account, the member's reward destination or the beneficiary's ATA by default.
+/// The signer acts as the delegate of the `beneficiary` if given.
+pub fn claim_all(ctx: &Context, to: Option<Pubkey>, beneficiary: Option<Pubkey>) -> Result<Value> {
+    let keys = ctx.factory_keys()?;
+    let beneficiary = beneficiary_or_signer(ctx, beneficiary)?;
+    let to = reward_vault_or_default(ctx, &keys, &beneficiary, to)?;
+    let beneficiary_filter = [(offset::MEMBER_STAKE_BENEFICIARY, beneficiary.to_bytes().to_vec())];
+    let stake_pools = ctx.rpc.get_program_accounts::<MemberStake>(&ctx.program_id, &beneficiary_filter)?
+        .iter()
+        .map(|(key, data)| Ok(deserialize::<MemberStake>(key, data)?.stake_pool))
+        .collect::<Result<Vec<Pubkey>>>()?;
+    if stake_pools.is_empty() {
+        return Err(format!("{} has no stakes", beneficiary).into());
+    }
+
+    send_member_instruction(ctx, |keys, signer| {
+        instruction::claim_all(keys, &signer.pubkey(), &beneficiary, &stake_pools, &to)
+    })
+}
+
+/// Stakes the reward in the same position.
+/// The signer acts as the delegate of the `beneficiary` if given.
+pub fn compound(ctx: &Context, pool: &str, beneficiary: Option<Pubkey>) -> Result<Value> {
+    let beneficiary = beneficiary_or_signer(ctx, beneficiary)?;
+
+    send_member_instruction(ctx, |keys, signer| {
+        instruction::compound_reward(keys, &signer.pubkey(), &beneficiary, &keys.stake_pool(pool))
+    })
+}
+
+pub fn unstake(ctx: &Context, pool: &str) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| {
+        instruction::start_unstake_all(keys, &signer.pubkey(), &keys.stake_pool(pool))
+    })
+}
+
+pub fn finish_unstake(ctx: &Context, pool: &str) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| {
+        instruction::finish_unstake_all(keys, &signer.pubkey(), &keys.stake_pool(pool))
+    })
+}
+
+/// Stakes the signer's NFT `mint` from its ATA, weighted by the pool's `weights` CSV if given,
+/// proving the signer is in the `allowlist` CSV of a permissioned pool.
+pub fn stake_nft(ctx: &Context, pool: &str, mint: Pubkey, weights: Option<&Path>, allowlist: Option<&Path>) -> Result<Value> {
+    let (weight, proof) = match weights {
+        Some(path) => read_nft_weights(path)?.proof(&mint).ok_or_else(|| format!("{} has no weight", mint))?,
+        None => (0, vec![]),
+    };
+    let signer = ctx.signer()?.pubkey();
+    let allowlist_proof = match allowlist {
+        Some(path) => read_allowlist(path)?.proof(&signer).ok_or_else(|| format!("{} isn't in the allowlist", signer))?,
+        None => vec![],
+    };
+    let nft_token_program = ctx.rpc.get_account_owner(&mint)?;
+    let keys = ctx.factory_keys()?;
+    let stake_pool = keys.stake_pool(pool);
+    let position_index = fetch_optional::<PositionIndex>(&ctx.rpc, &keys.position_index(&signer))?;
+    let nft_stakes: Vec<Pubkey> = position_index
+        .map(|index| index.positions)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|position| position.kind == PositionKind::Nft && position.stake_pool == stake_pool)
+        .map(|position| position.account)
+        .collect();
+
+    send_member_instruction(ctx, |keys, signer| {
+        let from = get_associated_token_address_with_program_id(&signer.pubkey(), &mint, &nft_token_program);
+        instruction::stake_nft(keys, &signer.pubkey(), &stake_pool, &mint, &nft_token_program, &from, weight, proof, allowlist_proof, &nft_stakes)
+    })
+}
+
+/// Claims the reward of the staked NFT as `claim` does.
+pub fn claim_nft(ctx: &Context, pool: &str, mint: Pubkey, to: Option<Pubkey>, beneficiary: Option<Pubkey>) -> Result<Value> {
+    let keys = ctx.factory_keys()?;
+    let beneficiary = beneficiary_or_signer(ctx, beneficiary)?;
+    let to = reward_vault_or_default(ctx, &keys, &beneficiary, to)?;
+
+    send_member_instruction(ctx, |keys, signer| {
+        instruction::claim_nft_reward(keys, &signer.pubkey(), &beneficiary, &keys.stake_pool(pool), &mint, &to)
+    })
+}
+
+pub fn unstake_nft(ctx: &Context, pool: &str, mint: Pubkey) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| {
+        instruction::start_unstake_nft(keys, &signer.pubkey(), &keys.stake_pool(pool), &mint)
+    })
+}
+
+/// Returns the NFT to the signer's ATA after the unstake delay.
+pub fn finish_unstake_nft(ctx: &Context, pool: &str, mint: Pubkey) -> Result<Value> {
+    let nft_token_program = ctx.rpc.get_account_owner(&mint)?;
+    send_member_instruction(ctx, |keys, signer| {
+        let to = get_associated_token_address_with_program_id(&signer.pubkey(), &mint, &nft_token_program);
+        instruction::finish_unstake_nft(keys, &signer.pubkey(), &keys.stake_pool(pool), &mint, &nft_token_program, &to)
+    })
+}
+
+/// Moves the unstaked tokens to the signer's `to` account, its ATA by default.
+pub fn unstake_and_withdraw(ctx: &Context, pool: &str, to: Option<Pubkey>) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| {
+        let to = to.unwrap_or_else(|| keys.stake_token_account(&signer.pubkey()));
+        instruction::unstake_and_withdraw(keys, &signer.pubkey(), &keys.stake_pool(pool), &to)
+    })
+}
+
+/// Withdraws the `vault_free` tokens to the signer's `to` account, its ATA by default.
+pub fn withdraw(ctx: &Context, to: Option<Pubkey>) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| {
+        let to = to.unwrap_or_else(|| keys.stake_token_account(&signer.pubkey()));
+        instruction::withdraw_all(keys, &signer.pubkey(), &to)
+    })
+}
+
+/// Closes the signer's member, sending the free tokens to the `to` account, its ATA by default.
+pub fn close(ctx: &Context, to: Option<Pubkey>) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| {
+        let to = to.unwrap_or_else(|| keys.stake_token_account(&signer.pubkey()));
+        instruction::close_member(keys, &signer.pubkey(), &to)
+    })
+}
+
+/// Unwraps all the SOL of the free vault to the signer's wallet.
+pub fn withdraw_sol(ctx: &Context) -> Result<Value> {
+    send_member_instruction(ctx, |keys, signer| instruction::withdraw_sol(keys, &signer.pubkey()))
+}
+
+/// The rewards of the position as the program calculates them, via simulation.
+pub fn pending(ctx: &Context, pool: &str, beneficiary: Option<Pubkey>) -> Result<Value> {
+    let keys = ctx.factory_keys()?;
+    let beneficiary = beneficiary_or_signer(ctx, beneficiary)?;
+    let stake_pool = keys.stake_pool(pool);
+    let member_stake = keys.member_stake(&stake_pool, &beneficiary);
+    let pending = ctx.rpc.get_pending_rewards(&keys, &stake_pool, &member_stake, &beneficiary)?;
+
+    Ok(json!({
+        "member_stake": member_stake.to_string(),
+        "gross_reward": pending.gross_reward,
+        "reward_owed": pending.reward_owed,
+        "owner_fee": pending.owner_fee,
+        "net_reward": pending.net_reward,
+        "next_claimable_at": pending.next_claimable_at,
+        "reward_payed_for": pending.reward_payed_for,
+    }))
+}
+
+fn beneficiary_or_signer(ctx: &Context, beneficiary: Option<Pubkey>) -> Result<Pubkey> {
+    match beneficiary {
+        Some(beneficiary) => Ok(beneficiary),
+        None => Ok(ctx.signer()?.pubkey()),
+    }
+}
+
+pub fn list(ctx: &Context) -> Result<Value> {
+    let members = ctx.rpc.get_program_accounts::<Member>(&ctx.program_id, &[])?;
+
+    members.iter()
+        .map(|(key, data)| {
+            let member: Member = deserialize(key, data)?;
+            Ok(json!({
+                "member": key.to_string(),
+                "beneficiary": member.beneficiary.to_string(),
+                "vault_free": member.vault_free.to_string(),
+                "delegate": member.delegate.map(|delegate| delegate.to_string()),
+                "reward_destination": member.reward_destination.map(|destination| destination.to_string()),
+                "auto_claim": member.auto_claim,
+                "open_positions": member.open_positions,
+            }))
+        })
+        .collect::<Result<Vec<Value>>>()
+        .map(Value::Array)
+}
+
+/// The member with all its stakes, pending unstakes and staked NFTs, found from its position index.
+/// The positions of a member opened before the index are found by scanning the program.
+pub fn show(ctx: &Context, beneficiary: Option<Pubkey>) -> Result<Value> {
+    let keys = ctx.factory_keys()?;
+    let beneficiary = beneficiary_or_signer(ctx, beneficiary)?;
+    let indexed = fetch_optional::<PositionIndex>(&ctx.rpc, &keys.position_index(&beneficiary))?.is_some();
+    let portfolio = if indexed {
+        portfolio::fetch_portfolio(&ctx.rpc, &keys, &beneficiary)?
+    } else {
+        let member_key = keys.member(&beneficiary);
+        let positions = portfolio::find_positions(&ctx.rpc, &ctx.program_id, &beneficiary)?;
+        portfolio::fetch_positions(&ctx.rpc, member_key, fetch_member(&ctx.rpc, &member_key)?, &positions)?
+    };
+    let member = &portfolio.member;
+    // A member created by `deposit-and-stake` has no free vault until it's needed.
+    let free = fetch_optional::<anchor_spl::token_interface::TokenAccount>(&ctx.rpc, &member.vault_free)?
+        .map(|vault| vault.amount)
+        .unwrap_or_default();
+    let now = ctx.rpc.get_block_time()?;
+
+    let stakes = portfolio.stakes.iter()
+        .map(|(key, member_stake)| {
+            let stake_pool = fetch_stake_pool(&ctx.rpc, &member_stake.stake_pool)?;
+            let staked = member_stake.staked_tokens;
+            let pending = estimate::pending_rewards(&stake_pool, member_stake, now)?;
+
+            Ok(json!({
+                "member_stake": key.to_string(),
+                "stake_pool": stake_pool.name,
+                "staked": staked,
+                "staked_at": member_stake.staked_at,
+                "reward_payed_for": pending.reward_payed_for,
+                "next_claimable_at": pending.next_claimable_at,
+                "net_reward": pending.net_reward,
+                "reward_owed": member_stake.reward_owed,
+            }))
+        })
+        .collect::<Result<Vec<Value>>>()?;
+
+    let pending_unstakes = portfolio.pending_unstakes.iter()
+        .map(|(key, unstake)| {
+            let stake_pool = fetch_stake_pool(&ctx.rpc, &unstake.stake_pool)?;
+            let amount = fetch_token_account(&ctx.rpc, &unstake.vault_pending_unstake)?.amount;
+
+            Ok(json!({
+                "member_pending_unstake": key.to_string(),
+                "stake_pool": stake_pool.name,
+                "amount": amount,
+                "unstaked_at": unstake.unstaked_at,
+                "withdrawable_at": unstake.unstaked_at.saturating_add(stake_pool.unstake_delay),
+            }))
+        })
+        .collect::<Result<Vec<Value>>>()?;
+
+    let nft_stakes = portfolio.nft_stakes.iter()
+        .map(|(key, nft_stake)| {
+            let stake_pool = fetch_stake_pool(&ctx.rpc, &nft_stake.stake_pool)?;
+
+            Ok(json!({
+                "nft_stake": key.to_string(),
+                "stake_pool": stake_pool.name,
+                "mint": nft_stake.mint.to_string(),
+                "weight": nft_stake.weight,
+                "staked_at": nft_stake.staked_at,
+                "reward_payed_for": nft_stake.reward_payed_for,
+                "reward_owed": nft_stake.reward_owed,
+                "unstaked_at": nft_stake.unstaked_at,
+                "withdrawable_at": nft_stake.unstaked_at.map(|at| at.saturating_add(stake_pool.unstake_delay)),
+            }))
+        })
+        .collect::<Result<Vec<Value>>>()?;
+
+    Ok(json!({
+        "member": portfolio.member_key.to_string(),
+        "beneficiary": beneficiary.to_string(),
+        "vault_free": member.vault_free.to_string(),
+        "delegate": member.delegate.map(|delegate| delegate.to_string()),
+        "reward_destination": member.reward_destination.map(|destination| destination.to_string()),
+        "auto_claim": member.auto_claim,
+        "open_positions": member.open_positions,
+        "pending_unstake_count": member.pending_unstakes,
+        "indexed": indexed,
+        "free": free,
+        "stakes": stakes,
+        "pending_unstakes": pending_unstakes,
+        "nft_stakes": nft_stakes,
+    }))
+}
+
+/// Creates the position index of the signer's member from the positions found on chain.
+pub fn index_positions(ctx: &Context) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let positions = portfolio::find_positions(&ctx.rpc, &ctx.program_id, &signer.pubkey())?;
+    let keys = ctx.factory_keys()?;
+    let signature = ctx.send(&signer, &[instruction::index_positions(&keys, &signer.pubkey(), &positions)])?;
+
+    Ok(json!({
+        "signature": signature.to_string(),
+        "positions": positions.len(),
+    }))
+}
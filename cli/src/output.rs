@@ -0,0 +1,57 @@
+use clap::ValueEnum;
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable `key: value` lines
+    Text,
+    /// Pretty printed JSON for scripting
+    Json,
+}
+
+pub fn print(format: OutputFormat, value: &Value) {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value).expect("JSON value serializes")),
+        OutputFormat::Text => print!("{}", to_text(value, 0)),
+    }
+}
+
+fn to_text(value: &Value, indent: usize) -> String {
+    let padding = "  ".repeat(indent);
+
+    match value {
+        Value::Object(fields) => fields.iter()
+            .map(|(key, value)| match value {
+                Value::Object(_) | Value::Array(_) => format!("{}{}:\n{}", padding, key, to_text(value, indent + 1)),
+                _ => format!("{}{}: {}\n", padding, key, scalar(value)),
+            })
+            .collect(),
+        Value::Array(items) => items.iter()
+            .map(|item| match item {
+                Value::Object(_) | Value::Array(_) => format!("{}-\n{}", padding, to_text(item, indent + 1)),
+                _ => format!("{}- {}\n", padding, scalar(item)),
+            })
+            .collect(),
+        _ => format!("{}{}\n", padding, scalar(value)),
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Null => "-".to_owned(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn nested_values_are_indented() {
+        let text = to_text(&json!({ "name": "fixed", "fee": { "bps": 100, "max": null }, "tags": ["a"] }), 0);
+        assert_eq!(text, "fee:\n  bps: 100\n  max: -\nname: fixed\ntags:\n  - a\n");
+    }
+}
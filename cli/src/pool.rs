@@ -0,0 +1,378 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anchor_lang::prelude::Pubkey;
+use serde_json::{json, Value};
+use solana_sdk::signature::Signer;
+use staking::account::{StakePool, StakePoolMetadata};
+use staking::fee::OwnerFee;
+use staking::limits::StakeLimits;
+use staking::merkle::MerkleNode;
+use staking::nft::NftConfig;
+use staking::reward::Reward;
+use staking_client::account::{deserialize, fetch_optional, fetch_stake_pool, fetch_token_account};
+use staking_client::allowlist::Allowlist;
+use staking_client::nft_weights::NftWeights;
+use staking_client::registry::list_pools;
+use staking_client::{audit, instruction};
+use crate::context::{Context, Result};
+use crate::stats;
+
+/// The reward type given as `fixed:<rate %>` or `unfixed:<tokens per period>`.
+#[derive(Clone, Copy, Debug)]
+pub struct RewardArg(pub Reward);
+
+impl FromStr for RewardArg {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let (kind, data) = value.split_once(':').ok_or("Expected fixed:<rate> or unfixed:<tokens>")?;
+        let data: u128 = data.parse().map_err(|err| format!("Invalid reward data {}: {}", data, err))?;
+
+        match kind {
+            "fixed" => Ok(RewardArg(Reward::Fixed { data })),
+            "unfixed" => Ok(RewardArg(Reward::Unfixed { data })),
+            _ => Err(format!("Unknown reward type {}", kind)),
+        }
+    }
+}
+
+pub struct NewStakePoolArgs {
+    pub name: String,
+    pub reward: Reward,
+    pub ends_at: u64,
+    pub fee_bps: u16,
+    pub max_fee_per_claim: Option<u64>,
+    pub fee_recipient: Option<Pubkey>,
+    pub unstake_delay: u64,
+    pub reward_period: u64,
+    /// The CSV of the beneficiaries allowed to stake, everyone by default.
+    pub allowlist: Option<PathBuf>,
+    pub limits: StakeLimits,
+}
+
+pub fn create(ctx: &Context, args: NewStakePoolArgs) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let stake_pool = keys.stake_pool(&args.name);
+    let pool_id = ctx.factory()?.pool_count + 1;
+    let allowlist_root = args.allowlist.as_deref().map(read_allowlist).transpose()?.map(|allowlist| allowlist.root());
+
+    let signature = ctx.send(&signer, &[instruction::new_stake_pool(&keys, &signer.pubkey(), pool_id, staking::instruction::NewStakePool {
+        name: args.name,
+        reward: args.reward,
+        ends_at: args.ends_at,
+        owner_fee: OwnerFee::new(args.fee_bps, args.max_fee_per_claim)?,
+        fee_recipient: args.fee_recipient.unwrap_or_else(|| signer.pubkey()),
+        unstake_delay: args.unstake_delay,
+        reward_period: args.reward_period,
+        allowlist_root,
+        limits: args.limits,
+    })])?;
+
+    Ok(json!({ "signature": signature.to_string(), "stake_pool": stake_pool.to_string(), "id": pool_id }))
+}
+
+pub fn reward_view(reward: &Reward) -> Value {
+    match *reward {
+        Reward::Fixed { data } => json!({ "type": "fixed", "reward_rate": data.to_string() }),
+        Reward::Unfixed { data } => json!({ "type": "unfixed", "reward_tokens_per_period": data.to_string() }),
+    }
+}
+
+pub fn view(key: &Pubkey, stake_pool: &StakePool) -> Value {
+    let reward = reward_view(&stake_pool.reward_type);
+
+    json!({
+        "stake_pool": key.to_string(),
+        "id": stake_pool.id,
+        "name": stake_pool.name,
+        "reward": reward,
+        "started_at": stake_pool.started_at,
+        "ends_at": stake_pool.ends_at,
+        "total_staked_tokens": stake_pool.total_staked_tokens.to_string(),
+        "owner_fee_bps": stake_pool.owner_fee.bps,
+        "max_owner_fee_per_claim": stake_pool.owner_fee.max_per_claim,
+        "fee_recipient": stake_pool.fee_recipient.to_string(),
+        "vault_fee": stake_pool.vault_fee.to_string(),
+        "unstake_delay": stake_pool.unstake_delay,
+        "reward_period": stake_pool.reward_period,
+        "allowlist_root": stake_pool.allowlist_root.as_ref().map(hex),
+        "max_total_staked": stake_pool.limits.max_total_staked.map(|max| max.to_string()),
+        "max_per_member": stake_pool.limits.max_per_member,
+        "min_stake_amount": stake_pool.limits.min_stake_amount,
+        "partial_payments": stake_pool.partial_payments,
+        "crank_tip_bps": stake_pool.crank_tip_bps,
+        "manager": stake_pool.manager.map(|manager| manager.to_string()),
+        "funded_rewards": stake_pool.funded_rewards,
+        "committed_rewards": stake_pool.committed_rewards.to_string(),
+        "nft": stake_pool.nft.as_ref().map(|nft| json!({
+            "collection": nft.collection.to_string(),
+            "base_weight": nft.base_weight,
+            "weights_root": nft.weights_root.as_ref().map(hex),
+        })),
+    })
+}
+
+pub fn show(ctx: &Context, name: &str) -> Result<Value> {
+    let keys = ctx.factory_keys()?;
+    let key = keys.stake_pool(name);
+    let mut view = view(&key, &fetch_stake_pool(&ctx.rpc, &key)?);
+    let metadata: Option<StakePoolMetadata> = fetch_optional(&ctx.rpc, &keys.stake_pool_metadata(&key))?;
+    view["metadata"] = json!(metadata.map(|metadata| json!({
+        "description_uri": metadata.description_uri,
+        "logo_uri": metadata.logo_uri,
+        "tags": metadata.tags,
+    })));
+
+    Ok(view)
+}
+
+pub fn fetch_all(ctx: &Context) -> Result<Vec<(Pubkey, StakePool)>> {
+    ctx.rpc.get_program_accounts::<StakePool>(&ctx.program_id, &[])?
+        .into_iter()
+        .map(|(key, data)| Ok((key, deserialize(&key, &data)?)))
+        .collect()
+}
+
+/// Lists the pools of the factory's registry with their lifecycle states.
+pub fn list(ctx: &Context) -> Result<Value> {
+    let keys = ctx.factory_keys()?;
+    let pools = list_pools(&ctx.rpc, &keys, ctx.factory()?.pool_count, ctx.rpc.get_block_time()?)?;
+
+    Ok(Value::Array(pools.iter()
+        .map(|pool| json!({
+            "id": pool.entry.id,
+            "stake_pool": pool.entry.stake_pool.to_string(),
+            "name": pool.stake_pool.name,
+            "status": format!("{:?}", pool.status).to_lowercase(),
+            "stake_token_mint": pool.entry.stake_token_mint.to_string(),
+            "reward_token_mint": pool.entry.reward_token_mint.to_string(),
+            "started_at": pool.stake_pool.started_at,
+            "ends_at": pool.stake_pool.ends_at,
+            "closed_at": pool.entry.closed_at,
+        }))
+        .collect()))
+}
+
+/// Adds a pool created before the registry to it.
+pub fn register(ctx: &Context, name: &str) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let key = keys.stake_pool(name);
+    let pool_id = ctx.factory()?.pool_count + 1;
+
+    let signature = ctx.send(&signer, &[instruction::register_stake_pool(&keys, &signer.pubkey(), &key, pool_id)])?;
+
+    Ok(json!({ "signature": signature.to_string(), "id": pool_id }))
+}
+
+/// Marks the ended pool with nothing staked closed in the registry.
+pub fn close(ctx: &Context, name: &str) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let key = keys.stake_pool(name);
+    let stake_pool = fetch_stake_pool(&ctx.rpc, &key)?;
+    if stake_pool.id == 0 {
+        return Err(format!("The pool {} isn't in the registry, register it first", name).into());
+    }
+
+    let signature = ctx.send(&signer, &[instruction::close_stake_pool(&keys, &signer.pubkey(), &key, stake_pool.id)])?;
+
+    Ok(json!({ "signature": signature.to_string(), "id": stake_pool.id }))
+}
+
+/// The total value locked in the pool and how long the factory's reward vault
+/// lasts with every active pool paying at its current rate.
+pub fn stats(ctx: &Context, name: &str) -> Result<Value> {
+    let factory = ctx.factory()?;
+    let key = ctx.factory_keys()?.stake_pool(name);
+    let stake_pool = fetch_stake_pool(&ctx.rpc, &key)?;
+    let stake_pools: Vec<StakePool> = fetch_all(ctx)?.into_iter().map(|(_, pool)| pool).collect();
+    let vault_reward = fetch_token_account(&ctx.rpc, &factory.vault_reward)?.amount;
+    let vault_fee = fetch_token_account(&ctx.rpc, &stake_pool.vault_fee)?.amount;
+    let now = ctx.rpc.get_block_time()?;
+    let runway = stats::reward_runway(vault_reward, &stake_pools, now)?;
+
+    Ok(json!({
+        "stake_pool": key.to_string(),
+        "tvl": stake_pool.total_staked_tokens.to_string(),
+        "reward_per_period": stats::reward_per_period(&stake_pool)?.to_string(),
+        "reward_period": stake_pool.reward_period,
+        "ends_at": stake_pool.ends_at,
+        "accrued_fees": vault_fee,
+        "vault_reward_amount": vault_reward,
+        "reward_runway_seconds": runway,
+        "reward_depleted_at": runway.map(|runway| now.saturating_add(runway)),
+    }))
+}
+
+/// Moves the accrued fees to the fee recipient's `to` account, its ATA by default.
+pub fn harvest_fees(ctx: &Context, name: &str, to: Option<Pubkey>) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let key = keys.stake_pool(name);
+    let stake_pool = fetch_stake_pool(&ctx.rpc, &key)?;
+    let to = to.unwrap_or_else(|| keys.reward_token_account(&stake_pool.fee_recipient));
+    let amount = fetch_token_account(&ctx.rpc, &stake_pool.vault_fee)?.amount;
+
+    let signature = ctx.send(&signer, &[instruction::harvest_fees(&keys, &signer.pubkey(), &key, &to)])?;
+
+    Ok(json!({ "signature": signature.to_string(), "amount": amount }))
+}
+
+/// Checks the invariants of the pool from the fetched accounts.
+/// With `send` the `audit_pool` instruction checks them on chain too,
+/// which fits only as many positions as a transaction does.
+pub fn audit(ctx: &Context, name: &str, send: bool) -> Result<Value> {
+    let keys = ctx.factory_keys()?;
+    let key = keys.stake_pool(name);
+    let positions = audit::fetch_positions(&ctx.rpc, &keys, &key)?;
+    let now = ctx.rpc.get_block_time()?;
+    let audit = audit::audit_pool(&ctx.rpc, &keys, &key, &positions, now)?;
+
+    let signature = if send {
+        let signer = ctx.signer()?;
+        Some(ctx.send(&signer, &[instruction::audit_pool(&keys, &key, &positions.with_vaults())])?.to_string())
+    } else {
+        None
+    };
+
+    let report = &audit.report;
+    Ok(json!({
+        "stake_pool": key.to_string(),
+        "healthy": audit.is_healthy(),
+        "positions": report.positions,
+        "total_staked_tokens": report.total_staked_tokens.to_string(),
+        "staked_in_positions": report.staked_in_positions.to_string(),
+        "short_vaults": report.short_vaults,
+        "accrued_rewards": report.accrued_rewards.to_string(),
+        "vault_reward": report.vault_reward,
+        "available_rewards": report.available_rewards,
+        "committed_rewards": report.committed_rewards.to_string(),
+        "vault_fee": report.vault_fee,
+        "violations": audit.violations.iter().map(|violation| format!("{:?}", violation)).collect::<Vec<_>>(),
+        "invalid_positions": audit.invalid_positions.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+        "signature": signature,
+    }))
+}
+
+/// Deposits reward tokens for the pool from the signer's `from` account, its ATA by default.
+pub fn fund(ctx: &Context, name: &str, amount: u64, from: Option<Pubkey>) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let key = keys.stake_pool(name);
+    let from = from.unwrap_or_else(|| keys.reward_token_account(&signer.pubkey()));
+
+    let signature = ctx.send(&signer, &[instruction::fund_stake_pool(&keys, &signer.pubkey(), &from, &key, amount)])?;
+
+    Ok(json!({ "signature": signature.to_string(), "amount": amount }))
+}
+
+pub fn set_metadata(ctx: &Context, name: &str, description_uri: String, logo_uri: String, tags: Vec<String>) -> Result<Value> {
+    StakePoolMetadata::validate(&description_uri, &logo_uri, &tags)?;
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let key = keys.stake_pool(name);
+
+    let set = instruction::set_pool_metadata(&keys, &signer.pubkey(), &key, description_uri, logo_uri, tags);
+    let signature = ctx.send(&signer, &[set])?;
+
+    Ok(json!({ "signature": signature.to_string(), "metadata": keys.stake_pool_metadata(&key).to_string() }))
+}
+
+/// How many tokens a new member is able to stake in the pool, as the program calculates it.
+pub fn capacity(ctx: &Context, name: &str) -> Result<Value> {
+    let keys = ctx.factory_keys()?;
+    let key = keys.stake_pool(name);
+    let capacity = ctx.rpc.get_stake_capacity(&keys, &key, &ctx.signer()?.pubkey())?;
+
+    Ok(json!({
+        "stake_pool": key.to_string(),
+        "remaining": capacity.remaining.map(|remaining| remaining.to_string()),
+        "max_stake": capacity.max_stake,
+        "min_stake_amount": capacity.min_stake_amount,
+    }))
+}
+
+pub fn read_allowlist(path: &Path) -> Result<Allowlist> {
+    let csv = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read the allowlist {}: {}", path.display(), err))?;
+    Ok(Allowlist::from_csv(&csv)?)
+}
+
+pub fn hex(node: &MerkleNode) -> String {
+    node.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The root of the allowlist CSV and the proof of the `beneficiary`, if given.
+pub fn allowlist(csv: &Path, beneficiary: Option<Pubkey>) -> Result<Value> {
+    let allowlist = read_allowlist(csv)?;
+    let proof = match beneficiary {
+        Some(beneficiary) => {
+            let proof = allowlist.proof(&beneficiary).ok_or_else(|| format!("{} isn't in the allowlist", beneficiary))?;
+            Some(proof.iter().map(hex).collect::<Vec<_>>())
+        },
+        None => None,
+    };
+
+    Ok(json!({ "root": hex(&allowlist.root()), "beneficiaries": allowlist.len(), "proof": proof }))
+}
+
+/// Replaces the allowlist of the pool with the CSV, `None` opens the pool to everyone.
+pub fn set_allowlist(ctx: &Context, name: &str, csv: Option<&Path>) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let key = keys.stake_pool(name);
+    let allowlist_root = csv.map(read_allowlist).transpose()?.map(|allowlist| allowlist.root());
+
+    let signature = ctx.send(&signer, &[instruction::set_allowlist_root(&keys, &signer.pubkey(), &key, allowlist_root)])?;
+
+    Ok(json!({ "signature": signature.to_string(), "allowlist_root": allowlist_root.as_ref().map(hex) }))
+}
+
+pub fn read_nft_weights(path: &Path) -> Result<NftWeights> {
+    let csv = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read the NFT weights {}: {}", path.display(), err))?;
+    Ok(NftWeights::from_csv(&csv)?)
+}
+
+/// Makes the pool accept the NFTs of the `collection`, weighted by the `weights` CSV if given.
+/// `None` turns it back into a pool of the stake tokens.
+pub fn set_nft_config(
+    ctx: &Context,
+    name: &str,
+    collection: Option<Pubkey>,
+    base_weight: u64,
+    weights: Option<&Path>,
+) -> Result<Value> {
+    let signer = ctx.signer()?;
+    let keys = ctx.factory_keys()?;
+    let key = keys.stake_pool(name);
+    let weights_root = weights.map(read_nft_weights).transpose()?.map(|weights| weights.root());
+    let nft = collection.map(|collection| NftConfig { collection, base_weight, weights_root });
+
+    let signature = ctx.send(&signer, &[instruction::set_nft_config(&keys, &signer.pubkey(), &key, nft)])?;
+
+    Ok(json!({ "signature": signature.to_string(), "weights_root": weights_root.as_ref().map(hex) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reward_arg() {
+        assert!(matches!("fixed:10".parse::<RewardArg>().unwrap().0, Reward::Fixed { data: 10 }));
+        assert!(matches!("unfixed:200".parse::<RewardArg>().unwrap().0, Reward::Unfixed { data: 200 }));
+        assert!("fixed".parse::<RewardArg>().is_err());
+        assert!("other:1".parse::<RewardArg>().is_err());
+    }
+
+    #[test]
+    fn hex_node() {
+        let mut node = [0; 32];
+        node[0] = 0xab;
+        node[31] = 0x01;
+        assert_eq!(hex(&node), format!("ab{}01", "0".repeat(60)));
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use anchor_lang::prelude::Pubkey;
+use serde_json::{json, Value};
+use staking::account::{StakePool, ACCOUNT_VERSION};
+use staking::fee::OwnerFee;
+use staking::limits::StakeLimits;
+use staking::reward::Reward;
+use staking::solvency;
+use staking_client::account::{fetch_stake_pool, fetch_token_account};
+use staking_client::simulation::{parse_scenario, Report, Simulation};
+use crate::context::{Context, Result};
+
+/// The parameters of a pool that doesn't exist yet.
+pub struct DraftPoolArgs {
+    pub reward: Reward,
+    pub duration: u64,
+    pub fee_bps: u16,
+    pub max_fee_per_claim: Option<u64>,
+    pub unstake_delay: u64,
+    pub reward_period: u64,
+    pub vault_reward: u64,
+}
+
+pub struct SimulateArgs {
+    pub scenario: PathBuf,
+    /// Replay the scenario against the existing pool and the factory's reward vault.
+    pub pool: Option<String>,
+    pub draft: Option<DraftPoolArgs>,
+    pub members_csv: Option<PathBuf>,
+    pub ledger_csv: Option<PathBuf>,
+}
+
+pub fn draft_pool(args: &DraftPoolArgs) -> Result<StakePool> {
+    Ok(StakePool {
+        name: "draft".to_owned(),
+        reward_type: args.reward,
+        started_at: 0,
+        ends_at: args.duration,
+        total_staked_tokens: 0,
+        bump: 0,
+        owner_fee: OwnerFee::new(args.fee_bps, args.max_fee_per_claim)?,
+        fee_recipient: Pubkey::default(),
+        vault_fee: Pubkey::default(),
+        unstake_delay: args.unstake_delay,
+        reward_period: args.reward_period,
+        allowlist_root: None,
+        limits: StakeLimits::default(),
+        partial_payments: false,
+        crank_tip_bps: 0,
+        nft: None,
+        version: ACCOUNT_VERSION,
+        seed: [0; 32],
+        id: 0,
+        manager: None,
+        funded_rewards: 0,
+        committed_rewards: 0,
+        reserved: [[0; 8]; 4],
+    })
+}
+
+pub fn simulate(ctx: &Context, args: SimulateArgs) -> Result<Value> {
+    let events = parse_scenario(&std::fs::read_to_string(&args.scenario)?)?;
+
+    let simulation = match (&args.pool, &args.draft) {
+        (Some(name), _) => {
+            let factory = ctx.factory()?;
+            let keys = ctx.factory_keys()?;
+            let stake_pool = fetch_stake_pool(&ctx.rpc, &keys.stake_pool(name))?;
+            let vault_reward = fetch_token_account(&ctx.rpc, &keys.vault_reward)?.amount;
+            let available = solvency::available_rewards(&factory, &stake_pool, vault_reward);
+            let committed_rewards = solvency::partition_committed(&factory, &stake_pool);
+            Simulation::new(stake_pool, available).with_committed_rewards(committed_rewards)
+        },
+        (None, Some(draft)) => Simulation::new(draft_pool(draft)?, draft.vault_reward),
+        (None, None) => return Err("Either the pool or its parameters are required".into()),
+    };
+
+    let report = simulation.run(&events)?;
+
+    if let Some(path) = &args.members_csv {
+        report.write_members_csv(create_file(path)?)?;
+    }
+    if let Some(path) = &args.ledger_csv {
+        report.write_ledger_csv(create_file(path)?)?;
+    }
+
+    Ok(view(&report))
+}
+
+fn create_file(path: &Path) -> Result<File> {
+    File::create(path).map_err(|err| format!("Failed to create {}: {}", path.display(), err).into())
+}
+
+pub fn view(report: &Report) -> Value {
+    let members: Vec<Value> = report.members.iter()
+        .map(|member| json!({
+            "member": member.member,
+            "net_reward": member.net_reward,
+            "owner_fee": member.owner_fee,
+            "rounding_dust": member.rounding_dust,
+            "unclaimed_reward": member.unclaimed_reward,
+            "apr_percent": member.apr(),
+            "failed_actions": member.failed_actions,
+        }))
+        .collect();
+
+    json!({
+        "total_net_reward": report.total_net_reward,
+        "total_owner_fee": report.total_owner_fee,
+        "total_rounding_dust": report.total_rounding_dust,
+        "vault_reward_left": report.vault_reward,
+        "vault_depleted_at": report.vault_depleted_at,
+        "members": members,
+    })
+}
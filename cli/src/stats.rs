@@ -0,0 +1,91 @@
+use staking::account::StakePool;
+use staking::math::{mul_div, TryMath, PRECISION};
+use staking::reward::Reward;
+use staking_client::Result;
+
+/// The amount of reward tokens the pool pays to all its members per `reward_period`,
+/// including the owner's fee.
+pub fn reward_per_period(stake_pool: &StakePool) -> Result<u128> {
+    if stake_pool.total_staked_tokens == 0 {
+        return Ok(0);
+    }
+
+    Ok(match stake_pool.reward_type {
+        Reward::Fixed { data: reward_rate } => stake_pool.total_staked_tokens.try_mul(100)?.try_div(reward_rate)?.try_div(100)?,
+        Reward::Unfixed { data: reward_tokens_per_period } => reward_tokens_per_period,
+    })
+}
+
+/// How many seconds the `vault_reward` balance lasts
+/// if every pool active at `now` keeps paying at its current rate.
+/// `None` if no active pool pays any reward.
+pub fn reward_runway(vault_reward: u64, stake_pools: &[StakePool], now: u64) -> Result<Option<u64>> {
+    let mut reward_per_second: u128 = 0; // fixed-point
+
+    for stake_pool in stake_pools.iter().filter(|pool| pool.ends_at > now && pool.reward_period > 0) {
+        let per_second = mul_div(reward_per_period(stake_pool)?, PRECISION, stake_pool.reward_period as u128)?;
+        reward_per_second = reward_per_second.try_add(per_second)?;
+    }
+
+    if reward_per_second == 0 {
+        return Ok(None);
+    }
+
+    let runway = mul_div(vault_reward as u128, PRECISION, reward_per_second)?;
+    Ok(Some(runway.min(u64::MAX as u128) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use staking::account::ACCOUNT_VERSION;
+    use anchor_lang::prelude::Pubkey;
+    use staking::fee::OwnerFee;
+    use staking::limits::StakeLimits;
+
+    fn stake_pool(reward: Reward, total_staked: u128, reward_period: u64, ends_at: u64) -> StakePool {
+        StakePool {
+            name: "test".to_owned(),
+            reward_type: reward,
+            started_at: 0,
+            ends_at,
+            total_staked_tokens: total_staked,
+            bump: 255,
+            owner_fee: OwnerFee::new(0, None).unwrap(),
+            fee_recipient: Pubkey::default(),
+            vault_fee: Pubkey::default(),
+            unstake_delay: 0,
+            reward_period,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id: 0,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        }
+    }
+
+    #[test]
+    fn fixed_pool_pays_pro_rata_staked_tokens() {
+        let pool = stake_pool(Reward::new_fixed(10), 5000, 60, 1000);
+        assert_eq!(reward_per_period(&pool).unwrap(), 500);
+    }
+
+    #[test]
+    fn runway_of_all_active_pools() {
+        let pools = vec![
+            stake_pool(Reward::new_fixed(10), 1000, 10, 1000), // 10 tokens/sec
+            stake_pool(Reward::new_unfixed(300), 1, 30, 1000), // 10 tokens/sec
+            stake_pool(Reward::new_unfixed(300), 1, 30, 100), // ended
+        ];
+
+        assert_eq!(reward_runway(10_000, &pools, 500).unwrap(), Some(500));
+        assert_eq!(reward_runway(10_000, &pools, 1000).unwrap(), None);
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token_interface::TokenAccount;
+use staking::account::*;
+use crate::error::{ClientError, Result};
+use crate::pda;
+
+/// Offsets of the account fields used to filter `getProgramAccounts`.
+/// The offsets include the 8 bytes of the discriminator.
+pub mod offset {
+    pub const MEMBER_BENEFICIARY: usize = 8;
+    pub const MEMBER_STAKE_STAKE_POOL: usize = 8;
+    pub const MEMBER_STAKE_BENEFICIARY: usize = 8 + 32;
+    pub const MEMBER_PENDING_UNSTAKE_STAKE_POOL: usize = 8;
+    pub const MEMBER_PENDING_UNSTAKE_BENEFICIARY: usize = 8 + 32;
+    pub const NFT_STAKE_STAKE_POOL: usize = 8;
+    pub const NFT_STAKE_BENEFICIARY: usize = 8 + 32;
+}
+
+/// A source of raw account data, e.g. the `RpcClient` or a snapshot of accounts.
+pub trait AccountFetcher {
+    /// Returns `None` if the account doesn't exist.
+    fn get_account_data(&self, key: &Pubkey) -> Result<Option<Vec<u8>>>;
+
+    /// Same as `get_account_data` for each of the `keys`, in their order.
+    fn get_multiple_account_data(&self, keys: &[Pubkey]) -> Result<Vec<Option<Vec<u8>>>> {
+        keys.iter().map(|key| self.get_account_data(key)).collect()
+    }
+}
+
+impl AccountFetcher for HashMap<Pubkey, Vec<u8>> {
+    fn get_account_data(&self, key: &Pubkey) -> Result<Option<Vec<u8>>> {
+        Ok(self.get(key).cloned())
+    }
+}
+
+/// Deserializes an account checking its discriminator.
+pub fn deserialize<T: AccountDeserialize>(key: &Pubkey, data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut &data[..]).map_err(|err| ClientError::Deserialize(*key, err.to_string()))
+}
+
+pub fn fetch<T: AccountDeserialize>(fetcher: &impl AccountFetcher, key: &Pubkey) -> Result<T> {
+    let data = fetcher.get_account_data(key)?.ok_or(ClientError::AccountNotFound(*key))?;
+    deserialize(key, &data)
+}
+
+/// Like `fetch`, but returns `None` if the account doesn't exist.
+pub fn fetch_optional<T: AccountDeserialize>(fetcher: &impl AccountFetcher, key: &Pubkey) -> Result<Option<T>> {
+    fetcher.get_account_data(key)?.map(|data| deserialize(key, &data)).transpose()
+}
+
+pub fn fetch_factory(fetcher: &impl AccountFetcher, program_id: &Pubkey) -> Result<Factory> {
+    fetch(fetcher, &pda::factory(program_id).0)
+}
+
+pub fn fetch_stake_pool(fetcher: &impl AccountFetcher, key: &Pubkey) -> Result<StakePool> {
+    fetch(fetcher, key)
+}
+
+pub fn fetch_stake_pool_metadata(fetcher: &impl AccountFetcher, key: &Pubkey) -> Result<StakePoolMetadata> {
+    fetch(fetcher, key)
+}
+
+pub fn fetch_member(fetcher: &impl AccountFetcher, key: &Pubkey) -> Result<Member> {
+    fetch(fetcher, key)
+}
+
+pub fn fetch_member_stake(fetcher: &impl AccountFetcher, key: &Pubkey) -> Result<MemberStake> {
+    fetch(fetcher, key)
+}
+
+pub fn fetch_member_pending_unstake(fetcher: &impl AccountFetcher, key: &Pubkey) -> Result<MemberPendingUnstake> {
+    fetch(fetcher, key)
+}
+
+pub fn fetch_nft_stake(fetcher: &impl AccountFetcher, key: &Pubkey) -> Result<NftStake> {
+    fetch(fetcher, key)
+}
+
+pub fn fetch_token_account(fetcher: &impl AccountFetcher, key: &Pubkey) -> Result<TokenAccount> {
+    fetch(fetcher, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AccountSerialize;
+
+    #[test]
+    fn fetches_a_serialized_account() {
+        let key = Pubkey::new_unique();
+        let member = Member { beneficiary: Pubkey::new_unique(), vault_free: Pubkey::new_unique(), bump: 254, delegate: None, reward_destination: None, auto_claim: false, open_positions: 0,
+            version: ACCOUNT_VERSION, pending_unstakes: 0, reserved: [[0; 4]; 15] };
+        let mut data = Vec::new();
+        member.try_serialize(&mut data).unwrap();
+
+        let accounts: HashMap<Pubkey, Vec<u8>> = vec![(key, data)].into_iter().collect();
+        let fetched = fetch_member(&accounts, &key).unwrap();
+
+        assert_eq!(fetched.beneficiary, member.beneficiary);
+        assert_eq!(fetched.bump, 254);
+        assert!(matches!(fetch_member_stake(&accounts, &key), Err(ClientError::Deserialize(..))));
+        assert!(matches!(fetch_member(&accounts, &Pubkey::new_unique()), Err(ClientError::AccountNotFound(_))));
+    }
+
+    #[test]
+    fn filter_offsets_match_the_layout() {
+        let member_stake = MemberStake {
+            stake_pool: Pubkey::new_unique(),
+            beneficiary: Pubkey::new_unique(),
+            vault_staked: Pubkey::new_unique(),
+            staked_at: 1,
+            reward_payed_for: 2,
+            bump: 3,
+            reward_owed: 0,
+            version: ACCOUNT_VERSION,
+            staked_tokens: 1000,
+            reserved: [[0; 8]; 7],
+        };
+        let mut data = Vec::new();
+        member_stake.try_serialize(&mut data).unwrap();
+
+        let field = |offset: usize| &data[offset..offset + 32];
+        assert_eq!(field(offset::MEMBER_STAKE_STAKE_POOL), member_stake.stake_pool.as_ref());
+        assert_eq!(field(offset::MEMBER_STAKE_BENEFICIARY), member_stake.beneficiary.as_ref());
+    }
+}
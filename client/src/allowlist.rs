@@ -0,0 +1,131 @@
+use std::str::FromStr;
+use anchor_lang::prelude::Pubkey;
+use staking::merkle::{self, MerkleNode};
+use crate::error::{ClientError, Result};
+
+/// The merkle tree of the beneficiaries allowed to stake in a permissioned pool.
+/// The `root` goes to the stake pool, each beneficiary stakes with its `proof`.
+pub struct Allowlist {
+    /// The leaves first, the root last. A node without a pair is moved to the next level as is.
+    levels: Vec<Vec<MerkleNode>>,
+}
+
+impl Allowlist {
+    pub fn new(beneficiaries: &[Pubkey]) -> Result<Allowlist> {
+        let leaves: Vec<MerkleNode> = beneficiaries.iter().map(merkle::leaf).collect();
+        if leaves.is_empty() {
+            return Err(ClientError::InvalidAllowlist("no beneficiaries".to_owned()));
+        }
+
+        Ok(Allowlist { levels: levels(leaves) })
+    }
+
+    /// Reads one beneficiary per line, the first comma separated field.
+    /// Skips empty lines, `#` comments and the `beneficiary` header.
+    pub fn from_csv(csv: &str) -> Result<Allowlist> {
+        let mut beneficiaries = vec![];
+
+        for (index, line) in csv.lines().enumerate() {
+            let field = line.split(',').next().unwrap_or_default().trim();
+            if field.is_empty() || field.starts_with('#') || field == "beneficiary" {
+                continue;
+            }
+
+            let beneficiary = Pubkey::from_str(field)
+                .map_err(|_| ClientError::InvalidAllowlist(format!("line {}: invalid pubkey {}", index + 1, field)))?;
+            beneficiaries.push(beneficiary);
+        }
+
+        Allowlist::new(&beneficiaries)
+    }
+
+    pub fn root(&self) -> MerkleNode {
+        root(&self.levels)
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The proof to pass to `stake`, `None` if the `beneficiary` isn't allowed.
+    pub fn proof(&self, beneficiary: &Pubkey) -> Option<Vec<MerkleNode>> {
+        proof(&self.levels, &merkle::leaf(beneficiary))
+    }
+}
+
+/// The levels of the merkle tree of the non-empty `leaves`, the sorted leaves first and the root last.
+/// A node without a pair is moved to the next level as is.
+pub(crate) fn levels(mut leaves: Vec<MerkleNode>) -> Vec<Vec<MerkleNode>> {
+    leaves.sort_unstable();
+    leaves.dedup();
+
+    let mut levels = vec![leaves];
+    while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+        let next = level.chunks(2)
+            .map(|pair| match pair {
+                [left, right] => merkle::parent(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+        levels.push(next);
+    }
+
+    levels
+}
+
+pub(crate) fn root(levels: &[Vec<MerkleNode>]) -> MerkleNode {
+    levels.last().expect("the tree isn't empty")[0]
+}
+
+/// The proof of the `leaf`, `None` if it isn't in the tree.
+pub(crate) fn proof(levels: &[Vec<MerkleNode>], leaf: &MerkleNode) -> Option<Vec<MerkleNode>> {
+    let mut index = levels[0].binary_search(leaf).ok()?;
+    let mut proof = vec![];
+
+    for level in &levels[..levels.len() - 1] {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        index /= 2;
+    }
+
+    Some(proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_beneficiary_has_a_valid_proof() {
+        for count in 1..=9 {
+            let beneficiaries: Vec<Pubkey> = (0..count).map(|_| Pubkey::new_unique()).collect();
+            let allowlist = Allowlist::new(&beneficiaries).unwrap();
+
+            assert_eq!(allowlist.len(), count);
+            for beneficiary in &beneficiaries {
+                let proof = allowlist.proof(beneficiary).unwrap();
+                assert!(merkle::verify(&allowlist.root(), beneficiary, &proof));
+            }
+            assert!(allowlist.proof(&Pubkey::new_unique()).is_none());
+        }
+    }
+
+    #[test]
+    fn reads_csv() {
+        let [a, b] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let csv = format!("beneficiary,note\n# partners\n{},acme\n\n{}\n{}\n", a, b, a);
+
+        let allowlist = Allowlist::from_csv(&csv).unwrap();
+
+        assert_eq!(allowlist.len(), 2);
+        assert_eq!(allowlist.root(), Allowlist::new(&[b, a]).unwrap().root());
+        assert!(matches!(Allowlist::from_csv("beneficiary\n"), Err(ClientError::InvalidAllowlist(_))));
+        assert!(matches!(Allowlist::from_csv("not-a-key\n"), Err(ClientError::InvalidAllowlist(_))));
+    }
+}
@@ -0,0 +1,216 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token_interface::TokenAccount;
+use staking::account::{MemberStake, NftStake};
+use staking::audit::AuditReport;
+use staking::error::SPError;
+use crate::account::{deserialize, fetch_factory, fetch_optional, fetch_stake_pool, fetch_token_account, offset, AccountFetcher};
+use crate::error::Result;
+use crate::pda::FactoryKeys;
+use crate::rpc::RpcClient;
+
+/// The outcome of the host-side audit. Unlike the `audit_pool` instruction,
+/// it collects every problem instead of failing on the first one.
+#[derive(Debug)]
+pub struct PoolAudit {
+    pub report: AuditReport,
+    pub violations: Vec<SPError>,
+    /// The `MemberStake` and `NftStake` accounts the instruction would reject with `AuditPositionInvalid`.
+    pub invalid_positions: Vec<Pubkey>,
+}
+
+impl PoolAudit {
+    pub fn is_healthy(&self) -> bool {
+        self.violations.is_empty() && self.invalid_positions.is_empty()
+    }
+}
+
+/// The positions of a stake pool, the `NftStake` accounts in an NFT pool.
+#[derive(Default)]
+pub struct PoolPositions {
+    pub stakes: Vec<(Pubkey, MemberStake)>,
+    pub nft_stakes: Vec<(Pubkey, NftStake)>,
+}
+
+impl PoolPositions {
+    /// The positions with their vaults, as the `audit_pool` instruction takes them.
+    pub fn with_vaults(&self) -> Vec<(Pubkey, Pubkey)> {
+        self.stakes.iter().map(|(key, member_stake)| (*key, member_stake.vault_staked))
+            .chain(self.nft_stakes.iter().map(|(key, nft_stake)| (*key, nft_stake.vault_nft)))
+            .collect()
+    }
+
+    /// The positions alone, as the actions settling the rewards of the pool take them.
+    pub fn keys(&self) -> Vec<Pubkey> {
+        self.stakes.iter().map(|(key, _)| *key)
+            .chain(self.nft_stakes.iter().map(|(key, _)| *key))
+            .collect()
+    }
+}
+
+/// Audits the `stake_pool` with the `positions` at `now`
+/// with the same checks the `audit_pool` instruction runs.
+pub fn audit_pool(
+    fetcher: &impl AccountFetcher,
+    keys: &FactoryKeys,
+    stake_pool_key: &Pubkey,
+    positions: &PoolPositions,
+    now: u64,
+) -> Result<PoolAudit> {
+    let factory = fetch_factory(fetcher, &keys.program_id)?;
+    let stake_pool = fetch_stake_pool(fetcher, stake_pool_key)?;
+    let vault_reward = fetch_token_account(fetcher, &keys.vault_reward)?.amount;
+    let vault_fee = fetch_token_account(fetcher, &keys.vault_fee(stake_pool_key))?.amount;
+
+    let mut report = AuditReport::new(*stake_pool_key, &stake_pool, &factory, vault_reward, vault_fee, now);
+    let mut invalid_positions = vec![];
+
+    for (key, member_stake) in &positions.stakes {
+        let vault_staked = fetch_optional::<TokenAccount>(fetcher, &member_stake.vault_staked)?;
+
+        match vault_staked {
+            Some(vault_staked) if member_stake.stake_pool == *stake_pool_key => {
+                report.add_position(&stake_pool, member_stake, vault_staked.amount)?;
+            },
+            _ => invalid_positions.push(*key),
+        }
+    }
+    for (key, nft_stake) in &positions.nft_stakes {
+        let vault_nft = fetch_optional::<TokenAccount>(fetcher, &nft_stake.vault_nft)?;
+
+        match vault_nft {
+            Some(vault_nft) if nft_stake.stake_pool == *stake_pool_key => {
+                report.add_nft_position(&stake_pool, nft_stake, vault_nft.amount)?;
+            },
+            _ => invalid_positions.push(*key),
+        }
+    }
+
+    Ok(PoolAudit { violations: report.violations(), report, invalid_positions })
+}
+
+/// Returns every `MemberStake` and `NftStake` of the `stake_pool`.
+pub fn fetch_positions(rpc: &RpcClient, keys: &FactoryKeys, stake_pool: &Pubkey) -> Result<PoolPositions> {
+    let stake_pool = stake_pool.to_bytes().to_vec();
+    let stakes = rpc.get_program_accounts::<MemberStake>(&keys.program_id, &[(offset::MEMBER_STAKE_STAKE_POOL, stake_pool.clone())])?;
+    let nft_stakes = rpc.get_program_accounts::<NftStake>(&keys.program_id, &[(offset::NFT_STAKE_STAKE_POOL, stake_pool)])?;
+
+    Ok(PoolPositions {
+        stakes: stakes.iter().map(|(key, data)| Ok((*key, deserialize(key, data)?))).collect::<Result<_>>()?,
+        nft_stakes: nft_stakes.iter().map(|(key, data)| Ok((*key, deserialize(key, data)?))).collect::<Result<_>>()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use anchor_lang::AccountSerialize;
+    use anchor_spl::token::spl_token;
+    use anchor_lang::solana_program::program_pack::Pack;
+    use staking::account::{Factory, StakePool, ACCOUNT_VERSION};
+    use staking::fee::OwnerFee;
+    use staking::limits::StakeLimits;
+    use staking::reward::Reward;
+
+    fn token_account(amount: u64) -> Vec<u8> {
+        let account = spl_token::state::Account {
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        };
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        account.pack_into_slice(&mut data);
+        data
+    }
+
+    fn member_stake(stake_pool: Pubkey, vault_staked: Pubkey) -> MemberStake {
+        MemberStake {
+            stake_pool,
+            beneficiary: Pubkey::new_unique(),
+            vault_staked,
+            staked_at: 0,
+            reward_payed_for: 0,
+            bump: 255,
+            reward_owed: 0,
+            version: ACCOUNT_VERSION,
+            staked_tokens: 1000,
+            reserved: [[0; 8]; 7],
+        }
+    }
+
+    #[test]
+    fn collects_every_problem() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let stake_pool_key = keys.stake_pool("fixed");
+        let stake_pool = StakePool {
+            name: "fixed".to_owned(),
+            reward_type: Reward::new_fixed(10),
+            started_at: 0,
+            ends_at: 10_000,
+            total_staked_tokens: 3000,
+            bump: 255,
+            owner_fee: OwnerFee::new(0, None).unwrap(),
+            fee_recipient: Pubkey::default(),
+            vault_fee: keys.vault_fee(&stake_pool_key),
+            unstake_delay: 0,
+            reward_period: 100,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id: 0,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        };
+        let mut stake_pool_data = vec![];
+        stake_pool.try_serialize(&mut stake_pool_data).unwrap();
+
+        let factory = Factory {
+            bump: 255,
+            owner: Pubkey::default(),
+            reward_token_mint: keys.reward_token_mint,
+            stake_token_mint: keys.stake_token_mint,
+            vault_reward: keys.vault_reward,
+            committed_rewards: 300,
+            version: ACCOUNT_VERSION,
+            pool_count: 0,
+            paused: false,
+            timelock_delay: 0,
+            action_count: 0,
+            funded_rewards: 0,
+            reserved: [0; 31],
+        };
+        let mut factory_data = vec![];
+        factory.try_serialize(&mut factory_data).unwrap();
+
+        let vault_staked = Pubkey::new_unique();
+        let mut accounts: HashMap<Pubkey, Vec<u8>> = HashMap::new();
+        accounts.insert(keys.factory, factory_data);
+        accounts.insert(stake_pool_key, stake_pool_data);
+        accounts.insert(keys.vault_reward, token_account(150));
+        accounts.insert(stake_pool.vault_fee, token_account(0));
+        accounts.insert(vault_staked, token_account(1000));
+
+        let positions = PoolPositions {
+            stakes: vec![
+                (Pubkey::new_unique(), member_stake(stake_pool_key, vault_staked)),
+                (Pubkey::new_unique(), member_stake(stake_pool_key, Pubkey::new_unique())),
+                (Pubkey::new_unique(), member_stake(Pubkey::new_unique(), vault_staked)),
+            ],
+            nft_stakes: vec![],
+        };
+
+        let audit = audit_pool(&accounts, &keys, &stake_pool_key, &positions, 200).unwrap();
+
+        assert_eq!(audit.report.positions, 1);
+        assert_eq!(audit.report.accrued_rewards, 200);
+        assert_eq!(audit.invalid_positions, vec![positions.stakes[1].0, positions.stakes[2].0]);
+        assert_eq!(audit.violations.len(), 3);
+        assert!(!audit.is_healthy());
+    }
+}
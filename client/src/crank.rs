@@ -0,0 +1,160 @@
+use anchor_lang::prelude::Pubkey;
+use staking::account::{Member, MemberStake};
+use staking::reward::has_accrued;
+use crate::account::{deserialize, fetch_stake_pool, offset, AccountFetcher};
+use crate::error::Result;
+use crate::pda::FactoryKeys;
+use crate::rpc::RpcClient;
+
+/// A position claimed by the `crank_claims` instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrankPosition {
+    pub stake_pool: Pubkey,
+    pub beneficiary: Pubkey,
+    /// The member's reward destination, or the beneficiary's ATA.
+    pub reward_vault: Pubkey,
+}
+
+impl CrankPosition {
+    pub fn new(keys: &FactoryKeys, stake_pool: Pubkey, member: &Member) -> CrankPosition {
+        CrankPosition {
+            stake_pool,
+            beneficiary: member.beneficiary,
+            reward_vault: member.reward_destination
+                .unwrap_or_else(|| keys.reward_token_account(&member.beneficiary)),
+        }
+    }
+}
+
+/// Picks the `positions` of the `members` opted in to the auto-claims
+/// with a reward to claim at `now`, the ones `crank_claims` doesn't skip.
+pub fn crankable_positions(
+    fetcher: &impl AccountFetcher,
+    keys: &FactoryKeys,
+    members: &[Member],
+    positions: &[MemberStake],
+    now: u64,
+) -> Result<Vec<CrankPosition>> {
+    let mut crankable = vec![];
+
+    for member_stake in positions {
+        let member = match members.iter().find(|member| member.beneficiary == member_stake.beneficiary) {
+            Some(member) if member.auto_claim => member,
+            _ => continue,
+        };
+        let stake_pool = fetch_stake_pool(fetcher, &member_stake.stake_pool)?;
+        if member_stake.reward_owed > 0 || has_accrued(&stake_pool, member_stake, member_stake.staked_tokens, now)? {
+            crankable.push(CrankPosition::new(keys, member_stake.stake_pool, member));
+        }
+    }
+
+    Ok(crankable)
+}
+
+/// Returns every `Member` opted in to the auto-claims.
+/// The members are filtered here, as the offset of `auto_claim` depends on the options before it.
+pub fn fetch_auto_claim_members(rpc: &RpcClient, keys: &FactoryKeys) -> Result<Vec<Member>> {
+    let members = rpc.get_program_accounts::<Member>(&keys.program_id, &[])?
+        .iter()
+        .map(|(key, data)| deserialize::<Member>(key, data))
+        .collect::<Result<Vec<Member>>>()?;
+
+    Ok(members.into_iter().filter(|member| member.auto_claim).collect())
+}
+
+/// Returns every `MemberStake` of the `beneficiary`.
+pub fn fetch_member_positions(rpc: &RpcClient, keys: &FactoryKeys, beneficiary: &Pubkey) -> Result<Vec<MemberStake>> {
+    let filter = [(offset::MEMBER_STAKE_BENEFICIARY, beneficiary.to_bytes().to_vec())];
+
+    rpc.get_program_accounts::<MemberStake>(&keys.program_id, &filter)?
+        .iter()
+        .map(|(key, data)| deserialize(key, data))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use anchor_lang::AccountSerialize;
+    use staking::account::{StakePool, ACCOUNT_VERSION};
+    use staking::fee::OwnerFee;
+    use staking::limits::StakeLimits;
+    use staking::reward::Reward;
+
+    fn member(auto_claim: bool, reward_destination: Option<Pubkey>) -> Member {
+        Member {
+            beneficiary: Pubkey::new_unique(),
+            vault_free: Pubkey::new_unique(),
+            bump: 255,
+            delegate: None,
+            reward_destination,
+            auto_claim,
+            open_positions: 1,
+            version: ACCOUNT_VERSION,
+            pending_unstakes: 0,
+            reserved: [[0; 4]; 15],
+        }
+    }
+
+    fn member_stake(stake_pool: Pubkey, beneficiary: Pubkey, staked_at: u64) -> MemberStake {
+        MemberStake { stake_pool, beneficiary, vault_staked: Pubkey::new_unique(), staked_at, reward_payed_for: 0, bump: 255,
+            reward_owed: 0, version: ACCOUNT_VERSION, staked_tokens: 1000, reserved: [[0; 8]; 7] }
+    }
+
+    #[test]
+    fn picks_opted_in_positions_with_a_reward() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let stake_pool_key = keys.stake_pool("fixed");
+        let stake_pool = StakePool {
+            name: "fixed".to_owned(),
+            reward_type: Reward::new_fixed(10),
+            started_at: 0,
+            ends_at: 10_000,
+            total_staked_tokens: 3000,
+            bump: 255,
+            owner_fee: OwnerFee::new(0, None).unwrap(),
+            fee_recipient: Pubkey::default(),
+            vault_fee: Pubkey::default(),
+            unstake_delay: 0,
+            reward_period: 100,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id: 0,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        };
+        let mut stake_pool_data = vec![];
+        stake_pool.try_serialize(&mut stake_pool_data).unwrap();
+        let mut accounts: HashMap<Pubkey, Vec<u8>> = HashMap::new();
+        accounts.insert(stake_pool_key, stake_pool_data);
+
+        let cold_vault = Pubkey::new_unique();
+        let members = [member(true, Some(cold_vault)), member(false, None), member(true, None)];
+        let positions = [
+            member_stake(stake_pool_key, members[0].beneficiary, 0),
+            member_stake(stake_pool_key, members[1].beneficiary, 0),
+            // The reward period hasn't passed yet.
+            member_stake(stake_pool_key, members[2].beneficiary, 150),
+        ];
+
+        let crankable = crankable_positions(&accounts, &keys, &members, &positions, 200).unwrap();
+
+        assert_eq!(crankable, vec![CrankPosition {
+            stake_pool: stake_pool_key,
+            beneficiary: members[0].beneficiary,
+            reward_vault: cold_vault,
+        }]);
+        assert_eq!(
+            CrankPosition::new(&keys, stake_pool_key, &members[2]).reward_vault,
+            keys.reward_token_account(&members[2].beneficiary),
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::Pubkey;
+use solana_sdk::signature::Signature;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("RPC transport error: {0}")]
+    Transport(String),
+    #[error("RPC error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("Unexpected RPC response: {0}")]
+    UnexpectedResponse(String),
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("Failed to deserialize account {0}: {1}")]
+    Deserialize(Pubkey, String),
+    #[error("Program error: {0}")]
+    Program(String),
+    #[error("Transaction {0} failed: {1}")]
+    TransactionFailed(Signature, String),
+    #[error("Transaction {0} was not confirmed in time")]
+    ConfirmationTimeout(Signature),
+    #[error("Invalid scenario: {0}")]
+    InvalidScenario(String),
+    #[error("Invalid allowlist: {0}")]
+    InvalidAllowlist(String),
+    #[error("Invalid NFT weights: {0}")]
+    InvalidNftWeights(String),
+}
+
+impl From<anchor_lang::error::Error> for ClientError {
+    fn from(err: anchor_lang::error::Error) -> Self {
+        ClientError::Program(err.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
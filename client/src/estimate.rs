@@ -0,0 +1,89 @@
+use anchor_lang::prelude::Pubkey;
+use staking::account::{StakePool, MemberStake, ACCOUNT_VERSION};
+use staking::math::TryMath;
+use staking::reward::PendingRewards;
+use crate::error::Result;
+
+/// Calculates the rewards of an existing `MemberStake` with the same code the program uses.
+pub fn pending_rewards(stake_pool: &StakePool, member_stake: &MemberStake, current_time: u64) -> Result<PendingRewards> {
+    Ok(PendingRewards::calculate(stake_pool, member_stake, member_stake.staked_tokens, current_time)?)
+}
+
+/// Estimates the rewards of a new stake of `amount` tokens made at `staked_at`
+/// and claimed at `claimed_at`, assuming the rest of the pool doesn't change.
+pub fn new_stake_rewards(
+    stake_pool: &StakePool,
+    amount: u64,
+    staked_at: u64,
+    claimed_at: u64,
+) -> Result<PendingRewards> {
+    let mut stake_pool = stake_pool.clone();
+    stake_pool.total_staked_tokens = stake_pool.total_staked_tokens.try_add(amount as u128)?;
+
+    let member_stake = MemberStake {
+        stake_pool: Pubkey::default(),
+        beneficiary: Pubkey::default(),
+        vault_staked: Pubkey::default(),
+        staked_at,
+        reward_payed_for: staked_at,
+        bump: 0,
+        reward_owed: 0,
+        version: ACCOUNT_VERSION,
+        staked_tokens: amount,
+        reserved: [[0; 8]; 7],
+    };
+
+    pending_rewards(&stake_pool, &member_stake, claimed_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use staking::fee::OwnerFee;
+    use staking::limits::StakeLimits;
+    use staking::reward::Reward;
+
+    fn stake_pool(reward: Reward, total_staked: u128) -> StakePool {
+        StakePool {
+            name: "test".to_owned(),
+            reward_type: reward,
+            started_at: 0,
+            ends_at: 10_000,
+            total_staked_tokens: total_staked,
+            bump: 255,
+            owner_fee: OwnerFee::new(500, None).unwrap(),
+            fee_recipient: Pubkey::default(),
+            vault_fee: Pubkey::default(),
+            unstake_delay: 0,
+            reward_period: 100,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id: 0,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        }
+    }
+
+    #[test]
+    fn new_stake_dilutes_unfixed_pool() {
+        // The new stake of 1000 tokens makes half of the pool
+        let pending = new_stake_rewards(&stake_pool(Reward::new_unfixed(400), 1000), 1000, 0, 300).unwrap();
+        assert_eq!(pending.gross_reward, 200);
+        assert_eq!(pending.owner_fee, 10);
+        assert_eq!(pending.net_reward, 190);
+    }
+
+    #[test]
+    fn new_stake_in_fixed_pool() {
+        let pending = new_stake_rewards(&stake_pool(Reward::new_fixed(10), 0), 1000, 0, 150).unwrap();
+        assert_eq!(pending.gross_reward, 100);
+        assert_eq!(pending.next_claimable_at, 100);
+    }
+}
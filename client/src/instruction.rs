@@ -0,0 +1,1041 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::{system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use staking::account::QueuedAction;
+use staking::merkle::MerkleNode;
+use staking::nft::{self, NftConfig};
+use staking::timelock::AdminAction;
+use staking::{accounts, instruction};
+use crate::crank::CrankPosition;
+use crate::migration::AccountKind;
+use crate::pda::{self, FactoryKeys};
+
+fn build(keys: &FactoryKeys, accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: keys.program_id,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Creates the factory. The `initializer` pays for the accounts, the `owner` is the admin holding all the roles.
+pub fn initialize(keys: &FactoryKeys, initializer: &Pubkey, owner: &Pubkey) -> Instruction {
+    build(
+        keys,
+        accounts::Initialize {
+            factory: keys.factory,
+            roles: keys.roles(),
+            reward_token_mint: keys.reward_token_mint,
+            stake_token_mint: keys.stake_token_mint,
+            vault_reward: keys.vault_reward,
+            initializer: *initializer,
+            token_program: keys.reward_token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::Initialize { owner: *owner },
+    )
+}
+
+/// Creates a stake pool named `args.name`, registered under the `pool_id`,
+/// the factory's `pool_count` plus one. The `creator` is the admin or the pool creator.
+pub fn new_stake_pool(keys: &FactoryKeys, creator: &Pubkey, pool_id: u64, args: instruction::NewStakePool) -> Instruction {
+    let stake_pool = keys.stake_pool(&args.name);
+
+    build(
+        keys,
+        accounts::NewStakePool {
+            factory: keys.factory,
+            roles: keys.roles(),
+            stake_pool,
+            pool_entry: keys.pool_entry(pool_id),
+            reward_token_mint: keys.reward_token_mint,
+            vault_fee: keys.vault_fee(&stake_pool),
+            creator: *creator,
+            clock: sysvar::clock::ID,
+            token_program: keys.reward_token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            rent: sysvar::rent::ID,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn create_member(keys: &FactoryKeys, beneficiary: &Pubkey) -> Instruction {
+    let member = keys.member(beneficiary);
+
+    build(
+        keys,
+        accounts::CreateMember {
+            factory: keys.factory,
+            stake_token_mint: keys.stake_token_mint,
+            member,
+            position_index: keys.position_index(beneficiary),
+            vault_free: keys.vault_free(&member),
+            beneficiary: *beneficiary,
+            system_program: system_program::ID,
+            token_program: keys.stake_token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::CreateMember {},
+    )
+}
+
+/// Moves `amount` tokens from the `beneficiary_token_account` to the member's `vault_free`.
+pub fn deposit(keys: &FactoryKeys, beneficiary: &Pubkey, beneficiary_token_account: &Pubkey, amount: u64) -> Instruction {
+    let member = keys.member(beneficiary);
+
+    build(
+        keys,
+        accounts::Deposit {
+            factory: keys.factory,
+            stake_token_mint: keys.stake_token_mint,
+            member,
+            vault_free: keys.vault_free(&member),
+            beneficiary: *beneficiary,
+            beneficiary_token_account: *beneficiary_token_account,
+            system_program: system_program::ID,
+            token_program: keys.stake_token_program,
+        },
+        instruction::Deposit { amount_to_deposit: amount },
+    )
+}
+
+/// Wraps the beneficiary's `lamports` into the member's `vault_free`. The stake tokens must be the wrapped SOL.
+pub fn deposit_sol(keys: &FactoryKeys, beneficiary: &Pubkey, lamports: u64) -> Instruction {
+    let member = keys.member(beneficiary);
+
+    build(
+        keys,
+        accounts::DepositSol {
+            factory: keys.factory,
+            member,
+            vault_free: keys.vault_free(&member),
+            beneficiary: *beneficiary,
+            system_program: system_program::ID,
+            token_program: keys.stake_token_program,
+        },
+        instruction::DepositSol { lamports_to_deposit: lamports },
+    )
+}
+
+/// Allows the `delegate` to act on the member's positions, `None` revokes it.
+pub fn set_delegate(keys: &FactoryKeys, beneficiary: &Pubkey, delegate: Option<Pubkey>) -> Instruction {
+    build(
+        keys,
+        accounts::SetDelegate {
+            factory: keys.factory,
+            member: keys.member(beneficiary),
+            beneficiary: *beneficiary,
+        },
+        instruction::SetDelegate { delegate },
+    )
+}
+
+/// Allows the rewards to be claimed to the `reward_destination` token account, `None` removes it.
+pub fn set_reward_destination(keys: &FactoryKeys, beneficiary: &Pubkey, reward_destination: Option<Pubkey>) -> Instruction {
+    build(
+        keys,
+        accounts::SetRewardDestination {
+            factory: keys.factory,
+            member: keys.member(beneficiary),
+            beneficiary: *beneficiary,
+        },
+        instruction::SetRewardDestination { reward_destination },
+    )
+}
+
+/// Lets anyone claim the member's rewards by `crank_claims`, or stops it.
+pub fn set_auto_claim(keys: &FactoryKeys, beneficiary: &Pubkey, auto_claim: bool) -> Instruction {
+    build(
+        keys,
+        accounts::SetAutoClaim {
+            factory: keys.factory,
+            member: keys.member(beneficiary),
+            beneficiary: *beneficiary,
+        },
+        instruction::SetAutoClaim { auto_claim },
+    )
+}
+
+/// Stakes `amount` tokens from the member's `vault_free`.
+/// The `authority` is the beneficiary or the member's delegate.
+/// The `proof` comes from the pool's `Allowlist`, empty for a pool open to everyone.
+pub fn stake(
+    keys: &FactoryKeys,
+    authority: &Pubkey,
+    beneficiary: &Pubkey,
+    stake_pool: &Pubkey,
+    amount: u64,
+    proof: Vec<MerkleNode>,
+) -> Instruction {
+    let member = keys.member(beneficiary);
+    let member_stake = keys.member_stake(stake_pool, beneficiary);
+
+    build(
+        keys,
+        accounts::Stake {
+            factory: keys.factory,
+            stake_token_mint: keys.stake_token_mint,
+            vault_reward: keys.vault_reward,
+            stake_pool: *stake_pool,
+            beneficiary: *beneficiary,
+            member,
+            position_index: keys.position_index(beneficiary),
+            authority: *authority,
+            vault_free: keys.vault_free(&member),
+            member_stake,
+            vault_staked: keys.vault_staked(&member_stake),
+            clock: sysvar::clock::ID,
+            token_program: keys.stake_token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            rent: sysvar::rent::ID,
+            system_program: system_program::ID,
+        },
+        instruction::Stake { tokens_to_stake_amount: amount, proof },
+    )
+}
+
+/// Stakes `amount` tokens straight from the `beneficiary_token_account`, creating the member on the first use.
+pub fn deposit_and_stake(
+    keys: &FactoryKeys,
+    beneficiary: &Pubkey,
+    stake_pool: &Pubkey,
+    beneficiary_token_account: &Pubkey,
+    amount: u64,
+    proof: Vec<MerkleNode>,
+) -> Instruction {
+    let member_stake = keys.member_stake(stake_pool, beneficiary);
+
+    build(
+        keys,
+        accounts::DepositAndStake {
+            factory: keys.factory,
+            stake_token_mint: keys.stake_token_mint,
+            vault_reward: keys.vault_reward,
+            stake_pool: *stake_pool,
+            beneficiary: *beneficiary,
+            member: keys.member(beneficiary),
+            position_index: keys.position_index(beneficiary),
+            beneficiary_token_account: *beneficiary_token_account,
+            member_stake,
+            vault_staked: keys.vault_staked(&member_stake),
+            clock: sysvar::clock::ID,
+            token_program: keys.stake_token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            rent: sysvar::rent::ID,
+            system_program: system_program::ID,
+        },
+        instruction::DepositAndStake { tokens_to_stake_amount: amount, proof },
+    )
+}
+
+/// Moves `amount` reward tokens from the `funder`'s `vault_funder` to the factory's `vault_reward`.
+/// The `funder` is the admin or the reward funder.
+pub fn deposit_reward(keys: &FactoryKeys, funder: &Pubkey, vault_funder: &Pubkey, amount: u64) -> Instruction {
+    build(
+        keys,
+        accounts::DepositReward {
+            factory: keys.factory,
+            reward_token_mint: keys.reward_token_mint,
+            roles: keys.roles(),
+            funder: *funder,
+            vault_funder: *vault_funder,
+            vault_reward: keys.vault_reward,
+            system_program: system_program::ID,
+            token_program: keys.reward_token_program,
+        },
+        instruction::DepositReward { reward_amount: amount },
+    )
+}
+
+/// Moves `amount` reward tokens from the `authority`'s `vault_authority` to the factory's `vault_reward`
+/// on behalf of the `stake_pool`. The `authority` is the admin or the pool's manager.
+pub fn fund_stake_pool(keys: &FactoryKeys, authority: &Pubkey, vault_authority: &Pubkey, stake_pool: &Pubkey, amount: u64) -> Instruction {
+    build(
+        keys,
+        accounts::FundStakePool {
+            factory: keys.factory,
+            reward_token_mint: keys.reward_token_mint,
+            stake_pool: *stake_pool,
+            authority: *authority,
+            vault_authority: *vault_authority,
+            vault_reward: keys.vault_reward,
+            token_program: keys.reward_token_program,
+        },
+        instruction::FundStakePool { amount },
+    )
+}
+
+/// Claims the reward to the `beneficiary_reward_vault`,
+/// owned by the beneficiary or set as the member's reward destination.
+/// The `authority` is the beneficiary or the member's delegate.
+pub fn claim_reward(
+    keys: &FactoryKeys,
+    authority: &Pubkey,
+    beneficiary: &Pubkey,
+    stake_pool: &Pubkey,
+    beneficiary_reward_vault: &Pubkey,
+) -> Instruction {
+    let member_stake = keys.member_stake(stake_pool, beneficiary);
+
+    build(
+        keys,
+        accounts::ClaimReward {
+            factory: keys.factory,
+            reward_token_mint: keys.reward_token_mint,
+            stake_pool: *stake_pool,
+            member_stake,
+            member: keys.member(beneficiary),
+            authority: *authority,
+            vault_reward: keys.vault_reward,
+            beneficiary: *beneficiary,
+            beneficiary_reward_vault: *beneficiary_reward_vault,
+            vault_fee: keys.vault_fee(stake_pool),
+            token_program: keys.reward_token_program,
+            clock: sysvar::clock::ID,
+        },
+        instruction::ClaimReward {},
+    )
+}
+
+/// Claims the rewards of the beneficiary's positions in the `stake_pools` to the `beneficiary_reward_vault` at once.
+/// The `authority` is the beneficiary or the member's delegate.
+pub fn claim_all(
+    keys: &FactoryKeys,
+    authority: &Pubkey,
+    beneficiary: &Pubkey,
+    stake_pools: &[Pubkey],
+    beneficiary_reward_vault: &Pubkey,
+) -> Instruction {
+    let mut instruction = build(
+        keys,
+        accounts::ClaimAll {
+            factory: keys.factory,
+            reward_token_mint: keys.reward_token_mint,
+            member: keys.member(beneficiary),
+            authority: *authority,
+            beneficiary: *beneficiary,
+            vault_reward: keys.vault_reward,
+            beneficiary_reward_vault: *beneficiary_reward_vault,
+            token_program: keys.reward_token_program,
+            clock: sysvar::clock::ID,
+        },
+        instruction::ClaimAll {},
+    );
+    instruction.accounts.extend(stake_pools.iter().flat_map(|stake_pool| {
+        let member_stake = keys.member_stake(stake_pool, beneficiary);
+        [
+            AccountMeta::new(*stake_pool, false),
+            AccountMeta::new(member_stake, false),
+            AccountMeta::new(keys.vault_fee(stake_pool), false),
+        ]
+    }));
+
+    instruction
+}
+
+/// Claims the rewards of the `positions` opted in to the auto-claims.
+/// The `cranker` receives the crank tips to the `cranker_reward_vault`.
+pub fn crank_claims(
+    keys: &FactoryKeys,
+    cranker: &Pubkey,
+    cranker_reward_vault: &Pubkey,
+    positions: &[CrankPosition],
+) -> Instruction {
+    let mut instruction = build(
+        keys,
+        accounts::CrankClaims {
+            factory: keys.factory,
+            reward_token_mint: keys.reward_token_mint,
+            vault_reward: keys.vault_reward,
+            cranker: *cranker,
+            cranker_reward_vault: *cranker_reward_vault,
+            token_program: keys.reward_token_program,
+            clock: sysvar::clock::ID,
+        },
+        instruction::CrankClaims {},
+    );
+    instruction.accounts.extend(positions.iter().flat_map(|position| {
+        let member_stake = keys.member_stake(&position.stake_pool, &position.beneficiary);
+        [
+            AccountMeta::new(position.stake_pool, false),
+            AccountMeta::new_readonly(keys.member(&position.beneficiary), false),
+            AccountMeta::new(member_stake, false),
+            AccountMeta::new(keys.vault_fee(&position.stake_pool), false),
+            AccountMeta::new(position.reward_vault, false),
+        ]
+    }));
+
+    instruction
+}
+
+/// Stakes the reward in the same position.
+/// The `authority` is the beneficiary or the member's delegate.
+pub fn compound_reward(keys: &FactoryKeys, authority: &Pubkey, beneficiary: &Pubkey, stake_pool: &Pubkey) -> Instruction {
+    let member_stake = keys.member_stake(stake_pool, beneficiary);
+
+    build(
+        keys,
+        accounts::CompoundReward {
+            factory: keys.factory,
+            reward_token_mint: keys.reward_token_mint,
+            stake_pool: *stake_pool,
+            member_stake,
+            member: keys.member(beneficiary),
+            authority: *authority,
+            beneficiary: *beneficiary,
+            vault_staked: keys.vault_staked(&member_stake),
+            vault_reward: keys.vault_reward,
+            vault_fee: keys.vault_fee(stake_pool),
+            token_program: keys.reward_token_program,
+            clock: sysvar::clock::ID,
+        },
+        instruction::CompoundReward {},
+    )
+}
+
+/// Moves the accrued fees of the `stake_pool` to the `fee_recipient_vault`.
+/// The `authority` is either the factory owner or the pool's fee recipient.
+pub fn harvest_fees(
+    keys: &FactoryKeys,
+    authority: &Pubkey,
+    stake_pool: &Pubkey,
+    fee_recipient_vault: &Pubkey,
+) -> Instruction {
+    build(
+        keys,
+        accounts::HarvestFees {
+            factory: keys.factory,
+            reward_token_mint: keys.reward_token_mint,
+            stake_pool: *stake_pool,
+            authority: *authority,
+            vault_fee: keys.vault_fee(stake_pool),
+            fee_recipient_vault: *fee_recipient_vault,
+            token_program: keys.reward_token_program,
+        },
+        instruction::HarvestFees {},
+    )
+}
+
+/// Stops the stakes and the claims. The `pauser` is the admin or the pauser.
+/// The pause is lifted by the `AdminAction::Unpause` queued action.
+pub fn pause(keys: &FactoryKeys, pauser: &Pubkey) -> Instruction {
+    build(
+        keys,
+        accounts::Pause {
+            factory: keys.factory,
+            roles: keys.roles(),
+            pauser: *pauser,
+        },
+        instruction::Pause {},
+    )
+}
+
+/// The stake pool of the pool actions as the remaining account.
+fn with_action_stake_pool(mut instruction: Instruction, action: &AdminAction) -> Instruction {
+    if let Some(stake_pool) = action.stake_pool() {
+        instruction.accounts.push(AccountMeta::new(stake_pool, false));
+    }
+    instruction
+}
+
+/// Queues the `action` with the `id`, the factory's `action_count` + 1, executable from the `eta`.
+/// The `proposer` pays for the queued action until it's executed or cancelled.
+pub fn queue_action(keys: &FactoryKeys, proposer: &Pubkey, id: u64, action: AdminAction, eta: u64) -> Instruction {
+    let instruction = build(
+        keys,
+        accounts::QueueAction {
+            factory: keys.factory,
+            roles: keys.roles(),
+            queued_action: keys.queued_action(id),
+            proposer: *proposer,
+            clock: sysvar::clock::ID,
+            system_program: system_program::ID,
+        },
+        instruction::QueueAction { action, eta },
+    );
+    with_action_stake_pool(instruction, &action)
+}
+
+/// Cancels the queued action. The `authority` is its proposer or the admin.
+pub fn cancel_action(keys: &FactoryKeys, authority: &Pubkey, queued_action: &QueuedAction) -> Instruction {
+    build(
+        keys,
+        accounts::CancelAction {
+            factory: keys.factory,
+            queued_action: keys.queued_action(queued_action.id),
+            proposer: queued_action.proposer,
+            authority: *authority,
+        },
+        instruction::CancelAction {},
+    )
+}
+
+/// Executes the queued action once its `eta` has come, while its proposer still may propose it. Signed by anyone.
+/// The actions settling the rewards of the pool take all its `positions`, `PoolPositions::keys`.
+pub fn execute_action(keys: &FactoryKeys, queued_action: &QueuedAction, positions: &[Pubkey]) -> Instruction {
+    let instruction = build(
+        keys,
+        accounts::ExecuteAction {
+            factory: keys.factory,
+            roles: keys.roles(),
+            queued_action: keys.queued_action(queued_action.id),
+            proposer: queued_action.proposer,
+            vault_reward: keys.vault_reward,
+            clock: sysvar::clock::ID,
+        },
+        instruction::ExecuteAction {},
+    );
+    let mut instruction = with_action_stake_pool(instruction, &queued_action.action);
+    instruction.accounts.extend(positions.iter().map(|position| AccountMeta::new(*position, false)));
+
+    instruction
+}
+
+/// Replaces the allowlist of the `stake_pool`, `None` opens it to everyone.
+pub fn set_allowlist_root(keys: &FactoryKeys, authority: &Pubkey, stake_pool: &Pubkey, allowlist_root: Option<MerkleNode>) -> Instruction {
+    build(
+        keys,
+        accounts::SetAllowlistRoot {
+            factory: keys.factory,
+            stake_pool: *stake_pool,
+            authority: *authority,
+        },
+        instruction::SetAllowlistRoot { allowlist_root },
+    )
+}
+
+pub fn set_nft_config(keys: &FactoryKeys, authority: &Pubkey, stake_pool: &Pubkey, nft: Option<NftConfig>) -> Instruction {
+    build(
+        keys,
+        accounts::SetNftConfig {
+            factory: keys.factory,
+            stake_pool: *stake_pool,
+            authority: *authority,
+        },
+        instruction::SetNftConfig { nft },
+    )
+}
+
+/// Adds a stake pool created before the registry to it under the `pool_id`, the factory's `pool_count` plus one.
+pub fn register_stake_pool(keys: &FactoryKeys, creator: &Pubkey, stake_pool: &Pubkey, pool_id: u64) -> Instruction {
+    build(
+        keys,
+        accounts::RegisterStakePool {
+            factory: keys.factory,
+            roles: keys.roles(),
+            stake_pool: *stake_pool,
+            pool_entry: keys.pool_entry(pool_id),
+            creator: *creator,
+            system_program: system_program::ID,
+        },
+        instruction::RegisterStakePool {},
+    )
+}
+
+/// Marks the ended and empty stake pool registered under the `pool_id` closed.
+pub fn close_stake_pool(keys: &FactoryKeys, owner: &Pubkey, stake_pool: &Pubkey, pool_id: u64) -> Instruction {
+    build(
+        keys,
+        accounts::CloseStakePool {
+            factory: keys.factory,
+            stake_pool: *stake_pool,
+            pool_entry: keys.pool_entry(pool_id),
+            owner: *owner,
+            clock: sysvar::clock::ID,
+        },
+        instruction::CloseStakePool {},
+    )
+}
+
+/// Sets the description and logo URIs and the tags of the pool, creating its metadata account paid by the `authority`,
+/// the admin or the pool's manager.
+pub fn set_pool_metadata(
+    keys: &FactoryKeys,
+    authority: &Pubkey,
+    stake_pool: &Pubkey,
+    description_uri: String,
+    logo_uri: String,
+    tags: Vec<String>,
+) -> Instruction {
+    build(
+        keys,
+        accounts::SetPoolMetadata {
+            factory: keys.factory,
+            stake_pool: *stake_pool,
+            metadata: keys.stake_pool_metadata(stake_pool),
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        instruction::SetPoolMetadata { description_uri, logo_uri, tags },
+    )
+}
+
+/// The read-only instruction that returns the `StakeCapacity` of the `stake_pool`.
+pub fn get_stake_capacity(keys: &FactoryKeys, stake_pool: &Pubkey) -> Instruction {
+    build(
+        keys,
+        accounts::GetStakeCapacity {
+            factory: keys.factory,
+            stake_pool: *stake_pool,
+        },
+        instruction::GetStakeCapacity {},
+    )
+}
+
+/// The read-only instruction that returns `PendingRewards` of the `member_stake`.
+pub fn get_pending_rewards(keys: &FactoryKeys, stake_pool: &Pubkey, member_stake: &Pubkey) -> Instruction {
+    build(
+        keys,
+        accounts::GetPendingRewards {
+            factory: keys.factory,
+            stake_pool: *stake_pool,
+            member_stake: *member_stake,
+            clock: sysvar::clock::ID,
+        },
+        instruction::GetPendingRewards {},
+    )
+}
+
+/// The read-only instruction that checks the invariants of the `stake_pool`
+/// with the `positions` given as `(member_stake, vault_staked)` pairs.
+pub fn audit_pool(keys: &FactoryKeys, stake_pool: &Pubkey, positions: &[(Pubkey, Pubkey)]) -> Instruction {
+    let mut instruction = build(
+        keys,
+        accounts::AuditPool {
+            factory: keys.factory,
+            stake_pool: *stake_pool,
+            vault_reward: keys.vault_reward,
+            vault_fee: keys.vault_fee(stake_pool),
+            clock: sysvar::clock::ID,
+        },
+        instruction::AuditPool {},
+    );
+    instruction.accounts.extend(positions.iter().flat_map(|(member_stake, vault_staked)| [
+        AccountMeta::new_readonly(*member_stake, false),
+        AccountMeta::new_readonly(*vault_staked, false),
+    ]));
+
+    instruction
+}
+
+pub fn start_unstake_all(keys: &FactoryKeys, beneficiary: &Pubkey, stake_pool: &Pubkey) -> Instruction {
+    let member_stake = keys.member_stake(stake_pool, beneficiary);
+
+    build(
+        keys,
+        accounts::StartUnstakeAll {
+            factory: keys.factory,
+            stake_token_mint: keys.stake_token_mint,
+            stake_pool: *stake_pool,
+            beneficiary: *beneficiary,
+            member: keys.member(beneficiary),
+            position_index: keys.position_index(beneficiary),
+            member_stake,
+            vault_staked: keys.vault_staked(&member_stake),
+            member_pending_unstake: keys.member_pending_unstake(stake_pool, beneficiary),
+            vault_pending_unstake: keys.vault_pending_unstake(stake_pool),
+            clock: sysvar::clock::ID,
+            token_program: keys.stake_token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            rent: sysvar::rent::ID,
+            system_program: system_program::ID,
+        },
+        instruction::StartUnstakeAll {},
+    )
+}
+
+pub fn finish_unstake_all(keys: &FactoryKeys, beneficiary: &Pubkey, stake_pool: &Pubkey) -> Instruction {
+    let member = keys.member(beneficiary);
+    let member_stake = keys.member_stake(stake_pool, beneficiary);
+
+    build(
+        keys,
+        accounts::FinishUnstakeAll {
+            factory: keys.factory,
+            stake_token_mint: keys.stake_token_mint,
+            stake_pool: *stake_pool,
+            beneficiary: *beneficiary,
+            member,
+            position_index: keys.position_index(beneficiary),
+            vault_free: keys.vault_free(&member),
+            member_stake,
+            vault_staked: keys.vault_staked(&member_stake),
+            member_pending_unstake: keys.member_pending_unstake(stake_pool, beneficiary),
+            vault_pending_unstake: keys.vault_pending_unstake(stake_pool),
+            clock: sysvar::clock::ID,
+            token_program: keys.stake_token_program,
+            system_program: system_program::ID,
+        },
+        instruction::FinishUnstakeAll {},
+    )
+}
+
+/// Stakes the NFT `mint`, owned by the `nft_token_program`, held by the `beneficiary_nft_account` in the NFT pool.
+/// The `weight` and its `proof` are required if the pool has the weights, ignored otherwise,
+/// the `allowlist_proof` of the beneficiary if the pool has the allowlist.
+/// The `nft_stakes` are the member's other `NftStake` positions of the pool in the order of its position index.
+#[allow(clippy::too_many_arguments)]
+pub fn stake_nft(
+    keys: &FactoryKeys,
+    beneficiary: &Pubkey,
+    stake_pool: &Pubkey,
+    mint: &Pubkey,
+    nft_token_program: &Pubkey,
+    beneficiary_nft_account: &Pubkey,
+    weight: u64,
+    proof: Vec<MerkleNode>,
+    allowlist_proof: Vec<MerkleNode>,
+    nft_stakes: &[Pubkey],
+) -> Instruction {
+    let nft_stake = keys.nft_stake(stake_pool, mint);
+
+    let mut instruction = build(
+        keys,
+        accounts::StakeNft {
+            factory: keys.factory,
+            vault_reward: keys.vault_reward,
+            stake_pool: *stake_pool,
+            beneficiary: *beneficiary,
+            member: keys.member(beneficiary),
+            position_index: keys.position_index(beneficiary),
+            nft_mint: *mint,
+            nft_metadata: nft::metadata_address(mint),
+            beneficiary_nft_account: *beneficiary_nft_account,
+            nft_stake,
+            vault_nft: pda::vault_nft(&nft_stake, mint, nft_token_program),
+            clock: sysvar::clock::ID,
+            token_program: *nft_token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            rent: sysvar::rent::ID,
+            system_program: system_program::ID,
+        },
+        instruction::StakeNft { weight, proof, allowlist_proof },
+    );
+    instruction.accounts.extend(nft_stakes.iter().map(|nft_stake| AccountMeta::new_readonly(*nft_stake, false)));
+
+    instruction
+}
+
+/// Claims the reward of the staked NFT `mint` as `claim_reward` does.
+pub fn claim_nft_reward(
+    keys: &FactoryKeys,
+    authority: &Pubkey,
+    beneficiary: &Pubkey,
+    stake_pool: &Pubkey,
+    mint: &Pubkey,
+    beneficiary_reward_vault: &Pubkey,
+) -> Instruction {
+    build(
+        keys,
+        accounts::ClaimNftReward {
+            factory: keys.factory,
+            reward_token_mint: keys.reward_token_mint,
+            stake_pool: *stake_pool,
+            nft_stake: keys.nft_stake(stake_pool, mint),
+            member: keys.member(beneficiary),
+            authority: *authority,
+            vault_reward: keys.vault_reward,
+            beneficiary: *beneficiary,
+            beneficiary_reward_vault: *beneficiary_reward_vault,
+            vault_fee: keys.vault_fee(stake_pool),
+            token_program: keys.reward_token_program,
+            clock: sysvar::clock::ID,
+        },
+        instruction::ClaimNftReward {},
+    )
+}
+
+pub fn start_unstake_nft(keys: &FactoryKeys, beneficiary: &Pubkey, stake_pool: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(
+        keys,
+        accounts::StartUnstakeNft {
+            factory: keys.factory,
+            stake_pool: *stake_pool,
+            beneficiary: *beneficiary,
+            nft_stake: keys.nft_stake(stake_pool, mint),
+            clock: sysvar::clock::ID,
+        },
+        instruction::StartUnstakeNft {},
+    )
+}
+
+/// Returns the NFT `mint` to the `beneficiary_nft_account` after the unstake delay.
+pub fn finish_unstake_nft(
+    keys: &FactoryKeys,
+    beneficiary: &Pubkey,
+    stake_pool: &Pubkey,
+    mint: &Pubkey,
+    nft_token_program: &Pubkey,
+    beneficiary_nft_account: &Pubkey,
+) -> Instruction {
+    let nft_stake = keys.nft_stake(stake_pool, mint);
+
+    build(
+        keys,
+        accounts::FinishUnstakeNft {
+            factory: keys.factory,
+            stake_pool: *stake_pool,
+            beneficiary: *beneficiary,
+            member: keys.member(beneficiary),
+            position_index: keys.position_index(beneficiary),
+            nft_stake,
+            nft_mint: *mint,
+            vault_nft: pda::vault_nft(&nft_stake, mint, nft_token_program),
+            beneficiary_nft_account: *beneficiary_nft_account,
+            clock: sysvar::clock::ID,
+            token_program: *nft_token_program,
+            system_program: system_program::ID,
+        },
+        instruction::FinishUnstakeNft {},
+    )
+}
+
+/// Moves the unstaked tokens to the `beneficiary_token_account` after the unstake delay.
+pub fn unstake_and_withdraw(
+    keys: &FactoryKeys,
+    beneficiary: &Pubkey,
+    stake_pool: &Pubkey,
+    beneficiary_token_account: &Pubkey,
+) -> Instruction {
+    let member_stake = keys.member_stake(stake_pool, beneficiary);
+
+    build(
+        keys,
+        accounts::UnstakeAndWithdraw {
+            factory: keys.factory,
+            stake_token_mint: keys.stake_token_mint,
+            stake_pool: *stake_pool,
+            beneficiary: *beneficiary,
+            member: keys.member(beneficiary),
+            position_index: keys.position_index(beneficiary),
+            beneficiary_token_account: *beneficiary_token_account,
+            member_stake,
+            vault_staked: keys.vault_staked(&member_stake),
+            member_pending_unstake: keys.member_pending_unstake(stake_pool, beneficiary),
+            vault_pending_unstake: keys.vault_pending_unstake(stake_pool),
+            clock: sysvar::clock::ID,
+            token_program: keys.stake_token_program,
+            system_program: system_program::ID,
+        },
+        instruction::UnstakeAndWithdraw {},
+    )
+}
+
+/// Closes the member without positions, moving the tokens left in its `vault_free` to the `beneficiary_token_account`.
+pub fn close_member(keys: &FactoryKeys, beneficiary: &Pubkey, beneficiary_token_account: &Pubkey) -> Instruction {
+    let member = keys.member(beneficiary);
+
+    build(
+        keys,
+        accounts::CloseMember {
+            factory: keys.factory,
+            stake_token_mint: keys.stake_token_mint,
+            member,
+            position_index: keys.position_index(beneficiary),
+            vault_free: keys.vault_free(&member),
+            beneficiary: *beneficiary,
+            beneficiary_token_account: *beneficiary_token_account,
+            system_program: system_program::ID,
+            token_program: keys.stake_token_program,
+        },
+        instruction::CloseMember {},
+    )
+}
+
+/// Creates the position index of a member opened before the index with all its `positions`:
+/// the `MemberStake`, `MemberPendingUnstake` and `NftStake` accounts of the beneficiary.
+pub fn index_positions(keys: &FactoryKeys, beneficiary: &Pubkey, positions: &[Pubkey]) -> Instruction {
+    let member = keys.member(beneficiary);
+    let mut instruction = build(
+        keys,
+        accounts::IndexPositions {
+            factory: keys.factory,
+            beneficiary: *beneficiary,
+            member,
+            position_index: keys.position_index(beneficiary),
+            system_program: system_program::ID,
+        },
+        instruction::IndexPositions {},
+    );
+    instruction.accounts.extend(positions.iter().map(|position| AccountMeta::new_readonly(*position, false)));
+
+    instruction
+}
+
+/// Moves all the tokens from the member's `vault_free` to the `beneficiary_token_account`.
+pub fn withdraw_all(keys: &FactoryKeys, beneficiary: &Pubkey, beneficiary_token_account: &Pubkey) -> Instruction {
+    let member = keys.member(beneficiary);
+
+    build(
+        keys,
+        accounts::WithdrawAll {
+            factory: keys.factory,
+            stake_token_mint: keys.stake_token_mint,
+            member,
+            vault_free: keys.vault_free(&member),
+            beneficiary: *beneficiary,
+            beneficiary_token_account: *beneficiary_token_account,
+            system_program: system_program::ID,
+            token_program: keys.stake_token_program,
+        },
+        instruction::WithdrawAll {},
+    )
+}
+
+/// Unwraps all the SOL of the member's `vault_free` to the beneficiary's wallet.
+pub fn withdraw_sol(keys: &FactoryKeys, beneficiary: &Pubkey) -> Instruction {
+    let member = keys.member(beneficiary);
+
+    build(
+        keys,
+        accounts::WithdrawSol {
+            factory: keys.factory,
+            stake_token_mint: keys.stake_token_mint,
+            member,
+            vault_free: keys.vault_free(&member),
+            vault_unwrap: pda::vault_unwrap(&keys.program_id, &member).0,
+            beneficiary: *beneficiary,
+            system_program: system_program::ID,
+            token_program: keys.stake_token_program,
+            rent: sysvar::rent::ID,
+        },
+        instruction::WithdrawSol {},
+    )
+}
+
+/// Upgrades the version 0 `account` of the `kind` to the current layout. The `payer` pays the rent of the added space.
+/// Takes the `keys` of `migration::factory_keys` as the factory itself may not be readable before its migration.
+/// The `remaining_accounts` are the ones `AccountKind::remaining_accounts` tells.
+pub fn migrate(keys: &FactoryKeys, payer: &Pubkey, account: &Pubkey, kind: AccountKind, remaining_accounts: &[AccountMeta]) -> Instruction {
+    let mut accounts = match kind {
+        AccountKind::StakePool => accounts::MigrateStakePool {
+            stake_pool: *account,
+            factory: keys.factory,
+            reward_token_mint: keys.reward_token_mint,
+            vault_fee: keys.vault_fee(account),
+            payer: *payer,
+            token_program: keys.reward_token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        AccountKind::Factory => accounts::MigrateFactory {
+            factory: *account,
+            roles: keys.roles(),
+            payer: *payer,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        _ => accounts::MigrateAccount {
+            account: *account,
+            payer: *payer,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+    };
+    let data = match kind {
+        AccountKind::Factory => instruction::MigrateFactory {}.data(),
+        AccountKind::StakePool => instruction::MigrateStakePool {}.data(),
+        AccountKind::Member => instruction::MigrateMember {}.data(),
+        AccountKind::MemberStake => instruction::MigrateMemberStake {}.data(),
+        AccountKind::MemberPendingUnstake => instruction::MigrateMemberPendingUnstake {}.data(),
+    };
+    accounts.extend_from_slice(remaining_accounts);
+
+    Instruction { program_id: keys.program_id, accounts, data }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::hash::hash;
+
+    #[test]
+    fn stake_instruction_layout() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let beneficiary = Pubkey::new_unique();
+        let stake_pool = keys.stake_pool("fixed");
+
+        let ix = stake(&keys, &beneficiary, &beneficiary, &stake_pool, 42, vec![[7; 32]]);
+
+        assert_eq!(ix.program_id, staking::ID);
+        assert_eq!(&ix.data[..8], &hash(b"global:stake").to_bytes()[..8]);
+        assert_eq!(&ix.data[8..16], &42u64.to_le_bytes()[..]);
+        assert_eq!(&ix.data[16..20], &1u32.to_le_bytes()[..]);
+        assert_eq!(&ix.data[20..], &[7; 32][..]);
+
+        let signers: Vec<Pubkey> = ix.accounts.iter().filter(|a| a.is_signer).map(|a| a.pubkey).collect();
+        assert_eq!(signers, vec![beneficiary]);
+        assert!(ix.accounts.iter().any(|a| a.pubkey == keys.member_stake(&stake_pool, &beneficiary) && a.is_writable));
+    }
+
+    #[test]
+    fn delegate_signs_for_the_beneficiary() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let [delegate, beneficiary] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let stake_pool = keys.stake_pool("fixed");
+
+        let ix = claim_reward(&keys, &delegate, &beneficiary, &stake_pool, &Pubkey::new_unique());
+
+        let signers: Vec<Pubkey> = ix.accounts.iter().filter(|a| a.is_signer).map(|a| a.pubkey).collect();
+        assert_eq!(signers, vec![delegate]);
+        assert!(ix.accounts.iter().any(|a| a.pubkey == keys.member_stake(&stake_pool, &beneficiary)));
+    }
+
+    #[test]
+    fn claim_all_appends_writable_positions() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let beneficiary = Pubkey::new_unique();
+        let stake_pools = [keys.stake_pool("fixed"), keys.stake_pool("unfixed")];
+
+        let ix = claim_all(&keys, &beneficiary, &beneficiary, &stake_pools, &Pubkey::new_unique());
+
+        let positions = &ix.accounts[ix.accounts.len() - 6..];
+        assert_eq!(positions[3].pubkey, stake_pools[1]);
+        assert_eq!(positions[4].pubkey, keys.member_stake(&stake_pools[1], &beneficiary));
+        assert!(positions.iter().all(|a| a.is_writable));
+    }
+
+    #[test]
+    fn stake_nft_escrows_the_nft_in_its_stake() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let [beneficiary, mint] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let stake_pool = keys.stake_pool("nft");
+
+        let staked = keys.nft_stake(&stake_pool, &Pubkey::new_unique());
+        let ix = stake_nft(&keys, &beneficiary, &stake_pool, &mint, &anchor_spl::token::ID, &Pubkey::new_unique(), 3, vec![], vec![], &[staked]);
+
+        let nft_stake = keys.nft_stake(&stake_pool, &mint);
+        assert!(ix.accounts.iter().any(|a| a.pubkey == nft_stake && a.is_writable));
+        assert!(ix.accounts.iter().any(|a| a.pubkey == pda::vault_nft(&nft_stake, &mint, &anchor_spl::token::ID) && a.is_writable));
+        assert!(ix.accounts.iter().any(|a| a.pubkey == nft::metadata_address(&mint) && !a.is_writable));
+        assert_eq!(ix.accounts.last(), Some(&AccountMeta::new_readonly(staked, false)));
+    }
+
+    #[test]
+    fn get_pending_rewards_is_read_only() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let stake_pool = keys.stake_pool("fixed");
+        let member_stake = keys.member_stake(&stake_pool, &Pubkey::new_unique());
+
+        let ix = get_pending_rewards(&keys, &stake_pool, &member_stake);
+
+        assert!(ix.accounts.iter().all(|a| !a.is_writable && !a.is_signer));
+    }
+
+    #[test]
+    fn audit_pool_appends_positions() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let stake_pool = keys.stake_pool("fixed");
+        let position = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let ix = audit_pool(&keys, &stake_pool, &[position]);
+
+        let tail: Vec<Pubkey> = ix.accounts.iter().rev().take(2).map(|a| a.pubkey).collect();
+        assert_eq!(tail, vec![position.1, position.0]);
+        assert!(ix.accounts.iter().all(|a| !a.is_writable && !a.is_signer));
+    }
+}
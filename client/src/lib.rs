@@ -0,0 +1,26 @@
+//! The Rust client of the staking program.
+//!
+//! Derives the program addresses, builds the instructions, the allowlists and the NFT weights,
+//! fetches the accounts and the portfolios of the members, lists the pools of the factory's registry and its queued admin actions, finds the positions to crank,
+//! estimates and simulates the rewards offline with the same code the program runs on chain,
+//! and finds the accounts to migrate to the current layout.
+pub mod account;
+pub mod allowlist;
+pub mod audit;
+pub mod crank;
+pub mod error;
+pub mod estimate;
+pub mod instruction;
+pub mod migration;
+pub mod nft_weights;
+pub mod pda;
+pub mod portfolio;
+pub mod registry;
+pub mod rpc;
+pub mod simulation;
+pub mod timelock;
+
+pub use error::{ClientError, Result};
+pub use pda::FactoryKeys;
+pub use rpc::RpcClient;
+pub use staking::ID as PROGRAM_ID;
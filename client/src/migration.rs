@@ -0,0 +1,157 @@
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use staking::account::*;
+use staking::migration::v0;
+use crate::account::AccountFetcher;
+use crate::error::{ClientError, Result};
+use crate::pda::{self, FactoryKeys};
+use crate::rpc::RpcClient;
+
+/// The versioned accounts the `migrate_*` instructions upgrade.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccountKind {
+    Factory,
+    StakePool,
+    Member,
+    MemberStake,
+    MemberPendingUnstake,
+}
+
+impl AccountKind {
+    pub const ALL: [AccountKind; 5] = [
+        AccountKind::Factory,
+        AccountKind::StakePool,
+        AccountKind::Member,
+        AccountKind::MemberStake,
+        AccountKind::MemberPendingUnstake,
+    ];
+
+    /// The space of the version 0 layout, without the discriminator.
+    pub fn v0_space(self) -> usize {
+        match self {
+            AccountKind::Factory => v0::Factory::SPACE,
+            AccountKind::StakePool => v0::StakePool::SPACE,
+            AccountKind::Member => v0::Member::SPACE,
+            AccountKind::MemberStake => v0::MemberStake::SPACE,
+            AccountKind::MemberPendingUnstake => v0::MemberPendingUnstake::SPACE,
+        }
+    }
+
+    pub fn discriminator(self) -> [u8; 8] {
+        match self {
+            AccountKind::Factory => Factory::discriminator(),
+            AccountKind::StakePool => StakePool::discriminator(),
+            AccountKind::Member => Member::discriminator(),
+            AccountKind::MemberStake => MemberStake::discriminator(),
+            AccountKind::MemberPendingUnstake => MemberPendingUnstake::discriminator(),
+        }
+    }
+
+    /// Checks the `data` is an account of this kind still in the version 0 layout.
+    pub fn is_v0(self, data: &[u8]) -> bool {
+        data.len() == 8 + self.v0_space() && data[..8] == self.discriminator()
+    }
+
+    /// The accounts the `migrate_*` instruction of the version 0 account `key` with the `data` takes besides it.
+    /// The positions are counted in their member, which is migrated before them.
+    pub fn remaining_accounts(self, program_id: &Pubkey, key: &Pubkey, data: &[u8]) -> Result<Vec<AccountMeta>> {
+        let member = |beneficiary: &Pubkey| pda::member(program_id, &pda::factory(program_id).0, beneficiary).0;
+
+        match self {
+            AccountKind::MemberStake => {
+                let member_stake = v0::MemberStake::deserialize(&mut &data[8..])
+                    .map_err(|err| ClientError::Deserialize(*key, err.to_string()))?;
+                Ok(vec![
+                    AccountMeta::new_readonly(member_stake.vault_staked, false),
+                    AccountMeta::new(member(&member_stake.beneficiary), false),
+                ])
+            },
+            AccountKind::MemberPendingUnstake => {
+                let unstake = v0::MemberPendingUnstake::deserialize(&mut &data[8..])
+                    .map_err(|err| ClientError::Deserialize(*key, err.to_string()))?;
+                Ok(vec![AccountMeta::new(member(&unstake.beneficiary), false)])
+            },
+            AccountKind::Factory | AccountKind::StakePool | AccountKind::Member => Ok(vec![]),
+        }
+    }
+}
+
+/// A version 0 account to migrate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct V0Account {
+    pub key: Pubkey,
+    pub kind: AccountKind,
+    /// The accounts its `migrate_*` instruction takes besides it.
+    pub remaining_accounts: Vec<AccountMeta>,
+}
+
+/// Finds the accounts of the `program_id` to migrate, the factory first.
+pub fn find_v0_accounts(rpc: &RpcClient, program_id: &Pubkey) -> Result<Vec<V0Account>> {
+    let mut found = vec![];
+    for kind in AccountKind::ALL {
+        let accounts = match kind {
+            AccountKind::Factory => rpc.get_program_accounts::<Factory>(program_id, &[])?,
+            AccountKind::StakePool => rpc.get_program_accounts::<StakePool>(program_id, &[])?,
+            AccountKind::Member => rpc.get_program_accounts::<Member>(program_id, &[])?,
+            AccountKind::MemberStake => rpc.get_program_accounts::<MemberStake>(program_id, &[])?,
+            AccountKind::MemberPendingUnstake => rpc.get_program_accounts::<MemberPendingUnstake>(program_id, &[])?,
+        };
+        for (key, data) in accounts.into_iter().filter(|(_, data)| kind.is_v0(data)) {
+            let remaining_accounts = kind.remaining_accounts(program_id, &key, &data)?;
+            found.push(V0Account { key, kind, remaining_accounts });
+        }
+    }
+
+    Ok(found)
+}
+
+/// The keys of the factory whether it's migrated or not, as the current layout starts with the fields of the version 0.
+pub fn factory_keys(rpc: &RpcClient, program_id: &Pubkey) -> Result<FactoryKeys> {
+    let key = pda::factory(program_id).0;
+    let data = rpc.get_account_data(&key)?.ok_or(ClientError::AccountNotFound(key))?;
+    let factory = v0::Factory::deserialize(&mut data.get(8..).unwrap_or_default())
+        .map_err(|err| ClientError::Deserialize(key, err.to_string()))?;
+    let stake_token_program = rpc.get_account_owner(&factory.stake_token_mint)?;
+    let reward_token_program = rpc.get_account_owner(&factory.reward_token_mint)?;
+
+    Ok(FactoryKeys::new(*program_id, factory.stake_token_mint, factory.reward_token_mint)
+        .with_token_programs(stake_token_program, reward_token_program))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v0_accounts_are_told_by_their_length() {
+        let mut data = Member::discriminator().to_vec();
+        data.resize(8 + v0::Member::SPACE, 0);
+        assert!(AccountKind::Member.is_v0(&data));
+        assert!(!AccountKind::MemberStake.is_v0(&data));
+
+        data.resize(8 + Member::SPACE, 0);
+        assert!(!AccountKind::Member.is_v0(&data));
+    }
+
+    #[test]
+    fn v0_positions_are_migrated_with_their_member() {
+        let (beneficiary, vault_staked) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = MemberStake::discriminator().to_vec();
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(beneficiary.as_ref());
+        data.extend_from_slice(vault_staked.as_ref());
+        data.resize(8 + v0::MemberStake::SPACE, 0);
+
+        let key = Pubkey::new_unique();
+        let member = pda::member(&staking::ID, &pda::factory(&staking::ID).0, &beneficiary).0;
+        assert_eq!(
+            AccountKind::MemberStake.remaining_accounts(&staking::ID, &key, &data).unwrap(),
+            vec![AccountMeta::new_readonly(vault_staked, false), AccountMeta::new(member, false)],
+        );
+        assert_eq!(
+            AccountKind::MemberPendingUnstake.remaining_accounts(&staking::ID, &key, &data).unwrap(),
+            vec![AccountMeta::new(member, false)],
+        );
+        assert!(AccountKind::Member.remaining_accounts(&staking::ID, &key, &data).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use anchor_lang::prelude::Pubkey;
+use staking::merkle::{self, MerkleNode};
+use crate::allowlist::{levels, proof, root};
+use crate::error::{ClientError, Result};
+
+/// The merkle tree of the weights of the NFTs an NFT pool accepts, e.g. derived from their traits.
+/// The `root` goes to the pool's `NftConfig`, each NFT is staked with its weight and `proof`.
+pub struct NftWeights {
+    weights: HashMap<Pubkey, u64>,
+    levels: Vec<Vec<MerkleNode>>,
+}
+
+impl NftWeights {
+    pub fn new(weights: &[(Pubkey, u64)]) -> Result<NftWeights> {
+        let mut by_mint = HashMap::new();
+        for (mint, weight) in weights {
+            if *weight == 0 {
+                return Err(ClientError::InvalidNftWeights(format!("{} has no weight", mint)));
+            }
+            if by_mint.insert(*mint, *weight).filter(|previous| previous != weight).is_some() {
+                return Err(ClientError::InvalidNftWeights(format!("{} has two weights", mint)));
+            }
+        }
+        if by_mint.is_empty() {
+            return Err(ClientError::InvalidNftWeights("no NFTs".to_owned()));
+        }
+
+        let leaves = by_mint.iter().map(|(mint, weight)| merkle::weight_leaf(mint, *weight)).collect();
+        Ok(NftWeights { weights: by_mint, levels: levels(leaves) })
+    }
+
+    /// Reads a `mint,weight` pair per line. Skips empty lines, `#` comments and the `mint` header.
+    pub fn from_csv(csv: &str) -> Result<NftWeights> {
+        let mut weights = vec![];
+
+        for (index, line) in csv.lines().enumerate() {
+            let mut fields = line.split(',').map(str::trim);
+            let mint = fields.next().unwrap_or_default();
+            if mint.is_empty() || mint.starts_with('#') || mint == "mint" {
+                continue;
+            }
+
+            let invalid = |what: &str| ClientError::InvalidNftWeights(format!("line {}: {}", index + 1, what));
+            let mint = Pubkey::from_str(mint).map_err(|_| invalid(&format!("invalid pubkey {}", mint)))?;
+            let weight = fields.next().unwrap_or_default();
+            let weight = weight.parse().map_err(|_| invalid(&format!("invalid weight {}", weight)))?;
+            weights.push((mint, weight));
+        }
+
+        NftWeights::new(&weights)
+    }
+
+    pub fn root(&self) -> MerkleNode {
+        root(&self.levels)
+    }
+
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The weight and the proof to pass to `stake_nft`, `None` if the `mint` has no weight.
+    pub fn proof(&self, mint: &Pubkey) -> Option<(u64, Vec<MerkleNode>)> {
+        let weight = *self.weights.get(mint)?;
+        Some((weight, proof(&self.levels, &merkle::weight_leaf(mint, weight))?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_nft_has_a_valid_proof() {
+        let csv: String = (1..=5).map(|weight| format!("{},{}\n", Pubkey::new_unique(), weight)).collect();
+        let weights = NftWeights::from_csv(&format!("mint,weight\n# rarity\n{}", csv)).unwrap();
+
+        assert_eq!(weights.len(), 5);
+        for line in csv.lines() {
+            let mint = Pubkey::from_str(line.split(',').next().unwrap()).unwrap();
+            let (weight, proof) = weights.proof(&mint).unwrap();
+            assert!(merkle::verify_leaf(&weights.root(), merkle::weight_leaf(&mint, weight), &proof));
+        }
+        assert!(weights.proof(&Pubkey::new_unique()).is_none());
+    }
+
+    #[test]
+    fn rejects_wrong_weights() {
+        let mint = Pubkey::new_unique();
+
+        assert!(matches!(NftWeights::new(&[(mint, 0)]), Err(ClientError::InvalidNftWeights(_))));
+        assert!(matches!(NftWeights::new(&[(mint, 1), (mint, 2)]), Err(ClientError::InvalidNftWeights(_))));
+        assert!(NftWeights::new(&[(mint, 2), (mint, 2)]).is_ok());
+        assert!(matches!(NftWeights::from_csv(&format!("{},x\n", mint)), Err(ClientError::InvalidNftWeights(_))));
+    }
+}
@@ -0,0 +1,279 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token::spl_token;
+use staking::account::{Factory, FactoryRoles, Member, PoolEntry, PositionIndex, QueuedAction, StakePool, StakePoolMetadata};
+
+/// The program derived address of the `Factory`.
+pub fn factory(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[Factory::PDA_SEED], program_id)
+}
+
+/// The program derived address of the `FactoryRoles`.
+pub fn roles(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[Factory::PDA_SEED, FactoryRoles::PDA_SEED], program_id)
+}
+
+/// The program derived address of a `StakePool` with the `name`.
+pub fn stake_pool(program_id: &Pubkey, factory: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[factory.as_ref(), &StakePool::seed_for(name)], program_id)
+}
+
+/// The address of a `StakePool` created before the pools were derived from the hash of the name.
+pub fn legacy_stake_pool(program_id: &Pubkey, factory: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[factory.as_ref(), name.as_bytes()], program_id)
+}
+
+/// The program derived address of the registry entry of the stake pool with the `id`.
+pub fn pool_entry(program_id: &Pubkey, factory: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[factory.as_ref(), PoolEntry::PDA_SEED, &id.to_le_bytes()], program_id)
+}
+
+/// The program derived address of the queued admin action with the `id`.
+pub fn queued_action(program_id: &Pubkey, factory: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[factory.as_ref(), QueuedAction::PDA_SEED, &id.to_le_bytes()], program_id)
+}
+
+/// The program derived address of the `StakePoolMetadata` of the `stake_pool`.
+pub fn stake_pool_metadata(program_id: &Pubkey, stake_pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[stake_pool.as_ref(), StakePoolMetadata::PDA_SEED], program_id)
+}
+
+/// The program derived address of the `Member` of the `beneficiary`.
+pub fn member(program_id: &Pubkey, factory: &Pubkey, beneficiary: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[beneficiary.as_ref(), factory.as_ref()], program_id)
+}
+
+/// The program derived address of the `PositionIndex` of the `member`.
+pub fn position_index(program_id: &Pubkey, member: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[member.as_ref(), PositionIndex::PDA_SEED], program_id)
+}
+
+/// The program derived address of the `MemberStake` of the `member` in the `stake_pool`.
+pub fn member_stake(program_id: &Pubkey, stake_pool: &Pubkey, member: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[stake_pool.as_ref(), member.as_ref()], program_id)
+}
+
+/// The program derived address of the `MemberPendingUnstake` of the `member_stake`.
+pub fn member_pending_unstake(program_id: &Pubkey, stake_pool: &Pubkey, member_stake: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[stake_pool.as_ref(), member_stake.as_ref()], program_id)
+}
+
+/// The program derived address of the `NftStake` of the NFT `mint` in the `stake_pool`.
+pub fn nft_stake(program_id: &Pubkey, stake_pool: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[stake_pool.as_ref(), mint.as_ref()], program_id)
+}
+
+/// The escrow vault holding the NFT of the `NftStake`, owned by the `token_program` of the NFT `mint`.
+pub fn vault_nft(nft_stake: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(nft_stake, mint, token_program)
+}
+
+/// The transit token account `withdraw_sol` unwraps the SOL of the `member` with.
+pub fn vault_unwrap(program_id: &Pubkey, member: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[Member::UNWRAP_SEED, member.as_ref()], program_id)
+}
+
+/// The vault with the reward tokens of the factory.
+pub fn vault_reward(factory: &Pubkey, reward_token_mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(factory, reward_token_mint, token_program)
+}
+
+/// The vault where the owner's fees of the stake pool are accrued.
+pub fn vault_fee(stake_pool: &Pubkey, reward_token_mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(stake_pool, reward_token_mint, token_program)
+}
+
+/// The `vault_free` of the member.
+pub fn vault_free(member: &Pubkey, stake_token_mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(member, stake_token_mint, token_program)
+}
+
+/// The vault with the staked tokens of the `MemberStake`.
+pub fn vault_staked(member_stake: &Pubkey, stake_token_mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(member_stake, stake_token_mint, token_program)
+}
+
+/// The vault with the tokens waiting for `unstake_delay` to pass.
+pub fn vault_pending_unstake(stake_pool: &Pubkey, stake_token_mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(stake_pool, stake_token_mint, token_program)
+}
+
+/// The addresses every instruction of the factory depends on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FactoryKeys {
+    pub program_id: Pubkey,
+    pub factory: Pubkey,
+    pub stake_token_mint: Pubkey,
+    pub reward_token_mint: Pubkey,
+    /// The token program owning the `stake_token_mint`, the SPL Token or the Token-2022 program.
+    pub stake_token_program: Pubkey,
+    /// The token program owning the `reward_token_mint`.
+    pub reward_token_program: Pubkey,
+    pub vault_reward: Pubkey,
+}
+
+impl FactoryKeys {
+    /// The keys of the factory of the mints owned by the SPL Token program.
+    pub fn new(program_id: Pubkey, stake_token_mint: Pubkey, reward_token_mint: Pubkey) -> FactoryKeys {
+        let (factory, _) = factory(&program_id);
+
+        FactoryKeys {
+            program_id,
+            factory,
+            stake_token_mint,
+            reward_token_mint,
+            stake_token_program: spl_token::ID,
+            reward_token_program: spl_token::ID,
+            vault_reward: vault_reward(&factory, &reward_token_mint, &spl_token::ID),
+        }
+    }
+
+    /// The same keys for the mints owned by the given token programs, e.g. the Token-2022 program.
+    pub fn with_token_programs(self, stake_token_program: Pubkey, reward_token_program: Pubkey) -> FactoryKeys {
+        FactoryKeys {
+            stake_token_program,
+            reward_token_program,
+            vault_reward: vault_reward(&self.factory, &self.reward_token_mint, &reward_token_program),
+            ..self
+        }
+    }
+
+    /// Collects the keys from the fetched `Factory` account and the owners of its mints.
+    pub fn from_account(
+        program_id: Pubkey,
+        factory_account: &Factory,
+        stake_token_program: Pubkey,
+        reward_token_program: Pubkey,
+    ) -> FactoryKeys {
+        FactoryKeys {
+            program_id,
+            factory: factory(&program_id).0,
+            stake_token_mint: factory_account.stake_token_mint,
+            reward_token_mint: factory_account.reward_token_mint,
+            stake_token_program,
+            reward_token_program,
+            vault_reward: factory_account.vault_reward,
+        }
+    }
+
+    pub fn roles(&self) -> Pubkey {
+        roles(&self.program_id).0
+    }
+
+    /// The associated token account of the stake tokens of the `owner`.
+    pub fn stake_token_account(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, &self.stake_token_mint, &self.stake_token_program)
+    }
+
+    /// The associated token account of the reward tokens of the `owner`.
+    pub fn reward_token_account(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, &self.reward_token_mint, &self.reward_token_program)
+    }
+
+    pub fn vault_fee(&self, stake_pool: &Pubkey) -> Pubkey {
+        vault_fee(stake_pool, &self.reward_token_mint, &self.reward_token_program)
+    }
+
+    pub fn vault_free(&self, member: &Pubkey) -> Pubkey {
+        vault_free(member, &self.stake_token_mint, &self.stake_token_program)
+    }
+
+    pub fn vault_staked(&self, member_stake: &Pubkey) -> Pubkey {
+        vault_staked(member_stake, &self.stake_token_mint, &self.stake_token_program)
+    }
+
+    pub fn vault_pending_unstake(&self, stake_pool: &Pubkey) -> Pubkey {
+        vault_pending_unstake(stake_pool, &self.stake_token_mint, &self.stake_token_program)
+    }
+
+    pub fn stake_pool(&self, name: &str) -> Pubkey {
+        stake_pool(&self.program_id, &self.factory, name).0
+    }
+
+    pub fn pool_entry(&self, id: u64) -> Pubkey {
+        pool_entry(&self.program_id, &self.factory, id).0
+    }
+
+    pub fn queued_action(&self, id: u64) -> Pubkey {
+        queued_action(&self.program_id, &self.factory, id).0
+    }
+
+    pub fn stake_pool_metadata(&self, stake_pool: &Pubkey) -> Pubkey {
+        stake_pool_metadata(&self.program_id, stake_pool).0
+    }
+
+    pub fn member(&self, beneficiary: &Pubkey) -> Pubkey {
+        member(&self.program_id, &self.factory, beneficiary).0
+    }
+
+    pub fn position_index(&self, beneficiary: &Pubkey) -> Pubkey {
+        position_index(&self.program_id, &self.member(beneficiary)).0
+    }
+
+    pub fn member_stake(&self, stake_pool: &Pubkey, beneficiary: &Pubkey) -> Pubkey {
+        member_stake(&self.program_id, stake_pool, &self.member(beneficiary)).0
+    }
+
+    pub fn member_pending_unstake(&self, stake_pool: &Pubkey, beneficiary: &Pubkey) -> Pubkey {
+        member_pending_unstake(&self.program_id, stake_pool, &self.member_stake(stake_pool, beneficiary)).0
+    }
+
+    pub fn nft_stake(&self, stake_pool: &Pubkey, mint: &Pubkey) -> Pubkey {
+        nft_stake(&self.program_id, stake_pool, mint).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_spl::associated_token::get_associated_token_address;
+    use anchor_spl::token_2022::spl_token_2022;
+
+    #[test]
+    fn member_stake_is_derived_from_the_member() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let beneficiary = Pubkey::new_unique();
+        let stake_pool = keys.stake_pool("fixed");
+
+        let (member, _) = member(&staking::ID, &keys.factory, &beneficiary);
+        let (member_stake, _) = member_stake(&staking::ID, &stake_pool, &member);
+
+        assert_eq!(keys.member(&beneficiary), member);
+        assert_eq!(keys.member_stake(&stake_pool, &beneficiary), member_stake);
+        assert_eq!(
+            keys.member_pending_unstake(&stake_pool, &beneficiary),
+            member_pending_unstake(&staking::ID, &stake_pool, &member_stake).0,
+        );
+    }
+
+    #[test]
+    fn stake_pool_is_derived_from_the_hash_of_the_name() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let name = "a name longer than thirty two bytes";
+
+        assert_eq!(keys.stake_pool(name), stake_pool(&staking::ID, &keys.factory, name).0);
+        assert_eq!(keys.stake_pool(" fixed "), keys.stake_pool("fixed"));
+        assert_ne!(keys.stake_pool("fixed"), legacy_stake_pool(&staking::ID, &keys.factory, "fixed").0);
+    }
+
+    #[test]
+    fn vault_reward_is_the_factory_ata() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(keys.vault_reward, get_associated_token_address(&keys.factory, &keys.reward_token_mint));
+    }
+
+    #[test]
+    fn vaults_are_derived_with_the_token_program_of_their_mint() {
+        let legacy = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let keys = legacy.with_token_programs(spl_token::ID, spl_token_2022::ID);
+        let (member, stake_pool) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        assert_eq!(keys.vault_free(&member), legacy.vault_free(&member));
+        assert_eq!(keys.vault_free(&member), get_associated_token_address(&member, &keys.stake_token_mint));
+        assert_ne!(keys.vault_reward, legacy.vault_reward);
+        assert_eq!(
+            keys.vault_fee(&stake_pool),
+            get_associated_token_address_with_program_id(&stake_pool, &keys.reward_token_mint, &spl_token_2022::ID),
+        );
+    }
+}
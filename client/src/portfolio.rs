@@ -0,0 +1,136 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Discriminator;
+use staking::account::*;
+use crate::account::{deserialize, offset, AccountFetcher};
+use crate::error::{ClientError, Result};
+use crate::pda::FactoryKeys;
+use crate::rpc::RpcClient;
+
+/// A member with the accounts of all its positions.
+pub struct Portfolio {
+    pub member_key: Pubkey,
+    pub member: Member,
+    pub stakes: Vec<(Pubkey, MemberStake)>,
+    pub pending_unstakes: Vec<(Pubkey, MemberPendingUnstake)>,
+    pub nft_stakes: Vec<(Pubkey, NftStake)>,
+}
+
+/// Fetches the member of the `beneficiary` with the positions of its `PositionIndex`, in two requests.
+pub fn fetch_portfolio(fetcher: &impl AccountFetcher, keys: &FactoryKeys, beneficiary: &Pubkey) -> Result<Portfolio> {
+    let member_key = keys.member(beneficiary);
+    let position_index_key = keys.position_index(beneficiary);
+    let mut accounts = fetcher.get_multiple_account_data(&[member_key, position_index_key])?.into_iter();
+    let member: Member = deserialize(&member_key, &accounts.next().flatten().ok_or(ClientError::AccountNotFound(member_key))?)?;
+    let position_index: PositionIndex = deserialize(
+        &position_index_key,
+        &accounts.next().flatten().ok_or(ClientError::AccountNotFound(position_index_key))?,
+    )?;
+    let positions: Vec<Pubkey> = position_index.positions.iter().map(|position| position.account).collect();
+
+    fetch_positions(fetcher, member_key, member, &positions)
+}
+
+/// Fetches the `positions` of the member, sorted by their kind.
+pub fn fetch_positions(fetcher: &impl AccountFetcher, member_key: Pubkey, member: Member, positions: &[Pubkey]) -> Result<Portfolio> {
+    let mut portfolio = Portfolio { member_key, member, stakes: vec![], pending_unstakes: vec![], nft_stakes: vec![] };
+    for (key, data) in positions.iter().zip(fetcher.get_multiple_account_data(positions)?) {
+        let data = data.ok_or(ClientError::AccountNotFound(*key))?;
+        match data.get(..8) {
+            Some(discriminator) if discriminator == MemberStake::discriminator() => portfolio.stakes.push((*key, deserialize(key, &data)?)),
+            Some(discriminator) if discriminator == MemberPendingUnstake::discriminator() => {
+                portfolio.pending_unstakes.push((*key, deserialize(key, &data)?))
+            },
+            Some(discriminator) if discriminator == NftStake::discriminator() => portfolio.nft_stakes.push((*key, deserialize(key, &data)?)),
+            _ => return Err(ClientError::Deserialize(*key, "not a position".to_owned())),
+        }
+    }
+
+    Ok(portfolio)
+}
+
+/// Finds the positions of the `beneficiary` by scanning the program,
+/// for the members opened before the position index to `index_positions` with.
+pub fn find_positions(rpc: &RpcClient, program_id: &Pubkey, beneficiary: &Pubkey) -> Result<Vec<Pubkey>> {
+    let beneficiary = beneficiary.to_bytes().to_vec();
+    let mut positions = vec![];
+    positions.extend(rpc.get_program_accounts::<MemberStake>(program_id, &[(offset::MEMBER_STAKE_BENEFICIARY, beneficiary.clone())])?);
+    positions.extend(rpc.get_program_accounts::<MemberPendingUnstake>(
+        program_id,
+        &[(offset::MEMBER_PENDING_UNSTAKE_BENEFICIARY, beneficiary.clone())],
+    )?);
+    positions.extend(rpc.get_program_accounts::<NftStake>(program_id, &[(offset::NFT_STAKE_BENEFICIARY, beneficiary)])?);
+
+    Ok(positions.into_iter().map(|(key, _)| key).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use anchor_lang::AccountSerialize;
+
+    fn serialized<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = vec![];
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn portfolio_is_fetched_from_the_position_index() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let beneficiary = Pubkey::new_unique();
+        let stake_pool = Pubkey::new_unique();
+        let member = Member {
+            beneficiary,
+            vault_free: Pubkey::new_unique(),
+            bump: 255,
+            delegate: None,
+            reward_destination: None,
+            auto_claim: false,
+            open_positions: 1,
+            version: ACCOUNT_VERSION,
+            pending_unstakes: 1,
+            reserved: [[0; 4]; 15],
+        };
+        let member_stake = MemberStake {
+            stake_pool,
+            beneficiary,
+            vault_staked: Pubkey::new_unique(),
+            staked_at: 100,
+            reward_payed_for: 100,
+            bump: 255,
+            reward_owed: 0,
+            version: ACCOUNT_VERSION,
+            staked_tokens: 1000,
+            reserved: [[0; 8]; 7],
+        };
+        let unstake = MemberPendingUnstake {
+            stake_pool,
+            beneficiary,
+            vault_pending_unstake: Pubkey::new_unique(),
+            unstaked_at: 200,
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; 64],
+        };
+        let (member_stake_key, unstake_key) = (keys.member_stake(&stake_pool, &beneficiary), keys.member_pending_unstake(&stake_pool, &beneficiary));
+        let mut position_index = PositionIndex { member: keys.member(&beneficiary), positions: vec![], bump: 255, version: ACCOUNT_VERSION, reserved: [0; 64] };
+        position_index.add(PositionKind::Stake, stake_pool, member_stake_key);
+        position_index.add(PositionKind::PendingUnstake, stake_pool, unstake_key);
+
+        let mut accounts: HashMap<Pubkey, Vec<u8>> = vec![
+            (keys.member(&beneficiary), serialized(&member)),
+            (member_stake_key, serialized(&member_stake)),
+            (unstake_key, serialized(&unstake)),
+        ].into_iter().collect();
+        assert!(matches!(fetch_portfolio(&accounts, &keys, &beneficiary), Err(ClientError::AccountNotFound(key)) if key == keys.position_index(&beneficiary)));
+
+        accounts.insert(keys.position_index(&beneficiary), serialized(&position_index));
+        let portfolio = fetch_portfolio(&accounts, &keys, &beneficiary).unwrap();
+        assert_eq!(portfolio.member.pending_unstakes, 1);
+        assert_eq!(portfolio.stakes.len(), 1);
+        assert_eq!(portfolio.stakes[0].0, member_stake_key);
+        assert_eq!(portfolio.pending_unstakes[0].1.unstaked_at, 200);
+        assert!(portfolio.nft_stakes.is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use anchor_lang::prelude::Pubkey;
+use staking::account::{PoolEntry, PoolStatus, StakePool};
+use crate::account::{deserialize, AccountFetcher};
+use crate::error::{ClientError, Result};
+use crate::pda::FactoryKeys;
+
+/// A stake pool of the factory's registry with its state at the time it was listed.
+pub struct RegisteredPool {
+    pub entry: PoolEntry,
+    pub stake_pool: StakePool,
+    pub status: PoolStatus,
+}
+
+/// Lists the `pool_count` stake pools of the factory's registry by their ids, with their states at `now`.
+pub fn list_pools(fetcher: &impl AccountFetcher, keys: &FactoryKeys, pool_count: u64, now: u64) -> Result<Vec<RegisteredPool>> {
+    let entry_keys: Vec<Pubkey> = (1..=pool_count).map(|id| keys.pool_entry(id)).collect();
+    let entries = entry_keys.iter()
+        .zip(fetcher.get_multiple_account_data(&entry_keys)?)
+        .map(|(key, data)| deserialize::<PoolEntry>(key, &data.ok_or(ClientError::AccountNotFound(*key))?))
+        .collect::<Result<Vec<_>>>()?;
+    let stake_pool_keys: Vec<Pubkey> = entries.iter().map(|entry| entry.stake_pool).collect();
+
+    entries.into_iter()
+        .zip(fetcher.get_multiple_account_data(&stake_pool_keys)?)
+        .map(|(entry, data)| {
+            let key = entry.stake_pool;
+            let stake_pool: StakePool = deserialize(&key, &data.ok_or(ClientError::AccountNotFound(key))?)?;
+            Ok(RegisteredPool { status: entry.status(&stake_pool, now), entry, stake_pool })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use anchor_lang::AccountSerialize;
+    use staking::account::ACCOUNT_VERSION;
+    use staking::fee::OwnerFee;
+    use staking::limits::StakeLimits;
+    use staking::reward::Reward;
+
+    fn entry(id: u64, closed_at: Option<u64>) -> PoolEntry {
+        PoolEntry {
+            id,
+            stake_pool: Pubkey::new_unique(),
+            stake_token_mint: Pubkey::new_unique(),
+            reward_token_mint: Pubkey::new_unique(),
+            closed_at,
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; 64],
+        }
+    }
+
+    fn stake_pool(id: u64) -> StakePool {
+        StakePool {
+            name: format!("pool {}", id),
+            reward_type: Reward::new_unfixed(100),
+            started_at: 100,
+            ends_at: 200,
+            total_staked_tokens: 0,
+            bump: 255,
+            owner_fee: OwnerFee::new(0, None).unwrap(),
+            fee_recipient: Pubkey::default(),
+            vault_fee: Pubkey::default(),
+            unstake_delay: 0,
+            reward_period: 10,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        }
+    }
+
+    #[test]
+    fn pools_are_listed_by_id_with_their_status() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let mut accounts = HashMap::new();
+        for entry in [entry(1, None), entry(2, Some(300))] {
+            let mut data = vec![];
+            stake_pool(entry.id).try_serialize(&mut data).unwrap();
+            accounts.insert(entry.stake_pool, data);
+            let mut data = vec![];
+            entry.try_serialize(&mut data).unwrap();
+            accounts.insert(keys.pool_entry(entry.id), data);
+        }
+
+        let pools = list_pools(&accounts, &keys, 2, 150).unwrap();
+        assert_eq!(pools.iter().map(|pool| pool.entry.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(pools[0].stake_pool.name, "pool 1");
+        assert_eq!(pools[0].status, PoolStatus::Active);
+        assert_eq!(pools[1].status, PoolStatus::Closed);
+        assert_eq!(list_pools(&accounts, &keys, 2, 250).unwrap()[0].status, PoolStatus::Ended);
+        assert!(list_pools(&accounts, &keys, 3, 150).is_err());
+    }
+}
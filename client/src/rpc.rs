@@ -0,0 +1,308 @@
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::Discriminator;
+use serde_json::{json, Value};
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use anchor_lang::AnchorDeserialize;
+use staking::limits::StakeCapacity;
+use staking::reward::PendingRewards;
+use crate::account::{fetch_factory, AccountFetcher};
+use crate::error::{ClientError, Result};
+use crate::instruction;
+use crate::pda::FactoryKeys;
+
+/// The most accounts a single `getMultipleAccounts` request returns.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+/// How long `send_and_confirm_transaction` waits for the confirmation.
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A minimal JSON RPC client covering the methods the staking client needs.
+pub struct RpcClient {
+    url: String,
+    commitment: String,
+}
+
+/// The outcome of `simulateTransaction`.
+#[derive(Debug, Default)]
+pub struct Simulation {
+    /// The error of the transaction if it failed.
+    pub err: Option<String>,
+    pub logs: Vec<String>,
+    /// The data set by the program with `set_return_data`.
+    pub return_data: Option<Vec<u8>>,
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> RpcClient {
+        RpcClient::new_with_commitment(url, "confirmed")
+    }
+
+    pub fn new_with_commitment(url: &str, commitment: &str) -> RpcClient {
+        RpcClient { url: url.to_owned(), commitment: commitment.to_owned() }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let response: Value = ureq::post(&self.url)
+            .send_json(json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .map_err(|err| ClientError::Transport(err.to_string()))?
+            .into_json()
+            .map_err(|err| ClientError::Transport(err.to_string()))?;
+
+        if let Some(err) = response.get("error") {
+            return Err(ClientError::Rpc {
+                code: err["code"].as_i64().unwrap_or_default(),
+                message: err["message"].as_str().unwrap_or_default().to_owned(),
+            });
+        }
+
+        response.get("result").cloned().ok_or_else(|| ClientError::UnexpectedResponse(response.to_string()))
+    }
+
+    /// Returns the key and data of every account of the `program_id`
+    /// of the type `T` with the `filters` applied. The filters are `(offset, bytes)`
+    /// pairs compared with the account data, the offset includes the discriminator.
+    pub fn get_program_accounts<T: Discriminator>(
+        &self,
+        program_id: &Pubkey,
+        filters: &[(usize, Vec<u8>)],
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>> {
+        let mut memcmp = vec![json!({ "memcmp": { "offset": 0, "bytes": bs58::encode(T::discriminator()).into_string() } })];
+        memcmp.extend(filters.iter().map(|(offset, bytes)| {
+            json!({ "memcmp": { "offset": offset, "bytes": bs58::encode(bytes).into_string() } })
+        }));
+
+        let result = self.request("getProgramAccounts", json!([
+            program_id.to_string(),
+            { "encoding": "base64", "commitment": self.commitment, "filters": memcmp },
+        ]))?;
+
+        result.as_array()
+            .ok_or_else(|| ClientError::UnexpectedResponse(result.to_string()))?
+            .iter()
+            .map(|item| Ok((parse_pubkey(&item["pubkey"])?, parse_base64(&item["account"]["data"][0])?)))
+            .collect()
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<Hash> {
+        let result = self.request("getLatestBlockhash", json!([{ "commitment": self.commitment }]))?;
+        let blockhash = result["value"]["blockhash"].as_str()
+            .ok_or_else(|| ClientError::UnexpectedResponse(result.to_string()))?;
+
+        Hash::from_str(blockhash).map_err(|err| ClientError::UnexpectedResponse(err.to_string()))
+    }
+
+    /// The current UNIX time of the cluster.
+    pub fn get_block_time(&self) -> Result<u64> {
+        let slot = self.request("getSlot", json!([{ "commitment": self.commitment }]))?;
+        let time = self.request("getBlockTime", json!([slot]))?;
+
+        time.as_u64().ok_or_else(|| ClientError::UnexpectedResponse(time.to_string()))
+    }
+
+    /// The program owning the account, e.g. the token program of a mint.
+    pub fn get_account_owner(&self, key: &Pubkey) -> Result<Pubkey> {
+        let result = self.request("getAccountInfo", json!([
+            key.to_string(),
+            { "encoding": "base64", "commitment": self.commitment },
+        ]))?;
+
+        match &result["value"] {
+            Value::Null => Err(ClientError::AccountNotFound(*key)),
+            account => parse_pubkey(&account["owner"]),
+        }
+    }
+
+    /// The keys of the factory of the program, with the token programs owning its mints.
+    pub fn get_factory_keys(&self, program_id: &Pubkey) -> Result<FactoryKeys> {
+        let factory = fetch_factory(self, program_id)?;
+        let stake_token_program = self.get_account_owner(&factory.stake_token_mint)?;
+        let reward_token_program = self.get_account_owner(&factory.reward_token_mint)?;
+
+        Ok(FactoryKeys::from_account(*program_id, &factory, stake_token_program, reward_token_program))
+    }
+
+    pub fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        let result = self.request("sendTransaction", json!([
+            encode_transaction(transaction)?,
+            { "encoding": "base64", "preflightCommitment": self.commitment },
+        ]))?;
+
+        parse_signature(&result)
+    }
+
+    /// Sends the transaction and waits until it reaches the client's commitment.
+    pub fn send_and_confirm_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        let signature = self.send_transaction(transaction)?;
+        let started_at = Instant::now();
+
+        while started_at.elapsed() < CONFIRMATION_TIMEOUT {
+            let result = self.request("getSignatureStatuses", json!([[signature.to_string()]]))?;
+            let status = &result["value"][0];
+
+            if !status.is_null() {
+                if !status["err"].is_null() {
+                    return Err(ClientError::TransactionFailed(signature, status["err"].to_string()));
+                }
+
+                let confirmation = status["confirmationStatus"].as_str().unwrap_or_default();
+                if confirmation == self.commitment || confirmation == "finalized" {
+                    return Ok(signature);
+                }
+            }
+
+            sleep(CONFIRMATION_POLL_INTERVAL);
+        }
+
+        Err(ClientError::ConfirmationTimeout(signature))
+    }
+
+    /// Simulates the transaction without requiring valid signatures.
+    pub fn simulate_transaction(&self, transaction: &Transaction) -> Result<Simulation> {
+        let result = self.request("simulateTransaction", json!([
+            encode_transaction(transaction)?,
+            { "encoding": "base64", "sigVerify": false, "replaceRecentBlockhash": true, "commitment": self.commitment },
+        ]))?;
+        let value = &result["value"];
+
+        let logs: Vec<String> = value["logs"].as_array()
+            .map(|logs| logs.iter().filter_map(|log| log.as_str().map(str::to_owned)).collect())
+            .unwrap_or_default();
+        let return_data = match value["returnData"]["data"][0].as_str() {
+            Some(data) => Some(decode_base64(data)?),
+            None => return_data_from_logs(&logs)?,
+        };
+
+        Ok(Simulation {
+            err: Some(&value["err"]).filter(|err| !err.is_null()).map(Value::to_string),
+            logs,
+            return_data,
+        })
+    }
+
+    /// Simulates the read-only `instruction` and returns the data it has set with `set_return_data`.
+    /// The `fee_payer` doesn't need to sign, it's only required to build the transaction.
+    pub fn simulate_view<T: AnchorDeserialize>(&self, instruction: Instruction, fee_payer: &Pubkey) -> Result<T> {
+        let transaction = Transaction::new_with_payer(&[instruction], Some(fee_payer));
+        let simulation = self.simulate_transaction(&transaction)?;
+
+        if let Some(err) = simulation.err {
+            return Err(ClientError::Program(format!("{} {:?}", err, simulation.logs)));
+        }
+
+        let data = simulation.return_data
+            .ok_or_else(|| ClientError::UnexpectedResponse("no return data".to_owned()))?;
+
+        T::try_from_slice(&data).map_err(|err| ClientError::UnexpectedResponse(err.to_string()))
+    }
+
+    /// Simulates the `get_pending_rewards` instruction of the `member_stake`.
+    pub fn get_pending_rewards(
+        &self,
+        keys: &FactoryKeys,
+        stake_pool: &Pubkey,
+        member_stake: &Pubkey,
+        fee_payer: &Pubkey,
+    ) -> Result<PendingRewards> {
+        self.simulate_view(instruction::get_pending_rewards(keys, stake_pool, member_stake), fee_payer)
+    }
+
+    /// Simulates the `get_stake_capacity` instruction of the `stake_pool`.
+    pub fn get_stake_capacity(&self, keys: &FactoryKeys, stake_pool: &Pubkey, fee_payer: &Pubkey) -> Result<StakeCapacity> {
+        self.simulate_view(instruction::get_stake_capacity(keys, stake_pool), fee_payer)
+    }
+}
+
+impl AccountFetcher for RpcClient {
+    fn get_account_data(&self, key: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let result = self.request("getAccountInfo", json!([
+            key.to_string(),
+            { "encoding": "base64", "commitment": self.commitment },
+        ]))?;
+
+        match &result["value"] {
+            Value::Null => Ok(None),
+            account => parse_base64(&account["data"][0]).map(Some),
+        }
+    }
+
+    fn get_multiple_account_data(&self, keys: &[Pubkey]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut accounts = Vec::with_capacity(keys.len());
+        for chunk in keys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let result = self.request("getMultipleAccounts", json!([
+                chunk.iter().map(|key| key.to_string()).collect::<Vec<_>>(),
+                { "encoding": "base64", "commitment": self.commitment },
+            ]))?;
+            let values = result["value"].as_array().ok_or_else(|| ClientError::UnexpectedResponse(result.to_string()))?;
+            for account in values {
+                accounts.push(match account {
+                    Value::Null => None,
+                    account => Some(parse_base64(&account["data"][0])?),
+                });
+            }
+        }
+
+        Ok(accounts)
+    }
+}
+
+/// Older validators don't return `returnData` from the simulation, but log it.
+fn return_data_from_logs(logs: &[String]) -> Result<Option<Vec<u8>>> {
+    logs.iter()
+        .rev()
+        .find_map(|log| log.strip_prefix("Program return: "))
+        .and_then(|log| log.split_whitespace().nth(1))
+        .map(decode_base64)
+        .transpose()
+}
+
+fn encode_transaction(transaction: &Transaction) -> Result<String> {
+    let bytes = bincode::serialize(transaction).map_err(|err| ClientError::Transport(err.to_string()))?;
+    Ok(base64::encode(bytes))
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>> {
+    base64::decode(data).map_err(|err| ClientError::UnexpectedResponse(err.to_string()))
+}
+
+fn parse_base64(value: &Value) -> Result<Vec<u8>> {
+    decode_base64(value.as_str().ok_or_else(|| ClientError::UnexpectedResponse(value.to_string()))?)
+}
+
+fn parse_pubkey(value: &Value) -> Result<Pubkey> {
+    value.as_str()
+        .and_then(|key| Pubkey::from_str(key).ok())
+        .ok_or_else(|| ClientError::UnexpectedResponse(value.to_string()))
+}
+
+fn parse_signature(value: &Value) -> Result<Signature> {
+    value.as_str()
+        .and_then(|signature| Signature::from_str(signature).ok())
+        .ok_or_else(|| ClientError::UnexpectedResponse(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn return_data_is_parsed_from_logs() {
+        let logs = vec![
+            "Program 8z5zCNBPzLuG1ZwkXfVVRCfpK5txF1UdHLfJjYFfmvQy invoke [1]".to_owned(),
+            format!("Program return: 8z5zCNBPzLuG1ZwkXfVVRCfpK5txF1UdHLfJjYFfmvQy {}", base64::encode([1, 2, 3])),
+            "Program 8z5zCNBPzLuG1ZwkXfVVRCfpK5txF1UdHLfJjYFfmvQy success".to_owned(),
+        ];
+
+        assert_eq!(return_data_from_logs(&logs).unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(return_data_from_logs(&logs[..1]).unwrap(), None);
+    }
+}
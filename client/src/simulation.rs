@@ -0,0 +1,705 @@
+//! Replays a scripted sequence of member actions against a stake pool
+//! with the same reward and fee code the program runs on chain,
+//! to see the reward economics of the pool before launching it.
+//!
+//! The scenario is a CSV of `time,member,action[,amount]` lines, where
+//! the `time` is the number of seconds since the pool started:
+//!
+//! ```text
+//! # time,member,action,amount
+//! 0,,deposit_reward,100000
+//! 0,alice,deposit,1000
+//! 0,alice,stake,1000
+//! 3600,alice,claim
+//! 7200,alice,unstake
+//! 7260,alice,finish_unstake
+//! 7260,alice,withdraw
+//! ```
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use anchor_lang::prelude::Pubkey;
+use staking::account::{MemberStake, RewardPosition, StakePool, ACCOUNT_VERSION};
+use staking::math::{mul_div, TryMath};
+use staking::reward::{PendingRewards, Reward};
+use staking::solvency;
+use crate::error::{ClientError, Result};
+
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// What a member (or the factory owner for `DepositReward`) does in the scenario.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Deposit(u64),
+    Stake(u64),
+    Claim,
+    /// `start_unstake_all`
+    Unstake,
+    /// `finish_unstake_all`
+    FinishUnstake,
+    /// `withdraw_all`
+    Withdraw,
+    DepositReward(u64),
+}
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Deposit(_) => "deposit",
+            Action::Stake(_) => "stake",
+            Action::Claim => "claim",
+            Action::Unstake => "unstake",
+            Action::FinishUnstake => "finish_unstake",
+            Action::Withdraw => "withdraw",
+            Action::DepositReward(_) => "deposit_reward",
+        }
+    }
+
+    fn amount(&self) -> Option<u64> {
+        match self {
+            Action::Deposit(amount) | Action::Stake(amount) | Action::DepositReward(amount) => Some(*amount),
+            _ => None,
+        }
+    }
+}
+
+/// The `action` of the `member` at `time` seconds since the pool started.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub time: u64,
+    pub member: String,
+    pub action: Action,
+}
+
+impl Event {
+    pub fn new(time: u64, member: &str, action: Action) -> Event {
+        Event { time, member: member.to_owned(), action }
+    }
+}
+
+/// Parses the scenario CSV, see the module docs for the format.
+/// Blank lines, `#` comments and a `time,...` header are skipped.
+pub fn parse_scenario(scenario: &str) -> Result<Vec<Event>> {
+    let mut events = vec![];
+
+    for (index, line) in scenario.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("time,") {
+            continue;
+        }
+
+        let invalid = |reason: &str| ClientError::InvalidScenario(format!("line {}: {}", index + 1, reason));
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let (time, member, action) = match fields[..] {
+            [time, member, action] | [time, member, action, _] => (time, member, action),
+            _ => return Err(invalid("expected time,member,action[,amount]")),
+        };
+        let time = u64::from_str(time).map_err(|_| invalid("invalid time"))?;
+        let amount = || -> Result<u64> {
+            fields.get(3)
+                .and_then(|amount| u64::from_str(amount).ok())
+                .ok_or_else(|| invalid("invalid amount"))
+        };
+
+        let action = match action {
+            "deposit" => Action::Deposit(amount()?),
+            "stake" => Action::Stake(amount()?),
+            "claim" => Action::Claim,
+            "unstake" => Action::Unstake,
+            "finish_unstake" => Action::FinishUnstake,
+            "withdraw" => Action::Withdraw,
+            "deposit_reward" => Action::DepositReward(amount()?),
+            _ => return Err(invalid("unknown action")),
+        };
+        if member.is_empty() && !matches!(action, Action::DepositReward(_)) {
+            return Err(invalid("missing member"));
+        }
+
+        events.push(Event::new(time, member, action));
+    }
+
+    Ok(events)
+}
+
+/// The outcome of a single event.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LedgerEntry {
+    /// The UNIX time of the event.
+    pub time: u64,
+    pub member: String,
+    pub action: &'static str,
+    pub amount: Option<u64>,
+    /// The reward the member received, after the owner's fee.
+    pub net_reward: u64,
+    pub owner_fee: u64,
+    /// The balance of the factory's reward vault after the event.
+    pub vault_reward: u64,
+    /// The name of the error the instruction would fail with.
+    pub error: Option<String>,
+}
+
+/// The totals of a member over the whole scenario.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemberReport {
+    pub member: String,
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub net_reward: u64,
+    pub owner_fee: u64,
+    /// The reward lost to the integer math of the claims.
+    pub rounding_dust: u64,
+    /// The reward accrued but not claimed at the end of the scenario.
+    pub unclaimed_reward: u64,
+    /// The sum of the staked tokens multiplied by the seconds they were
+    /// staked for while the pool was active.
+    pub token_seconds: u128,
+    pub failed_actions: usize,
+}
+
+impl MemberReport {
+    /// The annual rate of the net reward to the staked tokens in percent.
+    pub fn apr(&self) -> Option<f64> {
+        if self.token_seconds == 0 {
+            return None;
+        }
+
+        Some(self.net_reward as f64 * SECONDS_PER_YEAR as f64 / self.token_seconds as f64 * 100.0)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub ledger: Vec<LedgerEntry>,
+    pub members: Vec<MemberReport>,
+    pub total_net_reward: u64,
+    pub total_owner_fee: u64,
+    pub total_rounding_dust: u64,
+    /// The balance of the factory's reward vault at the end of the scenario.
+    pub vault_reward: u64,
+    /// The UNIX time of the first claim the reward vault couldn't pay.
+    pub vault_depleted_at: Option<u64>,
+}
+
+impl Report {
+    /// Writes a CSV row per member with the header.
+    pub fn write_members_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "member,deposited,withdrawn,net_reward,owner_fee,rounding_dust,unclaimed_reward,token_seconds,apr,failed_actions")?;
+
+        for member in &self.members {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{}",
+                csv_field(&member.member),
+                member.deposited,
+                member.withdrawn,
+                member.net_reward,
+                member.owner_fee,
+                member.rounding_dust,
+                member.unclaimed_reward,
+                member.token_seconds,
+                member.apr().map(|apr| format!("{:.4}", apr)).unwrap_or_default(),
+                member.failed_actions,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a CSV row per event with the header.
+    pub fn write_ledger_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "time,member,action,amount,net_reward,owner_fee,vault_reward,error")?;
+
+        for entry in &self.ledger {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{}",
+                entry.time,
+                csv_field(&entry.member),
+                entry.action,
+                entry.amount.map(|amount| amount.to_string()).unwrap_or_default(),
+                entry.net_reward,
+                entry.owner_fee,
+                entry.vault_reward,
+                entry.error.as_deref().unwrap_or_default(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Net reward:    {}", self.total_net_reward)?;
+        writeln!(f, "Owner fee:     {}", self.total_owner_fee)?;
+        writeln!(f, "Rounding dust: {}", self.total_rounding_dust)?;
+        writeln!(f, "Vault reward:  {}", self.vault_reward)?;
+        match self.vault_depleted_at {
+            Some(time) => write!(f, "Depleted at:   {}", time),
+            None => write!(f, "Depleted at:   never"),
+        }
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// The accounts of a member mirrored by the simulation.
+#[derive(Default)]
+struct MemberState {
+    vault_free: u64,
+    member_stake: Option<MemberStake>,
+    vault_staked: u64,
+    /// `(amount, unstaked_at)` of the `MemberPendingUnstake`.
+    pending_unstake: Option<(u64, u64)>,
+    report: MemberReport,
+}
+
+/// The stake pool with its members and the factory's reward vault.
+pub struct Simulation {
+    stake_pool: StakePool,
+    vault_reward: u64,
+    /// The rewards committed in the partition of the vault the pool pays from, `solvency::partition_committed`.
+    committed_rewards: u128,
+    members: BTreeMap<String, MemberState>,
+    report: Report,
+}
+
+impl Simulation {
+    /// Starts the simulation of the `stake_pool` as it is,
+    /// with `vault_reward` tokens of the factory's reward vault available to the pool.
+    pub fn new(stake_pool: StakePool, vault_reward: u64) -> Simulation {
+        Simulation { stake_pool, vault_reward, committed_rewards: 0, members: BTreeMap::new(), report: Report::default() }
+    }
+
+    /// Starts with the rewards committed by the other Fixed pools paying from the same part of the vault.
+    pub fn with_committed_rewards(mut self, committed_rewards: u128) -> Simulation {
+        self.committed_rewards = committed_rewards;
+        self
+    }
+
+    /// Replays the `events` in the order of their time and returns the report
+    /// at the time of the last event.
+    pub fn run(mut self, events: &[Event]) -> Result<Report> {
+        let mut events = events.to_vec();
+        events.sort_by_key(|event| event.time); // stable, keeps the order of simultaneous events
+
+        let mut now = self.stake_pool.started_at;
+        for event in &events {
+            now = self.stake_pool.started_at.try_add(event.time)?;
+            self.apply(now, event)?;
+        }
+
+        self.finish(now)
+    }
+
+    fn apply(&mut self, now: u64, event: &Event) -> Result<()> {
+        let mut entry = LedgerEntry {
+            time: now,
+            member: event.member.clone(),
+            action: event.action.name(),
+            amount: event.action.amount(),
+            ..LedgerEntry::default()
+        };
+
+        let result = match event.action {
+            Action::DepositReward(amount) => {
+                self.vault_reward = self.vault_reward.try_add(amount)?;
+                Ok(())
+            },
+            action => self.apply_member_action(now, &event.member, action, &mut entry)?,
+        };
+
+        if let Err(error) = result {
+            if error == "InsufficientAmountOfTokensToClaim" && self.report.vault_depleted_at.is_none() {
+                self.report.vault_depleted_at = Some(now);
+            }
+            if let Some(member) = self.members.get_mut(&event.member) {
+                member.report.failed_actions += 1;
+            }
+            entry.error = Some(error);
+        }
+
+        entry.vault_reward = self.vault_reward;
+        self.report.ledger.push(entry);
+
+        Ok(())
+    }
+
+    /// Mirrors the instruction of the `action`. The outer `Result` is an error of the simulation,
+    /// the inner one is the error the instruction would fail with.
+    fn apply_member_action(
+        &mut self,
+        now: u64,
+        member_name: &str,
+        action: Action,
+        entry: &mut LedgerEntry,
+    ) -> Result<std::result::Result<(), String>> {
+        let stake_pool = &mut self.stake_pool;
+        let member = self.members.entry(member_name.to_owned()).or_insert_with(|| MemberState {
+            report: MemberReport { member: member_name.to_owned(), ..MemberReport::default() },
+            ..MemberState::default()
+        });
+
+        match action {
+            Action::Deposit(amount) => {
+                member.vault_free = member.vault_free.try_add(amount)?;
+                member.report.deposited = member.report.deposited.try_add(amount)?;
+            },
+            Action::Stake(amount) => {
+                if amount > member.vault_free {
+                    return Ok(Err("NotEnoughFreeVaultAmount".to_owned()));
+                }
+                if member.member_stake.is_some() {
+                    return Ok(Err("MemberStakeAlreadyExists".to_owned()));
+                }
+                if let Err(err) = stake_pool.limits.check(stake_pool.total_staked_tokens, 0, amount) {
+                    return Ok(Err(error_name(err)));
+                }
+                let commitment = solvency::committed_reward(stake_pool, now, amount)?;
+                self.committed_rewards = match solvency::commit(self.committed_rewards, commitment, self.vault_reward) {
+                    Ok(committed_rewards) => committed_rewards,
+                    Err(err) => return Ok(Err(error_name(err))),
+                };
+
+                member.member_stake = Some(MemberStake {
+                    stake_pool: Pubkey::default(),
+                    beneficiary: Pubkey::default(),
+                    vault_staked: Pubkey::default(),
+                    staked_at: now,
+                    reward_payed_for: now,
+                    bump: 0,
+                    reward_owed: 0,
+                    version: ACCOUNT_VERSION,
+                    staked_tokens: amount,
+                    reserved: [[0; 8]; 7],
+                });
+                member.vault_free -= amount;
+                member.vault_staked = amount;
+                stake_pool.total_staked_tokens = stake_pool.total_staked_tokens.try_add(amount as u128)?;
+            },
+            Action::Claim => {
+                let member_stake = match &mut member.member_stake {
+                    Some(member_stake) => member_stake,
+                    None => return Ok(Err("MemberStakeNotFound".to_owned())),
+                };
+                let checkpoint = member_stake.reward_checkpoint();
+                let (gross_reward, reward_payed_for) = match stake_pool.reward_type.calculate(
+                    now,
+                    stake_pool.ends_at,
+                    checkpoint,
+                    member.vault_staked,
+                    stake_pool.reward_period,
+                    stake_pool.total_staked_tokens,
+                ) {
+                    Ok(reward) => reward,
+                    Err(err) => return Ok(Err(error_name(err))),
+                };
+                if gross_reward > solvency::payable_rewards(stake_pool, self.vault_reward, self.committed_rewards) {
+                    return Ok(Err("InsufficientAmountOfTokensToClaim".to_owned()));
+                }
+
+                let (owner_fee, net_reward) = stake_pool.owner_fee.split(gross_reward)?;
+                let periods = (reward_payed_for - checkpoint) / stake_pool.reward_period;
+                let dust = exact_reward(stake_pool, member.vault_staked, periods)?.try_sub(gross_reward as u128)?;
+
+                self.vault_reward -= gross_reward;
+                self.committed_rewards = solvency::release(self.committed_rewards, stake_pool, gross_reward as u128);
+                member_stake.reward_payed_for = reward_payed_for;
+                member.report.net_reward = member.report.net_reward.try_add(net_reward)?;
+                member.report.owner_fee = member.report.owner_fee.try_add(owner_fee)?;
+                member.report.rounding_dust = member.report.rounding_dust.try_add(to_u64_saturating(dust))?;
+                entry.net_reward = net_reward;
+                entry.owner_fee = owner_fee;
+            },
+            Action::Unstake => {
+                if member.member_stake.is_none() || member.vault_staked == 0 {
+                    return Ok(Err("NoStakedTokens".to_owned()));
+                }
+                if member.pending_unstake.is_some() {
+                    return Ok(Err("MemberPendingUnstakeAlreadyExists".to_owned()));
+                }
+
+                let staked_at = member.member_stake.as_ref().map(|stake| stake.staked_at).unwrap_or(now);
+                member.report.token_seconds = member.report.token_seconds
+                    .try_add(token_seconds(stake_pool, member.vault_staked, staked_at, now)?)?;
+                member.pending_unstake = Some((member.vault_staked, now));
+                let checkpoint = member.member_stake.as_ref().map(MemberStake::reward_checkpoint).unwrap_or(now);
+                let released = solvency::committed_reward(stake_pool, checkpoint, member.vault_staked)?;
+                self.committed_rewards = solvency::release(self.committed_rewards, stake_pool, released);
+                stake_pool.total_staked_tokens = stake_pool.total_staked_tokens.try_sub(member.vault_staked as u128)?;
+                member.vault_staked = 0;
+            },
+            Action::FinishUnstake => {
+                let (amount, unstaked_at) = match member.pending_unstake {
+                    Some(pending_unstake) => pending_unstake,
+                    None => return Ok(Err("MemberPendingUnstakeNotFound".to_owned())),
+                };
+                if now < unstaked_at.try_add(stake_pool.unstake_delay)? {
+                    return Ok(Err("NotAllowedFinishUnstakeYet".to_owned()));
+                }
+
+                member.vault_free = member.vault_free.try_add(amount)?;
+                member.pending_unstake = None;
+                member.member_stake = None;
+            },
+            Action::Withdraw => {
+                member.report.withdrawn = member.report.withdrawn.try_add(member.vault_free)?;
+                member.vault_free = 0;
+            },
+            Action::DepositReward(_) => unreachable!("the reward is deposited by the owner"),
+        }
+
+        Ok(Ok(()))
+    }
+
+    /// Adds the stakes that are still active at `now` to the report.
+    fn finish(mut self, now: u64) -> Result<Report> {
+        let mut report = self.report;
+
+        for member in self.members.values_mut() {
+            if let Some(member_stake) = &member.member_stake {
+                member.report.token_seconds = member.report.token_seconds
+                    .try_add(token_seconds(&self.stake_pool, member.vault_staked, member_stake.staked_at, now)?)?;
+
+                let pending = PendingRewards::calculate(&self.stake_pool, member_stake, member.vault_staked, now)?;
+                member.report.unclaimed_reward = pending.net_reward;
+            }
+
+            report.total_net_reward = report.total_net_reward.try_add(member.report.net_reward)?;
+            report.total_owner_fee = report.total_owner_fee.try_add(member.report.owner_fee)?;
+            report.total_rounding_dust = report.total_rounding_dust.try_add(member.report.rounding_dust)?;
+        }
+
+        report.members = self.members.into_values().map(|member| member.report).collect();
+        report.vault_reward = self.vault_reward;
+
+        Ok(report)
+    }
+}
+
+/// The reward of `periods` without rounding each period and the member's share.
+/// Unfixed pools pay a single period's share however many periods have passed.
+fn exact_reward(stake_pool: &StakePool, staked_by_user: u64, periods: u64) -> Result<u128> {
+    let staked_for_periods = (staked_by_user as u128).try_mul(periods as u128)?;
+
+    Ok(match stake_pool.reward_type {
+        Reward::Fixed { data: reward_rate } => staked_for_periods.try_div(reward_rate)?,
+        Reward::Unfixed { data: reward_tokens_per_period } => {
+            mul_div(staked_by_user as u128, reward_tokens_per_period, stake_pool.total_staked_tokens)?
+        },
+    })
+}
+
+/// `amount` multiplied by the seconds between `from` and `to` the pool was active.
+fn token_seconds(stake_pool: &StakePool, amount: u64, from: u64, to: u64) -> Result<u128> {
+    let seconds = to.min(stake_pool.ends_at).saturating_sub(from);
+    (amount as u128).try_mul(seconds as u128).map_err(ClientError::from)
+}
+
+fn to_u64_saturating(value: u128) -> u64 {
+    value.min(u64::MAX as u128) as u64
+}
+
+fn error_name(err: anchor_lang::error::Error) -> String {
+    match err {
+        anchor_lang::error::Error::AnchorError(err) => err.error_name,
+        err => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use staking::fee::OwnerFee;
+    use staking::limits::StakeLimits;
+
+    fn stake_pool(reward: Reward, fee_bps: u16) -> StakePool {
+        StakePool {
+            name: "test".to_owned(),
+            reward_type: reward,
+            started_at: 1000,
+            ends_at: 1000 + 10 * 100,
+            total_staked_tokens: 0,
+            bump: 255,
+            owner_fee: OwnerFee::new(fee_bps, None).unwrap(),
+            fee_recipient: Pubkey::default(),
+            vault_fee: Pubkey::default(),
+            unstake_delay: 50,
+            reward_period: 100,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id: 0,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        }
+    }
+
+    fn member<'a>(report: &'a Report, name: &str) -> &'a MemberReport {
+        report.members.iter().find(|member| member.member == name).unwrap()
+    }
+
+    #[test]
+    fn parses_scenario() {
+        let events = parse_scenario("time,member,action,amount\n# comment\n\n0,,deposit_reward,500\n10,alice,stake,100\n20,alice,claim\n").unwrap();
+
+        assert_eq!(events, vec![
+            Event::new(0, "", Action::DepositReward(500)),
+            Event::new(10, "alice", Action::Stake(100)),
+            Event::new(20, "alice", Action::Claim),
+        ]);
+        assert!(parse_scenario("0,alice,stake").is_err());
+        assert!(parse_scenario("0,,claim").is_err());
+        assert!(parse_scenario("0,alice,jump").is_err());
+    }
+
+    #[test]
+    fn members_share_unfixed_reward() {
+        let events = parse_scenario("
+            0,,deposit_reward,10000
+            0,alice,deposit,300
+            0,alice,stake,300
+            0,bob,deposit,600
+            0,bob,stake,600
+            200,alice,claim
+            200,bob,claim
+        ").unwrap();
+
+        let report = Simulation::new(stake_pool(Reward::new_unfixed(1000), 1000), 0).run(&events).unwrap();
+
+        // 1/3 and 2/3 of 1000 tokens, a single period is paid for the two periods passed
+        assert_eq!(member(&report, "alice").net_reward + member(&report, "alice").owner_fee, 333);
+        assert_eq!(member(&report, "bob").net_reward + member(&report, "bob").owner_fee, 666);
+        assert_eq!(report.total_owner_fee, 33 + 66);
+        assert_eq!(report.vault_reward, 10_000 - 333 - 666);
+        assert_eq!(report.total_rounding_dust, 0);
+    }
+
+    #[test]
+    fn rounding_dust_of_each_period() {
+        // 30 tokens divided by the rate 7 is 4.29 tokens per period
+        let events = parse_scenario("0,,deposit_reward,100\n0,alice,deposit,30\n0,alice,stake,30\n400,alice,claim").unwrap();
+
+        let report = Simulation::new(stake_pool(Reward::new_fixed(7), 0), 0).run(&events).unwrap();
+
+        assert_eq!(report.total_net_reward, 16);
+        assert_eq!(report.total_rounding_dust, 1);
+    }
+
+    #[test]
+    fn failed_claims_and_vault_depletion() {
+        let events = parse_scenario("
+            0,,deposit_reward,150
+            0,alice,deposit,1000
+            0,alice,stake,1000
+            50,alice,claim
+            100,alice,claim
+            300,alice,claim
+        ").unwrap();
+
+        // Unfixed pools don't commit their rewards, so nothing stops the vault running dry.
+        let report = Simulation::new(stake_pool(Reward::new_unfixed(100), 0), 0).run(&events).unwrap();
+
+        let errors: Vec<_> = report.ledger.iter().map(|entry| entry.error.as_deref()).collect();
+        assert_eq!(errors[3..], [Some("RewardPeriodNotPassed"), None, Some("InsufficientAmountOfTokensToClaim")]);
+        assert_eq!(report.vault_depleted_at, Some(1300));
+        assert_eq!(report.vault_reward, 50);
+        assert_eq!(member(&report, "alice").failed_actions, 2);
+        assert_eq!(member(&report, "alice").unclaimed_reward, 100);
+    }
+
+    #[test]
+    fn unstake_respects_the_delay_and_counts_stake_time() {
+        let events = parse_scenario("
+            0,,deposit_reward,1000
+            0,alice,deposit,1000
+            0,alice,stake,1000
+            100,alice,unstake
+            120,alice,finish_unstake
+            150,alice,finish_unstake
+            150,alice,withdraw
+        ").unwrap();
+
+        let report = Simulation::new(stake_pool(Reward::new_fixed(10), 0), 0).run(&events).unwrap();
+
+        let alice = member(&report, "alice");
+        assert_eq!(report.ledger[4].error.as_deref(), Some("NotAllowedFinishUnstakeYet"));
+        assert_eq!(alice.withdrawn, 1000);
+        assert_eq!(alice.token_seconds, 1000 * 100);
+        assert_eq!(alice.apr(), Some(0.0));
+    }
+
+    #[test]
+    fn stakes_respect_the_pool_limits() {
+        let events = parse_scenario("
+            0,alice,deposit,1000
+            0,bob,deposit,1000
+            0,alice,stake,600
+            0,bob,stake,500
+            10,bob,stake,400
+        ").unwrap();
+        let mut pool = stake_pool(Reward::new_unfixed(100), 0);
+        pool.limits = StakeLimits { max_total_staked: Some(1000), max_per_member: Some(600), min_stake_amount: 0 };
+
+        let report = Simulation::new(pool, 0).run(&events).unwrap();
+
+        let errors: Vec<_> = report.ledger.iter().map(|entry| entry.error.as_deref()).collect();
+        assert_eq!(errors[2..], [None, Some("StakePoolCapacityExceeded"), None]);
+    }
+
+    #[test]
+    fn fixed_stakes_are_limited_by_the_reward_budget() {
+        // 10 % of 1000 tokens for 10 periods commits 1000 reward tokens.
+        let events = parse_scenario("
+            0,,deposit_reward,1500
+            0,alice,deposit,1000
+            0,alice,stake,1000
+            0,bob,deposit,1000
+            0,bob,stake,1000
+            0,bob,stake,500
+            100,alice,unstake
+            100,carol,deposit,1000
+            100,carol,stake,1000
+        ").unwrap();
+
+        let report = Simulation::new(stake_pool(Reward::new_fixed(10), 0), 0).run(&events).unwrap();
+
+        // Unstaking releases the rest of alice's commitment for carol's 9 periods.
+        let errors: Vec<_> = report.ledger.iter().map(|entry| entry.error.as_deref()).collect();
+        assert_eq!(errors[4..], [Some("RewardBudgetExceeded"), None, None, None, None]);
+
+        let report = Simulation::new(stake_pool(Reward::new_fixed(10), 0), 1000)
+            .with_committed_rewards(500)
+            .run(&events[1..3])
+            .unwrap();
+        assert_eq!(report.ledger[1].error.as_deref(), Some("RewardBudgetExceeded"));
+    }
+
+    #[test]
+    fn exports_csv() {
+        let events = vec![Event::new(0, "a,b", Action::Deposit(10))];
+        let report = Simulation::new(stake_pool(Reward::new_fixed(10), 0), 0).run(&events).unwrap();
+
+        let mut members = vec![];
+        report.write_members_csv(&mut members).unwrap();
+        let members = String::from_utf8(members).unwrap();
+        assert_eq!(members.lines().nth(1), Some("\"a,b\",10,0,0,0,0,0,0,,0"));
+
+        let mut ledger = vec![];
+        report.write_ledger_csv(&mut ledger).unwrap();
+        assert_eq!(String::from_utf8(ledger).unwrap().lines().nth(1), Some("1000,\"a,b\",deposit,10,0,0,0,"));
+    }
+}
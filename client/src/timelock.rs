@@ -0,0 +1,50 @@
+use staking::account::QueuedAction;
+use crate::account::{deserialize, AccountFetcher};
+use crate::error::Result;
+use crate::pda::FactoryKeys;
+
+/// Lists the actions waiting in the factory's queue among the ids from 1 to the `action_count`.
+/// The executed and cancelled actions are closed, so they're skipped.
+pub fn list_queued_actions(fetcher: &impl AccountFetcher, keys: &FactoryKeys, action_count: u64) -> Result<Vec<QueuedAction>> {
+    let action_keys: Vec<_> = (1..=action_count).map(|id| keys.queued_action(id)).collect();
+
+    action_keys.iter()
+        .zip(fetcher.get_multiple_account_data(&action_keys)?)
+        .filter_map(|(key, data)| data.map(|data| deserialize(key, &data)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use anchor_lang::prelude::Pubkey;
+    use anchor_lang::AccountSerialize;
+    use staking::account::ACCOUNT_VERSION;
+    use staking::timelock::AdminAction;
+
+    #[test]
+    fn the_closed_actions_are_skipped() {
+        let keys = FactoryKeys::new(staking::ID, Pubkey::new_unique(), Pubkey::new_unique());
+        let mut accounts = HashMap::new();
+        for id in [1, 3] {
+            let queued_action = QueuedAction {
+                id,
+                action: AdminAction::SetTimelockDelay { delay: id * 60 },
+                proposer: Pubkey::new_unique(),
+                queued_at: 100,
+                eta: 200,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; 64],
+            };
+            let mut data = vec![];
+            queued_action.try_serialize(&mut data).unwrap();
+            accounts.insert(keys.queued_action(id), data);
+        }
+
+        let queue = list_queued_actions(&accounts, &keys, 3).unwrap();
+        assert_eq!(queue.iter().map(|queued_action| queued_action.id).collect::<Vec<_>>(), vec![1, 3]);
+        assert!(matches!(queue[1].action, AdminAction::SetTimelockDelay { delay: 180 }));
+    }
+}
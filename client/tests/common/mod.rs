@@ -0,0 +1,447 @@
+//! In-process bank harness for the staking program tests.
+#![allow(dead_code)]
+
+use anchor_lang::prelude::{AccountInfo, Pubkey};
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::{system_instruction, sysvar::clock::Clock};
+use anchor_lang::AccountDeserialize;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use spl_token_2022::extension::StateWithExtensions;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use staking::account::{Factory, QueuedAction};
+use staking::error::SPError;
+use staking::fee::OwnerFee;
+use staking::limits::StakeLimits;
+use staking::merkle::MerkleNode;
+use staking::reward::Reward;
+use staking::timelock::AdminAction;
+use staking_client::{instruction, FactoryKeys};
+
+/// Runs the program natively in the bank.
+/// `processor!` takes any lifetime of the account slice, while the Anchor entrypoint wants it to
+/// live as long as the account data. The clones share that data, and leaking them gives the slice
+/// the longer lifetime; a test leaks a few hundred bytes per instruction.
+fn process_instruction<'a>(program_id: &Pubkey, accounts: &[AccountInfo<'a>], data: &[u8]) -> ProgramResult {
+    let accounts: &'a [AccountInfo<'a>] = Box::leak(accounts.to_vec().into_boxed_slice());
+    staking::entry(program_id, accounts, data)
+}
+
+/// The UNIX time the tests start at.
+pub const START_TIME: i64 = 1_700_000_000;
+pub const REWARD_PERIOD: u64 = 1000;
+pub const UNSTAKE_DELAY: u64 = 100;
+pub const POOL_DURATION: u64 = 100 * REWARD_PERIOD;
+
+pub type TestResult = Result<(), BanksClientError>;
+
+pub struct TestEnv {
+    pub context: ProgramTestContext,
+    pub keys: FactoryKeys,
+    /// The owner of the factory and the authority of both mints.
+    pub owner: Keypair,
+    /// The owner's reward token account funded with `OWNER_REWARD_TOKENS`.
+    pub owner_reward_vault: Pubkey,
+    last_blockhash: Hash,
+}
+
+/// The mint of the stake tokens of the `TestEnv`.
+enum StakeMint {
+    Spl,
+    /// A Token-2022 mint with the transfer fee in basis points.
+    TransferFee(u16),
+    WrappedSol,
+}
+
+pub struct TestMember {
+    pub beneficiary: Keypair,
+    pub stake_vault: Pubkey,
+    pub reward_vault: Pubkey,
+}
+
+impl TestMember {
+    pub fn key(&self) -> Pubkey {
+        self.beneficiary.pubkey()
+    }
+}
+
+pub const OWNER_REWARD_TOKENS: u64 = 1_000_000_000;
+
+impl TestEnv {
+    /// Starts the bank with the initialized factory and the clock set to `START_TIME`.
+    pub async fn new() -> TestEnv {
+        TestEnv::start(StakeMint::Spl).await
+    }
+
+    /// Same as `new`, but the stake tokens are of a Token-2022 mint charging `fee_bps` on the transfers.
+    pub async fn new_with_transfer_fee(fee_bps: u16) -> TestEnv {
+        TestEnv::start(StakeMint::TransferFee(fee_bps)).await
+    }
+
+    /// Same as `new`, but the stake tokens are the wrapped SOL.
+    pub async fn new_with_wrapped_sol() -> TestEnv {
+        TestEnv::start(StakeMint::WrappedSol).await
+    }
+
+    async fn start(stake_mint: StakeMint) -> TestEnv {
+        let mut program = ProgramTest::new("staking", staking::ID, processor!(process_instruction));
+        program.prefer_bpf(false);
+        let context = program.start_with_context().await;
+        let last_blockhash = context.last_blockhash;
+        let owner = Keypair::new();
+        let stake_token_mint = Keypair::new();
+        let stake_token_program = match stake_mint {
+            StakeMint::Spl | StakeMint::WrappedSol => spl_token::ID,
+            StakeMint::TransferFee(_) => spl_token_2022::ID,
+        };
+        let stake_token_mint_key = match stake_mint {
+            StakeMint::WrappedSol => spl_token::native_mint::ID,
+            _ => stake_token_mint.pubkey(),
+        };
+        let reward_token_mint = Keypair::new();
+        let keys = FactoryKeys::new(staking::ID, stake_token_mint_key, reward_token_mint.pubkey())
+            .with_token_programs(stake_token_program, spl_token::ID);
+
+        let mut env = TestEnv {
+            context,
+            keys,
+            owner_reward_vault: keys.reward_token_account(&owner.pubkey()),
+            owner,
+            last_blockhash,
+        };
+
+        env.warp_to(START_TIME);
+        let owner_key = env.owner.pubkey();
+        env.airdrop(&owner_key, 10_000_000_000).await;
+        match stake_mint {
+            StakeMint::Spl => env.create_mint(&stake_token_mint).await,
+            StakeMint::TransferFee(fee_bps) => env.create_transfer_fee_mint(&stake_token_mint, fee_bps).await,
+            StakeMint::WrappedSol => env.add_native_mint().await,
+        }
+        env.create_mint(&reward_token_mint).await;
+        let owner_reward_vault = env.create_token_account(&owner_key, &reward_token_mint.pubkey()).await;
+        env.mint_to(&reward_token_mint.pubkey(), &owner_reward_vault, OWNER_REWARD_TOKENS).await;
+
+        let initialize = instruction::initialize(&env.keys, &owner_key, &owner_key);
+        env.process(&[initialize], &[&clone_keypair(&env.owner)]).await.unwrap();
+
+        env
+    }
+
+    /// Sends the transaction paid by the test payer.
+    /// Waits for a new blockhash, so the same instructions could be sent again.
+    pub async fn process(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> TestResult {
+        let mut blockhash = self.context.banks_client.get_latest_blockhash().await.unwrap();
+        while blockhash == self.last_blockhash {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            blockhash = self.context.banks_client.get_latest_blockhash().await.unwrap();
+        }
+        self.last_blockhash = blockhash;
+
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    pub async fn now(&mut self) -> i64 {
+        self.context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+    }
+
+    /// Moves the `Clock` sysvar to the `unix_timestamp`.
+    pub fn warp_to(&mut self, unix_timestamp: i64) {
+        let bank_clock = Clock { unix_timestamp, ..Clock::default() };
+        self.context.set_sysvar(&bank_clock);
+    }
+
+    pub async fn warp_by(&mut self, seconds: u64) {
+        let now = self.now().await;
+        self.warp_to(now + seconds as i64);
+    }
+
+    pub async fn airdrop(&mut self, to: &Pubkey, lamports: u64) {
+        let payer = self.context.payer.pubkey();
+        self.process(&[system_instruction::transfer(&payer, to, lamports)], &[]).await.unwrap();
+    }
+
+    pub async fn create_mint(&mut self, mint: &Keypair) {
+        self.create_mint_with_decimals(mint, 6).await
+    }
+
+    pub async fn create_mint_with_decimals(&mut self, mint: &Keypair, decimals: u8) {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let payer = self.context.payer.pubkey();
+        let authority = self.owner.pubkey();
+
+        self.process(&[
+            system_instruction::create_account(
+                &payer,
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_mint(&spl_token::ID, &mint.pubkey(), &authority, None, decimals).unwrap(),
+        ], &[mint]).await.unwrap();
+    }
+
+    /// Creates a Token-2022 mint charging `fee_bps` on the transfers, withheld in the receiving accounts.
+    pub async fn create_transfer_fee_mint(&mut self, mint: &Keypair, fee_bps: u16) {
+        use spl_token_2022::extension::{transfer_fee, ExtensionType};
+
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let payer = self.context.payer.pubkey();
+        let authority = self.owner.pubkey();
+        let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[ExtensionType::TransferFeeConfig]).unwrap();
+
+        self.process(&[
+            system_instruction::create_account(&payer, &mint.pubkey(), rent.minimum_balance(space), space as u64, &spl_token_2022::ID),
+            transfer_fee::instruction::initialize_transfer_fee_config(
+                &spl_token_2022::ID,
+                &mint.pubkey(),
+                Some(&authority),
+                Some(&authority),
+                fee_bps,
+                u64::MAX,
+            ).unwrap(),
+            spl_token_2022::instruction::initialize_mint(&spl_token_2022::ID, &mint.pubkey(), &authority, None, 6).unwrap(),
+        ], &[mint]).await.unwrap();
+    }
+
+    /// The token program owning the `mint`.
+    pub async fn token_program(&mut self, mint: &Pubkey) -> Pubkey {
+        self.context.banks_client.get_account(*mint).await.unwrap().expect("mint exists").owner
+    }
+
+    /// The bank starts without the wrapped SOL mint of the token program.
+    async fn add_native_mint(&mut self) {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let mint = spl_token::state::Mint {
+            decimals: spl_token::native_mint::DECIMALS,
+            is_initialized: true,
+            ..spl_token::state::Mint::default()
+        };
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint::pack(mint, &mut data).unwrap();
+
+        let mut account = AccountSharedData::new(rent.minimum_balance(data.len()), data.len(), &spl_token::ID);
+        account.set_data_from_slice(&data);
+        self.context.set_account(&spl_token::native_mint::ID, &account);
+    }
+
+    pub async fn lamports(&mut self, key: &Pubkey) -> u64 {
+        self.context.banks_client.get_balance(*key).await.unwrap()
+    }
+
+    /// Creates the associated token account of the `owner`.
+    pub async fn create_token_account(&mut self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let payer = self.context.payer.pubkey();
+        let token_program = self.token_program(mint).await;
+        let create = spl_associated_token_account::instruction::create_associated_token_account(&payer, owner, mint, &token_program);
+        self.process(&[create], &[]).await.unwrap();
+
+        get_associated_token_address_with_program_id(owner, mint, &token_program)
+    }
+
+    pub async fn mint_to(&mut self, mint: &Pubkey, to: &Pubkey, amount: u64) {
+        let owner = clone_keypair(&self.owner);
+        let token_program = self.token_program(mint).await;
+        let mint_to = spl_token_2022::instruction::mint_to(&token_program, mint, to, &owner.pubkey(), &[], amount).unwrap();
+        self.process(&[mint_to], &[&owner]).await.unwrap();
+    }
+
+    /// Mints an NFT to the ATA of the `owner` with the Metaplex metadata
+    /// naming it an item of the `collection`, `verified` or not.
+    pub async fn create_nft(&mut self, owner: &Pubkey, collection: &Pubkey, verified: bool) -> Pubkey {
+        let mint = Keypair::new();
+        self.create_mint_with_decimals(&mint, 0).await;
+        let holder = self.create_token_account(owner, &mint.pubkey()).await;
+        self.mint_to(&mint.pubkey(), &holder, 1).await;
+
+        let data = metadata(&mint.pubkey(), collection, verified);
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let mut account = AccountSharedData::new(rent.minimum_balance(data.len()), data.len(), &staking::nft::metadata_program::ID);
+        account.set_data_from_slice(&data);
+        self.context.set_account(&staking::nft::metadata_address(&mint.pubkey()), &account);
+
+        mint.pubkey()
+    }
+
+    /// Overwrites the account of the staking program at `key` with the `data`, e.g. in an old layout.
+    pub async fn set_program_account(&mut self, key: &Pubkey, data: Vec<u8>) {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let mut account = AccountSharedData::new(rent.minimum_balance(data.len()), data.len(), &staking::ID);
+        account.set_data_from_slice(&data);
+        self.context.set_account(key, &account);
+    }
+
+    /// Overwrites the balance of the token account at `key` of the legacy token program,
+    /// e.g. to leave a vault short of what it owes.
+    pub async fn set_token_balance(&mut self, key: &Pubkey, amount: u64) {
+        let account = self.context.banks_client.get_account(*key).await.unwrap().expect("token account exists");
+        let mut token_account = spl_token::state::Account::unpack(&account.data).unwrap();
+        token_account.amount = amount;
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account::pack(token_account, &mut data).unwrap();
+        let mut account = AccountSharedData::from(account);
+        account.set_data_from_slice(&data);
+        self.context.set_account(key, &account);
+    }
+
+    pub fn remove_account(&mut self, key: &Pubkey) {
+        self.context.set_account(key, &AccountSharedData::default());
+    }
+
+    pub async fn token_balance(&mut self, account: &Pubkey) -> u64 {
+        let account = self.context.banks_client.get_account(*account).await.unwrap().expect("token account exists");
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).unwrap().base.amount
+    }
+
+    pub async fn account_exists(&mut self, key: &Pubkey) -> bool {
+        self.context.banks_client.get_account(*key).await.unwrap().is_some()
+    }
+
+    pub async fn account_len(&mut self, key: &Pubkey) -> usize {
+        self.context.banks_client.get_account(*key).await.unwrap().expect("account exists").data.len()
+    }
+
+    pub async fn account<T: AccountDeserialize>(&mut self, key: &Pubkey) -> T {
+        let account = self.context.banks_client.get_account(*key).await.unwrap().expect("account exists");
+        T::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    /// Queues the `action` of the `proposer`, waits the timelock delay and executes it.
+    pub async fn execute_action(&mut self, proposer: &Keypair, action: AdminAction) -> TestResult {
+        self.execute_action_with_positions(proposer, action, &[]).await
+    }
+
+    /// Executes the `action` settling the rewards of the pool's `positions`.
+    pub async fn execute_action_with_positions(&mut self, proposer: &Keypair, action: AdminAction, positions: &[Pubkey]) -> TestResult {
+        let factory: Factory = self.account(&self.keys.factory.clone()).await;
+        let id = factory.action_count + 1;
+        let eta = self.now().await as u64 + factory.timelock_delay;
+        self.process(&[instruction::queue_action(&self.keys, &proposer.pubkey(), id, action, eta)], &[proposer]).await?;
+        self.warp_by(factory.timelock_delay).await;
+        let queued_action: QueuedAction = self.account(&self.keys.queued_action(id)).await;
+        self.process(&[instruction::execute_action(&self.keys, &queued_action, positions)], &[]).await
+    }
+
+    pub async fn deposit_reward(&mut self, amount: u64) -> TestResult {
+        let owner = clone_keypair(&self.owner);
+        let deposit = instruction::deposit_reward(&self.keys, &owner.pubkey(), &self.owner_reward_vault, amount);
+        self.process(&[deposit], &[&owner]).await
+    }
+
+    pub async fn try_create_stake_pool(&mut self, args: staking::instruction::NewStakePool) -> Result<Pubkey, BanksClientError> {
+        let owner = clone_keypair(&self.owner);
+        let stake_pool = self.keys.stake_pool(&args.name);
+        let factory = self.keys.factory;
+        let pool_id = self.account::<Factory>(&factory).await.pool_count + 1;
+        self.process(&[instruction::new_stake_pool(&self.keys, &owner.pubkey(), pool_id, args)], &[&owner]).await?;
+
+        Ok(stake_pool)
+    }
+
+    pub async fn create_stake_pool(&mut self, name: &str, reward: Reward) -> Pubkey {
+        let args = self.stake_pool_args(name, reward);
+        self.try_create_stake_pool(args).await.unwrap()
+    }
+
+    /// The parameters of a pool with a 10 % fee that lasts `POOL_DURATION` from now.
+    pub fn stake_pool_args(&self, name: &str, reward: Reward) -> staking::instruction::NewStakePool {
+        staking::instruction::NewStakePool {
+            name: name.to_owned(),
+            reward,
+            ends_at: START_TIME as u64 + POOL_DURATION,
+            owner_fee: OwnerFee::new(1000, None).unwrap(),
+            fee_recipient: self.owner.pubkey(),
+            unstake_delay: UNSTAKE_DELAY,
+            reward_period: REWARD_PERIOD,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+        }
+    }
+
+    /// Creates a funded beneficiary with the `Member` account
+    /// and deposits the `tokens` to its `vault_free`.
+    pub async fn new_member(&mut self, tokens: u64) -> TestMember {
+        let beneficiary = Keypair::new();
+        self.airdrop(&beneficiary.pubkey(), 1_000_000_000).await;
+        let stake_token_mint = self.keys.stake_token_mint;
+        let reward_token_mint = self.keys.reward_token_mint;
+        let stake_vault = self.create_token_account(&beneficiary.pubkey(), &stake_token_mint).await;
+        let reward_vault = self.create_token_account(&beneficiary.pubkey(), &reward_token_mint).await;
+        self.mint_to(&stake_token_mint, &stake_vault, tokens).await;
+
+        let member = TestMember { beneficiary, stake_vault, reward_vault };
+        self.process(&[
+            instruction::create_member(&self.keys, &member.key()),
+            instruction::deposit(&self.keys, &member.key(), &member.stake_vault, tokens),
+        ], &[&member.beneficiary]).await.unwrap();
+
+        member
+    }
+
+    pub async fn stake(&mut self, member: &TestMember, stake_pool: &Pubkey, amount: u64) -> TestResult {
+        self.stake_with_proof(member, stake_pool, amount, vec![]).await
+    }
+
+    pub async fn stake_with_proof(&mut self, member: &TestMember, stake_pool: &Pubkey, amount: u64, proof: Vec<MerkleNode>) -> TestResult {
+        let stake = instruction::stake(&self.keys, &member.key(), &member.key(), stake_pool, amount, proof);
+        self.process(&[stake], &[&member.beneficiary]).await
+    }
+
+    pub async fn claim(&mut self, member: &TestMember, stake_pool: &Pubkey) -> TestResult {
+        let claim = instruction::claim_reward(&self.keys, &member.key(), &member.key(), stake_pool, &member.reward_vault);
+        self.process(&[claim], &[&member.beneficiary]).await
+    }
+}
+
+/// The `MetadataV1` account data of the NFT `mint` up to its collection.
+fn metadata(mint: &Pubkey, collection: &Pubkey, verified: bool) -> Vec<u8> {
+    let mut data = vec![4];
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    data.extend_from_slice(mint.as_ref());
+    for text in ["Item", "ITEM", "https://example.com/item.json"] {
+        data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        data.extend_from_slice(text.as_bytes());
+    }
+    // No fee and creators, the primary sale, mutable, no edition nonce and token standard.
+    data.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0]);
+    data.extend_from_slice(&[1, verified as u8]);
+    data.extend_from_slice(collection.as_ref());
+    data.push(0);
+    data
+}
+
+pub fn clone_keypair(keypair: &Keypair) -> Keypair {
+    Keypair::from_bytes(&keypair.to_bytes()).unwrap()
+}
+
+/// Checks the transaction failed with the program's `error`.
+pub fn assert_sp_error(result: TestResult, error: SPError) {
+    assert_custom_error(result, error.into());
+}
+
+pub fn assert_custom_error(result: TestResult, code: u32) {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(_, InstructionError::Custom(actual)))) => {
+            assert_eq!(actual, code, "expected error {}, got {}", code, actual);
+        },
+        other => panic!("expected error {}, got {:?}", code, other),
+    }
+}
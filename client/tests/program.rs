@@ -0,0 +1,1409 @@
+//! Runs the staking program in an in-process bank and warps the clock
+//! to check the rewards and the unstake delay across reward periods.
+//! The program runs natively, so `cargo test` needs no BPF build of it.
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountSerialize, AnchorSerialize, Discriminator};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use common::*;
+use solana_sdk::signature::{Keypair, Signer};
+use staking::account::{
+    Factory, FactoryRoles, Member, MemberStake, NftStake, PoolEntry, PoolStatus, PositionIndex, PositionKind, QueuedAction, StakePool,
+    StakePoolMetadata, ACCOUNT_VERSION,
+};
+use staking::error::SPError;
+use staking::fee::OwnerFee;
+use staking::limits::StakeLimits;
+use staking::migration::v0;
+use staking::nft::NftConfig;
+use staking::reward::Reward;
+use staking::timelock::{AdminAction, MIN_TIMELOCK_DELAY};
+use staking_client::allowlist::Allowlist;
+use staking_client::crank::CrankPosition;
+use staking_client::migration::AccountKind;
+use staking_client::instruction;
+use staking_client::pda;
+
+const STAKED: u64 = 1000;
+
+#[tokio::test]
+async fn stake_claim_harvest_unstake_withdraw() {
+    let mut env = TestEnv::new().await;
+    let factory: Factory = env.account(&env.keys.factory.clone()).await;
+    assert_eq!(factory.owner, env.owner.pubkey());
+
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    env.deposit_reward(10_000).await.unwrap();
+    let vault_reward = env.keys.vault_reward;
+    assert_eq!(env.token_balance(&vault_reward).await, 10_000);
+
+    let member = env.new_member(STAKED).await;
+    env.stake(&member, &stake_pool, STAKED).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.total_staked_tokens, STAKED as u128);
+
+    // The view doesn't change any state.
+    let member_stake = env.keys.member_stake(&stake_pool, &member.key());
+    let view = instruction::get_pending_rewards(&env.keys, &stake_pool, &member_stake);
+    env.process(&[view], &[]).await.unwrap();
+
+    env.warp_by(2 * REWARD_PERIOD + 10).await;
+    env.claim(&member, &stake_pool).await.unwrap();
+
+    // 10 % of the stake for each of two periods, the owner takes 10 % of it.
+    assert_eq!(env.token_balance(&member.reward_vault).await, 180);
+    let vault_fee = env.keys.vault_fee(&stake_pool);
+    assert_eq!(env.token_balance(&vault_fee).await, 20);
+    let stake: MemberStake = env.account(&member_stake).await;
+    assert_eq!(stake.reward_payed_for, START_TIME as u64 + 2 * REWARD_PERIOD);
+
+    let owner = clone_keypair(&env.owner);
+    let harvest = instruction::harvest_fees(&env.keys, &owner.pubkey(), &stake_pool, &env.owner_reward_vault);
+    env.process(&[harvest], &[&owner]).await.unwrap();
+    assert_eq!(env.token_balance(&vault_fee).await, 0);
+    let owner_reward_vault = env.owner_reward_vault;
+    assert_eq!(env.token_balance(&owner_reward_vault).await, OWNER_REWARD_TOKENS - 10_000 + 20);
+
+    let unstake = instruction::start_unstake_all(&env.keys, &member.key(), &stake_pool);
+    env.process(&[unstake], &[&member.beneficiary]).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.total_staked_tokens, 0);
+
+    env.warp_by(UNSTAKE_DELAY).await;
+    let finish = instruction::finish_unstake_all(&env.keys, &member.key(), &stake_pool);
+    env.process(&[finish], &[&member.beneficiary]).await.unwrap();
+    assert!(!env.account_exists(&member_stake).await);
+    assert!(!env.account_exists(&env.keys.member_pending_unstake(&stake_pool, &member.key())).await);
+
+    let withdraw = instruction::withdraw_all(&env.keys, &member.key(), &member.stake_vault);
+    env.process(&[withdraw], &[&member.beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&member.stake_vault).await, STAKED);
+}
+
+#[tokio::test]
+async fn set_fee_recipient_moves_the_harvest() {
+    let mut env = TestEnv::new().await;
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let fee_recipient = Keypair::new();
+
+    let owner = clone_keypair(&env.owner);
+    let set = AdminAction::SetFeeRecipient { stake_pool, fee_recipient: fee_recipient.pubkey() };
+    env.execute_action(&owner, set).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.fee_recipient, fee_recipient.pubkey());
+
+    env.airdrop(&fee_recipient.pubkey(), 1_000_000_000).await;
+    let reward_token_mint = env.keys.reward_token_mint;
+    let recipient_vault = env.create_token_account(&fee_recipient.pubkey(), &reward_token_mint).await;
+    let harvest = instruction::harvest_fees(&env.keys, &fee_recipient.pubkey(), &stake_pool, &recipient_vault);
+    env.process(&[harvest], &[&fee_recipient]).await.unwrap();
+}
+
+#[tokio::test]
+async fn rewards_are_paid_for_full_periods_only() {
+    let mut env = TestEnv::new().await;
+    let stake_pool = env.create_stake_pool("unfixed", Reward::new_unfixed(500)).await;
+    env.deposit_reward(100_000).await.unwrap();
+    let member = env.new_member(STAKED).await;
+    env.stake(&member, &stake_pool, STAKED).await.unwrap();
+
+    env.warp_to(START_TIME + REWARD_PERIOD as i64 - 1);
+    assert_sp_error(env.claim(&member, &stake_pool).await, SPError::RewardPeriodNotPassed);
+
+    env.warp_to(START_TIME + REWARD_PERIOD as i64);
+    env.claim(&member, &stake_pool).await.unwrap();
+    assert_eq!(env.token_balance(&member.reward_vault).await, 450);
+
+    // The next claim counts from the end of the paid period, not from the claim.
+    env.warp_to(START_TIME + 3 * REWARD_PERIOD as i64 - 1);
+    env.claim(&member, &stake_pool).await.unwrap();
+    assert_eq!(env.token_balance(&member.reward_vault).await, 900);
+}
+
+#[tokio::test]
+async fn rewards_stop_at_the_end_of_the_pool() {
+    let mut env = TestEnv::new().await;
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    env.deposit_reward(100_000).await.unwrap();
+    let member = env.new_member(STAKED).await;
+    env.stake(&member, &stake_pool, STAKED).await.unwrap();
+
+    env.warp_to(START_TIME + 10 * POOL_DURATION as i64);
+    env.claim(&member, &stake_pool).await.unwrap();
+
+    let periods = POOL_DURATION / REWARD_PERIOD;
+    assert_eq!(env.token_balance(&member.reward_vault).await, 90 * periods);
+}
+
+#[tokio::test]
+async fn new_stake_pool_fails_on_invalid_params() {
+    let mut env = TestEnv::new().await;
+
+    let outsider = Keypair::new();
+    env.airdrop(&outsider.pubkey(), 1_000_000_000).await;
+    let args = env.stake_pool_args("stranger", Reward::new_fixed(10));
+    let create = instruction::new_stake_pool(&env.keys, &outsider.pubkey(), 1, args);
+    assert_sp_error(env.process(&[create], &[&outsider]).await, SPError::NewPoolOwnerMismatch);
+
+    let mut args = env.stake_pool_args("fee", Reward::new_fixed(10));
+    args.owner_fee = OwnerFee { bps: OwnerFee::MAX_BPS + 1, max_per_claim: None };
+    assert_sp_error(env.try_create_stake_pool(args).await.map(drop), SPError::OwnerFeeWrong);
+
+    let args = env.stake_pool_args("", Reward::new_fixed(10));
+    assert_sp_error(env.try_create_stake_pool(args).await.map(drop), SPError::StakePoolNameWrong);
+    let args = env.stake_pool_args("   ", Reward::new_fixed(10));
+    assert_sp_error(env.try_create_stake_pool(args).await.map(drop), SPError::StakePoolNameWrong);
+    let args = env.stake_pool_args(&"x".repeat(StakePool::NAME_MAX_LEN + 1), Reward::new_fixed(10));
+    assert_sp_error(env.try_create_stake_pool(args).await.map(drop), SPError::StakePoolNameWrong);
+}
+
+#[tokio::test]
+async fn deposits_fail_without_tokens() {
+    let mut env = TestEnv::new().await;
+    let member = env.new_member(STAKED).await;
+
+    let deposit = instruction::deposit(&env.keys, &member.key(), &member.stake_vault, 1);
+    assert_sp_error(env.process(&[deposit], &[&member.beneficiary]).await, SPError::InsufficientAmountOfTokensToDeposit);
+
+    assert_sp_error(env.deposit_reward(OWNER_REWARD_TOKENS + 1).await, SPError::NotEnoughOwnerRewardVaultAmount);
+
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    assert_sp_error(env.stake(&member, &stake_pool, STAKED + 1).await, SPError::NotEnoughFreeVaultAmount);
+}
+
+/// Creates the pool with the `reward`, stakes the `amount` in it
+/// and warps the clock to the end of the first reward period.
+async fn staked_for_one_period(env: &mut TestEnv, reward: Reward, amount: u64) -> (TestMember, Pubkey) {
+    let stake_pool = env.create_stake_pool("pool", reward).await;
+    let member = env.new_member(STAKED).await;
+    env.stake(&member, &stake_pool, amount).await.unwrap();
+    env.warp_by(REWARD_PERIOD).await;
+
+    (member, stake_pool)
+}
+
+#[tokio::test]
+async fn claim_fails_on_empty_reward_vault() {
+    let mut env = TestEnv::new().await;
+    // Fixed pools refuse the stakes the vault can't pay for.
+    let (member, stake_pool) = staked_for_one_period(&mut env, Reward::new_unfixed(100), STAKED).await;
+
+    assert_sp_error(env.claim(&member, &stake_pool).await, SPError::InsufficientAmountOfTokensToClaim);
+}
+
+#[tokio::test]
+async fn claim_fails_on_zero_stake() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10_000).await.unwrap();
+    let (member, stake_pool) = staked_for_one_period(&mut env, Reward::new_fixed(10), 0).await;
+
+    assert_sp_error(env.claim(&member, &stake_pool).await, SPError::UserStakeZero);
+
+    let unstake = instruction::start_unstake_all(&env.keys, &member.key(), &stake_pool);
+    assert_sp_error(env.process(&[unstake], &[&member.beneficiary]).await, SPError::NoStakedTokens);
+}
+
+#[tokio::test]
+async fn claim_fails_on_fixed_rate_out_of_range() {
+    for (rate, error) in [(1, SPError::RewardRateTooSmall), (100, SPError::RewardRateTooHigh)] {
+        let mut env = TestEnv::new().await;
+        env.deposit_reward(10_000).await.unwrap();
+        let (member, stake_pool) = staked_for_one_period(&mut env, Reward::new_fixed(rate), STAKED).await;
+
+        assert_sp_error(env.claim(&member, &stake_pool).await, error);
+    }
+}
+
+#[tokio::test]
+async fn claim_fails_on_nothing_to_share() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10_000).await.unwrap();
+    let (member, stake_pool) = staked_for_one_period(&mut env, Reward::new_unfixed(0), STAKED).await;
+
+    assert_sp_error(env.claim(&member, &stake_pool).await, SPError::TokensToShareEmpty);
+}
+
+#[tokio::test]
+async fn claim_fails_on_reward_overflow() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10_000).await.unwrap();
+    let (member, stake_pool) = staked_for_one_period(&mut env, Reward::new_unfixed(u128::MAX), STAKED).await;
+
+    assert_sp_error(env.claim(&member, &stake_pool).await, SPError::MathOverflow);
+}
+
+#[tokio::test]
+async fn claim_fails_on_zero_reward_period() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10_000).await.unwrap();
+    let mut args = env.stake_pool_args("pool", Reward::new_fixed(10));
+    args.reward_period = 0;
+    let stake_pool = env.try_create_stake_pool(args).await.unwrap();
+    let member = env.new_member(STAKED).await;
+    env.stake(&member, &stake_pool, STAKED).await.unwrap();
+
+    assert_sp_error(env.claim(&member, &stake_pool).await, SPError::DivisionByZero);
+}
+
+#[tokio::test]
+async fn finish_unstake_fails_before_the_delay() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10_000).await.unwrap();
+    let (member, stake_pool) = staked_for_one_period(&mut env, Reward::new_fixed(10), STAKED).await;
+
+    let unstake = instruction::start_unstake_all(&env.keys, &member.key(), &stake_pool);
+    env.process(&[unstake], &[&member.beneficiary]).await.unwrap();
+
+    env.warp_by(UNSTAKE_DELAY - 1).await;
+    let finish = instruction::finish_unstake_all(&env.keys, &member.key(), &stake_pool);
+    assert_sp_error(env.process(std::slice::from_ref(&finish), &[&member.beneficiary]).await, SPError::NotAllowedFinishUnstakeYet);
+
+    env.warp_by(1).await;
+    env.process(&[finish], &[&member.beneficiary]).await.unwrap();
+}
+
+#[tokio::test]
+async fn harvest_fails_for_outsider() {
+    let mut env = TestEnv::new().await;
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let outsider = Keypair::new();
+    env.airdrop(&outsider.pubkey(), 1_000_000_000).await;
+    let reward_token_mint = env.keys.reward_token_mint;
+    let outsider_vault = env.create_token_account(&outsider.pubkey(), &reward_token_mint).await;
+
+    let harvest = instruction::harvest_fees(&env.keys, &outsider.pubkey(), &stake_pool, &outsider_vault);
+    assert_sp_error(env.process(&[harvest], &[&outsider]).await, SPError::HarvestFeesAuthorityMismatch);
+}
+
+#[tokio::test]
+async fn audit_pool_checks_the_positions() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(150).await.unwrap();
+    let stake_pool = env.create_stake_pool("unfixed", Reward::new_unfixed(200)).await;
+    let mut positions = vec![];
+    for _ in 0..2 {
+        let member = env.new_member(STAKED).await;
+        env.stake(&member, &stake_pool, STAKED).await.unwrap();
+        let member_stake = env.keys.member_stake(&stake_pool, &member.key());
+        positions.push((member_stake, env.keys.vault_staked(&member_stake)));
+    }
+
+    let audit = instruction::audit_pool(&env.keys, &stake_pool, &positions);
+    env.process(&[audit], &[]).await.unwrap();
+
+    let audit = instruction::audit_pool(&env.keys, &stake_pool, &positions[..1]);
+    assert_sp_error(env.process(&[audit], &[]).await, SPError::AuditStakedTokensMismatch);
+
+    let audit = instruction::audit_pool(&env.keys, &stake_pool, &[positions[0], positions[0]]);
+    assert_sp_error(env.process(&[audit], &[]).await, SPError::AuditPositionInvalid);
+
+    // Both positions accrue 100 tokens, the vault holds 150.
+    env.warp_by(REWARD_PERIOD).await;
+    let audit = instruction::audit_pool(&env.keys, &stake_pool, &positions);
+    assert_sp_error(env.process(&[audit], &[]).await, SPError::AuditRewardVaultUnderfunded);
+}
+
+#[tokio::test]
+async fn allowlist_gates_the_stake() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(2 * 10 * STAKED).await.unwrap();
+    let partner = env.new_member(STAKED).await;
+    let outsider = env.new_member(STAKED).await;
+    let allowlist = Allowlist::new(&[partner.key(), Pubkey::new_unique()]).unwrap();
+    let mut args = env.stake_pool_args("partners", Reward::new_fixed(10));
+    args.allowlist_root = Some(allowlist.root());
+    let stake_pool = env.try_create_stake_pool(args).await.unwrap();
+
+    let partner_proof = allowlist.proof(&partner.key()).unwrap();
+    assert_sp_error(env.stake(&partner, &stake_pool, STAKED).await, SPError::BeneficiaryNotAllowed);
+    assert_sp_error(
+        env.stake_with_proof(&outsider, &stake_pool, STAKED, partner_proof.clone()).await,
+        SPError::BeneficiaryNotAllowed,
+    );
+    env.stake_with_proof(&partner, &stake_pool, STAKED, partner_proof).await.unwrap();
+
+    // Opening the pool lets everyone in.
+    let owner = clone_keypair(&env.owner);
+    let open = instruction::set_allowlist_root(&env.keys, &owner.pubkey(), &stake_pool, None);
+    env.process(&[open], &[&owner]).await.unwrap();
+    env.stake(&outsider, &stake_pool, STAKED).await.unwrap();
+}
+
+#[tokio::test]
+async fn allowlist_gates_the_nft_stake() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10 * STAKED).await.unwrap();
+    let partner = env.new_member(0).await;
+    let allowlist = Allowlist::new(&[partner.key(), Pubkey::new_unique()]).unwrap();
+    let mut args = env.stake_pool_args("apes", Reward::new_fixed(10));
+    args.allowlist_root = Some(allowlist.root());
+    let stake_pool = env.try_create_stake_pool(args).await.unwrap();
+    let owner = clone_keypair(&env.owner);
+    let collection = Pubkey::new_unique();
+    let nft = NftConfig { collection, base_weight: STAKED, weights_root: None };
+    let set_nft_config = instruction::set_nft_config(&env.keys, &owner.pubkey(), &stake_pool, Some(nft));
+    env.process(&[set_nft_config], &[&owner]).await.unwrap();
+    let mint = env.create_nft(&partner.key(), &collection, true).await;
+    let holder = get_associated_token_address(&partner.key(), &mint);
+
+    let stake = instruction::stake_nft(&env.keys, &partner.key(), &stake_pool, &mint, &spl_token::ID, &holder, 0, vec![], vec![], &[]);
+    assert_sp_error(env.process(&[stake], &[&partner.beneficiary]).await, SPError::BeneficiaryNotAllowed);
+    let proof = allowlist.proof(&partner.key()).unwrap();
+    let stake = instruction::stake_nft(&env.keys, &partner.key(), &stake_pool, &mint, &spl_token::ID, &holder, 0, vec![], proof, &[]);
+    env.process(&[stake], &[&partner.beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&holder).await, 0);
+}
+
+#[tokio::test]
+async fn stakes_respect_the_pool_limits() {
+    let mut env = TestEnv::new().await;
+    let mut args = env.stake_pool_args("capped", Reward::new_unfixed(100));
+    args.limits = StakeLimits { max_total_staked: Some(1500), max_per_member: Some(STAKED), min_stake_amount: 100 };
+    let stake_pool = env.try_create_stake_pool(args).await.unwrap();
+    let [alice, bob] = [env.new_member(2 * STAKED).await, env.new_member(STAKED).await];
+
+    assert_sp_error(env.stake(&alice, &stake_pool, 99).await, SPError::StakeAmountTooSmall);
+    assert_sp_error(env.stake(&alice, &stake_pool, STAKED + 1).await, SPError::MemberStakeLimitExceeded);
+    env.stake(&alice, &stake_pool, STAKED).await.unwrap();
+    assert_sp_error(env.stake(&bob, &stake_pool, 501).await, SPError::StakePoolCapacityExceeded);
+
+    let owner = clone_keypair(&env.owner);
+    env.execute_action(&owner, AdminAction::SetStakeLimits { stake_pool, limits: StakeLimits::default() }).await.unwrap();
+    env.stake(&bob, &stake_pool, STAKED).await.unwrap();
+}
+
+#[tokio::test]
+async fn fixed_pools_commit_their_rewards() {
+    let mut env = TestEnv::new().await;
+    // 10 % of the stake for 100 periods
+    let commitment = 10 * STAKED;
+    env.deposit_reward(commitment).await.unwrap();
+    // The pools last their 100 periods from the end of the timelocks of their partial payments.
+    let delay = 8 * REWARD_PERIOD;
+    let mut args = env.stake_pool_args("fixed", Reward::new_fixed(10));
+    args.ends_at += delay;
+    let fixed = env.try_create_stake_pool(args).await.unwrap();
+    let mut args = env.stake_pool_args("unfixed", Reward::new_unfixed(commitment as u128 / 2));
+    args.ends_at += delay;
+    let unfixed = env.try_create_stake_pool(args).await.unwrap();
+    let [alice, bob, carol] = [env.new_member(STAKED).await, env.new_member(STAKED).await, env.new_member(STAKED).await];
+
+    let owner = clone_keypair(&env.owner);
+    let partial_payments = AdminAction::SetPartialPayments { stake_pool: unfixed, partial_payments: true };
+    assert_sp_error(env.execute_action(&owner, partial_payments).await, SPError::PartialPaymentsNotSupported);
+    let partial_payments = AdminAction::SetPartialPayments { stake_pool: fixed, partial_payments: true };
+    env.execute_action(&owner, partial_payments).await.unwrap();
+    env.warp_to(START_TIME + delay as i64);
+
+    env.stake(&alice, &fixed, STAKED).await.unwrap();
+    assert_sp_error(env.stake(&bob, &fixed, 10).await, SPError::RewardBudgetExceeded);
+    let factory: Factory = env.account(&env.keys.factory.clone()).await;
+    assert_eq!(factory.committed_rewards, commitment as u128);
+
+    // The Unfixed pool can't pay out of the committed rewards.
+    env.stake(&carol, &unfixed, STAKED).await.unwrap();
+    env.warp_by(REWARD_PERIOD).await;
+    assert_sp_error(env.claim(&carol, &unfixed).await, SPError::InsufficientAmountOfTokensToClaim);
+
+    // A vault left with half of the commitments, as by the stakes made before the rewards were committed.
+    let vault_reward = env.keys.vault_reward;
+    env.set_token_balance(&vault_reward, commitment / 2).await;
+
+    // Half of the 100 tokens due is paid, 45 after the fee, the rest is owed.
+    env.claim(&alice, &fixed).await.unwrap();
+    assert_eq!(env.token_balance(&alice.reward_vault).await, 45);
+    let member_stake: MemberStake = env.account(&env.keys.member_stake(&fixed, &alice.key())).await;
+    assert_eq!(member_stake.reward_owed, 50);
+    let factory: Factory = env.account(&env.keys.factory.clone()).await;
+    assert_eq!(factory.committed_rewards, commitment as u128 - 50);
+
+    // The IOU is paid in full once the vault is topped up, without waiting for the next period.
+    env.deposit_reward(commitment / 2).await.unwrap();
+    env.claim(&alice, &fixed).await.unwrap();
+    assert_eq!(env.token_balance(&alice.reward_vault).await, 90);
+    let member_stake: MemberStake = env.account(&env.keys.member_stake(&fixed, &alice.key())).await;
+    assert_eq!(member_stake.reward_owed, 0);
+
+    // Unstaking releases what the stake has committed for the rest of the pool.
+    let unstake = instruction::start_unstake_all(&env.keys, &alice.key(), &fixed);
+    env.process(&[unstake], &[&alice.beneficiary]).await.unwrap();
+    let factory: Factory = env.account(&env.keys.factory.clone()).await;
+    assert_eq!(factory.committed_rewards, 0);
+}
+
+#[tokio::test]
+async fn tokens_sent_to_the_staked_vault_gain_no_rewards() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10 * STAKED).await.unwrap();
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let member = env.new_member(STAKED).await;
+    env.stake(&member, &stake_pool, STAKED).await.unwrap();
+
+    let member_stake = env.keys.member_stake(&stake_pool, &member.key());
+    let vault_staked = env.keys.vault_staked(&member_stake);
+    let stake_token_mint = env.keys.stake_token_mint;
+    env.mint_to(&stake_token_mint, &vault_staked, STAKED).await;
+    let stake: MemberStake = env.account(&member_stake).await;
+    assert_eq!(stake.staked_tokens, STAKED);
+
+    // 10 % of the staked tokens only, the owner takes 10 % of it.
+    env.warp_by(REWARD_PERIOD).await;
+    env.claim(&member, &stake_pool).await.unwrap();
+    assert_eq!(env.token_balance(&member.reward_vault).await, 90);
+
+    // The sent tokens are unstaked with the stake, without touching the stake of the pool.
+    let unstake = instruction::start_unstake_all(&env.keys, &member.key(), &stake_pool);
+    env.process(&[unstake], &[&member.beneficiary]).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.total_staked_tokens, 0);
+    let vault_pending_unstake = env.keys.vault_pending_unstake(&stake_pool);
+    assert_eq!(env.token_balance(&vault_pending_unstake).await, 2 * STAKED);
+}
+
+#[tokio::test]
+async fn only_the_tokens_received_after_the_transfer_fee_are_staked() {
+    // A Token-2022 stake mint withholding 1 % of every transfer.
+    let mut env = TestEnv::new_with_transfer_fee(100).await;
+    env.deposit_reward(100 * STAKED).await.unwrap();
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let member = env.new_member(10_000).await;
+    let vault_free = env.keys.vault_free(&env.keys.member(&member.key()));
+    assert_eq!(env.token_balance(&vault_free).await, 9_900);
+
+    env.stake(&member, &stake_pool, 9_900).await.unwrap();
+    let member_stake = env.keys.member_stake(&stake_pool, &member.key());
+    let stake: MemberStake = env.account(&member_stake).await;
+    assert_eq!(stake.staked_tokens, 9_801);
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.total_staked_tokens, 9_801);
+
+    // The vaults are closed with the fees they withheld harvested to the mint.
+    let unstake = instruction::start_unstake_all(&env.keys, &member.key(), &stake_pool);
+    env.process(&[unstake], &[&member.beneficiary]).await.unwrap();
+    env.warp_by(UNSTAKE_DELAY).await;
+    let finish = instruction::finish_unstake_all(&env.keys, &member.key(), &stake_pool);
+    env.process(&[finish], &[&member.beneficiary]).await.unwrap();
+    assert!(!env.account_exists(&env.keys.vault_staked(&member_stake)).await);
+    assert!(!env.account_exists(&member_stake).await);
+}
+
+#[tokio::test]
+async fn delegate_stakes_and_claims_for_the_beneficiary() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10_000).await.unwrap();
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let [alice, bot] = [env.new_member(STAKED).await, env.new_member(0).await];
+
+    assert_sp_error(
+        env.process(&[instruction::stake(&env.keys, &bot.key(), &alice.key(), &stake_pool, STAKED, vec![])], &[&bot.beneficiary]).await,
+        SPError::NotMemberAuthority,
+    );
+    let set_delegate = instruction::set_delegate(&env.keys, &alice.key(), Some(bot.key()));
+    env.process(&[set_delegate], &[&alice.beneficiary]).await.unwrap();
+
+    let stake = instruction::stake(&env.keys, &bot.key(), &alice.key(), &stake_pool, STAKED, vec![]);
+    env.process(&[stake], &[&bot.beneficiary]).await.unwrap();
+    let member_stake: MemberStake = env.account(&env.keys.member_stake(&stake_pool, &alice.key())).await;
+    assert_eq!(member_stake.beneficiary, alice.key());
+
+    // The reward only goes to the beneficiary's accounts.
+    env.warp_by(REWARD_PERIOD).await;
+    let claim = instruction::claim_reward(&env.keys, &bot.key(), &alice.key(), &stake_pool, &bot.reward_vault);
+    assert_sp_error(env.process(&[claim], &[&bot.beneficiary]).await, SPError::RewardDestinationNotAllowed);
+    let claim = instruction::claim_reward(&env.keys, &bot.key(), &alice.key(), &stake_pool, &alice.reward_vault);
+    env.process(&[claim], &[&bot.beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&alice.reward_vault).await, 90);
+
+    // The stake and the reward tokens differ.
+    let compound = instruction::compound_reward(&env.keys, &bot.key(), &alice.key(), &stake_pool);
+    assert_sp_error(env.process(&[compound], &[&bot.beneficiary]).await, SPError::CompoundNotSupported);
+
+    let revoke = instruction::set_delegate(&env.keys, &alice.key(), None);
+    env.process(&[revoke], &[&alice.beneficiary]).await.unwrap();
+    env.warp_by(REWARD_PERIOD).await;
+    let claim = instruction::claim_reward(&env.keys, &bot.key(), &alice.key(), &stake_pool, &alice.reward_vault);
+    assert_sp_error(env.process(&[claim], &[&bot.beneficiary]).await, SPError::NotMemberAuthority);
+}
+
+#[tokio::test]
+async fn claim_all_pays_every_position_to_the_reward_destination() {
+    let mut env = TestEnv::new().await;
+    // The 10_000 committed by the Fixed pool and the reward of the Unfixed one
+    env.deposit_reward(10_200).await.unwrap();
+    let stake_pools = [
+        env.create_stake_pool("fixed", Reward::new_fixed(10)).await,
+        env.create_stake_pool("unfixed", Reward::new_unfixed(200)).await,
+    ];
+    let alice = env.new_member(2 * STAKED).await;
+    for stake_pool in &stake_pools {
+        env.stake(&alice, stake_pool, STAKED).await.unwrap();
+    }
+    let cold_wallet = Keypair::new().pubkey();
+    let reward_token_mint = env.keys.reward_token_mint;
+    let cold_vault = env.create_token_account(&cold_wallet, &reward_token_mint).await;
+    env.warp_by(REWARD_PERIOD).await;
+
+    let claim_all = instruction::claim_all(&env.keys, &alice.key(), &alice.key(), &stake_pools, &cold_vault);
+    assert_sp_error(env.process(&[claim_all], &[&alice.beneficiary]).await, SPError::RewardDestinationNotAllowed);
+    let set_destination = instruction::set_reward_destination(&env.keys, &alice.key(), Some(cold_vault));
+    env.process(&[set_destination], &[&alice.beneficiary]).await.unwrap();
+
+    let twice = [stake_pools[0], stake_pools[0]];
+    let claim_all = instruction::claim_all(&env.keys, &alice.key(), &alice.key(), &twice, &cold_vault);
+    assert_sp_error(env.process(&[claim_all], &[&alice.beneficiary]).await, SPError::ClaimPositionInvalid);
+
+    // 100 from the Fixed pool and the whole 200 of the Unfixed one, less the 10 % fees.
+    let claim_all = instruction::claim_all(&env.keys, &alice.key(), &alice.key(), &stake_pools, &cold_vault);
+    env.process(&[claim_all], &[&alice.beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&cold_vault).await, 270);
+    assert_eq!(env.token_balance(&env.keys.vault_fee(&stake_pools[0])).await, 10);
+    assert_eq!(env.token_balance(&env.keys.vault_fee(&stake_pools[1])).await, 20);
+
+    // Nothing has accrued since, so the positions are skipped.
+    let claim_all = instruction::claim_all(&env.keys, &alice.key(), &alice.key(), &stake_pools, &alice.reward_vault);
+    env.process(&[claim_all], &[&alice.beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&alice.reward_vault).await, 0);
+}
+
+#[tokio::test]
+async fn crank_claims_pays_opted_in_members_and_tips_the_cranker() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(20 * STAKED).await.unwrap();
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let owner = clone_keypair(&env.owner);
+    env.execute_action(&owner, AdminAction::SetCrankTip { stake_pool, crank_tip_bps: 2000 }).await.unwrap();
+    let [alice, bob, cranker] = [env.new_member(STAKED).await, env.new_member(STAKED).await, env.new_member(0).await];
+    env.stake(&alice, &stake_pool, STAKED).await.unwrap();
+    env.stake(&bob, &stake_pool, STAKED).await.unwrap();
+    let opt_in = instruction::set_auto_claim(&env.keys, &alice.key(), true);
+    env.process(&[opt_in], &[&alice.beneficiary]).await.unwrap();
+    env.warp_by(REWARD_PERIOD).await;
+
+    let position = |member: &TestMember| CrankPosition {
+        stake_pool,
+        beneficiary: member.key(),
+        reward_vault: member.reward_vault,
+    };
+    let crank = instruction::crank_claims(&env.keys, &cranker.key(), &cranker.reward_vault, &[position(&alice), position(&bob)]);
+    assert_sp_error(env.process(&[crank], &[&cranker.beneficiary]).await, SPError::AutoClaimDisabled);
+    let mut stolen = position(&alice);
+    stolen.reward_vault = cranker.reward_vault;
+    let crank = instruction::crank_claims(&env.keys, &cranker.key(), &cranker.reward_vault, &[stolen]);
+    assert_sp_error(env.process(&[crank], &[&cranker.beneficiary]).await, SPError::RewardDestinationNotAllowed);
+
+    // 100 tokens of reward, the owner's fee is 10 and 20 % of it is the tip.
+    let crank = instruction::crank_claims(&env.keys, &cranker.key(), &cranker.reward_vault, &[position(&alice)]);
+    env.process(&[crank], &[&cranker.beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&alice.reward_vault).await, 90);
+    assert_eq!(env.token_balance(&cranker.reward_vault).await, 2);
+    assert_eq!(env.token_balance(&env.keys.vault_fee(&stake_pool)).await, 8);
+}
+
+#[tokio::test]
+async fn nft_pool_stakes_claims_and_returns_the_nft() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10_000).await.unwrap();
+    let stake_pool = env.create_stake_pool("apes", Reward::new_fixed(10)).await;
+    let owner = clone_keypair(&env.owner);
+    let collection = Pubkey::new_unique();
+    let nft = NftConfig { collection, base_weight: STAKED, weights_root: None };
+    let set_nft_config = instruction::set_nft_config(&env.keys, &owner.pubkey(), &stake_pool, Some(nft));
+    env.process(&[set_nft_config], &[&owner]).await.unwrap();
+    let alice = env.new_member(STAKED).await;
+    assert_sp_error(env.stake(&alice, &stake_pool, STAKED).await, SPError::NftPoolRequiresNfts);
+
+    let fake = env.create_nft(&alice.key(), &collection, false).await;
+    let holder = get_associated_token_address(&alice.key(), &fake);
+    let stake = instruction::stake_nft(&env.keys, &alice.key(), &stake_pool, &fake, &spl_token::ID, &holder, 0, vec![], vec![], &[]);
+    assert_sp_error(env.process(&[stake], &[&alice.beneficiary]).await, SPError::NftNotInCollection);
+
+    let mint = env.create_nft(&alice.key(), &collection, true).await;
+    let holder = get_associated_token_address(&alice.key(), &mint);
+    let stake = instruction::stake_nft(&env.keys, &alice.key(), &stake_pool, &mint, &spl_token::ID, &holder, 0, vec![], vec![], &[]);
+    env.process(&[stake], &[&alice.beneficiary]).await.unwrap();
+    let nft_stake_key = env.keys.nft_stake(&stake_pool, &mint);
+    let nft_stake: NftStake = env.account(&nft_stake_key).await;
+    assert_eq!(nft_stake.weight, STAKED);
+    assert_eq!(env.token_balance(&holder).await, 0);
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.total_staked_tokens, STAKED as u128);
+    let set_nft_config = instruction::set_nft_config(&env.keys, &owner.pubkey(), &stake_pool, None);
+    assert_sp_error(env.process(&[set_nft_config], &[&owner]).await, SPError::StakePoolNotEmpty);
+    // The NFT counts for its weight in the audit.
+    let position = (nft_stake_key, nft_stake.vault_nft);
+    env.process(&[instruction::audit_pool(&env.keys, &stake_pool, &[position])], &[]).await.unwrap();
+    assert_sp_error(env.process(&[instruction::audit_pool(&env.keys, &stake_pool, &[])], &[]).await, SPError::AuditStakedTokensMismatch);
+
+    // The NFT counts as 1000 staked tokens: 100 tokens of reward less the 10 % fee.
+    env.warp_by(REWARD_PERIOD).await;
+    let claim = instruction::claim_nft_reward(&env.keys, &alice.key(), &alice.key(), &stake_pool, &mint, &alice.reward_vault);
+    env.process(&[claim], &[&alice.beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&alice.reward_vault).await, 90);
+
+    let unstake = instruction::start_unstake_nft(&env.keys, &alice.key(), &stake_pool, &mint);
+    env.process(&[unstake], &[&alice.beneficiary]).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.total_staked_tokens, 0);
+    env.process(&[instruction::audit_pool(&env.keys, &stake_pool, &[position])], &[]).await.unwrap();
+    let finish = instruction::finish_unstake_nft(&env.keys, &alice.key(), &stake_pool, &mint, &spl_token::ID, &holder);
+    assert_sp_error(env.process(&[finish], &[&alice.beneficiary]).await, SPError::NotAllowedFinishUnstakeYet);
+
+    env.warp_by(UNSTAKE_DELAY).await;
+    let finish = instruction::finish_unstake_nft(&env.keys, &alice.key(), &stake_pool, &mint, &spl_token::ID, &holder);
+    env.process(&[finish], &[&alice.beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&holder).await, 1);
+    assert!(!env.account_exists(&nft_stake_key).await);
+}
+
+#[tokio::test]
+async fn finish_unstake_nft_releases_the_forfeited_reward() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10_000).await.unwrap();
+    let stake_pool = env.create_stake_pool("apes", Reward::new_fixed(10)).await;
+    let owner = clone_keypair(&env.owner);
+    let collection = Pubkey::new_unique();
+    let nft = NftConfig { collection, base_weight: STAKED, weights_root: None };
+    let set_nft_config = instruction::set_nft_config(&env.keys, &owner.pubkey(), &stake_pool, Some(nft));
+    env.process(&[set_nft_config], &[&owner]).await.unwrap();
+    let alice = env.new_member(0).await;
+    let mint = env.create_nft(&alice.key(), &collection, true).await;
+    let holder = get_associated_token_address(&alice.key(), &mint);
+    let stake = instruction::stake_nft(&env.keys, &alice.key(), &stake_pool, &mint, &spl_token::ID, &holder, 0, vec![], vec![], &[]);
+    env.process(&[stake], &[&alice.beneficiary]).await.unwrap();
+
+    // Giving the pool a manager settles the reward accrued by the NFT as owed.
+    let nft_stake_key = env.keys.nft_stake(&stake_pool, &mint);
+    let assign = AdminAction::SetPoolManager { stake_pool, manager: Some(Pubkey::new_unique()) };
+    env.execute_action_with_positions(&owner, assign, &[nft_stake_key]).await.unwrap();
+    let nft_stake: NftStake = env.account(&nft_stake_key).await;
+    assert!(nft_stake.reward_owed > 0);
+
+    // The unstake releases the reward of the periods left, the owed one stays committed until it is forfeited.
+    let unstake = instruction::start_unstake_nft(&env.keys, &alice.key(), &stake_pool, &mint);
+    env.process(&[unstake], &[&alice.beneficiary]).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.committed_rewards, nft_stake.reward_owed as u128);
+
+    env.warp_by(UNSTAKE_DELAY).await;
+    let finish = instruction::finish_unstake_nft(&env.keys, &alice.key(), &stake_pool, &mint, &spl_token::ID, &holder);
+    env.process(&[finish], &[&alice.beneficiary]).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.committed_rewards, 0);
+}
+
+#[tokio::test]
+async fn nft_stakes_count_the_member_nfts_in_the_caps() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(100 * STAKED).await.unwrap();
+    let mut args = env.stake_pool_args("apes", Reward::new_fixed(10));
+    args.limits = StakeLimits { max_total_staked: None, max_per_member: Some(STAKED * 3 / 2), min_stake_amount: 0 };
+    let stake_pool = env.try_create_stake_pool(args).await.unwrap();
+    let owner = clone_keypair(&env.owner);
+    let collection = Pubkey::new_unique();
+    let nft = NftConfig { collection, base_weight: STAKED, weights_root: None };
+    let set_nft_config = instruction::set_nft_config(&env.keys, &owner.pubkey(), &stake_pool, Some(nft));
+    env.process(&[set_nft_config], &[&owner]).await.unwrap();
+    let alice = env.new_member(0).await;
+    let [first, second] = [env.create_nft(&alice.key(), &collection, true).await, env.create_nft(&alice.key(), &collection, true).await];
+    let [first_holder, second_holder] = [get_associated_token_address(&alice.key(), &first), get_associated_token_address(&alice.key(), &second)];
+    let stake = instruction::stake_nft(&env.keys, &alice.key(), &stake_pool, &first, &spl_token::ID, &first_holder, 0, vec![], vec![], &[]);
+    env.process(&[stake], &[&alice.beneficiary]).await.unwrap();
+
+    // The NFT staked already has to be given, and counts for its weight.
+    let first_stake = env.keys.nft_stake(&stake_pool, &first);
+    let stake = instruction::stake_nft(&env.keys, &alice.key(), &stake_pool, &second, &spl_token::ID, &second_holder, 0, vec![], vec![], &[]);
+    assert_sp_error(env.process(&[stake], &[&alice.beneficiary]).await, SPError::PositionIndexWrong);
+    let stake = instruction::stake_nft(&env.keys, &alice.key(), &stake_pool, &second, &spl_token::ID, &second_holder, 0, vec![], vec![], &[first_stake]);
+    assert_sp_error(env.process(&[stake], &[&alice.beneficiary]).await, SPError::MemberStakeLimitExceeded);
+
+    // Once unstaking, it no longer counts.
+    let unstake = instruction::start_unstake_nft(&env.keys, &alice.key(), &stake_pool, &first);
+    env.process(&[unstake], &[&alice.beneficiary]).await.unwrap();
+    let stake = instruction::stake_nft(&env.keys, &alice.key(), &stake_pool, &second, &spl_token::ID, &second_holder, 0, vec![], vec![], &[first_stake]);
+    env.process(&[stake], &[&alice.beneficiary]).await.unwrap();
+}
+
+#[tokio::test]
+async fn deposit_and_withdraw_sol_wrap_the_lamports() {
+    let mut env = TestEnv::new().await;
+    let alice = env.new_member(0).await;
+    let deposit = instruction::deposit_sol(&env.keys, &alice.key(), 1000);
+    assert_sp_error(env.process(&[deposit], &[&alice.beneficiary]).await, SPError::StakeMintNotNative);
+
+    let mut env = TestEnv::new_with_wrapped_sol().await;
+    let alice = Keypair::new();
+    env.airdrop(&alice.pubkey(), 1_000_000_000).await;
+    env.process(&[instruction::create_member(&env.keys, &alice.pubkey())], &[&alice]).await.unwrap();
+    let vault_free = env.keys.vault_free(&env.keys.member(&alice.pubkey()));
+
+    env.process(&[instruction::deposit_sol(&env.keys, &alice.pubkey(), 400_000_000)], &[&alice]).await.unwrap();
+    assert_eq!(env.token_balance(&vault_free).await, 400_000_000);
+
+    let before = env.lamports(&alice.pubkey()).await;
+    env.process(&[instruction::withdraw_sol(&env.keys, &alice.pubkey())], &[&alice]).await.unwrap();
+    assert_eq!(env.token_balance(&vault_free).await, 0);
+    // The transit account's rent is paid back, the fees are paid by the test payer.
+    assert_eq!(env.lamports(&alice.pubkey()).await, before + 400_000_000);
+}
+
+#[tokio::test]
+async fn deposit_and_stake_skips_the_free_vault() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10_000).await.unwrap();
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let beneficiary = Keypair::new();
+    env.airdrop(&beneficiary.pubkey(), 1_000_000_000).await;
+    let stake_token_mint = env.keys.stake_token_mint;
+    let reward_token_mint = env.keys.reward_token_mint;
+    let stake_vault = env.create_token_account(&beneficiary.pubkey(), &stake_token_mint).await;
+    let reward_vault = env.create_token_account(&beneficiary.pubkey(), &reward_token_mint).await;
+    env.mint_to(&stake_token_mint, &stake_vault, 2 * STAKED).await;
+    let bob = TestMember { beneficiary, stake_vault, reward_vault };
+
+    let stake = instruction::deposit_and_stake(&env.keys, &bob.key(), &stake_pool, &bob.stake_vault, STAKED, vec![]);
+    env.process(&[stake], &[&bob.beneficiary]).await.unwrap();
+    let member: Member = env.account(&env.keys.member(&bob.key())).await;
+    assert_eq!(member.beneficiary, bob.key());
+    assert!(!env.account_exists(&member.vault_free).await);
+    let member_stake_key = env.keys.member_stake(&stake_pool, &bob.key());
+    let member_stake: MemberStake = env.account(&member_stake_key).await;
+    assert_eq!(env.token_balance(&member_stake.vault_staked).await, STAKED);
+    assert_eq!(env.token_balance(&bob.stake_vault).await, STAKED);
+
+    env.warp_by(REWARD_PERIOD).await;
+    env.claim(&bob, &stake_pool).await.unwrap();
+    assert_eq!(env.token_balance(&bob.reward_vault).await, 90);
+
+    env.process(&[instruction::start_unstake_all(&env.keys, &bob.key(), &stake_pool)], &[&bob.beneficiary]).await.unwrap();
+    let withdraw = instruction::unstake_and_withdraw(&env.keys, &bob.key(), &stake_pool, &bob.stake_vault);
+    assert_sp_error(env.process(&[withdraw], &[&bob.beneficiary]).await, SPError::NotAllowedFinishUnstakeYet);
+    env.warp_by(UNSTAKE_DELAY).await;
+    let withdraw = instruction::unstake_and_withdraw(&env.keys, &bob.key(), &stake_pool, &bob.stake_vault);
+    env.process(&[withdraw], &[&bob.beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&bob.stake_vault).await, 2 * STAKED);
+    assert!(!env.account_exists(&member_stake_key).await);
+
+    // The existing member is reused.
+    let stake = instruction::deposit_and_stake(&env.keys, &bob.key(), &stake_pool, &bob.stake_vault, STAKED, vec![]);
+    env.process(&[stake], &[&bob.beneficiary]).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.total_staked_tokens, STAKED as u128);
+}
+
+#[tokio::test]
+async fn deposit_and_stake_records_the_free_vault_of_the_token_2022_mint() {
+    // A Token-2022 stake mint withholding 1 % of every transfer.
+    let mut env = TestEnv::new_with_transfer_fee(100).await;
+    env.deposit_reward(100 * STAKED).await.unwrap();
+    let stake_pool = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let beneficiary = Keypair::new();
+    env.airdrop(&beneficiary.pubkey(), 1_000_000_000).await;
+    let stake_token_mint = env.keys.stake_token_mint;
+    let stake_vault = env.create_token_account(&beneficiary.pubkey(), &stake_token_mint).await;
+    env.mint_to(&stake_token_mint, &stake_vault, 20_000).await;
+
+    let stake = instruction::deposit_and_stake(&env.keys, &beneficiary.pubkey(), &stake_pool, &stake_vault, 10_000, vec![]);
+    env.process(&[stake], &[&beneficiary]).await.unwrap();
+    let member_stake: MemberStake = env.account(&env.keys.member_stake(&stake_pool, &beneficiary.pubkey())).await;
+    assert_eq!(member_stake.staked_tokens, 9_900);
+
+    // The free vault is the member's associated token account of the Token-2022 program, so it can be created later.
+    let member_key = env.keys.member(&beneficiary.pubkey());
+    let member: Member = env.account(&member_key).await;
+    assert_eq!(member.vault_free, env.keys.vault_free(&member_key));
+    assert_eq!(env.create_token_account(&member_key, &stake_token_mint).await, member.vault_free);
+
+    let deposit = instruction::deposit(&env.keys, &beneficiary.pubkey(), &stake_vault, 5_000);
+    env.process(&[deposit], &[&beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&member.vault_free).await, 4_950);
+}
+
+#[tokio::test]
+async fn close_member_requires_no_positions_and_returns_the_free_tokens() {
+    let mut env = TestEnv::new().await;
+    let stake_pool = env.create_stake_pool("unfixed", Reward::new_unfixed(100)).await;
+    let alice = env.new_member(STAKED).await;
+    env.stake(&alice, &stake_pool, STAKED).await.unwrap();
+    let member_key = env.keys.member(&alice.key());
+    let member: Member = env.account(&member_key).await;
+    assert_eq!(member.open_positions, 1);
+
+    let close = instruction::close_member(&env.keys, &alice.key(), &alice.stake_vault);
+    assert_sp_error(env.process(&[close], &[&alice.beneficiary]).await, SPError::MemberHasOpenPositions);
+
+    env.process(&[instruction::start_unstake_all(&env.keys, &alice.key(), &stake_pool)], &[&alice.beneficiary]).await.unwrap();
+    env.warp_by(UNSTAKE_DELAY).await;
+    env.process(&[instruction::finish_unstake_all(&env.keys, &alice.key(), &stake_pool)], &[&alice.beneficiary]).await.unwrap();
+    let member: Member = env.account(&member_key).await;
+    assert_eq!(member.open_positions, 0);
+
+    let rent_before = env.lamports(&alice.key()).await;
+    let close = instruction::close_member(&env.keys, &alice.key(), &alice.stake_vault);
+    env.process(&[close], &[&alice.beneficiary]).await.unwrap();
+    assert_eq!(env.token_balance(&alice.stake_vault).await, STAKED);
+    assert!(!env.account_exists(&member_key).await);
+    assert!(!env.account_exists(&member.vault_free).await);
+    assert!(env.lamports(&alice.key()).await > rent_before);
+}
+
+/// The bytes of an account in the version 0 `space`, without the version and the reserved space.
+fn v0_data(discriminator: [u8; 8], account: &impl AnchorSerialize, space: usize) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    account.serialize(&mut data).unwrap();
+    data.resize(8 + space, 0);
+    data
+}
+
+#[tokio::test]
+async fn v0_accounts_are_migrated_to_the_current_layout() {
+    let mut env = TestEnv::new().await;
+    let stake_pool = env.create_stake_pool("unfixed", Reward::new_unfixed(100)).await;
+    let alice = env.new_member(2 * STAKED).await;
+    env.stake(&alice, &stake_pool, STAKED).await.unwrap();
+    let member_key = env.keys.member(&alice.key());
+    let member: Member = env.account(&member_key).await;
+    let member_stake_key = env.keys.member_stake(&stake_pool, &alice.key());
+    let member_stake: MemberStake = env.account(&member_stake_key).await;
+    let factory_key = env.keys.factory;
+    let factory: Factory = env.account(&factory_key).await;
+    let pool: StakePool = env.account(&stake_pool).await;
+
+    let v0_factory = v0::Factory {
+        bump: factory.bump,
+        owner: factory.owner,
+        reward_token_mint: factory.reward_token_mint,
+        stake_token_mint: factory.stake_token_mint,
+        vault_reward: factory.vault_reward,
+    };
+    env.set_program_account(&factory_key, v0_data(Factory::discriminator(), &v0_factory, v0::Factory::SPACE)).await;
+    // The factories of the first deployment had no roles.
+    let roles_key = env.keys.roles();
+    env.remove_account(&roles_key);
+    // The pools of the first deployment were derived from their names.
+    let (baseline_key, baseline_bump) = pda::legacy_stake_pool(&staking::ID, &factory_key, "baseline");
+    let v0_pool = v0::StakePool {
+        name: "baseline".to_owned(),
+        reward_type: pool.reward_type,
+        started_at: pool.started_at,
+        ends_at: pool.ends_at,
+        total_staked_tokens: 0,
+        bump: baseline_bump,
+        owner_interest_percent: 15,
+        unstake_delay: pool.unstake_delay,
+        min_owner_reward: 1,
+        reward_period: pool.reward_period,
+    };
+    env.set_program_account(&baseline_key, v0_data(StakePool::discriminator(), &v0_pool, v0::StakePool::SPACE)).await;
+    let v0_member = v0::Member { beneficiary: member.beneficiary, vault_free: member.vault_free, bump: member.bump };
+    env.set_program_account(&member_key, v0_data(Member::discriminator(), &v0_member, v0::Member::SPACE)).await;
+    let v0_member_stake = v0::MemberStake {
+        stake_pool: member_stake.stake_pool,
+        beneficiary: member_stake.beneficiary,
+        vault_staked: member_stake.vault_staked,
+        staked_at: member_stake.staked_at,
+        reward_payed_for: member_stake.reward_payed_for,
+        bump: member_stake.bump,
+    };
+    let v0_member_stake_data = v0_data(MemberStake::discriminator(), &v0_member_stake, v0::MemberStake::SPACE);
+    env.set_program_account(&member_stake_key, v0_member_stake_data.clone()).await;
+    assert!(env.stake(&alice, &baseline_key, STAKED).await.is_err());
+
+    let remaining_accounts = AccountKind::MemberStake.remaining_accounts(&staking::ID, &member_stake_key, &v0_member_stake_data).unwrap();
+    let migrate = [
+        instruction::migrate(&env.keys, &alice.key(), &factory_key, AccountKind::Factory, &[]),
+        instruction::migrate(&env.keys, &alice.key(), &baseline_key, AccountKind::StakePool, &[]),
+        instruction::migrate(&env.keys, &alice.key(), &member_key, AccountKind::Member, &[]),
+        instruction::migrate(&env.keys, &alice.key(), &member_stake_key, AccountKind::MemberStake, &remaining_accounts),
+    ];
+    env.process(&migrate, &[&alice.beneficiary]).await.unwrap();
+
+    let factory: Factory = env.account(&factory_key).await;
+    assert_eq!(factory.owner, v0_factory.owner);
+    assert_eq!(factory.committed_rewards, 0);
+    assert_eq!(factory.version, ACCOUNT_VERSION);
+    assert_eq!(factory.timelock_delay, MIN_TIMELOCK_DELAY);
+    let roles: FactoryRoles = env.account(&roles_key).await;
+    assert_eq!((roles.pool_creator, roles.reward_funder, roles.pauser), (factory.owner, factory.owner, factory.owner));
+    let baseline: StakePool = env.account(&baseline_key).await;
+    assert_eq!(baseline.name, "baseline");
+    assert_eq!(baseline.owner_fee.bps, 1500);
+    assert_eq!(baseline.fee_recipient, factory.owner);
+    assert_eq!(baseline.vault_fee, env.keys.vault_fee(&baseline_key));
+    assert_eq!(baseline.pda_seed(), b"baseline");
+    assert_eq!(env.token_balance(&baseline.vault_fee).await, 0);
+    let member_stake: MemberStake = env.account(&member_stake_key).await;
+    assert_eq!(member_stake.staked_tokens, STAKED);
+    assert_eq!(member_stake.version, ACCOUNT_VERSION);
+    let member: Member = env.account(&member_key).await;
+    assert_eq!(member.open_positions, 1);
+    assert_eq!(member.version, ACCOUNT_VERSION);
+
+    let again = instruction::migrate(&env.keys, &alice.key(), &member_key, AccountKind::Member, &[]);
+    assert_sp_error(env.process(&[again], &[&alice.beneficiary]).await, SPError::AccountAlreadyMigrated);
+    env.stake(&alice, &baseline_key, STAKED).await.unwrap();
+    let member: Member = env.account(&member_key).await;
+    assert_eq!(member.open_positions, 2);
+}
+
+#[tokio::test]
+async fn pools_are_derived_from_the_hash_of_long_names_and_described_by_the_owner() {
+    let mut env = TestEnv::new().await;
+    let name = "Thirty two bytes long pool name!";
+    let stake_pool = env.create_stake_pool(name, Reward::new_unfixed(100)).await;
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.name, name);
+    assert_eq!(pool.pda_seed(), StakePool::seed_for(name));
+    let padded = env.create_stake_pool(" padded ", Reward::new_unfixed(100)).await;
+    assert_eq!(padded, env.keys.stake_pool("padded"));
+    let pool: StakePool = env.account(&padded).await;
+    assert_eq!(pool.name, "padded");
+    let alice = env.new_member(STAKED).await;
+    env.stake(&alice, &stake_pool, STAKED).await.unwrap();
+
+    let owner = clone_keypair(&env.owner);
+    let metadata_key = env.keys.stake_pool_metadata(&stake_pool);
+    let set = instruction::set_pool_metadata(
+        &env.keys, &owner.pubkey(), &stake_pool, "https://example.com/pool.json".to_owned(), String::new(), vec!["stable".to_owned()],
+    );
+    env.process(&[set], &[&owner]).await.unwrap();
+    let metadata: StakePoolMetadata = env.account(&metadata_key).await;
+    assert_eq!(metadata.stake_pool, stake_pool);
+    assert_eq!(metadata.description_uri, "https://example.com/pool.json");
+    assert_eq!(metadata.tags, vec!["stable".to_owned()]);
+
+    let tags = vec!["tag".to_owned(); StakePoolMetadata::TAGS_MAX + 1];
+    let set = instruction::set_pool_metadata(&env.keys, &owner.pubkey(), &stake_pool, String::new(), String::new(), tags);
+    assert_sp_error(env.process(&[set], &[&owner]).await, SPError::StakePoolMetadataWrong);
+    let set = instruction::set_pool_metadata(&env.keys, &alice.key(), &stake_pool, String::new(), String::new(), vec![]);
+    assert!(env.process(&[set], &[&alice.beneficiary]).await.is_err());
+}
+
+#[tokio::test]
+async fn pools_are_registered_by_id_until_closed() {
+    let mut env = TestEnv::new().await;
+    let fixed = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let unfixed = env.create_stake_pool("unfixed", Reward::new_unfixed(100)).await;
+    let factory_key = env.keys.factory;
+    let factory: Factory = env.account(&factory_key).await;
+    assert_eq!(factory.pool_count, 2);
+    let entry: PoolEntry = env.account(&env.keys.pool_entry(2)).await;
+    assert_eq!(entry.stake_pool, unfixed);
+    assert_eq!(entry.reward_token_mint, env.keys.reward_token_mint);
+    let unfixed_pool: StakePool = env.account(&unfixed).await;
+    assert_eq!(entry.status(&unfixed_pool, START_TIME as u64), PoolStatus::Active);
+    assert_eq!(entry.status(&unfixed_pool, unfixed_pool.ends_at), PoolStatus::Ended);
+    let pool: StakePool = env.account(&fixed).await;
+    assert_eq!(pool.id, 1);
+
+    let owner = clone_keypair(&env.owner);
+    let register = instruction::register_stake_pool(&env.keys, &owner.pubkey(), &fixed, 3);
+    assert_sp_error(env.process(&[register], &[&owner]).await, SPError::StakePoolRegisteredAlready);
+
+    let alice = env.new_member(STAKED).await;
+    env.stake(&alice, &unfixed, STAKED).await.unwrap();
+    let close = instruction::close_stake_pool(&env.keys, &owner.pubkey(), &unfixed, 2);
+    assert_sp_error(env.process(&[close], &[&owner]).await, SPError::StakePoolNotClosable);
+
+    env.warp_by(POOL_DURATION).await;
+    let close = instruction::close_stake_pool(&env.keys, &owner.pubkey(), &unfixed, 2);
+    assert_sp_error(env.process(&[close], &[&owner]).await, SPError::StakePoolNotClosable);
+    env.process(&[instruction::start_unstake_all(&env.keys, &alice.key(), &unfixed)], &[&alice.beneficiary]).await.unwrap();
+    let close = instruction::close_stake_pool(&env.keys, &owner.pubkey(), &unfixed, 2);
+    env.process(&[close], &[&owner]).await.unwrap();
+
+    let entry: PoolEntry = env.account(&env.keys.pool_entry(2)).await;
+    assert_eq!(entry.status(&unfixed_pool, env.now().await as u64), PoolStatus::Closed);
+    env.warp_by(UNSTAKE_DELAY).await;
+    env.process(&[instruction::finish_unstake_all(&env.keys, &alice.key(), &unfixed)], &[&alice.beneficiary]).await.unwrap();
+}
+
+#[tokio::test]
+async fn member_positions_are_indexed_until_closed() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10 * STAKED).await.unwrap();
+    let fixed = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let unfixed = env.create_stake_pool("unfixed", Reward::new_unfixed(100)).await;
+    let alice = env.new_member(2 * STAKED).await;
+    env.stake(&alice, &fixed, STAKED).await.unwrap();
+    env.stake(&alice, &unfixed, STAKED).await.unwrap();
+    env.process(&[instruction::start_unstake_all(&env.keys, &alice.key(), &fixed)], &[&alice.beneficiary]).await.unwrap();
+
+    let member_key = env.keys.member(&alice.key());
+    let member: Member = env.account(&member_key).await;
+    assert_eq!((member.open_positions, member.pending_unstakes), (2, 1));
+    let position_index_key = env.keys.position_index(&alice.key());
+    let position_index: PositionIndex = env.account(&position_index_key).await;
+    assert_eq!(position_index.member, member_key);
+    let indexed: Vec<(PositionKind, Pubkey, Pubkey)> = position_index.positions.iter()
+        .map(|position| (position.kind, position.stake_pool, position.account))
+        .collect();
+    assert_eq!(indexed, vec![
+        (PositionKind::Stake, fixed, env.keys.member_stake(&fixed, &alice.key())),
+        (PositionKind::Stake, unfixed, env.keys.member_stake(&unfixed, &alice.key())),
+        (PositionKind::PendingUnstake, fixed, env.keys.member_pending_unstake(&fixed, &alice.key())),
+    ]);
+
+    env.warp_by(UNSTAKE_DELAY).await;
+    env.process(&[instruction::finish_unstake_all(&env.keys, &alice.key(), &fixed)], &[&alice.beneficiary]).await.unwrap();
+    let position_index: PositionIndex = env.account(&position_index_key).await;
+    assert_eq!(position_index.positions.len(), 1);
+    assert_eq!(position_index.positions[0].stake_pool, unfixed);
+
+    env.process(&[
+        instruction::start_unstake_all(&env.keys, &alice.key(), &unfixed),
+    ], &[&alice.beneficiary]).await.unwrap();
+    env.warp_by(UNSTAKE_DELAY).await;
+    env.process(&[
+        instruction::finish_unstake_all(&env.keys, &alice.key(), &unfixed),
+        instruction::close_member(&env.keys, &alice.key(), &alice.stake_vault),
+    ], &[&alice.beneficiary]).await.unwrap();
+    assert!(!env.account_exists(&position_index_key).await);
+}
+
+#[tokio::test]
+async fn position_index_grows_with_the_positions() {
+    let mut env = TestEnv::new().await;
+    let count = PositionIndex::INITIAL_POSITIONS + 2;
+    let alice = env.new_member(count as u64 * STAKED).await;
+    let position_index_key = env.keys.position_index(&alice.key());
+    assert_eq!(env.account_len(&position_index_key).await, 8 + PositionIndex::SPACE);
+
+    for i in 0..count {
+        let stake_pool = env.create_stake_pool(&format!("pool {}", i), Reward::new_unfixed(100)).await;
+        env.stake(&alice, &stake_pool, STAKED).await.unwrap();
+    }
+
+    let position_index: PositionIndex = env.account(&position_index_key).await;
+    assert_eq!(position_index.positions.len(), count);
+    assert_eq!(env.account_len(&position_index_key).await, 8 + PositionIndex::space(count));
+}
+
+#[tokio::test]
+async fn positions_of_members_opened_before_the_index_are_indexed_once_complete() {
+    let mut env = TestEnv::new().await;
+    env.deposit_reward(10 * STAKED).await.unwrap();
+    let fixed = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let unfixed = env.create_stake_pool("unfixed", Reward::new_unfixed(100)).await;
+    let other = env.create_stake_pool("other", Reward::new_fixed(10)).await;
+    let alice = env.new_member(2 * STAKED).await;
+    env.stake(&alice, &fixed, STAKED).await.unwrap();
+    env.stake(&alice, &unfixed, STAKED).await.unwrap();
+    env.process(&[instruction::start_unstake_all(&env.keys, &alice.key(), &fixed)], &[&alice.beneficiary]).await.unwrap();
+
+    // The member as it was before the index: no index account and no count of the pending unstakes.
+    let member_key = env.keys.member(&alice.key());
+    let mut member: Member = env.account(&member_key).await;
+    member.pending_unstakes = 0;
+    let mut data = vec![];
+    member.try_serialize(&mut data).unwrap();
+    data.resize(8 + Member::SPACE, 0);
+    env.set_program_account(&member_key, data).await;
+    let position_index_key = env.keys.position_index(&alice.key());
+    env.remove_account(&position_index_key);
+    let stake = instruction::deposit_and_stake(&env.keys, &alice.key(), &other, &alice.stake_vault, 0, vec![]);
+    assert_sp_error(env.process(&[stake], &[&alice.beneficiary]).await, SPError::PositionIndexWrong);
+
+    let fixed_stake = env.keys.member_stake(&fixed, &alice.key());
+    let unfixed_stake = env.keys.member_stake(&unfixed, &alice.key());
+    let fixed_unstake = env.keys.member_pending_unstake(&fixed, &alice.key());
+    let index = instruction::index_positions(&env.keys, &alice.key(), &[fixed_stake, fixed_unstake]);
+    assert_sp_error(env.process(&[index], &[&alice.beneficiary]).await, SPError::PositionIndexWrong);
+    let index = instruction::index_positions(&env.keys, &alice.key(), &[fixed_stake, fixed_stake, fixed_unstake]);
+    assert_sp_error(env.process(&[index], &[&alice.beneficiary]).await, SPError::PositionIndexWrong);
+
+    let index = instruction::index_positions(&env.keys, &alice.key(), &[fixed_stake, unfixed_stake, fixed_unstake]);
+    env.process(&[index], &[&alice.beneficiary]).await.unwrap();
+    let member: Member = env.account(&member_key).await;
+    assert_eq!(member.pending_unstakes, 1);
+    let position_index: PositionIndex = env.account(&position_index_key).await;
+    assert_eq!(position_index.positions.len(), 3);
+
+    env.warp_by(UNSTAKE_DELAY).await;
+    env.process(&[instruction::finish_unstake_all(&env.keys, &alice.key(), &fixed)], &[&alice.beneficiary]).await.unwrap();
+    let member: Member = env.account(&member_key).await;
+    assert_eq!((member.open_positions, member.pending_unstakes), (1, 0));
+}
+
+#[tokio::test]
+async fn roles_are_held_apart_and_managers_change_their_pools() {
+    let mut env = TestEnv::new().await;
+    let owner = clone_keypair(&env.owner);
+    let roles_key = env.keys.roles();
+    let roles: FactoryRoles = env.account(&roles_key).await;
+    assert_eq!((roles.pool_creator, roles.reward_funder, roles.pauser), (owner.pubkey(), owner.pubkey(), owner.pubkey()));
+
+    let (creator, funder, pauser, manager) = (Keypair::new(), Keypair::new(), Keypair::new(), Keypair::new());
+    for keypair in [&creator, &funder, &pauser, &manager] {
+        env.airdrop(&keypair.pubkey(), 1_000_000_000).await;
+    }
+    let set = AdminAction::SetRoles { pool_creator: creator.pubkey(), reward_funder: funder.pubkey(), pauser: pauser.pubkey() };
+    assert_sp_error(env.execute_action(&creator, set).await, SPError::RoleRequired);
+    env.execute_action(&owner, set).await.unwrap();
+
+    // The admin keeps every role, the others only their own.
+    let stake_pool = env.create_stake_pool("admin", Reward::new_unfixed(100)).await;
+    let args = env.stake_pool_args("funder", Reward::new_fixed(10));
+    let create = instruction::new_stake_pool(&env.keys, &funder.pubkey(), 2, args);
+    assert_sp_error(env.process(&[create], &[&funder]).await, SPError::NewPoolOwnerMismatch);
+    let args = env.stake_pool_args("creator", Reward::new_fixed(10));
+    let create = instruction::new_stake_pool(&env.keys, &creator.pubkey(), 2, args);
+    env.process(&[create], &[&creator]).await.unwrap();
+    let funder_vault = env.create_token_account(&funder.pubkey(), &env.keys.reward_token_mint.clone()).await;
+    env.mint_to(&env.keys.reward_token_mint.clone(), &funder_vault, 100).await;
+    let deposit = instruction::deposit_reward(&env.keys, &funder.pubkey(), &funder_vault, 100);
+    env.process(&[deposit], &[&funder]).await.unwrap();
+    let deposit = instruction::deposit_reward(&env.keys, &creator.pubkey(), &funder_vault, 100);
+    assert_sp_error(env.process(&[deposit], &[&creator]).await, SPError::RoleRequired);
+
+    let root = Some([7; 32]);
+    let allowlist = instruction::set_allowlist_root(&env.keys, &manager.pubkey(), &stake_pool, root);
+    assert_sp_error(env.process(&[allowlist], &[&manager]).await, SPError::NotPoolManager);
+    let assign = AdminAction::SetPoolManager { stake_pool, manager: Some(manager.pubkey()) };
+    assert_sp_error(env.execute_action(&funder, assign).await, SPError::RoleRequired);
+    env.execute_action(&creator, assign).await.unwrap();
+    let allowlist = instruction::set_allowlist_root(&env.keys, &manager.pubkey(), &stake_pool, root);
+    env.process(&[allowlist], &[&manager]).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.allowlist_root, root);
+    let allowlist = instruction::set_allowlist_root(&env.keys, &manager.pubkey(), &stake_pool, None);
+    env.process(&[allowlist], &[&manager]).await.unwrap();
+    let limits = StakeLimits { min_stake_amount: 10, ..StakeLimits::default() };
+    env.execute_action(&manager, AdminAction::SetStakeLimits { stake_pool, limits }).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.limits, limits);
+    // The fees and the lifecycle of the pool stay with the admin.
+    let tip = AdminAction::SetCrankTip { stake_pool, crank_tip_bps: 50 };
+    assert_sp_error(env.execute_action(&manager, tip).await, SPError::RoleRequired);
+    let close = instruction::close_stake_pool(&env.keys, &manager.pubkey(), &stake_pool, 1);
+    assert_custom_error(env.process(&[close], &[&manager]).await, ErrorCode::ConstraintHasOne.into());
+    let manager_vault = env.create_token_account(&manager.pubkey(), &env.keys.reward_token_mint.clone()).await;
+    env.mint_to(&env.keys.reward_token_mint.clone(), &manager_vault, 300).await;
+    let fund = instruction::fund_stake_pool(&env.keys, &manager.pubkey(), &manager_vault, &stake_pool, 300);
+    env.process(&[fund], &[&manager]).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!((pool.manager, pool.funded_rewards), (Some(manager.pubkey()), 300));
+    let vault_reward = env.keys.vault_reward;
+    assert_eq!(env.token_balance(&vault_reward).await, 400);
+
+    let [alice, bob] = [env.new_member(STAKED).await, env.new_member(STAKED).await];
+    env.stake(&alice, &stake_pool, STAKED).await.unwrap();
+    let pause = instruction::pause(&env.keys, &manager.pubkey());
+    assert_sp_error(env.process(&[pause], &[&manager]).await, SPError::RoleRequired);
+    let pause = instruction::pause(&env.keys, &pauser.pubkey());
+    env.process(&[pause], &[&pauser]).await.unwrap();
+    assert_sp_error(env.stake(&bob, &stake_pool, STAKED).await, SPError::FactoryPaused);
+    env.process(&[instruction::start_unstake_all(&env.keys, &alice.key(), &stake_pool)], &[&alice.beneficiary]).await.unwrap();
+    env.execute_action(&pauser, AdminAction::Unpause).await.unwrap();
+    env.stake(&bob, &stake_pool, STAKED).await.unwrap();
+
+    // The actions of the proposers who lost their roles meanwhile are not executed.
+    let now = env.now().await as u64;
+    let factory: Factory = env.account(&env.keys.factory.clone()).await;
+    let (id, eta) = (factory.action_count + 1, now + MIN_TIMELOCK_DELAY);
+    let remove = AdminAction::SetPoolManager { stake_pool, manager: None };
+    env.process(&[instruction::queue_action(&env.keys, &creator.pubkey(), id, remove, eta)], &[&creator]).await.unwrap();
+    let set = AdminAction::SetRoles { pool_creator: owner.pubkey(), reward_funder: funder.pubkey(), pauser: pauser.pubkey() };
+    env.process(&[instruction::queue_action(&env.keys, &owner.pubkey(), id + 1, set, eta)], &[&owner]).await.unwrap();
+    env.warp_by(MIN_TIMELOCK_DELAY).await;
+    let queued_remove: QueuedAction = env.account(&env.keys.queued_action(id)).await;
+    let queued_set: QueuedAction = env.account(&env.keys.queued_action(id + 1)).await;
+    env.process(&[instruction::execute_action(&env.keys, &queued_set, &[])], &[]).await.unwrap();
+    let execute = instruction::execute_action(&env.keys, &queued_remove, &[]);
+    assert_sp_error(env.process(&[execute], &[]).await, SPError::RoleRequired);
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.manager, Some(manager.pubkey()));
+}
+
+#[tokio::test]
+async fn managed_pools_pay_their_rewards_from_their_funds_only() {
+    let mut env = TestEnv::new().await;
+    let owner = clone_keypair(&env.owner);
+    let factory_key = env.keys.factory;
+    let shared = env.create_stake_pool("shared", Reward::new_unfixed(100)).await;
+    let managed = env.create_stake_pool("managed", Reward::new_unfixed(100)).await;
+    let manager = Keypair::new();
+    env.airdrop(&manager.pubkey(), 1_000_000_000).await;
+    env.execute_action(&owner, AdminAction::SetPoolManager { stake_pool: managed, manager: Some(manager.pubkey()) }).await.unwrap();
+    let manager_vault = env.create_token_account(&manager.pubkey(), &env.keys.reward_token_mint.clone()).await;
+    env.mint_to(&env.keys.reward_token_mint.clone(), &manager_vault, 1000).await;
+    let fund = instruction::fund_stake_pool(&env.keys, &owner.pubkey(), &env.owner_reward_vault, &shared, 100);
+    assert_sp_error(env.process(&[fund], &[&owner]).await, SPError::StakePoolNotManaged);
+    let fund = instruction::fund_stake_pool(&env.keys, &manager.pubkey(), &manager_vault, &managed, 1000);
+    env.process(&[fund], &[&manager]).await.unwrap();
+
+    // The funds of the managed pool are not shared with the other pools.
+    let alice = env.new_member(2 * STAKED).await;
+    env.stake(&alice, &shared, STAKED).await.unwrap();
+    env.stake(&alice, &managed, STAKED).await.unwrap();
+    env.warp_by(REWARD_PERIOD).await;
+    assert_sp_error(env.claim(&alice, &shared).await, SPError::InsufficientAmountOfTokensToClaim);
+    env.claim(&alice, &managed).await.unwrap();
+    let pool: StakePool = env.account(&managed).await;
+    let factory: Factory = env.account(&factory_key).await;
+    assert!(pool.funded_rewards < 1000);
+    assert_eq!(factory.funded_rewards, pool.funded_rewards);
+
+    // Without a manager the funds left go to every pool, once the rewards accrued meanwhile are settled.
+    let positions = [env.keys.member_stake(&managed, &alice.key())];
+    let remove = AdminAction::SetPoolManager { stake_pool: managed, manager: None };
+    assert_sp_error(env.execute_action(&owner, remove).await, SPError::SettlementPositionsWrong);
+    env.execute_action_with_positions(&owner, remove, &positions).await.unwrap();
+    let pool: StakePool = env.account(&managed).await;
+    let factory: Factory = env.account(&factory_key).await;
+    assert_eq!((pool.funded_rewards, factory.funded_rewards), (0, 0));
+    // A single period's reward however many periods the two timelocks took
+    let member_stake: MemberStake = env.account(&positions[0]).await;
+    assert_eq!(member_stake.reward_owed, 100);
+    env.claim(&alice, &shared).await.unwrap();
+}
+
+#[tokio::test]
+async fn reward_and_manager_changes_settle_the_accrued_rewards_first() {
+    let mut env = TestEnv::new().await;
+    let owner = clone_keypair(&env.owner);
+    let vault_reward = env.keys.vault_reward;
+    let stake_pool = env.create_stake_pool("unfixed", Reward::new_unfixed(100)).await;
+    env.deposit_reward(10 * STAKED).await.unwrap();
+    let alice = env.new_member(STAKED).await;
+    env.stake(&alice, &stake_pool, STAKED).await.unwrap();
+    let positions = [env.keys.member_stake(&stake_pool, &alice.key())];
+
+    // The periods passed before the new rate keep the old one, a single period's reward of the pool.
+    let reward = AdminAction::SetReward { stake_pool, reward: Reward::new_unfixed(1000) };
+    assert_sp_error(env.execute_action(&owner, reward).await, SPError::SettlementPositionsWrong);
+    env.execute_action_with_positions(&owner, reward, &positions).await.unwrap();
+    let member_stake: MemberStake = env.account(&positions[0]).await;
+    assert_eq!(member_stake.reward_owed, 100);
+    env.claim(&alice, &stake_pool).await.unwrap();
+    assert_eq!(env.token_balance(&vault_reward).await, 10 * STAKED - 100);
+
+    // A pool getting a manager keeps the shared rewards accrued by its positions, at the new rate.
+    env.warp_by(REWARD_PERIOD).await;
+    let manager = Keypair::new();
+    let assign = AdminAction::SetPoolManager { stake_pool, manager: Some(manager.pubkey()) };
+    env.execute_action_with_positions(&owner, assign, &positions).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    let factory: Factory = env.account(&env.keys.factory.clone()).await;
+    assert_eq!((pool.funded_rewards, factory.funded_rewards), (1000, 1000));
+    env.claim(&alice, &stake_pool).await.unwrap();
+    assert_eq!(env.token_balance(&vault_reward).await, 10 * STAKED - 1100);
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!(pool.funded_rewards, 0);
+}
+
+#[tokio::test]
+async fn fixed_pools_commit_the_rewards_of_their_own_partition() {
+    let mut env = TestEnv::new().await;
+    let owner = clone_keypair(&env.owner);
+    let factory_key = env.keys.factory;
+    let managed = env.create_stake_pool("managed", Reward::new_unfixed(100)).await;
+    let fixed = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let manager = Keypair::new();
+    env.airdrop(&manager.pubkey(), 1_000_000_000).await;
+    env.execute_action(&owner, AdminAction::SetPoolManager { stake_pool: managed, manager: Some(manager.pubkey()) }).await.unwrap();
+    let manager_vault = env.create_token_account(&manager.pubkey(), &env.keys.reward_token_mint.clone()).await;
+    env.mint_to(&env.keys.reward_token_mint.clone(), &manager_vault, 10 * STAKED).await;
+    let fund = instruction::fund_stake_pool(&env.keys, &manager.pubkey(), &manager_vault, &managed, 10 * STAKED);
+    env.process(&[fund], &[&manager]).await.unwrap();
+
+    // The vault holds enough for the commitment, but only as the funds of the managed pool.
+    let alice = env.new_member(STAKED).await;
+    assert_sp_error(env.stake(&alice, &fixed, STAKED).await, SPError::RewardBudgetExceeded);
+    env.deposit_reward(10 * STAKED).await.unwrap();
+    env.stake(&alice, &fixed, STAKED).await.unwrap();
+    let pool: StakePool = env.account(&fixed).await;
+    let factory: Factory = env.account(&factory_key).await;
+    assert!(pool.committed_rewards > 0);
+    assert_eq!(factory.committed_rewards, pool.committed_rewards);
+    let pool: StakePool = env.account(&managed).await;
+    assert_eq!((pool.funded_rewards, pool.committed_rewards), (10 * STAKED, 0));
+}
+
+#[tokio::test]
+async fn unfixed_pools_leave_the_rewards_committed_by_the_fixed_pools() {
+    let mut env = TestEnv::new().await;
+    let factory_key = env.keys.factory;
+    let vault_reward = env.keys.vault_reward;
+    let fixed = env.create_stake_pool("fixed", Reward::new_fixed(10)).await;
+    let unfixed = env.create_stake_pool("unfixed", Reward::new_unfixed(10 * STAKED as u128)).await;
+    env.deposit_reward(10 * STAKED).await.unwrap();
+    let alice = env.new_member(STAKED).await;
+    env.stake(&alice, &fixed, STAKED).await.unwrap();
+    let bob = env.new_member(STAKED).await;
+    env.stake(&bob, &unfixed, STAKED).await.unwrap();
+    let factory: Factory = env.account(&factory_key).await;
+    let committed = factory.committed_rewards as u64;
+    assert!(committed > 0);
+
+    // The whole reward of the Unfixed pool would spend the rewards committed to the Fixed pool.
+    env.warp_by(REWARD_PERIOD).await;
+    assert_sp_error(env.claim(&bob, &unfixed).await, SPError::InsufficientAmountOfTokensToClaim);
+    assert_eq!(env.token_balance(&vault_reward).await, 10 * STAKED);
+
+    // It is paid once the vault holds its reward on top of the committed ones.
+    env.deposit_reward(committed).await.unwrap();
+    env.claim(&bob, &unfixed).await.unwrap();
+    assert_eq!(env.token_balance(&vault_reward).await, committed);
+    env.claim(&alice, &fixed).await.unwrap();
+}
+
+#[tokio::test]
+async fn admin_actions_wait_the_timelock_and_may_be_cancelled() {
+    let mut env = TestEnv::new().await;
+    let owner = clone_keypair(&env.owner);
+    let outsider = Keypair::new();
+    env.airdrop(&outsider.pubkey(), 1_000_000_000).await;
+    let factory_key = env.keys.factory;
+    let stake_pool = env.create_stake_pool("unfixed", Reward::new_unfixed(100)).await;
+
+    // The new factories wait the shortest delay, which is never lifted.
+    let factory: Factory = env.account(&factory_key).await;
+    assert_eq!(factory.timelock_delay, MIN_TIMELOCK_DELAY);
+    let now = env.now().await as u64;
+    let delay = AdminAction::SetTimelockDelay { delay: 0 };
+    let queue = instruction::queue_action(&env.keys, &owner.pubkey(), 1, delay, now + MIN_TIMELOCK_DELAY);
+    assert_sp_error(env.process(&[queue], &[&owner]).await, SPError::TimelockDelayWrong);
+    let delay = AdminAction::SetTimelockDelay { delay: 7200 };
+    let queue = instruction::queue_action(&env.keys, &owner.pubkey(), 1, delay, now + MIN_TIMELOCK_DELAY - 1);
+    assert_sp_error(env.process(&[queue], &[&owner]).await, SPError::TimelockEtaWrong);
+    env.process(&[instruction::queue_action(&env.keys, &owner.pubkey(), 1, delay, now + MIN_TIMELOCK_DELAY)], &[&owner]).await.unwrap();
+    env.warp_by(MIN_TIMELOCK_DELAY).await;
+    let queued_action: QueuedAction = env.account(&env.keys.queued_action(1)).await;
+    env.process(&[instruction::execute_action(&env.keys, &queued_action, &[])], &[]).await.unwrap();
+    let factory: Factory = env.account(&factory_key).await;
+    assert_eq!((factory.timelock_delay, factory.action_count), (7200, 1));
+    assert!(!env.account_exists(&env.keys.queued_action(1)).await);
+
+    let now = env.now().await as u64;
+    let fee = AdminAction::SetOwnerFee { stake_pool, owner_fee: OwnerFee::new(500, Some(10)).unwrap() };
+    let queue = instruction::queue_action(&env.keys, &owner.pubkey(), 2, fee, now + 7199);
+    assert_sp_error(env.process(&[queue], &[&owner]).await, SPError::TimelockEtaWrong);
+    let queue = instruction::queue_action(&env.keys, &outsider.pubkey(), 2, fee, now + 7200);
+    assert_sp_error(env.process(&[queue], &[&outsider]).await, SPError::RoleRequired);
+    let queue = instruction::queue_action(&env.keys, &owner.pubkey(), 2, fee, now + 7200);
+    env.process(&[queue], &[&owner]).await.unwrap();
+    let queued_action: QueuedAction = env.account(&env.keys.queued_action(2)).await;
+    assert_eq!((queued_action.proposer, queued_action.eta), (owner.pubkey(), now + 7200));
+    let execute = instruction::execute_action(&env.keys, &queued_action, &[]);
+    assert_sp_error(env.process(&[execute], &[]).await, SPError::TimelockNotElapsed);
+    env.warp_by(7200).await;
+    env.process(&[instruction::execute_action(&env.keys, &queued_action, &[])], &[]).await.unwrap();
+    let pool: StakePool = env.account(&stake_pool).await;
+    assert_eq!((pool.owner_fee.bps, pool.owner_fee.max_per_claim), (500, Some(10)));
+
+    let now = env.now().await as u64;
+    let transfer = AdminAction::TransferOwnership { owner: outsider.pubkey() };
+    env.process(&[instruction::queue_action(&env.keys, &owner.pubkey(), 3, transfer, now + 7200)], &[&owner]).await.unwrap();
+    let queued_action: QueuedAction = env.account(&env.keys.queued_action(3)).await;
+    let cancel = instruction::cancel_action(&env.keys, &outsider.pubkey(), &queued_action);
+    assert_sp_error(env.process(&[cancel], &[&outsider]).await, SPError::RoleRequired);
+    env.process(&[instruction::cancel_action(&env.keys, &owner.pubkey(), &queued_action)], &[&owner]).await.unwrap();
+    assert!(!env.account_exists(&env.keys.queued_action(3)).await);
+    env.warp_by(7200).await;
+    let factory: Factory = env.account(&factory_key).await;
+    assert_eq!((factory.owner, factory.action_count), (owner.pubkey(), 3));
+}
@@ -1,13 +1,34 @@
 use anchor_lang::prelude::*;
 use crate::context::*;
 use crate::error::SPError;
+use crate::math::TryMath;
 
-pub fn allow_finish_unstake<'info>(ctx: &Context<FinishUnstakeAll>) -> Result<()> {
+pub fn allow_finish_unstake(ctx: &Context<FinishUnstakeAll>) -> Result<()> {
     let unstaked_at: u64 = ctx.accounts.member_pending_unstake.unstaked_at;
     let unstake_delay: u64 = ctx.accounts.stake_pool.unstake_delay;
     let current_time: u64 = ctx.accounts.clock.unix_timestamp as u64; 
 
-    require!(current_time >= unstaked_at + unstake_delay, SPError::NotAllowedFinishUnstakeYet);
+    require!(current_time >= unstaked_at.try_add(unstake_delay)?, SPError::NotAllowedFinishUnstakeYet);
+
+    Ok(())
+}
+
+pub fn allow_unstake_and_withdraw(ctx: &Context<UnstakeAndWithdraw>) -> Result<()> {
+    let unstaked_at: u64 = ctx.accounts.member_pending_unstake.unstaked_at;
+    let unstake_delay: u64 = ctx.accounts.stake_pool.unstake_delay;
+    let current_time: u64 = ctx.accounts.clock.unix_timestamp as u64;
+
+    require!(current_time >= unstaked_at.try_add(unstake_delay)?, SPError::NotAllowedFinishUnstakeYet);
+
+    Ok(())
+}
+
+pub fn allow_finish_unstake_nft(ctx: &Context<FinishUnstakeNft>) -> Result<()> {
+    let unstaked_at: u64 = ctx.accounts.nft_stake.unstaked_at.ok_or(SPError::NftUnstakeNotStarted)?;
+    let unstake_delay: u64 = ctx.accounts.stake_pool.unstake_delay;
+    let current_time: u64 = ctx.accounts.clock.unix_timestamp as u64;
+
+    require!(current_time >= unstaked_at.try_add(unstake_delay)?, SPError::NotAllowedFinishUnstakeYet);
 
     Ok(())
 }
@@ -1,12 +1,29 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use crate::error::SPError;
+use crate::math::TryMath;
 use crate::reward::Reward;
+use crate::fee::OwnerFee;
+use crate::limits::StakeLimits;
+use crate::merkle::MerkleNode;
+use crate::nft::NftConfig;
+use crate::timelock::AdminAction;
+
+/// The layout version of the accounts created or migrated by this program.
+/// The accounts of the version 0 have neither the `version` nor the `reserved` space
+/// and are upgraded by the `migrate_*` instructions.
+/// The fields added later go between the `version` and the `reserved` space, shrinking the latter.
+pub const ACCOUNT_VERSION: u8 = 1;
 
 /// The program main state.
-/// These parameters cannot be changed after the initialization.
+/// The mints and the `vault_reward` are fixed at the initialization. The admin, the pause and the `timelock_delay`
+/// change through the queued actions, the counters and the rewards as the pools, the actions and the claims go.
 #[account]
 pub struct Factory {
     pub bump: u8,
-    /// The owner of the stake pool factory program.
+    /// The admin of the stake pool factory program. It assigns the `FactoryRoles`
+    /// and holds all of them, as well as the rights of the managers of every pool.
+    /// Changed by the `TransferOwnership` queued action only.
     pub owner: Pubkey,
     /// Describes the type of the reward tokens.
     /// The mint itself does not need to be under control of the stake pool owner.
@@ -15,17 +32,78 @@ pub struct Factory {
     /// Describes the type of the tokens that are allowed to be staked.
     /// The mint itself does not need to be under control of the stake pool owner or a Member.
     /// It could be the wrapped Sol mint or any other spl token mint.
+    /// With the wrapped Sol the members deposit and withdraw SOL by `deposit_sol` and `withdraw_sol`.
     pub stake_token_mint: Pubkey,
     /// The vault with the reward tokens to be transferred to the members on demand.
     pub vault_reward: Pubkey,
+    /// The rewards the Fixed pools without a manager have promised to their members and not paid yet,
+    /// including the IOUs. New stakes are refused when the `vault_reward` left to those pools can't cover it.
+    pub committed_rewards: u128,
+    /// The layout version, `ACCOUNT_VERSION` once initialized or migrated.
+    pub version: u8,
+    /// The stake pools in the registry, the id of the last `PoolEntry`.
+    pub pool_count: u64,
+    /// Set by the pauser to stop the stakes and the claims. Unstaking and withdrawing are never paused.
+    /// Lifted by the `Unpause` queued action only.
+    pub paused: bool,
+    /// The seconds the `QueuedAction`s wait before they become executable, from the `MIN_TIMELOCK_DELAY`
+    /// to the `MAX_TIMELOCK_DELAY`.
+    pub timelock_delay: u64,
+    /// The actions queued so far, the id of the last `QueuedAction`.
+    pub action_count: u64,
+    /// The `funded_rewards` of all the pools, kept in the `vault_reward` for those pools only.
+    pub funded_rewards: u64,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [u8; 31],
 }
 
 impl Factory {
-    pub const SPACE: usize = 1 + 32 * 4;
+    pub const SPACE: usize = 1 + 32 * 4 + 16 + 1 + 8 + 1 + 8 + 8 + 8 + 31;
     pub const PDA_KEY: &'static str = "factory";
     pub const PDA_SEED: & 'static [u8] = Self::PDA_KEY.as_bytes();
 }
 
+/// A role of the `FactoryRoles`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    /// Creates and registers the stake pools and assigns their managers.
+    PoolCreator,
+    /// Deposits the reward tokens into the factory's `vault_reward`.
+    RewardFunder,
+    /// Pauses and resumes the stakes and the claims.
+    Pauser,
+}
+
+/// The keys holding the roles of the factory besides its admin, the factory's `owner`.
+/// Created along with the factory, or by `migrate_factory` for the factories created before.
+/// Assigned by the `SetRoles` queued action.
+#[account]
+pub struct FactoryRoles {
+    pub pool_creator: Pubkey,
+    pub reward_funder: Pubkey,
+    pub pauser: Pubkey,
+    pub bump: u8,
+    /// The layout version, `ACCOUNT_VERSION` once initialized.
+    pub version: u8,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [u8; 64],
+}
+
+impl FactoryRoles {
+    pub const SPACE: usize = 32 * 3 + 1 + 1 + 64;
+    pub const PDA_SEED: &'static [u8] = b"roles";
+
+    /// Checks the `key` holds the `role` or is the admin of the `factory`.
+    pub fn allows(&self, factory: &Factory, role: Role, key: &Pubkey) -> bool {
+        let holder = match role {
+            Role::PoolCreator => &self.pool_creator,
+            Role::RewardFunder => &self.reward_funder,
+            Role::Pauser => &self.pauser,
+        };
+        key == holder || *key == factory.owner
+    }
+}
+
 #[account]
 pub struct StakePool {
     pub name: String,
@@ -48,19 +126,207 @@ pub struct StakePool {
     /// when this config was active.
     pub total_staked_tokens: u128,
     pub bump: u8,
-    /// The percentage of reward tokens the owner will receive from each user's reward.
-    pub owner_interest_percent: u8,
+    /// The share of each user's reward the owner will receive.
+    pub owner_fee: OwnerFee,
+    /// The wallet that receives the fees harvested from the `vault_fee`.
+    pub fee_recipient: Pubkey,
+    /// The vault owned by the stake pool where the owner's fees are accrued on each claim.
+    pub vault_fee: Pubkey,
     /// The time in seconds a Member have to wait to unstake the tokens
     pub unstake_delay: u64,
-    /// Minimum amount of reward tokens the owner will receive from each user's reward.
-    pub min_owner_reward: u32,
     /// The time in seconds a Member have to wait to receive the next reward.
     /// After each `reward_period` the Member are allowed to claim the reward.
     pub reward_period: u64,
+    /// The merkle root of the beneficiaries allowed to stake in the pool.
+    /// `None` means everyone is allowed.
+    pub allowlist_root: Option<MerkleNode>,
+    /// The amounts of tokens the pool accepts on stake.
+    pub limits: StakeLimits,
+    /// When the `vault_reward` is short of the `committed_rewards`, claims are paid pro rata
+    /// and the rest is owed, instead of failing. Fixed pools only.
+    pub partial_payments: bool,
+    /// The share of the owner's fee paid to the cranker of the auto-claims, in basis points.
+    pub crank_tip_bps: u16,
+    /// The collection of the NFTs the pool accepts instead of the stake tokens.
+    /// `None` means the pool is for the fungible stake tokens.
+    pub nft: Option<NftConfig>,
+    /// The layout version, `ACCOUNT_VERSION` once initialized or migrated.
+    pub version: u8,
+    /// The hash of the name the pool address is derived from.
+    /// Zeroed for the pools migrated from the first deployment, derived from the name itself.
+    pub seed: [u8; 32],
+    /// The id of the pool's `PoolEntry` in the factory's registry, 0 until registered.
+    pub id: u64,
+    /// The key describing this pool, setting its allowlist and stake limits and funding it, besides the admin.
+    /// Its fees and its lifecycle stay with the admin.
+    /// `None` if the pool has no manager.
+    pub manager: Option<Pubkey>,
+    /// The reward tokens deposited by `fund_stake_pool` for this pool and not paid yet.
+    /// A pool with a manager pays its rewards from them only.
+    pub funded_rewards: u64,
+    /// The rewards committed by the stakes of this Fixed pool, part of the factory's `committed_rewards`
+    /// while the pool has no manager. A pool with a manager covers them with its `funded_rewards` only.
+    pub committed_rewards: u128,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [[u8; 8]; 4],
 }
 
 impl StakePool {
-    pub const SPACE: usize = (10 + 4) + (1 + 16) + 8 + 8 + 16 + 1 + 1 + 8 + 4 + 8;
+    pub const NAME_MAX_LEN: usize = 32;
+    pub const SPACE: usize = (Self::NAME_MAX_LEN + 4) + (1 + 16) + 8 + 8 + 16 + 1 + OwnerFee::SPACE + 32 * 2 + 8 + 8 + (1 + 32)
+        + StakeLimits::SPACE + 1 + 2 + (1 + NftConfig::SPACE) + 1 + 32 + 8 + (1 + 32) + 8 + 16 + 32;
+
+    /// The seed of the address of the pool with the `name`, stored without its surrounding whitespace.
+    pub fn seed_for(name: &str) -> [u8; 32] {
+        hash(name.trim().as_bytes()).to_bytes()
+    }
+
+    /// The seed the address of the pool is derived from, along with the factory.
+    /// The pools migrated from the first deployment have no `seed`, their addresses are derived from their names,
+    /// which were up to 10 bytes long.
+    pub fn pda_seed(&self) -> &[u8] {
+        if self.seed == [0; 32] { self.name.as_bytes() } else { &self.seed }
+    }
+
+    /// Checks the `key` is the pool's manager or the admin of the `factory`.
+    pub fn is_managed_by(&self, factory: &Factory, key: &Pubkey) -> bool {
+        self.manager == Some(*key) || *key == factory.owner
+    }
+
+    pub fn has_manager(&self) -> bool {
+        self.manager.is_some()
+    }
+}
+
+/// A sensitive admin action waiting for its `eta`, at the address derived from its `id`.
+/// Closed once executed or cancelled, so the queue of the factory is the accounts left
+/// among the ids from 1 to the factory's `action_count`.
+#[account]
+pub struct QueuedAction {
+    pub id: u64,
+    pub action: AdminAction,
+    /// The signer who queued the action, refunded the rent once it's closed.
+    pub proposer: Pubkey,
+    pub queued_at: u64,
+    /// The time the action becomes executable, at least the `timelock_delay` after it was queued.
+    pub eta: u64,
+    pub bump: u8,
+    /// The layout version, `ACCOUNT_VERSION` once initialized.
+    pub version: u8,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [u8; 64],
+}
+
+impl QueuedAction {
+    pub const SPACE: usize = 8 + AdminAction::SPACE + 32 + 8 + 8 + 1 + 1 + 64;
+    pub const PDA_SEED: &'static [u8] = b"action";
+
+    /// Checks the `key` may queue the `action`: the admin, the pauser lifting the pause, the pool creator
+    /// assigning the managers, or the manager of the pool the reward or the stake limits of which change.
+    /// The fees stay with the admin. Checked again on execution, as the roles may have changed meanwhile.
+    pub fn may_propose(action: &AdminAction, factory: &Factory, roles: &FactoryRoles, stake_pool: Option<&StakePool>, key: &Pubkey) -> bool {
+        match (action, stake_pool) {
+            (AdminAction::Unpause, _) => roles.allows(factory, Role::Pauser, key),
+            (AdminAction::SetPoolManager { .. }, _) => roles.allows(factory, Role::PoolCreator, key),
+            (AdminAction::SetReward { .. } | AdminAction::SetStakeLimits { .. }, Some(stake_pool)) => stake_pool.is_managed_by(factory, key),
+            _ => *key == factory.owner,
+        }
+    }
+}
+
+/// The lifecycle state of a stake pool.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PoolStatus {
+    /// The pool gains the rewards from its creation until it ends.
+    Active,
+    /// The pool no longer gains the rewards, the members may still claim and unstake.
+    Ended,
+    /// The owner retired the ended pool once nothing was staked in it.
+    Closed,
+}
+
+/// An entry of the factory's registry of the stake pools, at the address derived from its `id`.
+/// The pools are enumerated by the ids from 1 to the factory's `pool_count`.
+#[account]
+pub struct PoolEntry {
+    pub id: u64,
+    pub stake_pool: Pubkey,
+    pub stake_token_mint: Pubkey,
+    pub reward_token_mint: Pubkey,
+    /// The UNIX timestamp when the owner closed the pool.
+    pub closed_at: Option<u64>,
+    pub bump: u8,
+    /// The layout version, `ACCOUNT_VERSION` once initialized.
+    pub version: u8,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [u8; 64],
+}
+
+impl PoolEntry {
+    pub const SPACE: usize = 8 + 32 * 3 + (1 + 8) + 1 + 1 + 64;
+    pub const PDA_SEED: &'static [u8] = b"pool";
+
+    /// Adds the `stake_pool` to the `factory`'s registry under the next id.
+    pub fn register(&mut self, factory: &mut Factory, stake_pool: &mut StakePool, stake_pool_key: Pubkey, bump: u8) -> Result<()> {
+        factory.pool_count = factory.pool_count.try_add(1)?;
+        stake_pool.id = factory.pool_count;
+
+        self.id = factory.pool_count;
+        self.stake_pool = stake_pool_key;
+        self.stake_token_mint = factory.stake_token_mint;
+        self.reward_token_mint = factory.reward_token_mint;
+        self.closed_at = None;
+        self.bump = bump;
+        self.version = ACCOUNT_VERSION;
+
+        Ok(())
+    }
+
+    /// The state of the entry's `stake_pool` at `now`, the times read from the pool itself.
+    pub fn status(&self, stake_pool: &StakePool, now: u64) -> PoolStatus {
+        if self.closed_at.is_some() {
+            PoolStatus::Closed
+        } else if now < stake_pool.ends_at {
+            PoolStatus::Active
+        } else {
+            PoolStatus::Ended
+        }
+    }
+}
+
+/// The description of a stake pool for the wallets and explorers, set by the owner.
+#[account]
+pub struct StakePoolMetadata {
+    pub stake_pool: Pubkey,
+    /// The URI of the description of the pool, e.g. a JSON document or a web page.
+    pub description_uri: String,
+    /// The URI of the logo of the pool.
+    pub logo_uri: String,
+    pub tags: Vec<String>,
+    pub bump: u8,
+    /// The layout version, `ACCOUNT_VERSION` once initialized.
+    pub version: u8,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [u8; 64],
+}
+
+impl StakePoolMetadata {
+    pub const URI_MAX_LEN: usize = 200;
+    pub const TAGS_MAX: usize = 5;
+    pub const TAG_MAX_LEN: usize = 16;
+    pub const SPACE: usize = 32 + (4 + Self::URI_MAX_LEN) * 2 + 4 + (4 + Self::TAG_MAX_LEN) * Self::TAGS_MAX + 1 + 1 + 64;
+    pub const PDA_SEED: &'static [u8] = b"metadata";
+
+    pub fn validate(description_uri: &str, logo_uri: &str, tags: &[String]) -> Result<()> {
+        require!(
+            description_uri.len() <= Self::URI_MAX_LEN
+                && logo_uri.len() <= Self::URI_MAX_LEN
+                && tags.len() <= Self::TAGS_MAX
+                && tags.iter().all(|tag| !tag.is_empty() && tag.len() <= Self::TAG_MAX_LEN),
+            SPError::StakePoolMetadataWrong
+        );
+        Ok(())
+    }
 }
 
 /// Member account represents a user of the stake pool factory program.
@@ -73,10 +339,105 @@ pub struct Member {
     /// Used as a transit zone between external and internal wallets/vaults.
     pub vault_free: Pubkey,
     pub bump: u8,
+    /// The wallet allowed to stake, claim and compound on the beneficiary's behalf.
+    /// The rewards always go to the beneficiary's accounts, the delegate can't withdraw or unstake.
+    pub delegate: Option<Pubkey>,
+    /// The reward token account the rewards may be claimed to besides the beneficiary's own accounts,
+    /// e.g. the ATA of a cold wallet. Set by the beneficiary only.
+    pub reward_destination: Option<Pubkey>,
+    /// The member lets anyone claim its rewards by `crank_claims`,
+    /// paid to the reward destination or the beneficiary's accounts.
+    pub auto_claim: bool,
+    /// The `MemberStake` and `NftStake` accounts of the member not closed yet.
+    /// The member can only be closed without any.
+    pub open_positions: u32,
+    /// The layout version, `ACCOUNT_VERSION` once initialized or migrated.
+    pub version: u8,
+    /// The `MemberPendingUnstake` accounts of the member not finished yet.
+    pub pending_unstakes: u32,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [[u8; 4]; 15],
 }
 
 impl Member {
-    pub const SPACE: usize = 32 * 2 + 8;
+    pub const SPACE: usize = 32 * 2 + 8 + (1 + 32) * 2 + 1 + 4 + 1 + 4 + 60;
+    /// The seed of the transit token account `withdraw_sol` unwraps the SOL with.
+    pub const UNWRAP_SEED: &'static [u8] = b"unwrap";
+
+    /// Checks the `key` is allowed to act on the member's positions.
+    pub fn is_authority(&self, key: &Pubkey) -> bool {
+        *key == self.beneficiary || self.delegate.as_ref() == Some(key)
+    }
+
+    /// Checks the rewards may be claimed to the token account `key` owned by the `owner`.
+    pub fn accepts_reward_vault(&self, key: &Pubkey, owner: &Pubkey) -> bool {
+        *owner == self.beneficiary || self.reward_destination.as_ref() == Some(key)
+    }
+}
+
+/// The kind of an account of the member's `PositionIndex`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PositionKind {
+    /// A `MemberStake`.
+    Stake,
+    /// A `MemberPendingUnstake`.
+    PendingUnstake,
+    /// An `NftStake`, pending the unstake once its `unstaked_at` is set.
+    Nft,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct IndexedPosition {
+    pub kind: PositionKind,
+    pub stake_pool: Pubkey,
+    /// The address of the position account.
+    pub account: Pubkey,
+}
+
+impl IndexedPosition {
+    pub const SPACE: usize = 1 + 32 + 32;
+}
+
+/// The open positions and the pending unstakes of a member, at the address derived from the member,
+/// so the portfolio of the member is found without scanning the program.
+/// Created with room for `INITIAL_POSITIONS`, it's reallocated as the member opens more.
+#[account]
+pub struct PositionIndex {
+    pub member: Pubkey,
+    pub positions: Vec<IndexedPosition>,
+    pub bump: u8,
+    /// The layout version, `ACCOUNT_VERSION` once initialized.
+    pub version: u8,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [u8; 64],
+}
+
+impl PositionIndex {
+    pub const INITIAL_POSITIONS: usize = 4;
+    pub const SPACE: usize = Self::space(Self::INITIAL_POSITIONS);
+    pub const PDA_SEED: &'static [u8] = b"positions";
+
+    /// The space of an index of the `positions` count.
+    pub const fn space(positions: usize) -> usize {
+        32 + 4 + IndexedPosition::SPACE * positions + 1 + 1 + 64
+    }
+
+    /// Starts the index of the `member` created by this instruction or without any positions.
+    pub fn open(&mut self, member: Pubkey, bump: u8) {
+        self.member = member;
+        self.positions = vec![];
+        self.bump = bump;
+        self.version = ACCOUNT_VERSION;
+    }
+
+    /// Adds the position, the index is grown to hold it by `space::fit_position_index`.
+    pub fn add(&mut self, kind: PositionKind, stake_pool: Pubkey, account: Pubkey) {
+        self.positions.push(IndexedPosition { kind, stake_pool, account });
+    }
+
+    pub fn remove(&mut self, account: &Pubkey) {
+        self.positions.retain(|position| position.account != *account);
+    }
 }
 
 #[account]
@@ -92,10 +453,109 @@ pub struct MemberStake {
     /// When the last reward was payed
     pub reward_payed_for: u64,
     pub bump: u8,
+    /// The reward left unpaid by a partial payment, paid first on the next claims.
+    pub reward_owed: u64,
+    /// The layout version, `ACCOUNT_VERSION` once initialized or migrated.
+    pub version: u8,
+    /// The tokens staked in the position, as received by the `vault_staked` on stake and compound.
+    /// The rewards are counted on it, so the tokens sent to the vault otherwise don't gain any.
+    pub staked_tokens: u64,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [[u8; 8]; 7],
 }
 
 impl MemberStake {
-    pub const SPACE: usize = 32 * 3 + 8 + 8 + 1;
+    pub const SPACE: usize = 32 * 3 + 8 + 8 + 1 + 8 + 1 + 8 + 56;
+
+    /// Starts the position of the `beneficiary` in the `stake_pool` with `staked_tokens` gaining the rewards from `now`.
+    pub fn open(&mut self, stake_pool: Pubkey, beneficiary: Pubkey, vault_staked: Pubkey, staked_tokens: u64, now: u64, bump: u8) {
+        self.stake_pool = stake_pool;
+        self.beneficiary = beneficiary;
+        self.vault_staked = vault_staked;
+        self.staked_tokens = staked_tokens;
+        self.staked_at = now;
+        self.reward_payed_for = now;
+        self.reward_owed = 0;
+        self.bump = bump;
+        self.version = ACCOUNT_VERSION;
+    }
+}
+
+/// A position gaining the rewards of a stake pool.
+pub trait RewardPosition {
+    /// The time the next reward period starts from.
+    fn reward_checkpoint(&self) -> u64;
+    /// The reward left unpaid by a partial payment.
+    fn reward_owed(&self) -> u64;
+    /// Records a claim of the periods up to `reward_payed_for` leaving `reward_owed` unpaid.
+    fn settle(&mut self, reward_payed_for: u64, reward_owed: u64);
+}
+
+impl RewardPosition for MemberStake {
+    fn reward_checkpoint(&self) -> u64 {
+        self.reward_payed_for.max(self.staked_at)
+    }
+
+    fn reward_owed(&self) -> u64 {
+        self.reward_owed
+    }
+
+    fn settle(&mut self, reward_payed_for: u64, reward_owed: u64) {
+        self.reward_payed_for = reward_payed_for;
+        self.reward_owed = reward_owed;
+    }
+}
+
+/// An NFT staked in an NFT pool. The NFT is held by the `vault_nft` owned by this account.
+#[account]
+pub struct NftStake {
+    /// StakePool the NFT is staked in
+    pub stake_pool: Pubkey,
+    /// The owner of the NFT and the Member account.
+    pub beneficiary: Pubkey,
+    /// The mint of the staked NFT.
+    pub mint: Pubkey,
+    /// The escrow token account holding the NFT.
+    pub vault_nft: Pubkey,
+    /// The tokens the NFT counts for in the pool's `total_staked_tokens`.
+    pub weight: u64,
+    /// The UNIX timestamp when the staking started
+    pub staked_at: u64,
+    /// When the last reward was payed
+    pub reward_payed_for: u64,
+    /// The reward left unpaid by a partial payment, paid first on the next claims.
+    pub reward_owed: u64,
+    /// The UNIX timestamp when the unstaking started. The NFT gains no rewards since.
+    pub unstaked_at: Option<u64>,
+    pub bump: u8,
+    /// The layout version, `ACCOUNT_VERSION` once initialized.
+    pub version: u8,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [u8; 64],
+}
+
+impl NftStake {
+    pub const SPACE: usize = 32 * 4 + 8 * 4 + (1 + 8) + 1 + 1 + 64;
+
+    /// The tokens the position gains the rewards for.
+    pub fn staked_weight(&self) -> u64 {
+        if self.unstaked_at.is_some() { 0 } else { self.weight }
+    }
+}
+
+impl RewardPosition for NftStake {
+    fn reward_checkpoint(&self) -> u64 {
+        self.reward_payed_for.max(self.staked_at)
+    }
+
+    fn reward_owed(&self) -> u64 {
+        self.reward_owed
+    }
+
+    fn settle(&mut self, reward_payed_for: u64, reward_owed: u64) {
+        self.reward_payed_for = reward_payed_for;
+        self.reward_owed = reward_owed;
+    }
 }
 
 #[account]
@@ -112,8 +572,12 @@ pub struct MemberPendingUnstake {
     /// The UNIX timestamp when the unstaking started
     pub unstaked_at: u64,
     pub bump: u8,
+    /// The layout version, `ACCOUNT_VERSION` once initialized or migrated.
+    pub version: u8,
+    /// Zeroed space for the fields added later, read as their zero values without a reallocation.
+    pub reserved: [u8; 64],
 }
 
 impl MemberPendingUnstake {
-    pub const SPACE: usize = 32 * 3 + 8 + 1;
+    pub const SPACE: usize = 32 * 3 + 8 + 1 + 1 + 64;
 }
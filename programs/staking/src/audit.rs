@@ -0,0 +1,321 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use crate::math::TryMath;
+use crate::reward::PendingRewards;
+use crate::solvency;
+
+/// The balances of a stake pool checked by the `audit_pool` instruction.
+/// Emitted as the event and written to the return data.
+#[event]
+#[derive(Debug, PartialEq)]
+pub struct AuditReport {
+    pub stake_pool: Pubkey,
+    /// The number of audited `MemberStake` and `NftStake` accounts.
+    pub positions: u32,
+    /// `StakePool::total_staked_tokens`
+    pub total_staked_tokens: u128,
+    /// The sum of the tokens staked by the positions, the weights of the NFTs in an NFT pool.
+    pub staked_in_positions: u128,
+    /// The number of positions whose `vault_staked` holds less than their staked tokens,
+    /// or whose `vault_nft` doesn't hold the NFT.
+    pub short_vaults: u32,
+    /// The rewards the positions are able to claim right now, including the owner's fee and the rewards owed.
+    pub accrued_rewards: u128,
+    /// The balance of the factory's `vault_reward` shared by all the pools.
+    pub vault_reward: u64,
+    /// The part of the `vault_reward` the pool pays its rewards from, its own funds if it has a manager.
+    pub available_rewards: u64,
+    /// The rewards committed by the pools paying from the same `available_rewards`, to be covered by them.
+    pub committed_rewards: u128,
+    /// The owner's fees accrued in the pool's `vault_fee` and not harvested yet.
+    pub vault_fee: u64,
+    pub audited_at: u64,
+}
+
+impl AuditReport {
+    pub fn new(
+        stake_pool_key: Pubkey,
+        stake_pool: &StakePool,
+        factory: &Factory,
+        vault_reward: u64,
+        vault_fee: u64,
+        audited_at: u64,
+    ) -> AuditReport {
+        AuditReport {
+            stake_pool: stake_pool_key,
+            positions: 0,
+            total_staked_tokens: stake_pool.total_staked_tokens,
+            staked_in_positions: 0,
+            short_vaults: 0,
+            accrued_rewards: 0,
+            vault_reward,
+            available_rewards: solvency::available_rewards(factory, stake_pool, vault_reward),
+            committed_rewards: solvency::partition_committed(factory, stake_pool),
+            vault_fee,
+            audited_at,
+        }
+    }
+
+    /// Adds a position of the `stake_pool` with the `vault_staked` balance.
+    pub fn add_position(&mut self, stake_pool: &StakePool, member_stake: &MemberStake, vault_staked: u64) -> Result<()> {
+        let staked = member_stake.staked_tokens;
+        self.add(stake_pool, member_stake.stake_pool, member_stake, staked, vault_staked < staked)
+    }
+
+    /// Adds an NFT staked in the `stake_pool` with the `vault_nft` balance.
+    /// The NFT counts for its weight until it is unstaked.
+    pub fn add_nft_position(&mut self, stake_pool: &StakePool, nft_stake: &NftStake, vault_nft: u64) -> Result<()> {
+        self.add(stake_pool, nft_stake.stake_pool, nft_stake, nft_stake.staked_weight(), vault_nft == 0)
+    }
+
+    fn add(&mut self, stake_pool: &StakePool, position_pool: Pubkey, position: &impl RewardPosition, staked: u64, short_vault: bool) -> Result<()> {
+        require!(position_pool == self.stake_pool, SPError::AuditPositionInvalid);
+
+        let pending = PendingRewards::calculate(stake_pool, position, staked, self.audited_at)?;
+
+        self.positions = self.positions.try_add(1)?;
+        self.staked_in_positions = self.staked_in_positions.try_add(staked as u128)?;
+        if short_vault {
+            self.short_vaults = self.short_vaults.try_add(1)?;
+        }
+        self.accrued_rewards = self.accrued_rewards
+            .try_add(pending.gross_reward as u128)?
+            .try_add(pending.reward_owed as u128)?;
+
+        Ok(())
+    }
+
+    /// The invariants the audited positions break, in the order they are checked.
+    pub fn violations(&self) -> Vec<SPError> {
+        let mut violations = vec![];
+
+        if self.staked_in_positions != self.total_staked_tokens || self.short_vaults > 0 {
+            violations.push(SPError::AuditStakedTokensMismatch);
+        }
+        if self.accrued_rewards > self.available_rewards as u128 {
+            violations.push(SPError::AuditRewardVaultUnderfunded);
+        }
+        if self.committed_rewards > self.available_rewards as u128 {
+            violations.push(SPError::AuditRewardsOvercommitted);
+        }
+
+        violations
+    }
+
+    /// Fails with the first violated invariant.
+    pub fn verify(&self) -> Result<()> {
+        match self.violations().first() {
+            Some(&violation) => Err(error!(violation)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fee::OwnerFee;
+    use crate::limits::StakeLimits;
+    use crate::reward::Reward;
+
+    fn stake_pool(total_staked: u128) -> StakePool {
+        StakePool {
+            name: "test".to_owned(),
+            reward_type: Reward::new_fixed(10),
+            started_at: 0,
+            ends_at: 10_000,
+            total_staked_tokens: total_staked,
+            bump: 255,
+            owner_fee: OwnerFee::new(0, None).unwrap(),
+            fee_recipient: Pubkey::default(),
+            vault_fee: Pubkey::default(),
+            unstake_delay: 0,
+            reward_period: 100,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id: 0,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        }
+    }
+
+    fn factory(committed_rewards: u128) -> Factory {
+        Factory {
+            bump: 255,
+            owner: Pubkey::default(),
+            reward_token_mint: Pubkey::default(),
+            stake_token_mint: Pubkey::default(),
+            vault_reward: Pubkey::default(),
+            committed_rewards,
+            version: ACCOUNT_VERSION,
+            pool_count: 0,
+            paused: false,
+            timelock_delay: 0,
+            action_count: 0,
+            funded_rewards: 0,
+            reserved: [0; 31],
+        }
+    }
+
+    fn member_stake(stake_pool: Pubkey, staked_tokens: u64) -> MemberStake {
+        MemberStake {
+            stake_pool,
+            beneficiary: Pubkey::default(),
+            vault_staked: Pubkey::default(),
+            staked_at: 0,
+            reward_payed_for: 0,
+            bump: 255,
+            reward_owed: 0,
+            version: ACCOUNT_VERSION,
+            staked_tokens,
+            reserved: [[0; 8]; 7],
+        }
+    }
+
+    fn nft_stake(stake_pool: Pubkey, weight: u64) -> NftStake {
+        NftStake {
+            stake_pool,
+            beneficiary: Pubkey::default(),
+            mint: Pubkey::default(),
+            vault_nft: Pubkey::default(),
+            weight,
+            staked_at: 0,
+            reward_payed_for: 0,
+            reward_owed: 0,
+            unstaked_at: None,
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; 64],
+        }
+    }
+
+    #[test]
+    fn balanced_pool_passes() {
+        let key = Pubkey::new_unique();
+        let pool = stake_pool(3000);
+        let mut report = AuditReport::new(key, &pool, &factory(600), 600, 0, 200);
+
+        report.add_position(&pool, &member_stake(key, 1000), 1000).unwrap();
+        // The tokens sent to the vault besides the stake don't count.
+        report.add_position(&pool, &member_stake(key, 2000), 2500).unwrap();
+
+        assert_eq!(report.positions, 2);
+        assert_eq!(report.staked_in_positions, 3000);
+        // 10 % for two periods
+        assert_eq!(report.accrued_rewards, 600);
+        assert!(report.violations().is_empty());
+        assert!(report.verify().is_ok());
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let key = Pubkey::new_unique();
+        let pool = stake_pool(3000);
+        let mut report = AuditReport::new(key, &pool, &factory(500), 100, 0, 200);
+
+        report.add_position(&pool, &member_stake(key, 1000), 1000).unwrap();
+
+        assert_eq!(report.violations().len(), 3);
+        assert_eq!(
+            ProgramError::from(report.verify().unwrap_err()),
+            ProgramError::from(error!(SPError::AuditStakedTokensMismatch)),
+        );
+    }
+
+    #[test]
+    fn nfts_count_for_their_weights() {
+        let key = Pubkey::new_unique();
+        let pool = stake_pool(3000);
+        let mut report = AuditReport::new(key, &pool, &factory(600), 600, 0, 200);
+        let mut unstaking = nft_stake(key, 500);
+        unstaking.unstaked_at = Some(150);
+        unstaking.reward_owed = 40;
+
+        report.add_nft_position(&pool, &nft_stake(key, 1000), 1).unwrap();
+        report.add_nft_position(&pool, &nft_stake(key, 2000), 1).unwrap();
+        // The unstaking NFT counts for nothing, but its reward owed is still due.
+        report.add_nft_position(&pool, &unstaking, 1).unwrap();
+
+        assert_eq!(report.positions, 3);
+        assert_eq!(report.staked_in_positions, 3000);
+        assert_eq!(report.accrued_rewards, 640);
+        assert!(matches!(report.violations()[..], [SPError::AuditRewardVaultUnderfunded]));
+
+        report.add_nft_position(&pool, &nft_stake(key, 0), 0).unwrap();
+        assert_eq!(report.short_vaults, 1);
+    }
+
+    #[test]
+    fn rewards_owed_are_accrued() {
+        let key = Pubkey::new_unique();
+        let pool = stake_pool(1000);
+        let mut report = AuditReport::new(key, &pool, &factory(0), 1000, 0, 250);
+        let mut owing = member_stake(key, 1000);
+        owing.reward_payed_for = 200;
+        owing.reward_owed = 70;
+
+        report.add_position(&pool, &owing, 1000).unwrap();
+
+        assert_eq!(report.accrued_rewards, 70);
+    }
+
+    #[test]
+    fn vaults_short_of_the_stake_are_reported() {
+        let key = Pubkey::new_unique();
+        let pool = stake_pool(1000);
+        let mut report = AuditReport::new(key, &pool, &factory(0), 1000, 0, 200);
+
+        report.add_position(&pool, &member_stake(key, 1000), 999).unwrap();
+
+        assert_eq!(report.short_vaults, 1);
+        assert!(matches!(report.violations()[..], [SPError::AuditStakedTokensMismatch]));
+    }
+
+    #[test]
+    fn commitments_beyond_the_vault_are_reported() {
+        let key = Pubkey::new_unique();
+        let pool = stake_pool(0);
+        let report = AuditReport::new(key, &pool, &factory(1001), 1000, 0, 200);
+
+        assert!(matches!(report.violations()[..], [SPError::AuditRewardsOvercommitted]));
+    }
+
+    #[test]
+    fn shortfalls_are_reported_per_partition() {
+        let key = Pubkey::new_unique();
+        // 600 of the 1000 in the vault are the funds of a managed pool.
+        let factory = Factory { funded_rewards: 600, ..factory(500) };
+        let pool = stake_pool(0);
+        let report = AuditReport::new(key, &pool, &factory, 1000, 0, 200);
+
+        assert_eq!((report.available_rewards, report.committed_rewards), (400, 500));
+        assert!(matches!(report.violations()[..], [SPError::AuditRewardsOvercommitted]));
+
+        let mut managed = stake_pool(1000);
+        managed.manager = Some(Pubkey::new_unique());
+        (managed.funded_rewards, managed.committed_rewards) = (600, 600);
+        let mut report = AuditReport::new(key, &managed, &factory, 1000, 0, 300);
+        // 10 % for three periods
+        report.add_position(&managed, &member_stake(key, 1000), 1000).unwrap();
+
+        assert_eq!((report.available_rewards, report.committed_rewards, report.accrued_rewards), (600, 600, 300));
+        assert!(report.violations().is_empty());
+    }
+
+    #[test]
+    fn position_of_another_pool_is_rejected() {
+        let pool = stake_pool(1000);
+        let mut report = AuditReport::new(Pubkey::new_unique(), &pool, &factory(0), 0, 0, 0);
+
+        let err = report.add_position(&pool, &member_stake(Pubkey::new_unique(), 1000), 1000).unwrap_err();
+        assert_eq!(ProgramError::from(err), ProgramError::from(error!(SPError::AuditPositionInvalid)));
+    }
+}
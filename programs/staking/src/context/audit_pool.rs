@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::audit::AuditReport;
+use crate::error::SPError;
+use anchor_lang::{Discriminator, Owners};
+use anchor_spl::token_interface::TokenAccount;
+
+#[derive(Accounts)]
+pub struct AuditPool<'info> {
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = vault_reward,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+        has_one = vault_fee,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub vault_fee: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+impl<'info> AuditPool<'info> {
+    /// Builds the report from the `positions` given as `[member_stake, vault_staked]`
+    /// or `[nft_stake, vault_nft]` pairs of the remaining accounts.
+    pub fn audit(&self, positions: &'info [AccountInfo<'info>]) -> Result<AuditReport> {
+        let positions = positions.chunks_exact(2);
+        require!(positions.remainder().is_empty(), SPError::AuditPositionInvalid);
+
+        let mut report = AuditReport::new(
+            self.stake_pool.key(),
+            &self.stake_pool,
+            &self.factory,
+            self.vault_reward.amount,
+            self.vault_fee.amount,
+            self.clock.unix_timestamp as u64,
+        );
+        let mut audited: Vec<&Pubkey> = Vec::with_capacity(positions.len());
+
+        for position in positions {
+            let (position_info, vault_info) = (&position[0], &position[1]);
+            require!(!audited.contains(&position_info.key), SPError::AuditPositionInvalid);
+            require!(position_info.owner == &crate::ID, SPError::AuditPositionInvalid);
+            require!(TokenAccount::owners().contains(vault_info.owner), SPError::AuditPositionInvalid);
+
+            let data = position_info.try_borrow_data()?;
+            let vault = TokenAccount::try_deserialize(&mut &vault_info.try_borrow_data()?[..])?;
+            if data.starts_with(&NftStake::discriminator()) {
+                let nft_stake = NftStake::try_deserialize(&mut &data[..])?;
+                require!(nft_stake.vault_nft == *vault_info.key, SPError::AuditPositionInvalid);
+                report.add_nft_position(&self.stake_pool, &nft_stake, vault.amount)?;
+            } else {
+                let member_stake = MemberStake::try_deserialize(&mut &data[..])?;
+                require!(member_stake.vault_staked == *vault_info.key, SPError::AuditPositionInvalid);
+                report.add_position(&self.stake_pool, &member_stake, vault.amount)?;
+            }
+            audited.push(position_info.key);
+        }
+
+        Ok(report)
+    }
+}
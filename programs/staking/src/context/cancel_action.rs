@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+
+#[derive(Accounts)]
+pub struct CancelAction<'info> {
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            QueuedAction::PDA_SEED,
+            &queued_action.id.to_le_bytes(),
+        ],
+        bump = queued_action.bump,
+        has_one = proposer,
+        close = proposer,
+    )]
+    pub queued_action: Box<Account<'info, QueuedAction>>,
+    /// CHECK: The proposer of the action, checked by its `has_one` and refunded the rent.
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+    /// The proposer or the admin.
+    #[account(
+        constraint = authority.key() == queued_action.proposer || authority.key() == factory.owner @ SPError::RoleRequired,
+    )]
+    pub authority: Signer<'info>,
+}
@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use crate::math::TryMath;
+use crate::reward::settle_claim;
+use crate::solvency;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
+
+#[derive(Accounts)]
+pub struct ClaimAll<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = reward_token_mint,
+        has_one = vault_reward,
+        constraint = !factory.paused @ SPError::FactoryPaused,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump = member.bump,
+        has_one = beneficiary,
+    )]
+    pub member: Account<'info, Member>,
+    #[account(
+        constraint = member.is_authority(authority.key) @ SPError::NotMemberAuthority,
+    )]
+    pub authority: Signer<'info>,
+    /// CHECK: The owner of the `member`, checked by its `has_one`.
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = member.accepts_reward_vault(&beneficiary_reward_vault.key(), &beneficiary_reward_vault.owner)
+            @ SPError::RewardDestinationNotAllowed,
+        constraint = beneficiary_reward_vault.mint == factory.reward_token_mint
+    )]
+    pub beneficiary_reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+impl<'info> ClaimAll<'info> {
+    /// Claims the reward of the `positions` given as
+    /// `[stake_pool, member_stake, vault_fee]` of the remaining accounts.
+    /// Accrues the owner's fee of each position in its pool's `vault_fee`
+    /// and returns the net reward of all the positions to be transferred at once.
+    /// The positions without a reward to claim yet are skipped.
+    pub fn claim_positions(&mut self, positions: &'info [AccountInfo<'info>]) -> Result<u64> {
+        let positions = positions.chunks_exact(3);
+        require!(positions.len() > 0 && positions.remainder().is_empty(), SPError::ClaimPositionInvalid);
+
+        let now = self.clock.unix_timestamp as u64;
+        let mut reward_tokens_available = self.vault_reward.amount;
+        let mut reward_tokens_for_user: u64 = 0;
+        let mut claimed: Vec<&Pubkey> = Vec::with_capacity(positions.len());
+
+        for position in positions {
+            let (stake_pool_info, member_stake_info, vault_fee_info) = (&position[0], &position[1], &position[2]);
+            require!(!claimed.contains(&member_stake_info.key), SPError::ClaimPositionInvalid);
+
+            let mut stake_pool = Account::<StakePool>::try_from(stake_pool_info)?;
+            let mut member_stake = Account::<MemberStake>::try_from(member_stake_info)?;
+            require!(member_stake.beneficiary == self.member.beneficiary, SPError::ClaimPositionInvalid);
+            require!(member_stake.stake_pool == stake_pool.key(), SPError::ClaimPositionInvalid);
+            require!(stake_pool.vault_fee == *vault_fee_info.key, SPError::ClaimPositionInvalid);
+            claimed.push(member_stake_info.key);
+
+            let staked_tokens = member_stake.staked_tokens;
+            let reward_tokens_to_transfer = match settle_claim(
+                &stake_pool,
+                &mut *member_stake,
+                staked_tokens,
+                now,
+                solvency::available_rewards(&self.factory, &stake_pool, reward_tokens_available),
+                solvency::partition_committed(&self.factory, &stake_pool),
+            )? {
+                Some(reward_tokens_to_transfer) => reward_tokens_to_transfer,
+                None => continue,
+            };
+            let (reward_tokens_for_owner, reward_tokens_for_position) = stake_pool.owner_fee.split(reward_tokens_to_transfer)?;
+
+            self.transfer_from_reward_vault(vault_fee_info.clone(), reward_tokens_for_owner)?;
+            reward_tokens_available = reward_tokens_available.try_sub(reward_tokens_to_transfer)?;
+            reward_tokens_for_user = reward_tokens_for_user.try_add(reward_tokens_for_position)?;
+            member_stake.exit(&crate::ID)?;
+
+            let factory = &mut self.factory;
+            solvency::release_from_partition(factory, &mut stake_pool, reward_tokens_to_transfer as u128);
+            solvency::spend_funded(factory, &mut stake_pool, reward_tokens_to_transfer)?;
+            stake_pool.exit(&crate::ID)?;
+        }
+
+        Ok(reward_tokens_for_user)
+    }
+
+    /// Transfers the net reward of all the claimed positions to the `beneficiary_reward_vault`.
+    pub fn transfer_reward_tokens_to_user(&self, amount: u64) -> Result<()> {
+        self.transfer_from_reward_vault(self.beneficiary_reward_vault.to_account_info(), amount)
+    }
+
+    fn transfer_from_reward_vault(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let seeds = &[
+            Factory::PDA_SEED,
+            &[self.factory.bump]
+        ];
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.vault_reward.to_account_info(),
+                    mint: self.reward_token_mint.to_account_info(),
+                    to,
+                    authority: self.factory.to_account_info()
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+            self.reward_token_mint.decimals,
+        )
+    }
+}
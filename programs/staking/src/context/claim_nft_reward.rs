@@ -0,0 +1,117 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use crate::reward::accrued_reward;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
+
+#[derive(Accounts)]
+pub struct ClaimNftReward<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = reward_token_mint,
+        constraint = !factory.paused @ SPError::FactoryPaused,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+        has_one = vault_fee,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        mut,
+        seeds = [
+            stake_pool.to_account_info().key.as_ref(),
+            nft_stake.mint.as_ref(),
+        ],
+        bump = nft_stake.bump,
+        has_one = stake_pool,
+        has_one = beneficiary,
+    )]
+    pub nft_stake: Account<'info, NftStake>,
+    #[account(
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump = member.bump,
+        has_one = beneficiary,
+    )]
+    pub member: Account<'info, Member>,
+    #[account(
+        constraint = member.is_authority(authority.key) @ SPError::NotMemberAuthority,
+    )]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = vault_reward.owner == factory.key(),
+        constraint = vault_reward.mint == factory.reward_token_mint
+    )]
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: The owner of the `member`, checked by its `has_one`.
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = member.accepts_reward_vault(&beneficiary_reward_vault.key(), &beneficiary_reward_vault.owner)
+            @ SPError::RewardDestinationNotAllowed,
+        constraint = beneficiary_reward_vault.mint == factory.reward_token_mint
+    )]
+    pub beneficiary_reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub vault_fee: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+impl<'info> ClaimNftReward<'info> {
+    pub fn calculate_reward_tokens(&self) -> Result<(u64, u64)> {
+        accrued_reward(
+            &self.stake_pool,
+            &*self.nft_stake,
+            self.nft_stake.staked_weight(),
+            self.clock.unix_timestamp as u64,
+        )
+    }
+
+    /// Transfers the net reward to the beneficiary
+    /// and accrues the owner's fee in the stake pool's `vault_fee`.
+    pub fn transfer_reward_tokens(&self, reward_tokens: u64) -> Result<()> {
+        let (reward_tokens_for_owner, reward_tokens_for_user) = self.stake_pool.owner_fee.split(reward_tokens)?;
+
+        self.transfer_from_reward_vault(self.beneficiary_reward_vault.to_account_info(), reward_tokens_for_user)?;
+        self.transfer_from_reward_vault(self.vault_fee.to_account_info(), reward_tokens_for_owner)
+    }
+
+    fn transfer_from_reward_vault(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let seeds = &[
+            Factory::PDA_SEED,
+            &[self.factory.bump]
+        ];
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.vault_reward.to_account_info(),
+                    mint: self.reward_token_mint.to_account_info(),
+                    to,
+                    authority: self.factory.to_account_info()
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+            self.reward_token_mint.decimals,
+        )
+    }
+}
@@ -1,20 +1,28 @@
 use anchor_lang::prelude::*;
 use crate::account::*;
-use anchor_spl::token::{self, TokenAccount, Token};
+use crate::error::SPError;
+use crate::reward::accrued_reward;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
 
 #[derive(Accounts)]
 pub struct ClaimReward<'info> {
     #[account(
+        mut,
         seeds = [Factory::PDA_SEED],
         bump = factory.bump,
+        has_one = reward_token_mint,
+        constraint = !factory.paused @ SPError::FactoryPaused,
     )]
     pub factory: Account<'info, Factory>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
+        mut,
         seeds = [
             factory.to_account_info().key.as_ref(),
-            stake_pool.name.as_ref(),
+            stake_pool.pda_seed(),
         ],
         bump = stake_pool.bump,
+        has_one = vault_fee,
     )]
     pub stake_pool: Account<'info, StakePool>,
     #[account(
@@ -24,7 +32,6 @@ pub struct ClaimReward<'info> {
             member.to_account_info().key.as_ref(),
         ],
         bump,
-        has_one = vault_staked,
         has_one = stake_pool,
         has_one = beneficiary,
     )]
@@ -39,59 +46,47 @@ pub struct ClaimReward<'info> {
     )]
     pub member: Account<'info, Member>,
     #[account(
-        constraint = vault_staked.owner == member_stake.key(),
+        constraint = member.is_authority(authority.key) @ SPError::NotMemberAuthority,
     )]
-    pub vault_staked: Box<Account<'info, TokenAccount>>,
+    pub authority: Signer<'info>,
     #[account(
         mut,
         constraint = vault_reward.owner == factory.key(),
         constraint = vault_reward.mint == factory.reward_token_mint
     )]
-    pub vault_reward: Box<Account<'info, TokenAccount>>,
-    #[account(mut)]
-    pub beneficiary: Signer<'info>,
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: The owner of the `member`, checked by its `has_one`.
+    pub beneficiary: UncheckedAccount<'info>,
     #[account(
         mut,
-        constraint = beneficiary_reward_vault.owner == beneficiary.key(),
+        constraint = member.accepts_reward_vault(&beneficiary_reward_vault.key(), &beneficiary_reward_vault.owner)
+            @ SPError::RewardDestinationNotAllowed,
         constraint = beneficiary_reward_vault.mint == factory.reward_token_mint
     )]
-    pub beneficiary_reward_vault: Box<Account<'info, TokenAccount>>,
-    #[account(
-        mut,
-        constraint = owner_fee_reward_vault.owner == factory.owner,
-        constraint = owner_fee_reward_vault.mint == factory.reward_token_mint
-    )]
-    pub owner_fee_reward_vault: Box<Account<'info, TokenAccount>>,
-    pub token_program: Program<'info, Token>,
+    pub beneficiary_reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub vault_fee: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub clock: Sysvar<'info, Clock>,
 }
 
 impl<'info> ClaimReward<'info> {
     pub fn calculate_reward_tokens(&self) -> Result<(u64, u64)> {
-        self.stake_pool.reward_type
-            .calculate(
-                self.clock.unix_timestamp as u64,
-                self.stake_pool.ends_at,
-                self.stake_pool.started_at,
-                self.vault_staked.amount,
-                self.stake_pool.reward_period,
-                self.stake_pool.total_staked_tokens,
-            )
+        accrued_reward(
+            &self.stake_pool,
+            &*self.member_stake,
+            self.member_stake.staked_tokens,
+            self.clock.unix_timestamp as u64,
+        )
     }
 
+    /// Transfers the net reward to the beneficiary
+    /// and accrues the owner's fee in the stake pool's `vault_fee`.
     pub fn transfer_reward_tokens(&self, reward_tokens: u64) -> Result<()> {
-        let mut reward_tokens_for_owner = reward_tokens
-            .checked_mul(self.stake_pool.owner_interest_percent as u64).unwrap()
-            .checked_div(100).unwrap();
-
-        if reward_tokens_for_owner == 0 {
-            reward_tokens_for_owner = self.stake_pool.min_owner_reward as u64;
-        }
-
-        let reward_tokens_for_user = reward_tokens.checked_sub(reward_tokens_for_owner).unwrap();
+        let (reward_tokens_for_owner, reward_tokens_for_user) = self.stake_pool.owner_fee.split(reward_tokens)?;
 
         self.transfer_reward_tokens_to_user(reward_tokens_for_user)?;
-        self.transfer_reward_tokens_to_owner(reward_tokens_for_owner)
+        self.transfer_reward_tokens_to_fee_vault(reward_tokens_for_owner)
     }
 
     fn transfer_reward_tokens_to_user(&self, amount: u64) -> Result<()> {
@@ -100,37 +95,45 @@ impl<'info> ClaimReward<'info> {
             &[self.factory.bump]
         ];
 
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
-                token::Transfer { 
+                token_interface::TransferChecked { 
                     from: self.vault_reward.to_account_info(), 
+                    mint: self.reward_token_mint.to_account_info(),
                     to: self.beneficiary_reward_vault.to_account_info(), 
                     authority: self.factory.to_account_info() 
                 },
                 &[&seeds[..]],
             ),
-            amount
+            amount,
+            self.reward_token_mint.decimals,
         )
     }
 
-    fn transfer_reward_tokens_to_owner(&self, amount: u64) -> Result<()> {
+    fn transfer_reward_tokens_to_fee_vault(&self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
         let seeds = &[
             Factory::PDA_SEED,
             &[self.factory.bump]
         ];
 
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
-                token::Transfer { 
+                token_interface::TransferChecked { 
                     from: self.vault_reward.to_account_info(), 
-                    to: self.owner_fee_reward_vault.to_account_info(), 
+                    mint: self.reward_token_mint.to_account_info(),
+                    to: self.vault_fee.to_account_info(), 
                     authority: self.factory.to_account_info() 
                 },
                 &[&seeds[..]],
             ),
-            amount
+            amount,
+            self.reward_token_mint.decimals,
         )
     }
 }
@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::transfer_fee;
+use crate::error::SPError;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, CloseAccount, Mint};
+
+#[derive(Accounts)]
+pub struct CloseMember<'info> {
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = stake_token_mint,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(mut)]
+    pub stake_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump = member.bump,
+        has_one = beneficiary,
+        has_one = vault_free,
+        constraint = member.open_positions == 0 @ SPError::MemberHasOpenPositions,
+        close = beneficiary,
+    )]
+    pub member: Account<'info, Member>,
+    #[account(
+        mut,
+        seeds = [
+            member.to_account_info().key.as_ref(),
+            PositionIndex::PDA_SEED,
+        ],
+        bump = position_index.bump,
+        has_one = member,
+        close = beneficiary,
+    )]
+    pub position_index: Box<Account<'info, PositionIndex>>,
+    /// CHECK: The `vault_free` of the member, checked by its `has_one`.
+    /// Doesn't exist if the member was created by `deposit_and_stake` and never needed it.
+    #[account(mut)]
+    pub vault_free: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    #[account(
+        mut,
+        constraint = beneficiary_token_account.owner == beneficiary.key(),
+        constraint = beneficiary_token_account.mint == factory.stake_token_mint,
+    )]
+    pub beneficiary_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CloseMember<'info> {
+    pub fn empty_and_close_free_vault(&self) -> Result<()> {
+        if self.vault_free.data_is_empty() {
+            return Ok(());
+        }
+        let amount = TokenAccount::try_deserialize(&mut &self.vault_free.try_borrow_data()?[..])?.amount;
+
+        let seeds = &[
+            self.beneficiary.to_account_info().key.as_ref(),
+            self.factory.to_account_info().key.as_ref(),
+            &[self.member.bump]
+        ];
+
+        if amount > 0 {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    token_interface::TransferChecked {
+                        from: self.vault_free.to_account_info(),
+                        mint: self.stake_token_mint.to_account_info(),
+                        to: self.beneficiary_token_account.to_account_info(),
+                        authority: self.member.to_account_info()
+                    },
+                    &[&seeds[..]],
+                ),
+                amount,
+                self.stake_token_mint.decimals,
+            )?;
+        }
+        transfer_fee::harvest_withheld_fees(
+            &self.token_program.to_account_info(),
+            &self.stake_token_mint.to_account_info(),
+            &[self.vault_free.to_account_info()],
+        )?;
+
+        token_interface::close_account(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                CloseAccount {
+                    account: self.vault_free.to_account_info(),
+                    destination: self.beneficiary.to_account_info(),
+                    authority: self.member.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+        )
+    }
+}
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+
+#[derive(Accounts)]
+pub struct CloseStakePool<'info> {
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = owner,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            PoolEntry::PDA_SEED,
+            &stake_pool.id.to_le_bytes(),
+        ],
+        bump = pool_entry.bump,
+        has_one = stake_pool,
+    )]
+    pub pool_entry: Account<'info, PoolEntry>,
+    pub owner: Signer<'info>,
+    pub clock: Sysvar<'info, Clock>,
+}
@@ -0,0 +1,111 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use crate::reward::accrued_reward;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
+
+#[derive(Accounts)]
+pub struct CompoundReward<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = reward_token_mint,
+        has_one = vault_reward,
+        constraint = factory.reward_token_mint == factory.stake_token_mint @ SPError::CompoundNotSupported,
+        constraint = !factory.paused @ SPError::FactoryPaused,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+        has_one = vault_fee,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        mut,
+        seeds = [
+            stake_pool.to_account_info().key.as_ref(),
+            member.to_account_info().key.as_ref(),
+        ],
+        bump,
+        has_one = vault_staked,
+        has_one = stake_pool,
+        has_one = beneficiary,
+    )]
+    pub member_stake: Account<'info, MemberStake>,
+    #[account(
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump = member.bump,
+        has_one = beneficiary,
+    )]
+    pub member: Account<'info, Member>,
+    #[account(
+        constraint = member.is_authority(authority.key) @ SPError::NotMemberAuthority,
+    )]
+    pub authority: Signer<'info>,
+    /// CHECK: The owner of the `member`, the reward stays in its `vault_staked`.
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = vault_staked.owner == member_stake.key(),
+    )]
+    pub vault_staked: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub vault_fee: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+impl<'info> CompoundReward<'info> {
+    pub fn calculate_reward_tokens(&self) -> Result<(u64, u64)> {
+        accrued_reward(
+            &self.stake_pool,
+            &*self.member_stake,
+            self.member_stake.staked_tokens,
+            self.clock.unix_timestamp as u64,
+        )
+    }
+
+    /// Stakes the net reward in the `vault_staked`
+    /// and accrues the owner's fee in the stake pool's `vault_fee`.
+    pub fn transfer_reward_tokens(&self, reward_tokens_for_user: u64, reward_tokens_for_owner: u64) -> Result<()> {
+        self.transfer_from_reward_vault(self.vault_staked.to_account_info(), reward_tokens_for_user)?;
+        if reward_tokens_for_owner == 0 {
+            return Ok(());
+        }
+        self.transfer_from_reward_vault(self.vault_fee.to_account_info(), reward_tokens_for_owner)
+    }
+
+    fn transfer_from_reward_vault(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
+        let seeds = &[
+            Factory::PDA_SEED,
+            &[self.factory.bump]
+        ];
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.vault_reward.to_account_info(),
+                    mint: self.reward_token_mint.to_account_info(),
+                    to,
+                    authority: self.factory.to_account_info()
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+            self.reward_token_mint.decimals,
+        )
+    }
+}
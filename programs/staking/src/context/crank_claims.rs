@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use crate::fee::split_crank_tip;
+use crate::math::TryMath;
+use crate::reward::settle_claim;
+use crate::solvency;
+use anchor_lang::Owners;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
+
+#[derive(Accounts)]
+pub struct CrankClaims<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = reward_token_mint,
+        has_one = vault_reward,
+        constraint = !factory.paused @ SPError::FactoryPaused,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub cranker: Signer<'info>,
+    /// Receives the tips of all the cranked positions.
+    #[account(
+        mut,
+        constraint = cranker_reward_vault.mint == factory.reward_token_mint,
+    )]
+    pub cranker_reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+impl<'info> CrankClaims<'info> {
+    /// Claims the reward of the `positions` given as
+    /// `[stake_pool, member, member_stake, vault_fee, reward_vault]` of the remaining accounts.
+    /// Pays the net reward of each position to its `reward_vault` accepted by the member
+    /// and the owner's fee less the crank tip to its pool's `vault_fee`.
+    /// Returns the tips of all the positions to be transferred at once.
+    /// The positions without a reward to claim yet are skipped.
+    pub fn crank_positions(&mut self, positions: &'info [AccountInfo<'info>]) -> Result<u64> {
+        let positions = positions.chunks_exact(5);
+        require!(positions.len() > 0 && positions.remainder().is_empty(), SPError::ClaimPositionInvalid);
+
+        let now = self.clock.unix_timestamp as u64;
+        let mut reward_tokens_available = self.vault_reward.amount;
+        let mut reward_tokens_for_cranker: u64 = 0;
+        let mut claimed: Vec<&Pubkey> = Vec::with_capacity(positions.len());
+
+        for position in positions {
+            let (stake_pool_info, member_info, member_stake_info, vault_fee_info, reward_vault_info) =
+                (&position[0], &position[1], &position[2], &position[3], &position[4]);
+            require!(!claimed.contains(&member_stake_info.key), SPError::ClaimPositionInvalid);
+            require!(TokenAccount::owners().contains(reward_vault_info.owner), SPError::ClaimPositionInvalid);
+
+            let mut stake_pool = Account::<StakePool>::try_from(stake_pool_info)?;
+            let member = Account::<Member>::try_from(member_info)?;
+            let mut member_stake = Account::<MemberStake>::try_from(member_stake_info)?;
+            let reward_vault = TokenAccount::try_deserialize(&mut &reward_vault_info.try_borrow_data()?[..])?;
+            require!(member.auto_claim, SPError::AutoClaimDisabled);
+            require!(member_stake.beneficiary == member.beneficiary, SPError::ClaimPositionInvalid);
+            require!(member_stake.stake_pool == stake_pool.key(), SPError::ClaimPositionInvalid);
+            require!(stake_pool.vault_fee == *vault_fee_info.key, SPError::ClaimPositionInvalid);
+            require!(
+                member.accepts_reward_vault(reward_vault_info.key, &reward_vault.owner)
+                    && reward_vault.mint == self.factory.reward_token_mint,
+                SPError::RewardDestinationNotAllowed
+            );
+            claimed.push(member_stake_info.key);
+
+            let staked_tokens = member_stake.staked_tokens;
+            let reward_tokens_to_transfer = match settle_claim(
+                &stake_pool,
+                &mut *member_stake,
+                staked_tokens,
+                now,
+                solvency::available_rewards(&self.factory, &stake_pool, reward_tokens_available),
+                solvency::partition_committed(&self.factory, &stake_pool),
+            )? {
+                Some(reward_tokens_to_transfer) => reward_tokens_to_transfer,
+                None => continue,
+            };
+            let (reward_tokens_for_owner, reward_tokens_for_user) = stake_pool.owner_fee.split(reward_tokens_to_transfer)?;
+            let (crank_tip, reward_tokens_for_owner) = split_crank_tip(reward_tokens_for_owner, stake_pool.crank_tip_bps)?;
+
+            self.transfer_from_reward_vault(reward_vault_info.clone(), reward_tokens_for_user)?;
+            self.transfer_from_reward_vault(vault_fee_info.clone(), reward_tokens_for_owner)?;
+            reward_tokens_available = reward_tokens_available.try_sub(reward_tokens_to_transfer)?;
+            reward_tokens_for_cranker = reward_tokens_for_cranker.try_add(crank_tip)?;
+            member_stake.exit(&crate::ID)?;
+
+            let factory = &mut self.factory;
+            solvency::release_from_partition(factory, &mut stake_pool, reward_tokens_to_transfer as u128);
+            solvency::spend_funded(factory, &mut stake_pool, reward_tokens_to_transfer)?;
+            stake_pool.exit(&crate::ID)?;
+        }
+
+        Ok(reward_tokens_for_cranker)
+    }
+
+    /// Transfers the tips of all the cranked positions to the `cranker_reward_vault`.
+    pub fn transfer_tips_to_cranker(&self, amount: u64) -> Result<()> {
+        self.transfer_from_reward_vault(self.cranker_reward_vault.to_account_info(), amount)
+    }
+
+    fn transfer_from_reward_vault(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let seeds = &[
+            Factory::PDA_SEED,
+            &[self.factory.bump]
+        ];
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.vault_reward.to_account_info(),
+                    mint: self.reward_token_mint.to_account_info(),
+                    to,
+                    authority: self.factory.to_account_info()
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+            self.reward_token_mint.decimals,
+        )
+    }
+}
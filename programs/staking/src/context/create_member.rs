@@ -1,6 +1,6 @@
 use anchor_lang::prelude::*;
 use crate::account::*;
-use anchor_spl::token::{TokenAccount, Token, Mint};
+use anchor_spl::token_interface::{TokenAccount, TokenInterface, Mint};
 use anchor_spl::associated_token::AssociatedToken;
 
 #[derive(Accounts)]
@@ -11,7 +11,7 @@ pub struct CreateMember<'info> {
         has_one = stake_token_mint,
     )]
     pub factory: Account<'info, Factory>,
-    pub stake_token_mint: Box<Account<'info, Mint>>,
+    pub stake_token_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         init,
         payer = beneficiary,
@@ -23,17 +23,29 @@ pub struct CreateMember<'info> {
         bump,
     )]
     pub member: Account<'info, Member>,
+    #[account(
+        init,
+        payer = beneficiary,
+        space = 8 + PositionIndex::SPACE,
+        seeds = [
+            member.to_account_info().key.as_ref(),
+            PositionIndex::PDA_SEED,
+        ],
+        bump,
+    )]
+    pub position_index: Box<Account<'info, PositionIndex>>,
     #[account(
         init,
         payer = beneficiary,
         associated_token::mint = stake_token_mint,
         associated_token::authority = member,
+        associated_token::token_program = token_program,
     )]
-    pub vault_free: Box<Account<'info, TokenAccount>>,
+    pub vault_free: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(mut)]
     pub beneficiary: Signer<'info>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub rent: Sysvar<'info, Rent>,
 }
@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
+use anchor_spl::associated_token::{self as ata, AssociatedToken};
+
+#[derive(Accounts)]
+pub struct DepositAndStake<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = stake_token_mint,
+        has_one = vault_reward,
+        constraint = !factory.paused @ SPError::FactoryPaused,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub stake_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    /// Created on the first use. Its `vault_free` isn't created until needed.
+    #[account(
+        init_if_needed,
+        payer = beneficiary,
+        space = 8 + Member::SPACE,
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump,
+    )]
+    pub member: Account<'info, Member>,
+    /// Created with the `member`, an existing member without one has to index its positions first.
+    #[account(
+        init_if_needed,
+        payer = beneficiary,
+        space = 8 + PositionIndex::SPACE,
+        seeds = [
+            member.to_account_info().key.as_ref(),
+            PositionIndex::PDA_SEED,
+        ],
+        bump,
+    )]
+    pub position_index: Box<Account<'info, PositionIndex>>,
+    #[account(
+        mut,
+        constraint = beneficiary_token_account.owner == beneficiary.key(),
+        constraint = beneficiary_token_account.mint == factory.stake_token_mint,
+    )]
+    pub beneficiary_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = beneficiary,
+        space = 8 + MemberStake::SPACE,
+        seeds = [
+            stake_pool.to_account_info().key.as_ref(),
+            member.to_account_info().key.as_ref(),
+        ],
+        bump,
+    )]
+    pub member_stake: Account<'info, MemberStake>,
+    #[account(
+        init,
+        payer = beneficiary,
+        associated_token::mint = stake_token_mint,
+        associated_token::authority = member_stake,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_staked: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub clock: Sysvar<'info, Clock>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> DepositAndStake<'info> {
+    /// Initializes the `member` and the `position_index` created by this instruction, existing ones are left as is.
+    /// A member opened before the position index has to index its positions with `index_positions` first.
+    pub fn initialize_member(&mut self, bump: u8, position_index_bump: u8) -> Result<()> {
+        if self.member.beneficiary == Pubkey::default() {
+            let member = &mut self.member;
+            member.beneficiary = self.beneficiary.key();
+            member.vault_free = ata::get_associated_token_address_with_program_id(
+                &member.key(),
+                &self.stake_token_mint.key(),
+                self.token_program.key,
+            );
+            member.bump = bump;
+            member.delegate = None;
+            member.reward_destination = None;
+            member.auto_claim = false;
+            member.open_positions = 0;
+            member.version = ACCOUNT_VERSION;
+            member.pending_unstakes = 0;
+        }
+
+        if self.position_index.member == Pubkey::default() {
+            require!(self.member.open_positions == 0, SPError::PositionIndexWrong);
+            self.position_index.open(self.member.key(), position_index_bump);
+        }
+
+        Ok(())
+    }
+
+    pub fn transfer_user_tokens_to_staked_vault(&self, amount_to_stake: u64) -> Result<()> {
+        token_interface::transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.beneficiary_token_account.to_account_info(),
+                    mint: self.stake_token_mint.to_account_info(),
+                    to: self.vault_staked.to_account_info(),
+                    authority: self.beneficiary.to_account_info(),
+                },
+            ),
+            amount_to_stake,
+            self.stake_token_mint.decimals,
+        )
+    }
+}
@@ -1,48 +1,58 @@
 use anchor_lang::prelude::*;
 use crate::account::*;
-use anchor_spl::token::{self, TokenAccount, Token};
+use crate::error::SPError;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
 
 #[derive(Accounts)]
 pub struct DepositReward<'info> {
     #[account(
         seeds = [Factory::PDA_SEED],
         bump = factory.bump,
+        has_one = reward_token_mint,
     )]
     pub factory: Account<'info, Factory>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [Factory::PDA_SEED, FactoryRoles::PDA_SEED],
+        bump = roles.bump,
+    )]
+    pub roles: Account<'info, FactoryRoles>,
+    /// The admin or the reward funder.
     #[account(
         mut,
-        constraint = owner.key() == factory.owner,
+        constraint = roles.allows(&factory, Role::RewardFunder, funder.key) @ SPError::RoleRequired,
     )]
-    pub owner: Signer<'info>,
+    pub funder: Signer<'info>,
     #[account(
         mut,
-        constraint = vault_owner.owner == owner.key(),
-        constraint = vault_owner.mint == factory.reward_token_mint
+        constraint = vault_funder.owner == funder.key(),
+        constraint = vault_funder.mint == factory.reward_token_mint
     )]
-    pub vault_owner: Box<Account<'info, TokenAccount>>,
+    pub vault_funder: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         constraint = vault_reward.owner == factory.key(),
         constraint = vault_reward.mint == factory.reward_token_mint
     )]
-    pub vault_reward: Box<Account<'info, TokenAccount>>,
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 impl<'info> DepositReward<'info> {
     pub fn transfer_tokens_to_reward_vault(&self, amount: u64) -> Result<()> {
         let token_program = self.token_program.to_account_info();
-        let from = self.vault_owner.to_account_info();
+        let from = self.vault_funder.to_account_info();
         let to = self.vault_reward.to_account_info();
-        let authority = self.owner.to_account_info();
+        let authority = self.funder.to_account_info();
 
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new(
                 token_program,
-                token::Transfer { from, to, authority },
+                token_interface::TransferChecked { from, mint: self.reward_token_mint.to_account_info(), to, authority },
             ), 
-            amount
+            amount,
+            self.reward_token_mint.decimals,
         )
     }
 }
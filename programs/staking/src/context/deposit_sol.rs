@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use crate::account::*;
+use crate::error::SPError;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, SyncNative};
+
+#[derive(Accounts)]
+pub struct DepositSol<'info> {
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        constraint = factory.stake_token_mint == spl_token::native_mint::ID @ SPError::StakeMintNotNative,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump = member.bump,
+        has_one = vault_free,
+    )]
+    pub member: Account<'info, Member>,
+    #[account(mut)]
+    pub vault_free: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> DepositSol<'info> {
+    /// Moves the lamports to the wrapped SOL `vault_free` and syncs its token amount.
+    pub fn wrap_lamports_to_free_vault(&self, lamports: u64) -> Result<()> {
+        system_program::transfer(
+            CpiContext::new(
+                self.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: self.beneficiary.to_account_info(),
+                    to: self.vault_free.to_account_info(),
+                },
+            ),
+            lamports
+        )?;
+
+        token_interface::sync_native(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                SyncNative { account: self.vault_free.to_account_info() },
+            ),
+        )
+    }
+}
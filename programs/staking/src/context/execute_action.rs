@@ -0,0 +1,91 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use crate::math::TryMath;
+use crate::reward::settle_accrued;
+use crate::solvency;
+use crate::timelock::AdminAction;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::TokenAccount;
+
+/// Signed by anyone once the `eta` has come, the action being public since it was queued.
+#[derive(Accounts)]
+pub struct ExecuteAction<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = vault_reward,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED, FactoryRoles::PDA_SEED],
+        bump = roles.bump,
+    )]
+    pub roles: Account<'info, FactoryRoles>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            QueuedAction::PDA_SEED,
+            &queued_action.id.to_le_bytes(),
+        ],
+        bump = queued_action.bump,
+        has_one = proposer,
+        close = proposer,
+        constraint = queued_action.eta <= clock.unix_timestamp as u64 @ SPError::TimelockNotElapsed,
+    )]
+    pub queued_action: Box<Account<'info, QueuedAction>>,
+    /// CHECK: The proposer of the action, checked by its `has_one` and refunded the rent.
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+impl<'info> ExecuteAction<'info> {
+    /// Settles the rewards accrued by the `positions` of the `stake_pool`, its `MemberStake` and `NftStake` accounts
+    /// following it in the remaining accounts, before the `action` changes the reward or the manager of the pool.
+    /// The positions have to cover all the tokens staked in the pool.
+    pub fn settle_rewards(&mut self, action: &AdminAction, stake_pool: &mut StakePool, positions: &'info [AccountInfo<'info>]) -> Result<()> {
+        let now = self.clock.unix_timestamp as u64;
+        let stake_pool_key = action.stake_pool().ok_or(SPError::ActionStakePoolWrong)?;
+        let mut settled: Vec<&Pubkey> = Vec::with_capacity(positions.len());
+        let mut staked_in_positions: u128 = 0;
+        let mut owed: u64 = 0;
+
+        for position_info in positions {
+            require!(!settled.contains(&position_info.key), SPError::SettlementPositionsWrong);
+            settled.push(position_info.key);
+
+            let is_nft = position_info.try_borrow_data()?.starts_with(&NftStake::discriminator());
+            let (staked, reward_owed) = if is_nft {
+                let mut nft_stake = Account::<NftStake>::try_from(position_info)?;
+                require!(nft_stake.stake_pool == stake_pool_key, SPError::SettlementPositionsWrong);
+                let staked = nft_stake.staked_weight();
+                let reward_owed = settle_accrued(stake_pool, &mut *nft_stake, staked, now)?;
+                nft_stake.exit(&crate::ID)?;
+                (staked, reward_owed)
+            } else {
+                let mut member_stake = Account::<MemberStake>::try_from(position_info)?;
+                require!(member_stake.stake_pool == stake_pool_key, SPError::SettlementPositionsWrong);
+                let staked = member_stake.staked_tokens;
+                let reward_owed = settle_accrued(stake_pool, &mut *member_stake, staked, now)?;
+                member_stake.exit(&crate::ID)?;
+                (staked, reward_owed)
+            };
+            staked_in_positions = staked_in_positions.try_add(staked as u128)?;
+            owed = owed.try_add(reward_owed)?;
+        }
+        require!(staked_in_positions == stake_pool.total_staked_tokens, SPError::SettlementPositionsWrong);
+
+        if let AdminAction::SetPoolManager { manager: Some(_), .. } = action {
+            if !stake_pool.has_manager() {
+                solvency::earmark_owed(&mut self.factory, stake_pool, owed, self.vault_reward.amount)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,20 +1,25 @@
 use anchor_lang::prelude::*;
 use crate::account::*;
-use anchor_spl::token::{self, TokenAccount, Token, CloseAccount};
+use crate::transfer_fee;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, CloseAccount, Mint};
 
 
 #[derive(Accounts)]
 pub struct FinishUnstakeAll<'info> {
     #[account(
+        mut,
         seeds = [Factory::PDA_SEED], 
         bump = factory.bump,
+        has_one = stake_token_mint,
     )]
     pub factory: Account<'info, Factory>,
+    #[account(mut)]
+    pub stake_token_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         seeds = [
             factory.to_account_info().key.as_ref(),
-            stake_pool.name.as_ref()
+            stake_pool.pda_seed()
         ],
         bump = stake_pool.bump,
     )]
@@ -22,6 +27,7 @@ pub struct FinishUnstakeAll<'info> {
     #[account(mut)]
     pub beneficiary: Signer<'info>,
     #[account(
+        mut,
         seeds = [
             beneficiary.to_account_info().key.as_ref(),
             factory.to_account_info().key.as_ref(),
@@ -30,8 +36,18 @@ pub struct FinishUnstakeAll<'info> {
         has_one = vault_free,
     )]
     pub member: Account<'info, Member>,
+    #[account(
+        mut,
+        seeds = [
+            member.to_account_info().key.as_ref(),
+            PositionIndex::PDA_SEED,
+        ],
+        bump = position_index.bump,
+        has_one = member,
+    )]
+    pub position_index: Box<Account<'info, PositionIndex>>,
     #[account(mut)]
-    pub vault_free: Box<Account<'info, TokenAccount>>,
+    pub vault_free: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         seeds = [
@@ -44,7 +60,7 @@ pub struct FinishUnstakeAll<'info> {
     )]
     pub member_stake: Account<'info, MemberStake>,
     #[account(mut)]
-    pub vault_staked: Box<Account<'info, TokenAccount>>,
+    pub vault_staked: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         seeds = [
@@ -61,42 +77,53 @@ pub struct FinishUnstakeAll<'info> {
         constraint = vault_pending_unstake.owner == stake_pool.key(),
         constraint = vault_pending_unstake.mint == factory.stake_token_mint,
     )]
-    pub vault_pending_unstake: Box<Account<'info, TokenAccount>>,
+    pub vault_pending_unstake: Box<InterfaceAccount<'info, TokenAccount>>,
     pub clock: Sysvar<'info, Clock>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> FinishUnstakeAll<'info> {
+    /// Moves the transfer fees withheld in the vaults to the mint, so the vaults could be closed.
+    pub fn harvest_withheld_fees(&self) -> Result<()> {
+        transfer_fee::harvest_withheld_fees(
+            &self.token_program.to_account_info(),
+            &self.stake_token_mint.to_account_info(),
+            &[self.vault_pending_unstake.to_account_info(), self.vault_staked.to_account_info()],
+        )
+    }
+
     pub fn transfer_pu_tokens_to_free_vault(&self, amount: u64) -> Result<()> {
         let seeds: &[&[u8]] = &[
             self.factory.to_account_info().key.as_ref(),
-            self.stake_pool.name.as_ref(),
+            self.stake_pool.pda_seed(),
             &[self.stake_pool.bump]
         ];
 
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
-                token::Transfer {
+                token_interface::TransferChecked {
                     from: self.vault_pending_unstake.to_account_info(),
+                    mint: self.stake_token_mint.to_account_info(),
                     to: self.vault_free.to_account_info(),
                     authority: self.stake_pool.to_account_info(),
                 },
-                &[&seeds[..]],
+                &[seeds],
             ),
-            amount
+            amount,
+            self.stake_token_mint.decimals,
         )
     }
 
     pub fn close_pending_unstake_vault(&self) -> Result<()> {
         let seeds: &[&[u8]] = &[
             self.factory.to_account_info().key.as_ref(),
-            self.stake_pool.name.as_ref(),
+            self.stake_pool.pda_seed(),
             &[self.stake_pool.bump]
         ];
 
-        token::close_account(
+        token_interface::close_account(
             CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
                 CloseAccount {
@@ -104,7 +131,7 @@ impl<'info> FinishUnstakeAll<'info> {
                     destination: self.beneficiary.to_account_info(),
                     authority: self.stake_pool.to_account_info(),
                 },
-                &[&seeds[..]]
+                &[seeds]
             ),
         )
     }
@@ -116,7 +143,7 @@ impl<'info> FinishUnstakeAll<'info> {
             &[self.member_stake.bump]
         ];
 
-        token::close_account(
+        token_interface::close_account(
             CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
                 CloseAccount {
@@ -124,7 +151,7 @@ impl<'info> FinishUnstakeAll<'info> {
                     destination: self.beneficiary.to_account_info(),
                     authority: self.member_stake.to_account_info(),
                 },
-                &[&seeds[..]]
+                &[seeds]
             ),
         )
     }
@@ -0,0 +1,115 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, CloseAccount, Mint};
+
+#[derive(Accounts)]
+pub struct FinishUnstakeNft<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump = member.bump,
+        has_one = beneficiary,
+    )]
+    pub member: Account<'info, Member>,
+    #[account(
+        mut,
+        seeds = [
+            member.to_account_info().key.as_ref(),
+            PositionIndex::PDA_SEED,
+        ],
+        bump = position_index.bump,
+        has_one = member,
+    )]
+    pub position_index: Box<Account<'info, PositionIndex>>,
+    #[account(
+        mut,
+        seeds = [
+            stake_pool.to_account_info().key.as_ref(),
+            nft_stake.mint.as_ref(),
+        ],
+        bump = nft_stake.bump,
+        has_one = stake_pool,
+        has_one = beneficiary,
+        has_one = vault_nft,
+        close = beneficiary,
+    )]
+    pub nft_stake: Account<'info, NftStake>,
+    #[account(address = nft_stake.mint)]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub vault_nft: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = beneficiary_nft_account.owner == beneficiary.key(),
+        constraint = beneficiary_nft_account.mint == nft_stake.mint,
+    )]
+    pub beneficiary_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub clock: Sysvar<'info, Clock>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> FinishUnstakeNft<'info> {
+    pub fn transfer_nft_to_beneficiary(&self) -> Result<()> {
+        let seeds: &[&[u8]] = &[
+            self.stake_pool.to_account_info().key.as_ref(),
+            self.nft_stake.mint.as_ref(),
+            &[self.nft_stake.bump]
+        ];
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.vault_nft.to_account_info(),
+                    mint: self.nft_mint.to_account_info(),
+                    to: self.beneficiary_nft_account.to_account_info(),
+                    authority: self.nft_stake.to_account_info(),
+                },
+                &[seeds],
+            ),
+            self.vault_nft.amount,
+            self.nft_mint.decimals,
+        )
+    }
+
+    pub fn close_nft_vault(&self) -> Result<()> {
+        let seeds: &[&[u8]] = &[
+            self.stake_pool.to_account_info().key.as_ref(),
+            self.nft_stake.mint.as_ref(),
+            &[self.nft_stake.bump]
+        ];
+
+        token_interface::close_account(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                CloseAccount {
+                    account: self.vault_nft.to_account_info(),
+                    destination: self.beneficiary.to_account_info(),
+                    authority: self.nft_stake.to_account_info(),
+                },
+                &[seeds]
+            ),
+        )
+    }
+}
@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
+
+#[derive(Accounts)]
+pub struct FundStakePool<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = reward_token_mint,
+        has_one = vault_reward,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+        constraint = stake_pool.has_manager() @ SPError::StakePoolNotManaged,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    /// The admin or the pool's manager.
+    #[account(
+        constraint = stake_pool.is_managed_by(&factory, authority.key) @ SPError::NotPoolManager,
+    )]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = vault_authority.owner == authority.key(),
+        constraint = vault_authority.mint == factory.reward_token_mint,
+    )]
+    pub vault_authority: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FundStakePool<'info> {
+    pub fn transfer_tokens_to_reward_vault(&self, amount: u64) -> Result<()> {
+        token_interface::transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.vault_authority.to_account_info(),
+                    mint: self.reward_token_mint.to_account_info(),
+                    to: self.vault_reward.to_account_info(),
+                    authority: self.authority.to_account_info(),
+                },
+            ),
+            amount,
+            self.reward_token_mint.decimals,
+        )
+    }
+}
@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
+
+#[derive(Accounts)]
+pub struct HarvestFees<'info> {
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = reward_token_mint,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+        has_one = vault_fee,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        constraint = authority.key() == factory.owner || authority.key() == stake_pool.fee_recipient
+            @ SPError::HarvestFeesAuthorityMismatch,
+    )]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault_fee: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = fee_recipient_vault.owner == stake_pool.fee_recipient,
+        constraint = fee_recipient_vault.mint == factory.reward_token_mint,
+    )]
+    pub fee_recipient_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> HarvestFees<'info> {
+    pub fn transfer_fees_to_recipient(&self) -> Result<()> {
+        let seeds: &[&[u8]] = &[
+            self.factory.to_account_info().key.as_ref(),
+            self.stake_pool.pda_seed(),
+            &[self.stake_pool.bump]
+        ];
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.vault_fee.to_account_info(),
+                    mint: self.reward_token_mint.to_account_info(),
+                    to: self.fee_recipient_vault.to_account_info(),
+                    authority: self.stake_pool.to_account_info(),
+                },
+                &[seeds],
+            ),
+            self.vault_fee.amount,
+            self.reward_token_mint.decimals,
+        )
+    }
+}
@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use crate::account::*;
+use crate::error::SPError;
+
+#[derive(Accounts)]
+pub struct IndexPositions<'info> {
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump = member.bump,
+        has_one = beneficiary,
+    )]
+    pub member: Account<'info, Member>,
+    #[account(
+        init,
+        payer = beneficiary,
+        space = 8 + PositionIndex::SPACE,
+        seeds = [
+            member.to_account_info().key.as_ref(),
+            PositionIndex::PDA_SEED,
+        ],
+        bump,
+    )]
+    pub position_index: Box<Account<'info, PositionIndex>>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> IndexPositions<'info> {
+    /// The kind and the stake pool of a `MemberStake`, a `MemberPendingUnstake` or an `NftStake` of the beneficiary.
+    pub fn position_of(&self, account: &AccountInfo) -> Result<(PositionKind, Pubkey)> {
+        require!(account.owner == &crate::ID, SPError::PositionIndexWrong);
+        let data = account.try_borrow_data()?;
+        let discriminator = data.get(..8).ok_or(SPError::PositionIndexWrong)?;
+
+        let (kind, stake_pool, beneficiary) = if discriminator == MemberStake::discriminator() {
+            let stake = MemberStake::try_deserialize(&mut &data[..])?;
+            (PositionKind::Stake, stake.stake_pool, stake.beneficiary)
+        } else if discriminator == MemberPendingUnstake::discriminator() {
+            let unstake = MemberPendingUnstake::try_deserialize(&mut &data[..])?;
+            (PositionKind::PendingUnstake, unstake.stake_pool, unstake.beneficiary)
+        } else if discriminator == NftStake::discriminator() {
+            let nft_stake = NftStake::try_deserialize(&mut &data[..])?;
+            (PositionKind::Nft, nft_stake.stake_pool, nft_stake.beneficiary)
+        } else {
+            return err!(SPError::PositionIndexWrong);
+        };
+        require!(beneficiary == self.beneficiary.key(), SPError::PositionIndexWrong);
+
+        Ok((kind, stake_pool))
+    }
+}
@@ -1,7 +1,7 @@
 use anchor_lang::prelude::*;
 use crate::account::*;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{TokenAccount, Token, Mint};
+use anchor_spl::token_interface::{TokenAccount, TokenInterface, Mint};
 
 #[derive(Accounts)]
 pub struct Initialize<'info> {
@@ -13,18 +13,27 @@ pub struct Initialize<'info> {
         bump,
     )]
     pub factory: Account<'info, Factory>,
-    pub reward_token_mint: Box<Account<'info, Mint>>,
-    pub stake_token_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = initializer,
+        space = 8 + FactoryRoles::SPACE,
+        seeds = [Factory::PDA_SEED, FactoryRoles::PDA_SEED],
+        bump,
+    )]
+    pub roles: Account<'info, FactoryRoles>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub stake_token_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         init,
         payer = initializer,
         associated_token::mint = reward_token_mint,
         associated_token::authority = factory,
+        associated_token::token_program = token_program,
     )]
-    pub vault_reward: Box<Account<'info, TokenAccount>>,
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(mut)]
     pub initializer: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
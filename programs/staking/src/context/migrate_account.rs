@@ -0,0 +1,162 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface, Mint};
+use crate::account::*;
+use crate::error::SPError;
+use crate::migration;
+use crate::space;
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    /// CHECK: A version 0 account of this program, told by its discriminator and length in `migration::upgrade`.
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    /// Pays the rent of the added space.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateAccount<'info> {
+    /// Reallocates the `account` of `old_space` to `space` and rewrites it in the current layout of `New`.
+    pub fn migrate<Old, New>(&self, old_space: usize, space: usize) -> Result<()>
+    where
+        Old: AnchorDeserialize,
+        New: From<Old> + AccountSerialize + Discriminator,
+    {
+        self.migrate_with::<Old, New>(old_space, space, |_| Ok(()))
+    }
+
+    /// Same as `migrate`, with the fields the v0 account doesn't have filled in by `complete`.
+    pub fn migrate_with<Old, New>(&self, old_space: usize, space: usize, complete: impl FnOnce(&mut New) -> Result<()>) -> Result<()>
+    where
+        Old: AnchorDeserialize,
+        New: From<Old> + AccountSerialize + Discriminator,
+    {
+        let upgraded = migration::upgrade_with::<Old, New>(&self.account.try_borrow_data()?, old_space, space, complete)?;
+        rewrite(&self.account, &self.payer, &self.system_program, upgraded)
+    }
+}
+
+#[derive(Accounts)]
+pub struct MigrateFactory<'info> {
+    /// CHECK: The version 0 `Factory`, told by its discriminator and length in `migration::upgrade`.
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [Factory::PDA_SEED],
+        bump,
+    )]
+    pub factory: UncheckedAccount<'info>,
+    /// The v0 factories had no roles, the admin held them all.
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + FactoryRoles::SPACE,
+        seeds = [Factory::PDA_SEED, FactoryRoles::PDA_SEED],
+        bump,
+    )]
+    pub roles: Account<'info, FactoryRoles>,
+    /// Pays the rent of the added space and the roles.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateFactory<'info> {
+    /// Rewrites the v0 `factory` in the current layout and gives all the `roles` to its owner.
+    pub fn migrate(&mut self, roles_bump: u8) -> Result<()> {
+        let mut owner = Pubkey::default();
+        let upgraded = migration::upgrade_with::<migration::v0::Factory, Factory>(
+            &self.factory.try_borrow_data()?,
+            migration::v0::Factory::SPACE,
+            Factory::SPACE,
+            |factory| {
+                owner = factory.owner;
+                Ok(())
+            },
+        )?;
+        rewrite(&self.factory, &self.payer, &self.system_program, upgraded)?;
+
+        let roles = &mut self.roles;
+        roles.pool_creator = owner;
+        roles.reward_funder = owner;
+        roles.pauser = owner;
+        roles.bump = roles_bump;
+        roles.version = ACCOUNT_VERSION;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct MigrateStakePool<'info> {
+    /// CHECK: A version 0 `StakePool`, told by its discriminator and length in `migration::upgrade`.
+    #[account(mut, owner = crate::ID)]
+    pub stake_pool: UncheckedAccount<'info>,
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = reward_token_mint,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// The v0 pools paid the owner's fee straight to the factory owner, so they have no fee vault yet.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = reward_token_mint,
+        associated_token::authority = stake_pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_fee: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Pays the rent of the added space and the fee vault.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateStakePool<'info> {
+    /// Rewrites the v0 `stake_pool` in the current layout, its fees going to the factory owner through the `vault_fee`.
+    pub fn migrate(&self) -> Result<()> {
+        let upgraded = migration::upgrade_with::<migration::v0::StakePool, StakePool>(
+            &self.stake_pool.try_borrow_data()?,
+            migration::v0::StakePool::SPACE,
+            StakePool::SPACE,
+            |stake_pool| {
+                stake_pool.fee_recipient = self.factory.owner;
+                stake_pool.vault_fee = self.vault_fee.key();
+                Ok(())
+            },
+        )?;
+        rewrite(&self.stake_pool, &self.payer, &self.system_program, upgraded)
+    }
+}
+
+/// Counts a migrated position in the `Member` of its `beneficiary`, given as the `member_info`.
+/// The v0 members didn't count their positions, so each position adds itself as it's migrated.
+pub fn count_position<'info>(
+    member_info: &'info AccountInfo<'info>,
+    beneficiary: &Pubkey,
+    count: impl FnOnce(&mut Member) -> Result<()>,
+) -> Result<()> {
+    let mut member = Account::<Member>::try_from(member_info).map_err(|_| SPError::MigrationAccountWrong)?;
+    require!(member.beneficiary == *beneficiary, SPError::MigrationAccountWrong);
+    let factory = Pubkey::find_program_address(&[Factory::PDA_SEED], &crate::ID).0;
+    let member_key = Pubkey::create_program_address(&[beneficiary.as_ref(), factory.as_ref(), &[member.bump]], &crate::ID)
+        .map_err(|_| SPError::MigrationAccountWrong)?;
+    require!(member_key == *member_info.key, SPError::MigrationAccountWrong);
+
+    count(&mut member)?;
+    member.exit(&crate::ID)
+}
+
+/// Reallocates the `account` to the size of the `upgraded` data, the `payer` topping up the rent, and writes it.
+fn rewrite<'info>(account: &AccountInfo<'info>, payer: &AccountInfo<'info>, system_program: &AccountInfo<'info>, upgraded: Vec<u8>) -> Result<()> {
+    space::grow(account, payer, system_program, upgraded.len())?;
+    account.try_borrow_mut_data()?.copy_from_slice(&upgraded);
+
+    Ok(())
+}
@@ -8,3 +8,34 @@ mod stake; pub use stake::*;
 mod start_unstake_all; pub use start_unstake_all::*;
 mod withdraw_all; pub use withdraw_all::*;
 mod create_member; pub use create_member::*;
+mod harvest_fees; pub use harvest_fees::*;
+mod get_pending_rewards; pub use get_pending_rewards::*;
+mod audit_pool; pub use audit_pool::*;
+mod set_allowlist_root; pub use set_allowlist_root::*;
+mod get_stake_capacity; pub use get_stake_capacity::*;
+mod set_delegate; pub use set_delegate::*;
+mod compound_reward; pub use compound_reward::*;
+mod set_reward_destination; pub use set_reward_destination::*;
+mod claim_all; pub use claim_all::*;
+mod set_auto_claim; pub use set_auto_claim::*;
+mod crank_claims; pub use crank_claims::*;
+mod set_nft_config; pub use set_nft_config::*;
+mod stake_nft; pub use stake_nft::*;
+mod claim_nft_reward; pub use claim_nft_reward::*;
+mod start_unstake_nft; pub use start_unstake_nft::*;
+mod finish_unstake_nft; pub use finish_unstake_nft::*;
+mod deposit_sol; pub use deposit_sol::*;
+mod withdraw_sol; pub use withdraw_sol::*;
+mod deposit_and_stake; pub use deposit_and_stake::*;
+mod unstake_and_withdraw; pub use unstake_and_withdraw::*;
+mod close_member; pub use close_member::*;
+mod migrate_account; pub use migrate_account::*;
+mod set_pool_metadata; pub use set_pool_metadata::*;
+mod register_stake_pool; pub use register_stake_pool::*;
+mod close_stake_pool; pub use close_stake_pool::*;
+mod index_positions; pub use index_positions::*;
+mod pause; pub use pause::*;
+mod fund_stake_pool; pub use fund_stake_pool::*;
+mod queue_action; pub use queue_action::*;
+mod cancel_action; pub use cancel_action::*;
+mod execute_action; pub use execute_action::*;
@@ -1,6 +1,9 @@
 use anchor_lang::prelude::*;
 use crate::account::*;
 use crate::error::SPError;
+use crate::math::TryMath;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface, Mint};
 
 #[derive(Accounts)]
 #[instruction(name: String)]
@@ -9,22 +12,55 @@ pub struct NewStakePool<'info> {
         mut,
         seeds = [Factory::PDA_SEED], 
         bump = factory.bump,
-        has_one = owner @ SPError::NewPoolOwnerMismatch
+        has_one = reward_token_mint,
     )]
     pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [Factory::PDA_SEED, FactoryRoles::PDA_SEED],
+        bump = roles.bump,
+    )]
+    pub roles: Account<'info, FactoryRoles>,
     #[account(
         init,
-        payer = owner,
+        payer = creator,
         space = 8 + StakePool::SPACE,
         seeds = [
             factory.to_account_info().key.as_ref(),
-            name.as_bytes()
+            StakePool::seed_for(&name).as_ref(),
         ],
         bump,
     )]
     pub stake_pool: Account<'info, StakePool>,
-    #[account(mut)]
-    pub owner: Signer<'info>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + PoolEntry::SPACE,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            PoolEntry::PDA_SEED,
+            &factory.pool_count.try_add(1)?.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub pool_entry: Box<Account<'info, PoolEntry>>,
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = creator,
+        associated_token::mint = reward_token_mint,
+        associated_token::authority = stake_pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_fee: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// The admin or the pool creator.
+    #[account(
+        mut,
+        constraint = roles.allows(&factory, Role::PoolCreator, creator.key) @ SPError::NewPoolOwnerMismatch,
+    )]
+    pub creator: Signer<'info>,
     pub clock: Sysvar<'info, Clock>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
 }
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+
+#[derive(Accounts)]
+pub struct Pause<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [Factory::PDA_SEED, FactoryRoles::PDA_SEED],
+        bump = roles.bump,
+    )]
+    pub roles: Account<'info, FactoryRoles>,
+    /// The admin or the pauser.
+    #[account(
+        constraint = roles.allows(&factory, Role::Pauser, pauser.key) @ SPError::RoleRequired,
+    )]
+    pub pauser: Signer<'info>,
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::math::TryMath;
+
+#[derive(Accounts)]
+pub struct QueueAction<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [Factory::PDA_SEED, FactoryRoles::PDA_SEED],
+        bump = roles.bump,
+    )]
+    pub roles: Account<'info, FactoryRoles>,
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + QueuedAction::SPACE,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            QueuedAction::PDA_SEED,
+            &factory.action_count.try_add(1)?.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub queued_action: Box<Account<'info, QueuedAction>>,
+    /// The admin, the pauser lifting the pause, the pool creator assigning a manager
+    /// or the manager of the pool the action changes. See `QueuedAction::may_propose`.
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub clock: Sysvar<'info, Clock>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use crate::math::TryMath;
+
+#[derive(Accounts)]
+pub struct RegisterStakePool<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [Factory::PDA_SEED, FactoryRoles::PDA_SEED],
+        bump = roles.bump,
+    )]
+    pub roles: Account<'info, FactoryRoles>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+        constraint = stake_pool.id == 0 @ SPError::StakePoolRegisteredAlready,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + PoolEntry::SPACE,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            PoolEntry::PDA_SEED,
+            &factory.pool_count.try_add(1)?.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub pool_entry: Account<'info, PoolEntry>,
+    /// The admin or the pool creator.
+    #[account(
+        mut,
+        constraint = roles.allows(&factory, Role::PoolCreator, creator.key) @ SPError::RoleRequired,
+    )]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+
+#[derive(Accounts)]
+pub struct SetNftConfig<'info> {
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    /// The admin or the pool's manager.
+    #[account(
+        constraint = stake_pool.is_managed_by(&factory, authority.key) @ SPError::NotPoolManager,
+    )]
+    pub authority: Signer<'info>,
+}
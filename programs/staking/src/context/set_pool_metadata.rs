@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+
+#[derive(Accounts)]
+pub struct SetPoolMetadata<'info> {
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + StakePoolMetadata::SPACE,
+        seeds = [
+            stake_pool.to_account_info().key.as_ref(),
+            StakePoolMetadata::PDA_SEED,
+        ],
+        bump,
+    )]
+    pub metadata: Account<'info, StakePoolMetadata>,
+    /// The admin or the pool's manager.
+    #[account(
+        mut,
+        constraint = stake_pool.is_managed_by(&factory, authority.key) @ SPError::NotPoolManager,
+    )]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
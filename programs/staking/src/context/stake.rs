@@ -1,29 +1,35 @@
 use anchor_lang::prelude::*;
 use crate::account::*;
-use anchor_spl::token::{self, TokenAccount, Token, Mint};
+use crate::error::SPError;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
 use anchor_spl::associated_token::AssociatedToken;
 
 #[derive(Accounts)]
 pub struct Stake<'info> {
     #[account(
+        mut,
         seeds = [Factory::PDA_SEED],
         bump = factory.bump,
         has_one = stake_token_mint,
+        has_one = vault_reward,
+        constraint = !factory.paused @ SPError::FactoryPaused,
     )]
     pub factory: Account<'info, Factory>,
-    pub stake_token_mint: Box<Account<'info, Mint>>,
+    pub stake_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         seeds = [
             factory.to_account_info().key.as_ref(),
-            stake_pool.name.as_ref(),
+            stake_pool.pda_seed(),
         ],
         bump = stake_pool.bump,
     )]
     pub stake_pool: Account<'info, StakePool>,
-    #[account(mut)]
-    pub beneficiary: Signer<'info>,
+    /// CHECK: The owner of the `member`, checked by its `has_one`.
+    pub beneficiary: UncheckedAccount<'info>,
     #[account(
+        mut,
         seeds = [
             beneficiary.to_account_info().key.as_ref(),
             factory.to_account_info().key.as_ref(),
@@ -33,11 +39,27 @@ pub struct Stake<'info> {
         has_one = vault_free,
     )]
     pub member: Account<'info, Member>,
+    #[account(
+        mut,
+        seeds = [
+            member.to_account_info().key.as_ref(),
+            PositionIndex::PDA_SEED,
+        ],
+        bump = position_index.bump,
+        has_one = member,
+    )]
+    pub position_index: Box<Account<'info, PositionIndex>>,
+    /// The beneficiary or the delegate of the `member`, pays for the new accounts.
+    #[account(
+        mut,
+        constraint = member.is_authority(authority.key) @ SPError::NotMemberAuthority,
+    )]
+    pub authority: Signer<'info>,
     #[account(mut)]
-    pub vault_free: Box<Account<'info, TokenAccount>>,
+    pub vault_free: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init,
-        payer = beneficiary,
+        payer = authority,
         space = 8 + MemberStake::SPACE,
         seeds = [
             stake_pool.to_account_info().key.as_ref(),
@@ -48,13 +70,14 @@ pub struct Stake<'info> {
     pub member_stake: Account<'info, MemberStake>,
     #[account(
         init,
-        payer = beneficiary,
+        payer = authority,
         associated_token::mint = stake_token_mint,
         associated_token::authority = member_stake,
+        associated_token::token_program = token_program,
     )]
-    pub vault_staked: Box<Account<'info, TokenAccount>>, 
+    pub vault_staked: Box<InterfaceAccount<'info, TokenAccount>>, 
     pub clock: Sysvar<'info, Clock>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
@@ -73,13 +96,14 @@ impl<'info> Stake<'info> {
             &[self.member.bump]
         ];
 
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 token_program,
-                token::Transfer { from, to, authority },
+                token_interface::TransferChecked { from, mint: self.stake_token_mint.to_account_info(), to, authority },
                 &[&seeds[..]],
             ),
-            amount_to_stake
+            amount_to_stake,
+            self.stake_token_mint.decimals,
         )
     }
 }
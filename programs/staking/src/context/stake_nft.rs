@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use crate::math::TryMath;
+use crate::nft;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
+use anchor_spl::associated_token::AssociatedToken;
+
+#[derive(Accounts)]
+pub struct StakeNft<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = vault_reward,
+        constraint = !factory.paused @ SPError::FactoryPaused,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub vault_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump = member.bump,
+        has_one = beneficiary,
+    )]
+    pub member: Account<'info, Member>,
+    #[account(
+        mut,
+        seeds = [
+            member.to_account_info().key.as_ref(),
+            PositionIndex::PDA_SEED,
+        ],
+        bump = position_index.bump,
+        has_one = member,
+    )]
+    pub position_index: Box<Account<'info, PositionIndex>>,
+    #[account(
+        constraint = nft_mint.decimals == 0 && nft_mint.supply == 1 @ SPError::NotAnNft,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: The Metaplex metadata of the `nft_mint`, parsed by the instruction.
+    #[account(
+        address = nft::metadata_address(&nft_mint.key()),
+        owner = nft::metadata_program::ID,
+    )]
+    pub nft_metadata: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = beneficiary_nft_account.owner == beneficiary.key(),
+        constraint = beneficiary_nft_account.mint == nft_mint.key(),
+    )]
+    pub beneficiary_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = beneficiary,
+        space = 8 + NftStake::SPACE,
+        seeds = [
+            stake_pool.to_account_info().key.as_ref(),
+            nft_mint.to_account_info().key.as_ref(),
+        ],
+        bump,
+    )]
+    pub nft_stake: Account<'info, NftStake>,
+    #[account(
+        init,
+        payer = beneficiary,
+        associated_token::mint = nft_mint,
+        associated_token::authority = nft_stake,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_nft: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub clock: Sysvar<'info, Clock>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> StakeNft<'info> {
+    /// The collection the `nft_mint` is a verified item of according to its metadata.
+    pub fn verified_collection(&self) -> Result<Option<Pubkey>> {
+        let data = self.nft_metadata.try_borrow_data()?;
+        Ok(nft::verified_collection(&data, &self.nft_mint.key()))
+    }
+
+    /// The weight the member has staked in the pool with its `NftStake` positions, given as the `positions`
+    /// in the order of the member's position index, so the stake caps count the NFTs staked already.
+    pub fn member_staked_weight(&self, positions: &'info [AccountInfo<'info>]) -> Result<u64> {
+        let stake_pool = self.stake_pool.key();
+        let indexed: Vec<&IndexedPosition> = self.position_index.positions.iter()
+            .filter(|position| position.kind == PositionKind::Nft && position.stake_pool == stake_pool)
+            .collect();
+        require!(indexed.len() == positions.len(), SPError::PositionIndexWrong);
+
+        let mut staked_weight: u64 = 0;
+        for (position, position_info) in indexed.into_iter().zip(positions) {
+            require!(position.account == position_info.key(), SPError::PositionIndexWrong);
+            let nft_stake = Account::<NftStake>::try_from(position_info)?;
+            staked_weight = staked_weight.try_add(nft_stake.staked_weight())?;
+        }
+
+        Ok(staked_weight)
+    }
+
+    pub fn transfer_nft_to_vault(&self) -> Result<()> {
+        token_interface::transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.beneficiary_nft_account.to_account_info(),
+                    mint: self.nft_mint.to_account_info(),
+                    to: self.vault_nft.to_account_info(),
+                    authority: self.beneficiary.to_account_info(),
+                },
+            ),
+            1,
+            self.nft_mint.decimals,
+        )
+    }
+}
@@ -1,23 +1,24 @@
 use anchor_lang::prelude::*;
 use crate::account::*;
-use anchor_spl::token::{self, TokenAccount, Token, Mint};
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
 use anchor_spl::associated_token::AssociatedToken;
 
 
 #[derive(Accounts)]
 pub struct StartUnstakeAll<'info> {
     #[account(
+        mut,
         seeds = [Factory::PDA_SEED], 
         bump = factory.bump,
         has_one = stake_token_mint,
     )]
     pub factory: Account<'info, Factory>,
-    pub stake_token_mint: Box<Account<'info, Mint>>,
+    pub stake_token_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         seeds = [
             factory.to_account_info().key.as_ref(),
-            stake_pool.name.as_ref(),
+            stake_pool.pda_seed(),
         ],
         bump = stake_pool.bump,
     )]
@@ -25,6 +26,7 @@ pub struct StartUnstakeAll<'info> {
     #[account(mut)]
     pub beneficiary: Signer<'info>,
     #[account(
+        mut,
         seeds = [
             beneficiary.to_account_info().key.as_ref(),
             factory.to_account_info().key.as_ref(),
@@ -32,6 +34,16 @@ pub struct StartUnstakeAll<'info> {
         bump = member.bump,
     )]
     pub member: Account<'info, Member>,
+    #[account(
+        mut,
+        seeds = [
+            member.to_account_info().key.as_ref(),
+            PositionIndex::PDA_SEED,
+        ],
+        bump = position_index.bump,
+        has_one = member,
+    )]
+    pub position_index: Box<Account<'info, PositionIndex>>,
     #[account(
         mut,
         seeds = [
@@ -43,7 +55,7 @@ pub struct StartUnstakeAll<'info> {
     )]
     pub member_stake: Account<'info, MemberStake>,
     #[account(mut)]
-    pub vault_staked: Box<Account<'info, TokenAccount>>,
+    pub vault_staked: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init,
         payer = beneficiary,
@@ -60,10 +72,11 @@ pub struct StartUnstakeAll<'info> {
         payer = beneficiary,
         associated_token::mint = stake_token_mint,
         associated_token::authority = stake_pool,
+        associated_token::token_program = token_program,
     )]
-    pub vault_pending_unstake: Box<Account<'info, TokenAccount>>,
+    pub vault_pending_unstake: Box<InterfaceAccount<'info, TokenAccount>>,
     pub clock: Sysvar<'info, Clock>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
@@ -77,17 +90,19 @@ impl<'info> StartUnstakeAll<'info> {
             &[self.member_stake.bump]
         ];
 
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
-                token::Transfer {
+                token_interface::TransferChecked {
                     from: self.vault_staked.to_account_info(),
+                    mint: self.stake_token_mint.to_account_info(),
                     to: self.vault_pending_unstake.to_account_info(),
                     authority: self.member_stake.to_account_info(),
                 },
                 &[&seeds[..]],
             ),
-            amount
+            amount,
+            self.stake_token_mint.decimals,
         )
     }
 }
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+
+#[derive(Accounts)]
+pub struct StartUnstakeNft<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed(),
+        ],
+        bump = stake_pool.bump,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    pub beneficiary: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            stake_pool.to_account_info().key.as_ref(),
+            nft_stake.mint.as_ref(),
+        ],
+        bump = nft_stake.bump,
+        has_one = stake_pool,
+        has_one = beneficiary,
+        constraint = nft_stake.unstaked_at.is_none() @ SPError::NftUnstakeNotStarted,
+    )]
+    pub nft_stake: Account<'info, NftStake>,
+    pub clock: Sysvar<'info, Clock>,
+}
@@ -0,0 +1,160 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::transfer_fee;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, CloseAccount, Mint};
+
+#[derive(Accounts)]
+pub struct UnstakeAndWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = stake_token_mint,
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(mut)]
+    pub stake_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            factory.to_account_info().key.as_ref(),
+            stake_pool.pda_seed()
+        ],
+        bump = stake_pool.bump,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump = member.bump,
+    )]
+    pub member: Account<'info, Member>,
+    #[account(
+        mut,
+        seeds = [
+            member.to_account_info().key.as_ref(),
+            PositionIndex::PDA_SEED,
+        ],
+        bump = position_index.bump,
+        has_one = member,
+    )]
+    pub position_index: Box<Account<'info, PositionIndex>>,
+    #[account(
+        mut,
+        constraint = beneficiary_token_account.owner == beneficiary.key(),
+        constraint = beneficiary_token_account.mint == factory.stake_token_mint,
+    )]
+    pub beneficiary_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            stake_pool.to_account_info().key.as_ref(),
+            member.to_account_info().key.as_ref(),
+        ],
+        bump,
+        has_one = vault_staked,
+        close = beneficiary,
+    )]
+    pub member_stake: Account<'info, MemberStake>,
+    #[account(mut)]
+    pub vault_staked: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            stake_pool.to_account_info().key.as_ref(),
+            member_stake.to_account_info().key.as_ref(),
+        ],
+        bump = member_pending_unstake.bump,
+        has_one = vault_pending_unstake,
+        close = beneficiary,
+    )]
+    pub member_pending_unstake: Account<'info, MemberPendingUnstake>,
+    #[account(
+        mut,
+        constraint = vault_pending_unstake.owner == stake_pool.key(),
+        constraint = vault_pending_unstake.mint == factory.stake_token_mint,
+    )]
+    pub vault_pending_unstake: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub clock: Sysvar<'info, Clock>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> UnstakeAndWithdraw<'info> {
+    /// Moves the transfer fees withheld in the vaults to the mint, so the vaults could be closed.
+    pub fn harvest_withheld_fees(&self) -> Result<()> {
+        transfer_fee::harvest_withheld_fees(
+            &self.token_program.to_account_info(),
+            &self.stake_token_mint.to_account_info(),
+            &[self.vault_pending_unstake.to_account_info(), self.vault_staked.to_account_info()],
+        )
+    }
+
+    pub fn transfer_pu_tokens_to_beneficiary(&self, amount: u64) -> Result<()> {
+        let seeds: &[&[u8]] = &[
+            self.factory.to_account_info().key.as_ref(),
+            self.stake_pool.pda_seed(),
+            &[self.stake_pool.bump]
+        ];
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.vault_pending_unstake.to_account_info(),
+                    mint: self.stake_token_mint.to_account_info(),
+                    to: self.beneficiary_token_account.to_account_info(),
+                    authority: self.stake_pool.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+            self.stake_token_mint.decimals,
+        )
+    }
+
+    pub fn close_pending_unstake_vault(&self) -> Result<()> {
+        let seeds: &[&[u8]] = &[
+            self.factory.to_account_info().key.as_ref(),
+            self.stake_pool.pda_seed(),
+            &[self.stake_pool.bump]
+        ];
+
+        token_interface::close_account(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                CloseAccount {
+                    account: self.vault_pending_unstake.to_account_info(),
+                    destination: self.beneficiary.to_account_info(),
+                    authority: self.stake_pool.to_account_info(),
+                },
+                &[seeds]
+            ),
+        )
+    }
+
+    pub fn close_stake_vault(&self) -> Result<()> {
+        let seeds: &[&[u8]] = &[
+            self.stake_pool.to_account_info().key.as_ref(),
+            self.member.to_account_info().key.as_ref(),
+            &[self.member_stake.bump]
+        ];
+
+        token_interface::close_account(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                CloseAccount {
+                    account: self.vault_staked.to_account_info(),
+                    destination: self.beneficiary.to_account_info(),
+                    authority: self.member_stake.to_account_info(),
+                },
+                &[seeds]
+            ),
+        )
+    }
+}
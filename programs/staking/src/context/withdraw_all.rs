@@ -1,14 +1,16 @@
 use anchor_lang::prelude::*;
 use crate::account::*;
-use anchor_spl::token::{self, TokenAccount, Token};
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
 
 #[derive(Accounts)]
 pub struct WithdrawAll<'info> {
     #[account(
         seeds = [Factory::PDA_SEED],
         bump = factory.bump,
+        has_one = stake_token_mint,
     )]
     pub factory: Account<'info, Factory>,
+    pub stake_token_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         seeds = [
             beneficiary.to_account_info().key.as_ref(),
@@ -19,13 +21,13 @@ pub struct WithdrawAll<'info> {
     )]
     pub member: Account<'info, Member>,
     #[account(mut)]
-    pub vault_free: Box<Account<'info, TokenAccount>>,
+    pub vault_free: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(mut)]
     pub beneficiary: Signer<'info>,
     #[account(mut)]
-    pub beneficiary_token_account: Box<Account<'info, TokenAccount>>,
+    pub beneficiary_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 impl<'info> WithdrawAll<'info> {
@@ -38,17 +40,19 @@ impl<'info> WithdrawAll<'info> {
             &[self.member.bump]
         ];
 
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
-                token::Transfer { 
+                token_interface::TransferChecked { 
                     from: self.vault_free.to_account_info(), 
+                    mint: self.stake_token_mint.to_account_info(),
                     to: self.beneficiary_token_account.to_account_info(), 
                     authority: self.member.to_account_info() 
                 },
                 &[&seeds[..]],
             ),
-            amount
+            amount,
+            self.stake_token_mint.decimals,
         )
     }
 }
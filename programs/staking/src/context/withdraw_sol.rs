@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use crate::account::*;
+use crate::error::SPError;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint, CloseAccount};
+
+#[derive(Accounts)]
+pub struct WithdrawSol<'info> {
+    #[account(
+        seeds = [Factory::PDA_SEED],
+        bump = factory.bump,
+        has_one = stake_token_mint,
+        constraint = factory.stake_token_mint == spl_token::native_mint::ID @ SPError::StakeMintNotNative,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub stake_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [
+            beneficiary.to_account_info().key.as_ref(),
+            factory.to_account_info().key.as_ref(),
+        ],
+        bump = member.bump,
+        has_one = vault_free,
+    )]
+    pub member: Account<'info, Member>,
+    #[account(mut)]
+    pub vault_free: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// The transit account closed within the instruction to unwrap the tokens,
+    /// the `vault_free` itself stays open.
+    #[account(
+        init,
+        payer = beneficiary,
+        seeds = [
+            Member::UNWRAP_SEED,
+            member.to_account_info().key.as_ref(),
+        ],
+        bump,
+        token::mint = stake_token_mint,
+        token::authority = member,
+        token::token_program = token_program,
+    )]
+    pub vault_unwrap: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> WithdrawSol<'info> {
+    /// Moves all the `vault_free` tokens to the `vault_unwrap` and closes it,
+    /// paying the lamports and the rent back to the beneficiary.
+    pub fn unwrap_free_vault_to_beneficiary(&self) -> Result<()> {
+        let seeds = &[
+            self.beneficiary.to_account_info().key.as_ref(),
+            self.factory.to_account_info().key.as_ref(),
+            &[self.member.bump]
+        ];
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: self.vault_free.to_account_info(),
+                    mint: self.stake_token_mint.to_account_info(),
+                    to: self.vault_unwrap.to_account_info(),
+                    authority: self.member.to_account_info()
+                },
+                &[&seeds[..]],
+            ),
+            self.vault_free.amount,
+            self.stake_token_mint.decimals,
+        )?;
+
+        token_interface::close_account(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                CloseAccount {
+                    account: self.vault_unwrap.to_account_info(),
+                    destination: self.beneficiary.to_account_info(),
+                    authority: self.member.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+        )
+    }
+}
@@ -2,7 +2,7 @@ use anchor_lang::prelude::*;
 
 #[error_code]
 pub enum SPError {
-    #[msg("A new instance of the stake pool can be created only by the owner of the program")]
+    #[msg("A new instance of the stake pool can be created only by the admin or the pool creator")]
     NewPoolOwnerMismatch,
     #[msg("The reward type is unknown. Please check the RewardType.")]
     RewardTypeMismatch,
@@ -26,12 +26,106 @@ pub enum SPError {
     NoStakedTokens,
     #[msg("Unstake delay hasn't passed yet")]
     NotAllowedFinishUnstakeYet,
-    #[msg("Owner interest should be between 1% and 99%")]
-    OwnerInterestWrong,
+    #[msg("Owner fee should be between 0 and 10000 basis points")]
+    OwnerFeeWrong,
     #[msg("Before staking you should add enough tokens to the free_vault")]
     NotEnoughFreeVaultAmount,
     #[msg("Stake pool name to short or too long")]
     StakePoolNameWrong,
     #[msg("Not enough reward tokens in the provided owner reward vault")]
     NotEnoughOwnerRewardVaultAmount,
+    #[msg("Arithmetic operation overflowed")]
+    MathOverflow,
+    #[msg("Arithmetic operation underflowed")]
+    MathUnderflow,
+    #[msg("Division by zero")]
+    DivisionByZero,
+    #[msg("Only the factory owner or the fee recipient can harvest the fees")]
+    HarvestFeesAuthorityMismatch,
+    #[msg("The audited position doesn't belong to the stake pool or is given twice")]
+    AuditPositionInvalid,
+    #[msg("The staked tokens of the stake pool don't match the balances of the positions")]
+    AuditStakedTokensMismatch,
+    #[msg("The rewards available to the pool don't cover the rewards accrued by the positions")]
+    AuditRewardVaultUnderfunded,
+    #[msg("The beneficiary isn't in the stake pool's allowlist or the proof is wrong")]
+    BeneficiaryNotAllowed,
+    #[msg("The minimum stake amount exceeds the maximum")]
+    StakeLimitsWrong,
+    #[msg("The amount is less than the minimum stake amount of the pool")]
+    StakeAmountTooSmall,
+    #[msg("The stake exceeds the maximum tokens a member can stake in the pool")]
+    MemberStakeLimitExceeded,
+    #[msg("The stake exceeds the remaining capacity of the pool")]
+    StakePoolCapacityExceeded,
+    #[msg("The reward vault doesn't cover the rewards committed by the fixed-rate pools")]
+    RewardBudgetExceeded,
+    #[msg("Partial payments are only supported by the fixed-rate pools")]
+    PartialPaymentsNotSupported,
+    #[msg("Only the beneficiary or the delegate of the member can do it")]
+    NotMemberAuthority,
+    #[msg("Compounding requires the reward and the stake tokens to be the same")]
+    CompoundNotSupported,
+    #[msg("The rewards can only be claimed to the beneficiary's accounts or the member's reward destination")]
+    RewardDestinationNotAllowed,
+    #[msg("The claimed position doesn't belong to the member or is given twice")]
+    ClaimPositionInvalid,
+    #[msg("The member hasn't opted in to the auto-claims")]
+    AutoClaimDisabled,
+    #[msg("The crank tip exceeds the owner's fee")]
+    CrankTipWrong,
+    #[msg("The stake pool doesn't accept NFTs")]
+    NotAnNftPool,
+    #[msg("The stake pool accepts only the NFTs of its collection")]
+    NftPoolRequiresNfts,
+    #[msg("The mint isn't an NFT")]
+    NotAnNft,
+    #[msg("The NFT isn't a verified item of the stake pool's collection")]
+    NftNotInCollection,
+    #[msg("The weight of the NFT isn't in the stake pool's weights or the proof is wrong")]
+    NftWeightNotProven,
+    #[msg("The base weight of the NFTs should be greater than 0")]
+    NftConfigWrong,
+    #[msg("The stake pool has staked tokens")]
+    StakePoolNotEmpty,
+    #[msg("The unstaking of the NFT isn't started or is started already")]
+    NftUnstakeNotStarted,
+    #[msg("The stake tokens of the factory aren't the wrapped SOL")]
+    StakeMintNotNative,
+    #[msg("The member has open stakes or pending unstakes")]
+    MemberHasOpenPositions,
+    #[msg("The account has the current layout already")]
+    AccountAlreadyMigrated,
+    #[msg("The URIs or the tags of the stake pool metadata are too long or too many")]
+    StakePoolMetadataWrong,
+    #[msg("The stake pool is in the registry already")]
+    StakePoolRegisteredAlready,
+    #[msg("The stake pool can only be closed once ended with nothing staked")]
+    StakePoolNotClosable,
+    #[msg("The positions don't match the member's open positions")]
+    PositionIndexWrong,
+    #[msg("The signer doesn't hold the factory role the instruction requires")]
+    RoleRequired,
+    #[msg("Only the admin or the pool's manager may change the pool")]
+    NotPoolManager,
+    #[msg("The stakes and the claims are paused")]
+    FactoryPaused,
+    #[msg("The eta of the action is sooner than the timelock delay")]
+    TimelockEtaWrong,
+    #[msg("The eta of the queued action hasn't come yet")]
+    TimelockNotElapsed,
+    #[msg("The timelock delay is shorter than the minimum or longer than the maximum")]
+    TimelockDelayWrong,
+    #[msg("The reward of a pool only changes its rate, and a Fixed pool's only with nothing staked")]
+    RewardChangeWrong,
+    #[msg("The stake pool of the action is missing or different")]
+    ActionStakePoolWrong,
+    #[msg("The rewards committed by the pools exceed the rewards available to them")]
+    AuditRewardsOvercommitted,
+    #[msg("The account given with the migrated account doesn't belong to it")]
+    MigrationAccountWrong,
+    #[msg("Only the stake pools with a manager are funded on their own")]
+    StakePoolNotManaged,
+    #[msg("The positions settled before the action don't cover the stake of the pool")]
+    SettlementPositionsWrong,
 }
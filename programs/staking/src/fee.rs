@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+use crate::error::SPError;
+use crate::math::*;
+
+/// The share of each claimed reward that goes to the stake pool owner.
+/// The fee is accrued in the pool's `vault_fee` and harvested by the owner in bulk.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct OwnerFee {
+    /// The fee in basis points of the claimed reward.
+    /// E.g. reward: 1000 tokens, `bps`: 250 (2.5 %).
+    /// Fee: 25 tokens, the member receives 975 tokens.
+    /// Could be zero to disable the fee.
+    pub bps: u16,
+    /// The maximum amount of reward tokens the owner receives from a single claim.
+    /// `None` means the fee is not capped.
+    pub max_per_claim: Option<u64>,
+}
+
+impl OwnerFee {
+    pub const SPACE: usize = 2 + (1 + 8);
+    /// 100 %
+    pub const MAX_BPS: u16 = 10_000;
+
+    pub fn new(bps: u16, max_per_claim: Option<u64>) -> Result<OwnerFee> {
+        let fee = OwnerFee { bps, max_per_claim };
+        fee.validate()?;
+        Ok(fee)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        require!(self.bps <= Self::MAX_BPS, SPError::OwnerFeeWrong);
+        Ok(())
+    }
+
+    /// Splits the `reward` into the owner's fee and the member's net reward.
+    /// Returns `(fee, net_reward)`. The fee is rounded down and never exceeds the reward.
+    pub fn split(&self, reward: u64) -> Result<(u64, u64)> {
+        self.validate()?;
+
+        let mut fee = to_u64(mul_div(reward as u128, self.bps as u128, Self::MAX_BPS as u128)?)?;
+        if let Some(max_fee) = self.max_per_claim {
+            fee = fee.min(max_fee);
+        }
+
+        Ok((fee, reward.try_sub(fee)?))
+    }
+}
+
+/// The part of the owner's `fee` paid to the cranker of an auto-claim.
+/// Returns `(tip, fee)`, the rest of the fee goes to the owner as usual.
+pub fn split_crank_tip(fee: u64, tip_bps: u16) -> Result<(u64, u64)> {
+    require!(tip_bps <= OwnerFee::MAX_BPS, SPError::CrankTipWrong);
+
+    let tip = to_u64(mul_div(fee as u128, tip_bps as u128, OwnerFee::MAX_BPS as u128)?)?;
+    Ok((tip, fee.try_sub(tip)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn fee_in_basis_points() {
+        let (fee, net) = OwnerFee::new(250, None).unwrap().split(1000).unwrap();
+        assert_eq!(fee, 25);
+        assert_eq!(net, 975);
+    }
+
+    #[test]
+    fn zero_fee() {
+        let (fee, net) = OwnerFee::new(0, None).unwrap().split(1000).unwrap();
+        assert_eq!(fee, 0);
+        assert_eq!(net, 1000);
+    }
+
+    #[test]
+    fn small_reward_does_not_underflow() {
+        let (fee, net) = OwnerFee::new(100, None).unwrap().split(1).unwrap();
+        assert_eq!(fee, 0);
+        assert_eq!(net, 1);
+    }
+
+    #[test]
+    fn crank_tip_is_a_share_of_the_fee() {
+        assert_eq!(split_crank_tip(100, 1000).unwrap(), (10, 90));
+        assert_eq!(split_crank_tip(9, 1000).unwrap(), (0, 9));
+        assert_eq!(split_crank_tip(100, OwnerFee::MAX_BPS).unwrap(), (100, 0));
+        assert!(split_crank_tip(100, OwnerFee::MAX_BPS + 1).is_err());
+    }
+
+    #[test]
+    fn fee_is_capped() {
+        let (fee, net) = OwnerFee::new(5000, Some(10)).unwrap().split(1000).unwrap();
+        assert_eq!(fee, 10);
+        assert_eq!(net, 990);
+    }
+
+    #[test]
+    fn fee_above_100_percent_is_rejected() {
+        assert!(OwnerFee::new(OwnerFee::MAX_BPS + 1, None).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn split_preserves_reward(
+            bps in 0..=OwnerFee::MAX_BPS,
+            max_per_claim in any::<Option<u64>>(),
+            reward in any::<u64>(),
+        ) {
+            let (fee, net) = OwnerFee { bps, max_per_claim }.split(reward).unwrap();
+            prop_assert_eq!(fee + net, reward);
+            prop_assert!(fee <= max_per_claim.unwrap_or(u64::MAX));
+        }
+    }
+}
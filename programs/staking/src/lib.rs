@@ -1,9 +1,24 @@
+#![allow(clippy::result_large_err, clippy::too_many_arguments)]
+
 use anchor_lang::prelude::*;
-mod reward; use reward::*;
-mod account; use account::*;
+use anchor_lang::solana_program::program::set_return_data;
+use anchor_spl::token_interface::TokenAccount;
+pub mod math; use math::TryMath;
+pub mod reward; use reward::*;
+pub mod fee; use fee::*;
+pub mod limits; use limits::*;
+pub mod account; use account::*;
+pub mod audit;
+pub mod merkle; use merkle::MerkleNode;
+pub mod nft; use nft::NftConfig;
+pub mod solvency;
+mod space;
+pub mod migration; use migration::v0;
+pub mod timelock; use timelock::*;
+mod transfer_fee;
 mod context; use context::*;
 mod access_control; use access_control::*;
-mod error; use error::SPError;
+pub mod error; use error::SPError;
 
 declare_id!("8z5zCNBPzLuG1ZwkXfVVRCfpK5txF1UdHLfJjYFfmvQy");
 
@@ -11,44 +26,68 @@ declare_id!("8z5zCNBPzLuG1ZwkXfVVRCfpK5txF1UdHLfJjYFfmvQy");
 pub mod staking {
     use super::*;
 
-    /// Create the stake factory.
+    /// Create the stake factory. The `owner` is its admin and holds all the roles until the `SetRoles` queued action assigns them.
     pub fn initialize(ctx: Context<Initialize>, owner: Pubkey) -> Result<()> {
         let factory = &mut ctx.accounts.factory;
-        factory.bump = *ctx.bumps.get(Factory::PDA_KEY).unwrap();
+        factory.bump = ctx.bumps.factory;
         factory.owner = owner;
         factory.reward_token_mint = ctx.accounts.reward_token_mint.key();
         factory.stake_token_mint = ctx.accounts.stake_token_mint.key();
         factory.vault_reward = ctx.accounts.vault_reward.key();
+        factory.committed_rewards = 0;
+        factory.timelock_delay = MIN_TIMELOCK_DELAY;
+        factory.version = ACCOUNT_VERSION;
+
+        let roles = &mut ctx.accounts.roles;
+        roles.pool_creator = owner;
+        roles.reward_funder = owner;
+        roles.pauser = owner;
+        roles.bump = ctx.bumps.roles;
+        roles.version = ACCOUNT_VERSION;
 
         Ok(())
     }
 
-    /// Create a new stake pool instance
+    /// Create a new stake pool instance.
+    /// The address of the pool is derived from the hash of the trimmed `name`, so the name may be up to 32 bytes long.
     pub fn new_stake_pool(
         ctx: Context<NewStakePool>,
         name: String,
         reward: Reward,
         ends_at: u64,
-        min_owner_reward: u32,
-        owner_interest_percent: u8,
+        owner_fee: OwnerFee,
+        fee_recipient: Pubkey,
         unstake_delay: u64,
         reward_period: u64,
+        allowlist_root: Option<MerkleNode>,
+        limits: StakeLimits,
     ) -> Result<()> {
         let name = name.trim();
-        require!(owner_interest_percent > 0 && owner_interest_percent < 100, SPError::OwnerInterestWrong);
-        require!(name.len() > 0 && name.len() <= 10, SPError::StakePoolNameWrong);
+        require!(!name.is_empty() && name.len() <= StakePool::NAME_MAX_LEN, SPError::StakePoolNameWrong);
+        owner_fee.validate()?;
+        limits.validate()?;
+        let seed = StakePool::seed_for(name);
 
         let stake_pool = &mut ctx.accounts.stake_pool;
         stake_pool.name = name.to_owned();
+        stake_pool.seed = seed;
         stake_pool.started_at = ctx.accounts.clock.unix_timestamp as u64;
         stake_pool.ends_at = ends_at;
         stake_pool.total_staked_tokens = 0;
-        stake_pool.min_owner_reward = min_owner_reward;
         stake_pool.reward_type = reward;
-        stake_pool.bump = *ctx.bumps.get("stake_pool").expect("bump for StakePool exists");
-        stake_pool.owner_interest_percent = owner_interest_percent;
+        stake_pool.bump = ctx.bumps.stake_pool;
+        stake_pool.owner_fee = owner_fee;
+        stake_pool.fee_recipient = fee_recipient;
+        stake_pool.vault_fee = ctx.accounts.vault_fee.key();
         stake_pool.unstake_delay = unstake_delay;
         stake_pool.reward_period = reward_period;
+        stake_pool.allowlist_root = allowlist_root;
+        stake_pool.limits = limits;
+        stake_pool.version = ACCOUNT_VERSION;
+
+        let stake_pool_key = stake_pool.key();
+        let bump = ctx.bumps.pool_entry;
+        ctx.accounts.pool_entry.register(&mut ctx.accounts.factory, stake_pool, stake_pool_key, bump)?;
 
         Ok(())
     }
@@ -58,7 +97,40 @@ pub mod staking {
         let member = &mut ctx.accounts.member;
         member.beneficiary = *ctx.accounts.beneficiary.key;
         member.vault_free = ctx.accounts.vault_free.key();
-        member.bump = *ctx.bumps.get("member").expect("bump for Member exists");
+        member.bump = ctx.bumps.member;
+        member.delegate = None;
+        member.reward_destination = None;
+        member.auto_claim = false;
+        member.open_positions = 0;
+        member.version = ACCOUNT_VERSION;
+        member.pending_unstakes = 0;
+
+        let member_key = member.key();
+        ctx.accounts.position_index.open(member_key, ctx.bumps.position_index);
+
+        Ok(())
+    }
+
+    /// Allow the `delegate` to stake, claim and compound on the member's behalf. `None` revokes it.
+    /// The delegate never controls where the tokens go: the rewards are paid to the beneficiary's accounts,
+    /// and only the beneficiary is able to unstake and withdraw.
+    pub fn set_delegate(ctx: Context<SetDelegate>, delegate: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.member.delegate = delegate;
+
+        Ok(())
+    }
+
+    /// Allow the rewards to be claimed to the `reward_destination` token account,
+    /// e.g. the ATA of a cold wallet, besides the beneficiary's own accounts. `None` removes it.
+    pub fn set_reward_destination(ctx: Context<SetRewardDestination>, reward_destination: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.member.reward_destination = reward_destination;
+
+        Ok(())
+    }
+
+    /// Opt in to or out of the auto-claims, letting anyone claim the member's rewards by `crank_claims`.
+    pub fn set_auto_claim(ctx: Context<SetAutoClaim>, auto_claim: bool) -> Result<()> {
+        ctx.accounts.member.auto_claim = auto_claim;
 
         Ok(())
     }
@@ -77,76 +149,517 @@ pub mod staking {
         ctx.accounts.transfer_user_tokens_to_program(amount_to_deposit)
     }
 
+    /// Wrap the beneficiary's lamports into the member's `vault_free`, when the stake tokens are the wrapped SOL.
+    /// Same as `deposit` with no need to wrap the SOL beforehand.
+    pub fn deposit_sol(ctx: Context<DepositSol>, lamports_to_deposit: u64) -> Result<()> {
+        require!(lamports_to_deposit <= ctx.accounts.beneficiary.lamports(), SPError::InsufficientAmountOfTokensToDeposit);
+        ctx.accounts.wrap_lamports_to_free_vault(lamports_to_deposit)
+    }
+
     /// Move tokens from the `vault free` to the `MemberStake vault`
     /// Tokens inside `MemberStake vault` allow to get rewards pro rata staked amount.
     /// Member can stake coins from one's `vault free` to any stake.
     /// Member must claim the rewards before staking more tokens to the same pool.
-    pub fn stake(ctx: Context<Stake>, tokens_to_stake_amount: u64) -> Result<()> {
+    /// A stake in a Fixed pool commits the rewards for the rest of the pool's time,
+    /// it's refused if the `vault_reward` doesn't cover them with the rewards committed already.
+    /// The `proof` of the beneficiary is required if the pool has the allowlist, ignored otherwise.
+    /// Signed by the beneficiary or the member's delegate, who pays for the new accounts.
+    pub fn stake(ctx: Context<Stake>, tokens_to_stake_amount: u64, proof: Vec<MerkleNode>) -> Result<()> {
+        require!(ctx.accounts.stake_pool.nft.is_none(), SPError::NftPoolRequiresNfts);
         require!(tokens_to_stake_amount <= ctx.accounts.vault_free.amount, SPError::NotEnoughFreeVaultAmount);
+        if let Some(root) = ctx.accounts.stake_pool.allowlist_root {
+            require!(merkle::verify(&root, ctx.accounts.beneficiary.key, &proof), SPError::BeneficiaryNotAllowed);
+        }
+        // The `member_stake` is new, so the member has nothing staked in the pool yet.
+        let stake_pool = &ctx.accounts.stake_pool;
+        stake_pool.limits.check(stake_pool.total_staked_tokens, 0, tokens_to_stake_amount)?;
 
-        let member_stake = &mut ctx.accounts.member_stake;
-        member_stake.beneficiary = ctx.accounts.beneficiary.key();
-        member_stake.vault_staked = ctx.accounts.vault_staked.key();
-        member_stake.staked_at = ctx.accounts.clock.unix_timestamp as u64;
-        member_stake.bump = *ctx.bumps.get("member_stake").expect("bump for MemberStake exists");
-        member_stake.stake_pool = ctx.accounts.stake_pool.key();
-
+        // Only the tokens the `vault_staked` actually receives are staked,
+        // so a mint charging a fee on transfers never inflates the pool.
         ctx.accounts.transfer_tokens_to_staked_vault(tokens_to_stake_amount)?;
-        ctx.accounts.stake_pool.total_staked_tokens += tokens_to_stake_amount as u128;
+        ctx.accounts.vault_staked.reload()?;
+        let tokens_staked = ctx.accounts.vault_staked.amount;
+
+        let now = ctx.accounts.clock.unix_timestamp as u64;
+        let commitment = solvency::committed_reward(&ctx.accounts.stake_pool, now, tokens_staked)?;
+        let vault_reward = ctx.accounts.vault_reward.amount;
+        solvency::commit_to_partition(&mut ctx.accounts.factory, &mut ctx.accounts.stake_pool, commitment, vault_reward)?;
+
+        ctx.accounts.member_stake.open(
+            ctx.accounts.stake_pool.key(),
+            ctx.accounts.beneficiary.key(),
+            ctx.accounts.vault_staked.key(),
+            tokens_staked,
+            now,
+            ctx.bumps.member_stake,
+        );
+        let member = &mut ctx.accounts.member;
+        member.open_positions = member.open_positions.try_add(1)?;
+        ctx.accounts.position_index.add(PositionKind::Stake, ctx.accounts.stake_pool.key(), ctx.accounts.member_stake.key());
+        space::fit_position_index(&ctx.accounts.position_index, &ctx.accounts.authority, &ctx.accounts.system_program)?;
+
+        let stake_pool = &mut ctx.accounts.stake_pool;
+        stake_pool.total_staked_tokens = stake_pool.total_staked_tokens.try_add(tokens_staked as u128)?;
+
+        Ok(())
+    }
+
+    /// Move tokens from the beneficiary's wallet straight to the `MemberStake vault`,
+    /// same as `deposit` and `stake` in one go. The `Member` is created on the first use,
+    /// its `vault_free` is left to be created when the member needs it.
+    /// Signed by the beneficiary, who pays for the new accounts.
+    pub fn deposit_and_stake(ctx: Context<DepositAndStake>, tokens_to_stake_amount: u64, proof: Vec<MerkleNode>) -> Result<()> {
+        require!(ctx.accounts.stake_pool.nft.is_none(), SPError::NftPoolRequiresNfts);
+        require!(tokens_to_stake_amount <= ctx.accounts.beneficiary_token_account.amount, SPError::InsufficientAmountOfTokensToDeposit);
+        if let Some(root) = ctx.accounts.stake_pool.allowlist_root {
+            require!(merkle::verify(&root, ctx.accounts.beneficiary.key, &proof), SPError::BeneficiaryNotAllowed);
+        }
+        let stake_pool = &ctx.accounts.stake_pool;
+        stake_pool.limits.check(stake_pool.total_staked_tokens, 0, tokens_to_stake_amount)?;
+        ctx.accounts.initialize_member(
+            ctx.bumps.member,
+            ctx.bumps.position_index,
+        )?;
+
+        ctx.accounts.transfer_user_tokens_to_staked_vault(tokens_to_stake_amount)?;
+        ctx.accounts.vault_staked.reload()?;
+        let tokens_staked = ctx.accounts.vault_staked.amount;
+
+        let now = ctx.accounts.clock.unix_timestamp as u64;
+        let commitment = solvency::committed_reward(&ctx.accounts.stake_pool, now, tokens_staked)?;
+        let vault_reward = ctx.accounts.vault_reward.amount;
+        solvency::commit_to_partition(&mut ctx.accounts.factory, &mut ctx.accounts.stake_pool, commitment, vault_reward)?;
+
+        ctx.accounts.member_stake.open(
+            ctx.accounts.stake_pool.key(),
+            ctx.accounts.beneficiary.key(),
+            ctx.accounts.vault_staked.key(),
+            tokens_staked,
+            now,
+            ctx.bumps.member_stake,
+        );
+        let member = &mut ctx.accounts.member;
+        member.open_positions = member.open_positions.try_add(1)?;
+        ctx.accounts.position_index.add(PositionKind::Stake, ctx.accounts.stake_pool.key(), ctx.accounts.member_stake.key());
+        space::fit_position_index(&ctx.accounts.position_index, &ctx.accounts.beneficiary, &ctx.accounts.system_program)?;
+
+        let stake_pool = &mut ctx.accounts.stake_pool;
+        stake_pool.total_staked_tokens = stake_pool.total_staked_tokens.try_add(tokens_staked as u128)?;
 
         Ok(())
     }
 
     /// Deposit a reward for stakers.
     /// The reward is distributed on demand pro rata staked tokens.
+    /// Signed by the admin or the reward funder.
     pub fn deposit_reward(ctx: Context<DepositReward>, reward_amount: u64) -> Result<()> {
-        require!(reward_amount <= ctx.accounts.vault_owner.amount, SPError::NotEnoughOwnerRewardVaultAmount);
+        require!(reward_amount <= ctx.accounts.vault_funder.amount, SPError::NotEnoughOwnerRewardVaultAmount);
         ctx.accounts.transfer_tokens_to_reward_vault(reward_amount)
     }
 
-    /// Claim the reward for staked tokens
+    /// Claim the reward for staked tokens together with the reward owed by the previous claims.
+    /// If the pool has the partial payments on and the `vault_reward` is short of the committed rewards,
+    /// the member receives the same share of the reward as everyone else and the rest is owed.
+    /// Signed by the beneficiary or the member's delegate, the reward goes to the beneficiary's account
+    /// or the member's reward destination.
     pub fn claim_reward(ctx: Context<ClaimReward>) -> Result<()> {
-        let (reward_tokens_to_transfer, reward_payed_for) = ctx.accounts.calculate_reward_tokens()?;
+        let (accrued_reward, reward_payed_for) = ctx.accounts.calculate_reward_tokens()?;
+        let reward_due = accrued_reward.try_add(ctx.accounts.member_stake.reward_owed)?;
 
-        let reward_tokens_available = ctx.accounts.vault_reward.amount;
-        require!(reward_tokens_to_transfer <= reward_tokens_available, SPError::InsufficientAmountOfTokensToClaim);
+        let reward_tokens_to_transfer = solvency::payment(
+            &ctx.accounts.stake_pool,
+            reward_due,
+            solvency::available_rewards(&ctx.accounts.factory, &ctx.accounts.stake_pool, ctx.accounts.vault_reward.amount),
+            solvency::partition_committed(&ctx.accounts.factory, &ctx.accounts.stake_pool),
+        )?;
 
         ctx.accounts.transfer_reward_tokens(reward_tokens_to_transfer)?;
-        ctx.accounts.member_stake.reward_payed_for = reward_payed_for;
+        let member_stake = &mut ctx.accounts.member_stake;
+        member_stake.reward_payed_for = reward_payed_for;
+        member_stake.reward_owed = reward_due.try_sub(reward_tokens_to_transfer)?;
+
+        let factory = &mut ctx.accounts.factory;
+        solvency::release_from_partition(factory, &mut ctx.accounts.stake_pool, reward_tokens_to_transfer as u128);
+        solvency::spend_funded(factory, &mut ctx.accounts.stake_pool, reward_tokens_to_transfer)?;
+
+        Ok(())
+    }
+
+    /// Claim the rewards of the member's positions given as the remaining accounts
+    /// and transfer them to the beneficiary's account or the member's reward destination at once.
+    /// Each position is `[stake_pool, member_stake, vault_fee]`, all writable.
+    /// The positions are paid as by `claim_reward`, those without a reward to claim yet are skipped.
+    /// Signed by the beneficiary or the member's delegate.
+    pub fn claim_all<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimAll<'info>>) -> Result<()> {
+        let reward_tokens_for_user = ctx.accounts.claim_positions(ctx.remaining_accounts)?;
+        ctx.accounts.transfer_reward_tokens_to_user(reward_tokens_for_user)
+    }
+
+    /// Claim the rewards of a batch of positions whose members have opted in to the auto-claims. Permissionless.
+    /// Each position is `[stake_pool, member, member_stake, vault_fee, reward_vault]`
+    /// of the remaining accounts, all writable but the member, where the `reward_vault` is the member's reward destination
+    /// or the beneficiary's account. The positions are paid as by `claim_reward`,
+    /// those without a reward to claim yet are skipped.
+    /// The cranker receives the pool's crank tip out of the owner's fee of each claim.
+    pub fn crank_claims<'info>(ctx: Context<'_, '_, 'info, 'info, CrankClaims<'info>>) -> Result<()> {
+        let reward_tokens_for_cranker = ctx.accounts.crank_positions(ctx.remaining_accounts)?;
+        ctx.accounts.transfer_tips_to_cranker(reward_tokens_for_cranker)
+    }
+
+    /// Claim the reward and stake it in the same position, when the reward and the stake tokens are the same.
+    /// The owner's fee is taken as on claim. The stake caps of the pool apply to the compounded reward,
+    /// and in a Fixed pool it commits the rewards for the rest of the pool's time.
+    /// Signed by the beneficiary or the member's delegate.
+    pub fn compound_reward(ctx: Context<CompoundReward>) -> Result<()> {
+        require!(ctx.accounts.member_stake.staked_tokens > 0, SPError::NoStakedTokens);
+        let (accrued_reward, reward_payed_for) = ctx.accounts.calculate_reward_tokens()?;
+        let reward_due = accrued_reward.try_add(ctx.accounts.member_stake.reward_owed)?;
+
+        let stake_pool = &ctx.accounts.stake_pool;
+        let vault_reward = ctx.accounts.vault_reward.amount;
+        let available = solvency::available_rewards(&ctx.accounts.factory, stake_pool, vault_reward);
+        let committed = solvency::partition_committed(&ctx.accounts.factory, stake_pool);
+        let reward_tokens_to_transfer = solvency::payment(stake_pool, reward_due, available, committed)?;
+        let (reward_tokens_for_owner, reward_tokens_to_stake) = stake_pool.owner_fee.split(reward_tokens_to_transfer)?;
+        stake_pool.limits.check_caps(stake_pool.total_staked_tokens, ctx.accounts.member_stake.staked_tokens, reward_tokens_to_stake)?;
+
+        let staked_before = ctx.accounts.vault_staked.amount;
+        ctx.accounts.transfer_reward_tokens(reward_tokens_to_stake, reward_tokens_for_owner)?;
+        ctx.accounts.vault_staked.reload()?;
+        let reward_tokens_staked = ctx.accounts.vault_staked.amount.try_sub(staked_before)?;
+
+        let commitment = solvency::committed_reward(&ctx.accounts.stake_pool, reward_payed_for, reward_tokens_staked)?;
+        let (factory, stake_pool) = (&mut ctx.accounts.factory, &mut ctx.accounts.stake_pool);
+        solvency::release_from_partition(factory, stake_pool, reward_tokens_to_transfer as u128);
+        solvency::spend_funded(factory, stake_pool, reward_tokens_to_transfer)?;
+        solvency::commit_to_partition(factory, stake_pool, commitment, vault_reward.try_sub(reward_tokens_to_transfer)?)?;
+
+        let member_stake = &mut ctx.accounts.member_stake;
+        member_stake.reward_payed_for = reward_payed_for;
+        member_stake.reward_owed = reward_due.try_sub(reward_tokens_to_transfer)?;
+        member_stake.staked_tokens = member_stake.staked_tokens.try_add(reward_tokens_staked)?;
+
+        let stake_pool = &mut ctx.accounts.stake_pool;
+        stake_pool.total_staked_tokens = stake_pool.total_staked_tokens.try_add(reward_tokens_staked as u128)?;
+
+        Ok(())
+    }
+
+    /// Transfer the owner's fees accrued in the stake pool's `vault_fee`
+    /// to the fee recipient of the pool in one go.
+    pub fn harvest_fees(ctx: Context<HarvestFees>) -> Result<()> {
+        ctx.accounts.transfer_fees_to_recipient()
+    }
+
+    /// Replace the allowlist of the stake pool. `None` opens the pool to everyone.
+    /// Doesn't affect the stakes made already.
+    pub fn set_allowlist_root(ctx: Context<SetAllowlistRoot>, allowlist_root: Option<MerkleNode>) -> Result<()> {
+        ctx.accounts.stake_pool.allowlist_root = allowlist_root;
+
+        Ok(())
+    }
+
+    /// Make the stake pool accept the NFTs of a verified collection instead of the stake tokens.
+    /// `None` turns it back into a pool of the stake tokens. Only while nothing is staked in the pool.
+    pub fn set_nft_config(ctx: Context<SetNftConfig>, nft: Option<NftConfig>) -> Result<()> {
+        if let Some(nft) = &nft {
+            nft.validate()?;
+        }
+        let stake_pool = &mut ctx.accounts.stake_pool;
+        require!(stake_pool.total_staked_tokens == 0, SPError::StakePoolNotEmpty);
+        stake_pool.nft = nft;
+
+        Ok(())
+    }
+
+    /// Add a stake pool created before the registry to it, under the next id.
+    pub fn register_stake_pool(ctx: Context<RegisterStakePool>) -> Result<()> {
+        let stake_pool_key = ctx.accounts.stake_pool.key();
+        let bump = ctx.bumps.pool_entry;
+        ctx.accounts.pool_entry.register(&mut ctx.accounts.factory, &mut ctx.accounts.stake_pool, stake_pool_key, bump)
+    }
+
+    /// Mark the ended stake pool with nothing staked closed in the registry.
+    /// The members may still claim their rewards and finish their unstakes. Signed by the admin.
+    pub fn close_stake_pool(ctx: Context<CloseStakePool>) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp as u64;
+        let stake_pool = &ctx.accounts.stake_pool;
+        let pool_entry = &mut ctx.accounts.pool_entry;
+        require!(
+            now >= stake_pool.ends_at && stake_pool.total_staked_tokens == 0 && pool_entry.closed_at.is_none(),
+            SPError::StakePoolNotClosable
+        );
+        pool_entry.closed_at = Some(now);
+
+        Ok(())
+    }
+
+    /// Describe the stake pool for the wallets and explorers, creating its metadata account on the first call.
+    pub fn set_pool_metadata(
+        ctx: Context<SetPoolMetadata>,
+        description_uri: String,
+        logo_uri: String,
+        tags: Vec<String>,
+    ) -> Result<()> {
+        StakePoolMetadata::validate(&description_uri, &logo_uri, &tags)?;
+
+        let metadata = &mut ctx.accounts.metadata;
+        metadata.stake_pool = ctx.accounts.stake_pool.key();
+        metadata.description_uri = description_uri;
+        metadata.logo_uri = logo_uri;
+        metadata.tags = tags;
+        metadata.bump = ctx.bumps.metadata;
+        metadata.version = ACCOUNT_VERSION;
+
+        Ok(())
+    }
+
+    /// Calculate the reward the member is able to claim for the `MemberStake` right now.
+    /// Doesn't change any state. The `PendingRewards` are written to the return data,
+    /// so the instruction could be simulated by a client or called via CPI.
+    pub fn get_pending_rewards(ctx: Context<GetPendingRewards>) -> Result<()> {
+        let pending = PendingRewards::calculate(
+            &ctx.accounts.stake_pool,
+            &*ctx.accounts.member_stake,
+            ctx.accounts.member_stake.staked_tokens,
+            ctx.accounts.clock.unix_timestamp as u64,
+        )?;
+        set_return_data(&pending.try_to_vec()?);
+
+        Ok(())
+    }
+
+    /// Calculate how many tokens a new member is able to stake in the pool right now.
+    /// Doesn't change any state. The `StakeCapacity` is written to the return data.
+    pub fn get_stake_capacity(ctx: Context<GetStakeCapacity>) -> Result<()> {
+        let stake_pool = &ctx.accounts.stake_pool;
+        let capacity = StakeCapacity::new(&stake_pool.limits, stake_pool.total_staked_tokens);
+        set_return_data(&capacity.try_to_vec()?);
+
+        Ok(())
+    }
+
+    /// Check the conservation invariants of the stake pool. Permissionless and read-only.
+    /// Takes every `MemberStake` of the pool with its `vault_staked`, or every `NftStake`
+    /// with its `vault_nft` in an NFT pool, as the remaining accounts.
+    /// Fails with the first violated invariant, otherwise emits the `AuditReport`
+    /// and writes it to the return data.
+    pub fn audit_pool<'info>(ctx: Context<'_, '_, 'info, 'info, AuditPool<'info>>) -> Result<()> {
+        let report = ctx.accounts.audit(ctx.remaining_accounts)?;
+        report.verify()?;
+
+        set_return_data(&report.try_to_vec()?);
+        emit!(report);
 
         Ok(())
     }
 
     /// Move tokens from the `staked vault` to the `pending unstaking vault`.
     /// Save data to finish unstaking in the `pending unstaking` account provided by the user.
+    /// The rewards not claimed yet are forfeited and released from the committed rewards.
+    /// Any tokens sent to the `staked vault` besides the stake are unstaked with it.
     pub fn start_unstake_all(ctx: Context<StartUnstakeAll>) -> Result<()> {
-        require!(ctx.accounts.vault_staked.amount > 0, SPError::NoStakedTokens);
+        let staked_tokens = ctx.accounts.member_stake.staked_tokens;
+        require!(staked_tokens > 0, SPError::NoStakedTokens);
 
         let unstake = &mut ctx.accounts.member_pending_unstake;
-        unstake.bump = *ctx.bumps.get("member_pending_unstake").expect("bump for MemberPendingUnstake exists");
+        unstake.bump = ctx.bumps.member_pending_unstake;
         unstake.stake_pool = ctx.accounts.stake_pool.key();
         unstake.beneficiary = ctx.accounts.beneficiary.key();
         unstake.vault_pending_unstake = ctx.accounts.vault_pending_unstake.key();
         unstake.unstaked_at = ctx.accounts.clock.unix_timestamp as u64;
+        unstake.version = ACCOUNT_VERSION;
+        let member = &mut ctx.accounts.member;
+        member.pending_unstakes = member.pending_unstakes.try_add(1)?;
+        ctx.accounts.position_index.add(
+            PositionKind::PendingUnstake,
+            ctx.accounts.stake_pool.key(),
+            ctx.accounts.member_pending_unstake.key(),
+        );
+        space::fit_position_index(&ctx.accounts.position_index, &ctx.accounts.beneficiary, &ctx.accounts.system_program)?;
+
+        let vault_amount = ctx.accounts.vault_staked.amount;
+        ctx.accounts.transfer_staked_tokens_to_pu_vault(vault_amount)?;
 
-        let stake_amount = ctx.accounts.vault_staked.amount;
-        ctx.accounts.transfer_staked_tokens_to_pu_vault(stake_amount)?;
+        let stake_pool = &mut ctx.accounts.stake_pool;
+        stake_pool.total_staked_tokens = stake_pool.total_staked_tokens.try_sub(staked_tokens as u128)?;
 
-        ctx.accounts.stake_pool.total_staked_tokens -= stake_amount as u128;
+        let member_stake = &mut ctx.accounts.member_stake;
+        let checkpoint = member_stake.reward_checkpoint();
+        member_stake.staked_tokens = 0;
+        let released = solvency::committed_reward(&ctx.accounts.stake_pool, checkpoint, staked_tokens)?;
+        let factory = &mut ctx.accounts.factory;
+        solvency::release_from_partition(factory, &mut ctx.accounts.stake_pool, released);
 
         Ok(())
     }
 
     /// Moves tokens from `pending unstaking vault` to `free vault`.
-    /// Destroys Stake and Unstake accounts and vaults
+    /// Destroys Stake and Unstake accounts and vaults.
+    /// The reward still owed to the member is forfeited, so it has to be claimed before.
     #[access_control(allow_finish_unstake(&ctx))]
     pub fn finish_unstake_all(ctx: Context<FinishUnstakeAll>) -> Result<()> {
+        let forfeited = ctx.accounts.member_stake.reward_owed as u128;
+        let factory = &mut ctx.accounts.factory;
+        solvency::release_from_partition(factory, &mut ctx.accounts.stake_pool, forfeited);
+
+        let member = &mut ctx.accounts.member;
+        member.open_positions = member.open_positions.try_sub(1)?;
+        member.pending_unstakes = member.pending_unstakes.try_sub(1)?;
+        let position_index = &mut ctx.accounts.position_index;
+        position_index.remove(&ctx.accounts.member_stake.key());
+        position_index.remove(&ctx.accounts.member_pending_unstake.key());
+
         let unstake_amount = ctx.accounts.vault_pending_unstake.amount;
         ctx.accounts.transfer_pu_tokens_to_free_vault(unstake_amount)?;
+        ctx.accounts.harvest_withheld_fees()?;
+        ctx.accounts.close_pending_unstake_vault()?;
+        ctx.accounts.close_stake_vault()
+    }
+
+    /// Move an NFT of the pool's collection from the beneficiary's wallet to the escrow `vault_nft`.
+    /// The NFT counts for its `weight` of stake tokens in the pool, proven by the `proof`
+    /// if the pool has the weights, otherwise it's the base weight and the arguments are ignored.
+    /// The `allowlist_proof` of the beneficiary is required if the pool has the allowlist, ignored otherwise.
+    /// The stake caps and the committed rewards apply as to the stake tokens, the member's stake in the pool
+    /// is the weight of its other `NftStake` positions of the pool, the remaining accounts in the order of its position index.
+    pub fn stake_nft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, StakeNft<'info>>,
+        weight: u64,
+        proof: Vec<MerkleNode>,
+        allowlist_proof: Vec<MerkleNode>,
+    ) -> Result<()> {
+        let nft = ctx.accounts.stake_pool.nft.ok_or(SPError::NotAnNftPool)?;
+        if let Some(root) = ctx.accounts.stake_pool.allowlist_root {
+            require!(merkle::verify(&root, ctx.accounts.beneficiary.key, &allowlist_proof), SPError::BeneficiaryNotAllowed);
+        }
+        require!(ctx.accounts.verified_collection()? == Some(nft.collection), SPError::NftNotInCollection);
+        let nft_mint = ctx.accounts.nft_mint.key();
+        let weight = match nft.weights_root {
+            Some(root) => {
+                require!(merkle::verify_leaf(&root, merkle::weight_leaf(&nft_mint, weight), &proof), SPError::NftWeightNotProven);
+                weight
+            },
+            None => nft.base_weight,
+        };
+        let member_staked_weight = ctx.accounts.member_staked_weight(ctx.remaining_accounts)?;
+        let stake_pool = &ctx.accounts.stake_pool;
+        stake_pool.limits.check_caps(stake_pool.total_staked_tokens, member_staked_weight, weight)?;
+
+        let now = ctx.accounts.clock.unix_timestamp as u64;
+        let commitment = solvency::committed_reward(stake_pool, now, weight)?;
+        let vault_reward = ctx.accounts.vault_reward.amount;
+        solvency::commit_to_partition(&mut ctx.accounts.factory, &mut ctx.accounts.stake_pool, commitment, vault_reward)?;
+
+        ctx.accounts.transfer_nft_to_vault()?;
+
+        let nft_stake = &mut ctx.accounts.nft_stake;
+        nft_stake.stake_pool = ctx.accounts.stake_pool.key();
+        nft_stake.beneficiary = ctx.accounts.beneficiary.key();
+        nft_stake.mint = nft_mint;
+        nft_stake.vault_nft = ctx.accounts.vault_nft.key();
+        nft_stake.weight = weight;
+        nft_stake.staked_at = now;
+        nft_stake.reward_payed_for = now;
+        nft_stake.reward_owed = 0;
+        nft_stake.unstaked_at = None;
+        nft_stake.bump = ctx.bumps.nft_stake;
+        nft_stake.version = ACCOUNT_VERSION;
+        let member = &mut ctx.accounts.member;
+        member.open_positions = member.open_positions.try_add(1)?;
+        ctx.accounts.position_index.add(PositionKind::Nft, ctx.accounts.stake_pool.key(), ctx.accounts.nft_stake.key());
+        space::fit_position_index(&ctx.accounts.position_index, &ctx.accounts.beneficiary, &ctx.accounts.system_program)?;
+
+        let stake_pool = &mut ctx.accounts.stake_pool;
+        stake_pool.total_staked_tokens = stake_pool.total_staked_tokens.try_add(weight as u128)?;
+
+        Ok(())
+    }
+
+    /// Claim the reward for a staked NFT as `claim_reward` does for the stake tokens.
+    /// Signed by the beneficiary or the member's delegate.
+    pub fn claim_nft_reward(ctx: Context<ClaimNftReward>) -> Result<()> {
+        let (accrued_reward, reward_payed_for) = ctx.accounts.calculate_reward_tokens()?;
+        let reward_due = accrued_reward.try_add(ctx.accounts.nft_stake.reward_owed)?;
+
+        let reward_tokens_to_transfer = solvency::payment(
+            &ctx.accounts.stake_pool,
+            reward_due,
+            solvency::available_rewards(&ctx.accounts.factory, &ctx.accounts.stake_pool, ctx.accounts.vault_reward.amount),
+            solvency::partition_committed(&ctx.accounts.factory, &ctx.accounts.stake_pool),
+        )?;
+
+        ctx.accounts.transfer_reward_tokens(reward_tokens_to_transfer)?;
+        ctx.accounts.nft_stake.settle(reward_payed_for, reward_due.try_sub(reward_tokens_to_transfer)?);
+
+        let factory = &mut ctx.accounts.factory;
+        solvency::release_from_partition(factory, &mut ctx.accounts.stake_pool, reward_tokens_to_transfer as u128);
+        solvency::spend_funded(factory, &mut ctx.accounts.stake_pool, reward_tokens_to_transfer)?;
+
+        Ok(())
+    }
+
+    /// Stop the NFT gaining the rewards and start the unstake delay.
+    /// The rewards not claimed yet are forfeited and released from the committed rewards.
+    pub fn start_unstake_nft(ctx: Context<StartUnstakeNft>) -> Result<()> {
+        let weight = ctx.accounts.nft_stake.weight;
+        let checkpoint = ctx.accounts.nft_stake.reward_checkpoint();
+        ctx.accounts.nft_stake.unstaked_at = Some(ctx.accounts.clock.unix_timestamp as u64);
+
+        let stake_pool = &mut ctx.accounts.stake_pool;
+        stake_pool.total_staked_tokens = stake_pool.total_staked_tokens.try_sub(weight as u128)?;
+
+        let released = solvency::committed_reward(&ctx.accounts.stake_pool, checkpoint, weight)?;
+        let factory = &mut ctx.accounts.factory;
+        solvency::release_from_partition(factory, &mut ctx.accounts.stake_pool, released);
+
+        Ok(())
+    }
+
+    /// Return the NFT to the beneficiary's token account after the unstake delay.
+    /// Destroys the `NftStake` and its vault, the reward still owed is forfeited.
+    #[access_control(allow_finish_unstake_nft(&ctx))]
+    pub fn finish_unstake_nft(ctx: Context<FinishUnstakeNft>) -> Result<()> {
+        let forfeited = ctx.accounts.nft_stake.reward_owed as u128;
+        let factory = &mut ctx.accounts.factory;
+        solvency::release_from_partition(factory, &mut ctx.accounts.stake_pool, forfeited);
+
+        let member = &mut ctx.accounts.member;
+        member.open_positions = member.open_positions.try_sub(1)?;
+        ctx.accounts.position_index.remove(&ctx.accounts.nft_stake.key());
+
+        ctx.accounts.transfer_nft_to_beneficiary()?;
+        ctx.accounts.close_nft_vault()
+    }
+
+    /// Same as `finish_unstake_all`, but the tokens go straight to the beneficiary's wallet,
+    /// skipping the `free vault`.
+    #[access_control(allow_unstake_and_withdraw(&ctx))]
+    pub fn unstake_and_withdraw(ctx: Context<UnstakeAndWithdraw>) -> Result<()> {
+        let forfeited = ctx.accounts.member_stake.reward_owed as u128;
+        let factory = &mut ctx.accounts.factory;
+        solvency::release_from_partition(factory, &mut ctx.accounts.stake_pool, forfeited);
+
+        let member = &mut ctx.accounts.member;
+        member.open_positions = member.open_positions.try_sub(1)?;
+        member.pending_unstakes = member.pending_unstakes.try_sub(1)?;
+        let position_index = &mut ctx.accounts.position_index;
+        position_index.remove(&ctx.accounts.member_stake.key());
+        position_index.remove(&ctx.accounts.member_pending_unstake.key());
+
+        let unstake_amount = ctx.accounts.vault_pending_unstake.amount;
+        ctx.accounts.transfer_pu_tokens_to_beneficiary(unstake_amount)?;
+        ctx.accounts.harvest_withheld_fees()?;
         ctx.accounts.close_pending_unstake_vault()?;
         ctx.accounts.close_stake_vault()
     }
 
+    /// Close the member without open stakes or pending unstakes, moving the tokens left in the `free vault`
+    /// to the beneficiary's wallet and returning the rent of the vault, the member and its position index to the beneficiary.
+    pub fn close_member(ctx: Context<CloseMember>) -> Result<()> {
+        ctx.accounts.empty_and_close_free_vault()
+    }
+
     /// Withdraw tokens from internal `free vault` controlled by the program
     /// to external user's wallet controlled by the user.
     /// 
@@ -157,4 +670,197 @@ pub mod staking {
         ctx.accounts.transfer_free_vault_tokens_to_beneficiary()
     }
 
+    /// Withdraw all the wrapped SOL from the `free vault` to the beneficiary's wallet as lamports,
+    /// when the stake tokens are the wrapped SOL. Same as `withdraw_all` with no need to unwrap the SOL afterwards.
+    pub fn withdraw_sol(ctx: Context<WithdrawSol>) -> Result<()> {
+        ctx.accounts.unwrap_free_vault_to_beneficiary()
+    }
+
+    /// Upgrade a `Factory` created before the accounts were versioned to the current layout.
+    /// Anyone may migrate the accounts, the `payer` pays the rent of the added space.
+    /// The factory goes first, then the stake pools and the members, then the members' positions.
+    /// The `FactoryRoles` are created along, all held by the admin until the `SetRoles` queued action.
+    pub fn migrate_factory(ctx: Context<MigrateFactory>) -> Result<()> {
+        ctx.accounts.migrate(ctx.bumps.roles)
+    }
+
+    /// Upgrade a version 0 `StakePool` to the current layout. Its percent fee is kept in basis points
+    /// and goes to the factory owner through the pool's `vault_fee`, created by the `payer`.
+    pub fn migrate_stake_pool(ctx: Context<MigrateStakePool>) -> Result<()> {
+        ctx.accounts.migrate()
+    }
+
+    /// Upgrade a version 0 `Member` to the current layout.
+    /// It has no positions until they are migrated themselves.
+    pub fn migrate_member(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<v0::Member, Member>(v0::Member::SPACE, Member::SPACE)
+    }
+
+    /// Upgrade a version 0 `MemberStake` to the current layout and count it in the migrated `Member`.
+    /// Its `vault_staked` and the member are the remaining accounts,
+    /// the balance of the vault is recorded as the staked tokens.
+    pub fn migrate_member_stake<'info>(ctx: Context<'_, '_, 'info, 'info, MigrateAccount<'info>>) -> Result<()> {
+        let [vault_staked_info, member_info] = ctx.remaining_accounts else {
+            return err!(SPError::MigrationAccountWrong);
+        };
+        let vault_staked = InterfaceAccount::<TokenAccount>::try_from(vault_staked_info)?;
+        let mut beneficiary = Pubkey::default();
+        ctx.accounts.migrate_with::<v0::MemberStake, MemberStake>(v0::MemberStake::SPACE, MemberStake::SPACE, |member_stake| {
+            require!(member_stake.vault_staked == vault_staked.key(), SPError::MigrationAccountWrong);
+            member_stake.staked_tokens = vault_staked.amount;
+            beneficiary = member_stake.beneficiary;
+            Ok(())
+        })?;
+
+        count_position(member_info, &beneficiary, |member| {
+            member.open_positions = member.open_positions.try_add(1)?;
+            Ok(())
+        })
+    }
+
+    /// Upgrade a version 0 `MemberPendingUnstake` to the current layout and count it in the migrated `Member`,
+    /// which is the remaining account.
+    pub fn migrate_member_pending_unstake<'info>(ctx: Context<'_, '_, 'info, 'info, MigrateAccount<'info>>) -> Result<()> {
+        let [member_info] = ctx.remaining_accounts else {
+            return err!(SPError::MigrationAccountWrong);
+        };
+        let mut beneficiary = Pubkey::default();
+        ctx.accounts.migrate_with::<v0::MemberPendingUnstake, MemberPendingUnstake>(
+            v0::MemberPendingUnstake::SPACE,
+            MemberPendingUnstake::SPACE,
+            |unstake| {
+                beneficiary = unstake.beneficiary;
+                Ok(())
+            },
+        )?;
+
+        count_position(member_info, &beneficiary, |member| {
+            member.pending_unstakes = member.pending_unstakes.try_add(1)?;
+            Ok(())
+        })
+    }
+
+    /// Create the position index of a member opened before the index existed, from its `MemberStake`,
+    /// `MemberPendingUnstake` and `NftStake` accounts given as the remaining accounts.
+    /// All of the positions have to be given, so the index is complete once created.
+    pub fn index_positions<'info>(ctx: Context<'_, '_, '_, 'info, IndexPositions<'info>>) -> Result<()> {
+        let mut positions = Vec::with_capacity(ctx.remaining_accounts.len());
+        for account in ctx.remaining_accounts {
+            require!(!positions.iter().any(|(_, _, key)| key == account.key), SPError::PositionIndexWrong);
+            let (kind, stake_pool) = ctx.accounts.position_of(account)?;
+            positions.push((kind, stake_pool, account.key()));
+        }
+        let pending_unstakes = positions.iter().filter(|(kind, _, _)| *kind == PositionKind::PendingUnstake).count();
+        let open_positions = positions.len() - pending_unstakes;
+        require!(open_positions == ctx.accounts.member.open_positions as usize, SPError::PositionIndexWrong);
+
+        let member_key = ctx.accounts.member.key();
+        let position_index = &mut ctx.accounts.position_index;
+        position_index.open(member_key, ctx.bumps.position_index);
+        for (kind, stake_pool, key) in positions {
+            position_index.add(kind, stake_pool, key);
+        }
+        space::fit_position_index(position_index, &ctx.accounts.beneficiary, &ctx.accounts.system_program)?;
+        ctx.accounts.member.pending_unstakes = pending_unstakes as u32;
+
+        Ok(())
+    }
+
+    /// Stop the stakes and the claims of every pool. Signed by the admin or the pauser.
+    /// The pause is lifted by the `Unpause` queued action.
+    pub fn pause(ctx: Context<Pause>) -> Result<()> {
+        ctx.accounts.factory.paused = true;
+
+        Ok(())
+    }
+
+    /// Deposit reward tokens on behalf of the stake pool with a manager, signed by its manager or the admin.
+    /// The deposits are kept in the factory's `vault_reward` and counted in the pool's `funded_rewards`,
+    /// which only the pool pays its rewards from.
+    pub fn fund_stake_pool(ctx: Context<FundStakePool>, amount: u64) -> Result<()> {
+        require!(amount <= ctx.accounts.vault_authority.amount, SPError::NotEnoughOwnerRewardVaultAmount);
+        // Only the tokens the `vault_reward` actually receives are counted,
+        // as a mint may charge a fee on transfers.
+        let vault_reward_before = ctx.accounts.vault_reward.amount;
+        ctx.accounts.transfer_tokens_to_reward_vault(amount)?;
+        ctx.accounts.vault_reward.reload()?;
+        let funded = ctx.accounts.vault_reward.amount.try_sub(vault_reward_before)?;
+
+        let stake_pool = &mut ctx.accounts.stake_pool;
+        stake_pool.funded_rewards = stake_pool.funded_rewards.try_add(funded)?;
+        let factory = &mut ctx.accounts.factory;
+        factory.funded_rewards = factory.funded_rewards.try_add(funded)?;
+
+        Ok(())
+    }
+
+    /// Queue the sensitive admin `action` to be executed from the `eta`, at least the factory's `timelock_delay` from now.
+    /// The stake pool of the pool actions is the remaining account.
+    pub fn queue_action<'info>(ctx: Context<'_, '_, 'info, 'info, QueueAction<'info>>, action: AdminAction, eta: u64) -> Result<()> {
+        action.validate()?;
+        let stake_pool = action_stake_pool(&action, ctx.remaining_accounts)?;
+        let accounts = &ctx.accounts;
+        let proposer = accounts.proposer.key();
+        require!(
+            QueuedAction::may_propose(&action, &accounts.factory, &accounts.roles, stake_pool.as_deref(), &proposer),
+            SPError::RoleRequired
+        );
+        let now = accounts.clock.unix_timestamp as u64;
+        timelock::check_eta(now, accounts.factory.timelock_delay, eta)?;
+
+        let factory = &mut ctx.accounts.factory;
+        factory.action_count = factory.action_count.try_add(1)?;
+        let queued_action = &mut ctx.accounts.queued_action;
+        queued_action.id = factory.action_count;
+        queued_action.action = action;
+        queued_action.proposer = proposer;
+        queued_action.queued_at = now;
+        queued_action.eta = eta;
+        queued_action.bump = ctx.bumps.queued_action;
+        queued_action.version = ACCOUNT_VERSION;
+
+        emit!(ActionQueued { id: queued_action.id, action, proposer, eta });
+
+        Ok(())
+    }
+
+    /// Cancel the queued action, signed by its proposer or the admin.
+    pub fn cancel_action(ctx: Context<CancelAction>) -> Result<()> {
+        let queued_action = &ctx.accounts.queued_action;
+        emit!(ActionCancelled {
+            id: queued_action.id,
+            action: queued_action.action,
+            cancelled_by: ctx.accounts.authority.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Execute the queued action once its `eta` has come, while its proposer still may propose it.
+    /// The stake pool of the pool actions is the first remaining account. The actions changing the reward
+    /// or the manager of the pool take all the positions of the pool after it, writable, to settle their rewards first.
+    pub fn execute_action<'info>(ctx: Context<'_, '_, 'info, 'info, ExecuteAction<'info>>) -> Result<()> {
+        let action = ctx.accounts.queued_action.action;
+        let mut stake_pool = action_stake_pool(&action, ctx.remaining_accounts)?;
+        let accounts = &mut *ctx.accounts;
+        require!(
+            QueuedAction::may_propose(&action, &accounts.factory, &accounts.roles, stake_pool.as_deref(), &accounts.proposer.key()),
+            SPError::RoleRequired
+        );
+        if let (true, Some(stake_pool)) = (action.settles_rewards(), stake_pool.as_deref_mut()) {
+            accounts.settle_rewards(&action, stake_pool, &ctx.remaining_accounts[1..])?;
+        }
+        action.apply(&mut accounts.factory, &mut accounts.roles, stake_pool.as_deref_mut())?;
+        if let Some(stake_pool) = stake_pool {
+            stake_pool.exit(ctx.program_id)?;
+        }
+
+        emit!(ActionExecuted {
+            id: ctx.accounts.queued_action.id,
+            action,
+            executed_at: ctx.accounts.clock.unix_timestamp as u64,
+        });
+
+        Ok(())
+    }
 }
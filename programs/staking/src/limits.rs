@@ -0,0 +1,125 @@
+use anchor_lang::prelude::*;
+use crate::error::SPError;
+use crate::math::TryMath;
+
+/// The amounts of tokens a stake pool accepts.
+/// Caps the dilution of an Unfixed pool and the liabilities of a Fixed one.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct StakeLimits {
+    /// The maximum `total_staked_tokens` of the pool. `None` means unlimited.
+    pub max_total_staked: Option<u128>,
+    /// The maximum tokens a single member has staked in the pool. `None` means unlimited.
+    pub max_per_member: Option<u64>,
+    /// The minimum tokens of a single stake. Zero disables the minimum.
+    pub min_stake_amount: u64,
+}
+
+impl StakeLimits {
+    pub const SPACE: usize = (1 + 16) + (1 + 8) + 8;
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(max_per_member) = self.max_per_member {
+            require!(self.min_stake_amount <= max_per_member, SPError::StakeLimitsWrong);
+        }
+        if let Some(max_total_staked) = self.max_total_staked {
+            require!(self.min_stake_amount as u128 <= max_total_staked, SPError::StakeLimitsWrong);
+        }
+        Ok(())
+    }
+
+    /// Checks the member having `member_staked` tokens in the pool
+    /// with `total_staked` tokens is allowed to stake `amount` more.
+    pub fn check(&self, total_staked: u128, member_staked: u64, amount: u64) -> Result<()> {
+        require!(amount >= self.min_stake_amount, SPError::StakeAmountTooSmall);
+        self.check_caps(total_staked, member_staked, amount)
+    }
+
+    /// Same as `check`, but any `amount` is large enough. A compounded reward isn't a new stake.
+    pub fn check_caps(&self, total_staked: u128, member_staked: u64, amount: u64) -> Result<()> {
+        if let Some(max_per_member) = self.max_per_member {
+            require!(member_staked.try_add(amount)? <= max_per_member, SPError::MemberStakeLimitExceeded);
+        }
+        if let Some(max_total_staked) = self.max_total_staked {
+            require!(total_staked.try_add(amount as u128)? <= max_total_staked, SPError::StakePoolCapacityExceeded);
+        }
+        Ok(())
+    }
+
+    /// The tokens the pool with `total_staked` tokens accepts until it's full. `None` if unlimited.
+    pub fn remaining_capacity(&self, total_staked: u128) -> Option<u128> {
+        self.max_total_staked.map(|max_total_staked| max_total_staked.saturating_sub(total_staked))
+    }
+}
+
+/// How many tokens a new member is able to stake in the pool right now.
+/// Returned by the `get_stake_capacity` instruction as the return data.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct StakeCapacity {
+    /// The tokens the pool accepts until `max_total_staked`. `None` if unlimited.
+    pub remaining: Option<u128>,
+    /// The largest stake allowed by both the pool and the per-member cap. `None` if unlimited.
+    pub max_stake: Option<u64>,
+    pub min_stake_amount: u64,
+}
+
+impl StakeCapacity {
+    pub fn new(limits: &StakeLimits, total_staked: u128) -> StakeCapacity {
+        let remaining = limits.remaining_capacity(total_staked);
+        let remaining_u64 = remaining.map(|remaining| remaining.min(u64::MAX as u128) as u64);
+        let max_stake = match (remaining_u64, limits.max_per_member) {
+            (Some(remaining), Some(max_per_member)) => Some(remaining.min(max_per_member)),
+            (remaining, max_per_member) => remaining.or(max_per_member),
+        };
+
+        StakeCapacity { remaining, max_stake, min_stake_amount: limits.min_stake_amount }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_error(result: Result<()>, error: SPError) {
+        assert_eq!(ProgramError::from(result.unwrap_err()), ProgramError::from(error!(error)));
+    }
+
+    #[test]
+    fn unlimited_by_default() {
+        let limits = StakeLimits::default();
+        assert!(limits.validate().is_ok());
+        assert!(limits.check(u64::MAX as u128, 0, u64::MAX).is_ok());
+        assert_eq!(limits.remaining_capacity(1000), None);
+        assert_eq!(StakeCapacity::new(&limits, 1000).max_stake, None);
+    }
+
+    #[test]
+    fn enforces_every_limit() {
+        let limits = StakeLimits { max_total_staked: Some(1000), max_per_member: Some(300), min_stake_amount: 10 };
+
+        assert!(limits.check(700, 0, 300).is_ok());
+        assert_error(limits.check(0, 0, 9), SPError::StakeAmountTooSmall);
+        assert_error(limits.check(0, 200, 101), SPError::MemberStakeLimitExceeded);
+        assert_error(limits.check(800, 0, 201), SPError::StakePoolCapacityExceeded);
+        assert!(limits.check_caps(0, 0, 9).is_ok());
+        assert_error(limits.check_caps(0, 295, 6), SPError::MemberStakeLimitExceeded);
+    }
+
+    #[test]
+    fn capacity_is_the_tightest_limit() {
+        let limits = StakeLimits { max_total_staked: Some(1000), max_per_member: Some(300), min_stake_amount: 0 };
+
+        assert_eq!(StakeCapacity::new(&limits, 0).max_stake, Some(300));
+        assert_eq!(StakeCapacity::new(&limits, 900), StakeCapacity { remaining: Some(100), max_stake: Some(100), min_stake_amount: 0 });
+        // The cap may be lowered below the staked tokens.
+        assert_eq!(limits.remaining_capacity(1200), Some(0));
+    }
+
+    #[test]
+    fn minimum_above_maximum_is_wrong() {
+        let limits = StakeLimits { max_total_staked: None, max_per_member: Some(10), min_stake_amount: 11 };
+        assert_error(limits.validate(), SPError::StakeLimitsWrong);
+
+        let limits = StakeLimits { max_total_staked: Some(10), max_per_member: None, min_stake_amount: 11 };
+        assert_error(limits.validate(), SPError::StakeLimitsWrong);
+    }
+}
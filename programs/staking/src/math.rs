@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+use crate::error::SPError;
+use std::convert::TryFrom;
+
+/// The scale of the fixed-point numbers used by the program.
+/// E.g. a member's share of the pool equal to 0.25 is stored as `PRECISION / 4`.
+pub const PRECISION: u128 = 1_000_000_000_000;
+
+/// Checked arithmetic that fails with an `SPError` instead of panicking.
+pub trait TryMath: Sized {
+    fn try_add(self, rhs: Self) -> Result<Self>;
+    fn try_sub(self, rhs: Self) -> Result<Self>;
+    fn try_mul(self, rhs: Self) -> Result<Self>;
+    fn try_div(self, rhs: Self) -> Result<Self>;
+}
+
+macro_rules! impl_try_math {
+    ($($t:ty),*) => {$(
+        impl TryMath for $t {
+            fn try_add(self, rhs: Self) -> Result<Self> {
+                self.checked_add(rhs).ok_or_else(|| error!(SPError::MathOverflow))
+            }
+
+            fn try_sub(self, rhs: Self) -> Result<Self> {
+                self.checked_sub(rhs).ok_or_else(|| error!(SPError::MathUnderflow))
+            }
+
+            fn try_mul(self, rhs: Self) -> Result<Self> {
+                self.checked_mul(rhs).ok_or_else(|| error!(SPError::MathOverflow))
+            }
+
+            fn try_div(self, rhs: Self) -> Result<Self> {
+                self.checked_div(rhs).ok_or_else(|| error!(SPError::DivisionByZero))
+            }
+        }
+    )*};
+}
+
+impl_try_math!(u32, u64, u128);
+
+/// Computes `value * numerator / denominator` without losing precision
+/// on the intermediate product. The result is rounded down.
+pub fn mul_div(value: u128, numerator: u128, denominator: u128) -> Result<u128> {
+    value.try_mul(numerator)?.try_div(denominator)
+}
+
+/// Computes `numerator / denominator` as a fixed-point number.
+pub fn fixed_ratio(numerator: u128, denominator: u128) -> Result<u128> {
+    mul_div(numerator, PRECISION, denominator)
+}
+
+/// Applies a fixed-point `ratio` to `value` rounding the result down.
+pub fn apply_ratio(value: u128, ratio: u128) -> Result<u128> {
+    mul_div(value, ratio, PRECISION)
+}
+
+/// Narrows `value` to `u64`, the type of the token amounts.
+pub fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| error!(SPError::MathOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_error<T: std::fmt::Debug>(result: Result<T>, expected: SPError) {
+        assert_eq!(ProgramError::from(result.unwrap_err()), ProgramError::from(Error::from(expected)));
+    }
+
+    #[test]
+    fn checked_operations_return_errors() {
+        assert_error(u64::MAX.try_add(1), SPError::MathOverflow);
+        assert_error(0u64.try_sub(1), SPError::MathUnderflow);
+        assert_error(u128::MAX.try_mul(2), SPError::MathOverflow);
+        assert_error(1u128.try_div(0), SPError::DivisionByZero);
+        assert_error(to_u64(u64::MAX as u128 + 1), SPError::MathOverflow);
+    }
+
+    #[test]
+    fn mul_div_keeps_precision() {
+        assert_eq!(mul_div(200, 50, 1000).unwrap(), 10);
+        assert_eq!(mul_div(u64::MAX as u128, u64::MAX as u128, u64::MAX as u128).unwrap(), u64::MAX as u128);
+        assert_eq!(apply_ratio(200, fixed_ratio(50, 1000).unwrap()).unwrap(), 10);
+        assert_eq!(apply_ratio(3, fixed_ratio(1, 3).unwrap()).unwrap(), 0);
+    }
+}
@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak::hashv;
+
+/// A node of the merkle tree of the beneficiaries allowed to stake in a permissioned pool
+/// or of the weights of the NFTs of an NFT pool.
+pub type MerkleNode = [u8; 32];
+
+/// Leaves and inner nodes are hashed with different prefixes,
+/// so an inner node can't be passed off as a leaf.
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub fn leaf(beneficiary: &Pubkey) -> MerkleNode {
+    hashv(&[LEAF_PREFIX, beneficiary.as_ref()]).0
+}
+
+/// The leaf of the `weight` of the NFT `mint` in the weights of an NFT pool.
+pub fn weight_leaf(mint: &Pubkey, weight: u64) -> MerkleNode {
+    hashv(&[LEAF_PREFIX, mint.as_ref(), &weight.to_le_bytes()]).0
+}
+
+/// The pair is sorted before hashing, so a proof doesn't need to tell left from right.
+pub fn parent(a: &MerkleNode, b: &MerkleNode) -> MerkleNode {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, left, right]).0
+}
+
+/// Checks the `beneficiary` is in the tree with the `root`.
+/// The `proof` is the siblings of the nodes on the path from the leaf to the root.
+pub fn verify(root: &MerkleNode, beneficiary: &Pubkey, proof: &[MerkleNode]) -> bool {
+    verify_leaf(root, leaf(beneficiary), proof)
+}
+
+/// Checks the `leaf` is in the tree with the `root`.
+pub fn verify_leaf(root: &MerkleNode, leaf: MerkleNode, proof: &[MerkleNode]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| parent(&node, sibling));
+    computed == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_the_path_to_the_root() {
+        let [a, b, c] = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let ab = parent(&leaf(&a), &leaf(&b));
+        let root = parent(&ab, &leaf(&c));
+
+        assert!(verify(&root, &a, &[leaf(&b), leaf(&c)]));
+        assert!(verify(&root, &b, &[leaf(&a), leaf(&c)]));
+        assert!(verify(&root, &c, &[ab]));
+        assert!(!verify(&root, &c, &[leaf(&a)]));
+        assert!(!verify(&root, &Pubkey::new_unique(), &[leaf(&b), leaf(&c)]));
+        // A single beneficiary is the root itself.
+        assert!(verify(&leaf(&a), &a, &[]));
+    }
+
+    #[test]
+    fn weight_is_part_of_the_leaf() {
+        let [a, b] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let root = parent(&weight_leaf(&a, 3), &weight_leaf(&b, 1));
+
+        assert!(verify_leaf(&root, weight_leaf(&a, 3), &[weight_leaf(&b, 1)]));
+        assert!(!verify_leaf(&root, weight_leaf(&a, 30), &[weight_leaf(&b, 1)]));
+        assert!(!verify(&root, &a, &[weight_leaf(&b, 1)]));
+    }
+
+    #[test]
+    fn inner_node_is_not_a_leaf() {
+        let [a, b] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let ab = parent(&leaf(&a), &leaf(&b));
+
+        assert_ne!(leaf(&Pubkey::new_from_array(ab)), ab);
+    }
+}
@@ -0,0 +1,352 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use crate::account::*;
+use crate::error::SPError;
+use crate::fee::OwnerFee;
+use crate::limits::StakeLimits;
+use crate::timelock::MIN_TIMELOCK_DELAY;
+
+/// The layouts of the accounts as first deployed, before the `version` and the `reserved` space were added.
+pub mod v0 {
+    use anchor_lang::prelude::*;
+    use crate::reward::Reward;
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct Factory {
+        pub bump: u8,
+        pub owner: Pubkey,
+        pub reward_token_mint: Pubkey,
+        pub stake_token_mint: Pubkey,
+        pub vault_reward: Pubkey,
+    }
+
+    impl Factory {
+        pub const SPACE: usize = 1 + 32 * 4;
+    }
+
+    /// The names were up to 10 bytes and the pool addresses were derived from the names themselves.
+    /// The owner's fee was a percent of each reward, paid to the factory owner on every claim.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct StakePool {
+        pub name: String,
+        pub reward_type: Reward,
+        pub started_at: u64,
+        pub ends_at: u64,
+        pub total_staked_tokens: u128,
+        pub bump: u8,
+        pub owner_interest_percent: u8,
+        pub unstake_delay: u64,
+        /// The fee taken when the percent of a reward rounded down to zero.
+        pub min_owner_reward: u32,
+        pub reward_period: u64,
+    }
+
+    impl StakePool {
+        pub const SPACE: usize = (10 + 4) + (1 + 16) + 8 + 8 + 16 + 1 + 1 + 8 + 4 + 8;
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct Member {
+        pub beneficiary: Pubkey,
+        pub vault_free: Pubkey,
+        pub bump: u8,
+    }
+
+    impl Member {
+        pub const SPACE: usize = 32 * 2 + 8;
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct MemberStake {
+        pub stake_pool: Pubkey,
+        pub beneficiary: Pubkey,
+        pub vault_staked: Pubkey,
+        pub staked_at: u64,
+        pub reward_payed_for: u64,
+        pub bump: u8,
+    }
+
+    impl MemberStake {
+        pub const SPACE: usize = 32 * 3 + 8 + 8 + 1;
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct MemberPendingUnstake {
+        pub stake_pool: Pubkey,
+        pub beneficiary: Pubkey,
+        pub vault_pending_unstake: Pubkey,
+        pub unstaked_at: u64,
+        pub bump: u8,
+    }
+
+    impl MemberPendingUnstake {
+        pub const SPACE: usize = 32 * 3 + 8 + 1;
+    }
+}
+
+impl From<v0::Factory> for Factory {
+    fn from(v0: v0::Factory) -> Self {
+        Factory {
+            bump: v0.bump,
+            owner: v0.owner,
+            reward_token_mint: v0.reward_token_mint,
+            stake_token_mint: v0.stake_token_mint,
+            vault_reward: v0.vault_reward,
+            // The stakes made before the commitments were tracked have committed nothing.
+            committed_rewards: 0,
+            version: ACCOUNT_VERSION,
+            pool_count: 0,
+            paused: false,
+            timelock_delay: MIN_TIMELOCK_DELAY,
+            action_count: 0,
+            funded_rewards: 0,
+            reserved: [0; 31],
+        }
+    }
+}
+
+impl From<v0::StakePool> for StakePool {
+    fn from(v0: v0::StakePool) -> Self {
+        StakePool {
+            name: v0.name,
+            reward_type: v0.reward_type,
+            started_at: v0.started_at,
+            ends_at: v0.ends_at,
+            total_staked_tokens: v0.total_staked_tokens,
+            bump: v0.bump,
+            // The minimum fee has no counterpart, the fee is the percent alone.
+            owner_fee: OwnerFee { bps: v0.owner_interest_percent as u16 * 100, max_per_claim: None },
+            // Set to the factory owner and the pool's fee vault by `migrate_stake_pool`.
+            fee_recipient: Pubkey::default(),
+            vault_fee: Pubkey::default(),
+            unstake_delay: v0.unstake_delay,
+            reward_period: v0.reward_period,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id: 0,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        }
+    }
+}
+
+impl From<v0::Member> for Member {
+    fn from(v0: v0::Member) -> Self {
+        Member {
+            beneficiary: v0.beneficiary,
+            vault_free: v0.vault_free,
+            bump: v0.bump,
+            delegate: None,
+            reward_destination: None,
+            auto_claim: false,
+            // Counted by the migrations of the member's positions.
+            open_positions: 0,
+            version: ACCOUNT_VERSION,
+            pending_unstakes: 0,
+            reserved: [[0; 4]; 15],
+        }
+    }
+}
+
+impl From<v0::MemberStake> for MemberStake {
+    fn from(v0: v0::MemberStake) -> Self {
+        MemberStake {
+            stake_pool: v0.stake_pool,
+            beneficiary: v0.beneficiary,
+            vault_staked: v0.vault_staked,
+            staked_at: v0.staked_at,
+            reward_payed_for: v0.reward_payed_for,
+            bump: v0.bump,
+            reward_owed: 0,
+            version: ACCOUNT_VERSION,
+            // Recorded from the `vault_staked` by `migrate_member_stake`.
+            staked_tokens: 0,
+            reserved: [[0; 8]; 7],
+        }
+    }
+}
+
+impl From<v0::MemberPendingUnstake> for MemberPendingUnstake {
+    fn from(v0: v0::MemberPendingUnstake) -> Self {
+        MemberPendingUnstake {
+            stake_pool: v0.stake_pool,
+            beneficiary: v0.beneficiary,
+            vault_pending_unstake: v0.vault_pending_unstake,
+            unstaked_at: v0.unstaked_at,
+            bump: v0.bump,
+            version: ACCOUNT_VERSION,
+            reserved: [0; 64],
+        }
+    }
+}
+
+/// Rewrites the `data` of a version 0 account `New` into `space` bytes of the current layout.
+/// The v0 accounts are told by their length, `old_space`.
+pub fn upgrade<Old, New>(data: &[u8], old_space: usize, space: usize) -> Result<Vec<u8>>
+where
+    Old: AnchorDeserialize,
+    New: From<Old> + AccountSerialize + Discriminator,
+{
+    upgrade_with::<Old, New>(data, old_space, space, |_| Ok(()))
+}
+
+/// Same as `upgrade`, with the fields the v0 account doesn't have filled in by `complete`.
+pub fn upgrade_with<Old, New>(data: &[u8], old_space: usize, space: usize, complete: impl FnOnce(&mut New) -> Result<()>) -> Result<Vec<u8>>
+where
+    Old: AnchorDeserialize,
+    New: From<Old> + AccountSerialize + Discriminator,
+{
+    require!(data.len() >= 8 && data[..8] == New::discriminator(), ErrorCode::AccountDiscriminatorMismatch);
+    require!(data.len() == 8 + old_space, SPError::AccountAlreadyMigrated);
+
+    let old = Old::deserialize(&mut &data[8..]).map_err(|_| ErrorCode::AccountDidNotDeserialize)?;
+    let mut new = New::from(old);
+    complete(&mut new)?;
+    let mut upgraded = Vec::with_capacity(8 + space);
+    new.try_serialize(&mut upgraded)?;
+    upgraded.resize(8 + space, 0);
+
+    Ok(upgraded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reward::Reward;
+
+    /// An account as the first deployment wrote it: the fields in the baseline order, padded to its space.
+    fn baseline_bytes(discriminator: [u8; 8], fields: &[&[u8]], space: usize) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        for field in fields {
+            data.extend_from_slice(field);
+        }
+        assert!(data.len() <= 8 + space);
+        data.resize(8 + space, 0);
+        data
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        let mut bytes = (value.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn v0_factory_is_upgraded_from_the_baseline_layout() {
+        let (owner, reward_token_mint, stake_token_mint, vault_reward) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let data = baseline_bytes(
+            Factory::discriminator(),
+            &[&[254], owner.as_ref(), reward_token_mint.as_ref(), stake_token_mint.as_ref(), vault_reward.as_ref()],
+            v0::Factory::SPACE,
+        );
+        assert_eq!(data.len(), 8 + 129);
+
+        let upgraded = upgrade::<v0::Factory, Factory>(&data, v0::Factory::SPACE, Factory::SPACE).unwrap();
+        let factory = Factory::try_deserialize(&mut &upgraded[..]).unwrap();
+        assert_eq!(factory.bump, 254);
+        assert_eq!(factory.owner, owner);
+        assert_eq!(factory.reward_token_mint, reward_token_mint);
+        assert_eq!(factory.stake_token_mint, stake_token_mint);
+        assert_eq!(factory.vault_reward, vault_reward);
+        assert_eq!(factory.committed_rewards, 0);
+        assert_eq!(factory.version, ACCOUNT_VERSION);
+    }
+
+    #[test]
+    fn v0_stake_pool_is_upgraded_with_the_percent_fee_in_basis_points() {
+        let data = baseline_bytes(
+            StakePool::discriminator(),
+            &[
+                &string("pool"),
+                &[0], &10u128.to_le_bytes(),
+                &100u64.to_le_bytes(),
+                &200u64.to_le_bytes(),
+                &1000u128.to_le_bytes(),
+                &[254],
+                &[15],
+                &10u64.to_le_bytes(),
+                &5u32.to_le_bytes(),
+                &20u64.to_le_bytes(),
+            ],
+            v0::StakePool::SPACE,
+        );
+        assert_eq!(data.len(), 8 + 85);
+
+        let upgraded = upgrade::<v0::StakePool, StakePool>(&data, v0::StakePool::SPACE, StakePool::SPACE).unwrap();
+
+        assert_eq!(upgraded.len(), 8 + StakePool::SPACE);
+        let stake_pool = StakePool::try_deserialize(&mut &upgraded[..]).unwrap();
+        assert_eq!(stake_pool.name, "pool");
+        assert!(matches!(stake_pool.reward_type, Reward::Fixed { data: 10 }));
+        assert_eq!((stake_pool.started_at, stake_pool.ends_at), (100, 200));
+        assert_eq!(stake_pool.total_staked_tokens, 1000);
+        assert_eq!(stake_pool.bump, 254);
+        assert_eq!(stake_pool.owner_fee.bps, 1500);
+        assert_eq!(stake_pool.owner_fee.max_per_claim, None);
+        assert_eq!((stake_pool.unstake_delay, stake_pool.reward_period), (10, 20));
+        assert_eq!(stake_pool.version, ACCOUNT_VERSION);
+        assert_eq!(stake_pool.pda_seed(), b"pool");
+    }
+
+    #[test]
+    fn v0_member_is_upgraded_and_only_once() {
+        let (beneficiary, vault_free) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = baseline_bytes(Member::discriminator(), &[beneficiary.as_ref(), vault_free.as_ref(), &[253]], v0::Member::SPACE);
+        assert_eq!(data.len(), 8 + 72);
+
+        let upgraded = upgrade::<v0::Member, Member>(&data, v0::Member::SPACE, Member::SPACE).unwrap();
+        let member = Member::try_deserialize(&mut &upgraded[..]).unwrap();
+        assert_eq!(member.beneficiary, beneficiary);
+        assert_eq!(member.vault_free, vault_free);
+        assert_eq!(member.bump, 253);
+        assert_eq!(member.delegate, None);
+        assert!(!member.auto_claim);
+        assert_eq!((member.open_positions, member.pending_unstakes), (0, 0));
+        assert_eq!(member.version, ACCOUNT_VERSION);
+
+        let err = upgrade::<v0::Member, Member>(&upgraded, v0::Member::SPACE, Member::SPACE).unwrap_err();
+        assert_eq!(ProgramError::from(err), ProgramError::from(error!(SPError::AccountAlreadyMigrated)));
+    }
+
+    #[test]
+    fn v0_member_stake_is_upgraded_owing_nothing() {
+        let (stake_pool, beneficiary, vault_staked) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let data = baseline_bytes(
+            MemberStake::discriminator(),
+            &[stake_pool.as_ref(), beneficiary.as_ref(), vault_staked.as_ref(), &100u64.to_le_bytes(), &150u64.to_le_bytes(), &[252]],
+            v0::MemberStake::SPACE,
+        );
+        assert_eq!(data.len(), 8 + 113);
+
+        let upgraded = upgrade::<v0::MemberStake, MemberStake>(&data, v0::MemberStake::SPACE, MemberStake::SPACE).unwrap();
+        let member_stake = MemberStake::try_deserialize(&mut &upgraded[..]).unwrap();
+        assert_eq!((member_stake.stake_pool, member_stake.beneficiary, member_stake.vault_staked), (stake_pool, beneficiary, vault_staked));
+        assert_eq!((member_stake.staked_at, member_stake.reward_payed_for, member_stake.bump), (100, 150, 252));
+        assert_eq!((member_stake.reward_owed, member_stake.staked_tokens), (0, 0));
+    }
+
+    #[test]
+    fn upgrade_refuses_other_accounts() {
+        let (stake_pool, beneficiary, vault_pending_unstake) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let data = baseline_bytes(
+            MemberPendingUnstake::discriminator(),
+            &[stake_pool.as_ref(), beneficiary.as_ref(), vault_pending_unstake.as_ref(), &100u64.to_le_bytes(), &[255]],
+            v0::MemberPendingUnstake::SPACE,
+        );
+
+        assert!(upgrade::<v0::MemberStake, MemberStake>(&data, v0::MemberStake::SPACE, MemberStake::SPACE).is_err());
+        let upgraded = upgrade::<v0::MemberPendingUnstake, MemberPendingUnstake>(&data, v0::MemberPendingUnstake::SPACE, MemberPendingUnstake::SPACE).unwrap();
+        let unstake = MemberPendingUnstake::try_deserialize(&mut &upgraded[..]).unwrap();
+        assert_eq!(unstake.vault_pending_unstake, vault_pending_unstake);
+        assert_eq!(unstake.unstaked_at, 100);
+        assert_eq!(unstake.version, ACCOUNT_VERSION);
+    }
+}
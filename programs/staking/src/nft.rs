@@ -0,0 +1,126 @@
+use anchor_lang::prelude::*;
+use crate::error::SPError;
+use crate::merkle::MerkleNode;
+
+/// The Metaplex Token Metadata program that owns the metadata of the NFTs.
+pub mod metadata_program {
+    anchor_lang::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+}
+
+/// Makes the stake pool accept the NFTs of a verified collection instead of the fungible stake tokens.
+/// Each staked NFT counts for its weight in the pool's `total_staked_tokens`,
+/// so the rewards are calculated the same way as for the fungible tokens.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct NftConfig {
+    /// The collection mint the NFTs have to be verified items of.
+    pub collection: Pubkey,
+    /// The weight of every NFT if there are no `weights`.
+    pub base_weight: u64,
+    /// The merkle root of the `(mint, weight)` pairs, e.g. derived from the traits of the NFTs.
+    /// `None` means every NFT has the `base_weight`.
+    pub weights_root: Option<MerkleNode>,
+}
+
+impl NftConfig {
+    pub const SPACE: usize = 32 + 8 + (1 + 32);
+
+    pub fn validate(&self) -> Result<()> {
+        require!(self.base_weight > 0, SPError::NftConfigWrong);
+        Ok(())
+    }
+}
+
+/// The address of the metadata account of the `mint`.
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    let seeds = &[b"metadata".as_ref(), metadata_program::ID.as_ref(), mint.as_ref()];
+    Pubkey::find_program_address(seeds, &metadata_program::ID).0
+}
+
+/// The prefix of the Metaplex `Metadata` account up to the collection.
+#[derive(AnchorDeserialize)]
+struct Metadata {
+    key: u8,
+    _update_authority: Pubkey,
+    mint: Pubkey,
+    _name: String,
+    _symbol: String,
+    _uri: String,
+    _seller_fee_basis_points: u16,
+    _creators: Option<Vec<Creator>>,
+    _primary_sale_happened: bool,
+    _is_mutable: bool,
+    _edition_nonce: Option<u8>,
+    _token_standard: Option<u8>,
+    collection: Option<Collection>,
+}
+
+#[derive(AnchorDeserialize)]
+struct Creator {
+    _address: Pubkey,
+    _verified: bool,
+    _share: u8,
+}
+
+#[derive(AnchorDeserialize)]
+struct Collection {
+    verified: bool,
+    key: Pubkey,
+}
+
+/// `Key::MetadataV1` of the Metaplex program.
+const METADATA_V1: u8 = 4;
+
+/// The collection the `mint` is a verified item of, read from the metadata account `data`.
+/// `None` if the metadata is of another mint, has no collection or the collection isn't verified.
+pub fn verified_collection(data: &[u8], mint: &Pubkey) -> Option<Pubkey> {
+    let metadata = Metadata::deserialize(&mut &data[..]).ok()?;
+    if metadata.key != METADATA_V1 || metadata.mint != *mint {
+        return None;
+    }
+
+    metadata.collection.filter(|collection| collection.verified).map(|collection| collection.key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(mint: &Pubkey, collection: Option<(bool, Pubkey)>) -> Vec<u8> {
+        let mut data = vec![METADATA_V1];
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(mint.as_ref());
+        // The name, the symbol and the uri are padded by Metaplex.
+        for (text, padded) in [("Item #1", 32), ("ITEM", 10), ("https://example.com/1.json", 200)] {
+            let mut text = text.as_bytes().to_vec();
+            text.resize(padded, 0);
+            data.extend_from_slice(&(padded as u32).to_le_bytes());
+            data.extend_from_slice(&text);
+        }
+        data.extend_from_slice(&500u16.to_le_bytes());
+        data.extend_from_slice(&[1, 1, 0, 0, 0]);
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&[1, 100]);
+        data.extend_from_slice(&[1, 1, 1, 254, 1, 0]);
+        match collection {
+            Some((verified, key)) => {
+                data.extend_from_slice(&[1, verified as u8]);
+                data.extend_from_slice(key.as_ref());
+            },
+            None => data.push(0),
+        }
+        // The uses and the rest of the account.
+        data.extend_from_slice(&[0; 64]);
+        data
+    }
+
+    #[test]
+    fn reads_the_verified_collection() {
+        let [mint, collection] = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        assert_eq!(verified_collection(&metadata(&mint, Some((true, collection))), &mint), Some(collection));
+        assert_eq!(verified_collection(&metadata(&mint, Some((false, collection))), &mint), None);
+        assert_eq!(verified_collection(&metadata(&mint, None), &mint), None);
+        assert_eq!(verified_collection(&metadata(&mint, Some((true, collection))), &Pubkey::new_unique()), None);
+        assert_eq!(verified_collection(&[METADATA_V1], &mint), None);
+    }
+}
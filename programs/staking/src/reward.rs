@@ -1,8 +1,10 @@
 use anchor_lang::prelude::*;
 use crate::error::SPError;
-use std::convert::TryFrom;
+use crate::math::*;
+use crate::account::{StakePool, RewardPosition};
+use crate::solvency;
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
 pub enum Reward {
     /// Member will receive a fixed amount of reward tokens pro rata one's staked tokens.
     /// E.g. staked tokens: 300, `reward_per_token`: 5 %.
@@ -30,7 +32,7 @@ impl Reward {
         Reward::Unfixed { data: reward_tokens_per_period }
     }
 
-    pub fn calculate<'info>(
+    pub fn calculate(
         &self,
         current_time: u64,
         program_ends_at: u64,
@@ -40,48 +42,228 @@ impl Reward {
         total_staked: u128,
     ) -> Result<(u64, u64)> {
         require!(staked_by_user > 0, SPError::UserStakeZero);
-        require!(current_time >= staked_at.checked_add(reward_period).unwrap(), SPError::RewardPeriodNotPassed);
+        require!(current_time >= staked_at.try_add(reward_period)?, SPError::RewardPeriodNotPassed);
 
         let last_reward_time = if program_ends_at > current_time { current_time } else { program_ends_at };
-        let total_reward_time_passed = last_reward_time.checked_sub(staked_at).unwrap();
-        let full_reward_periods_amount = total_reward_time_passed.checked_div(reward_period).unwrap();
-        let full_reward_periods_end_at: u64 = full_reward_periods_amount.checked_mul(reward_period).unwrap()
-            .checked_add(staked_at).unwrap();
+        let total_reward_time_passed = last_reward_time.saturating_sub(staked_at);
+        let full_reward_periods_amount = total_reward_time_passed.try_div(reward_period)?;
+        let full_reward_periods_end_at: u64 = full_reward_periods_amount.try_mul(reward_period)?
+            .try_add(staked_at)?;
 
-        let reward_amount: u64 = match self {
+        let reward_amount: u128 = match self {
             Reward::Fixed{ data: reward_rate} => {
-                let reward_rate = reward_rate.to_owned() as u64; // % 
+                let reward_rate = *reward_rate; // %
                 require!(reward_rate > 1, SPError::RewardRateTooSmall);
                 require!(reward_rate < 100, SPError::RewardRateTooHigh);
 
-                staked_by_user
-                    .checked_mul(100).unwrap()
-                    .checked_div(reward_rate).unwrap()
-                    .checked_div(100).unwrap()
-                    .checked_mul(full_reward_periods_amount).unwrap()
+                (staked_by_user as u128)
+                    .try_mul(100)?
+                    .try_div(reward_rate)?
+                    .try_div(100)?
+                    .try_mul(full_reward_periods_amount as u128)?
             },
             Reward::Unfixed{ data: reward_tokens_per_period } => {
-                require!(reward_tokens_per_period > &0, SPError::TokensToShareEmpty);
+                require!(*reward_tokens_per_period > 0, SPError::TokensToShareEmpty);
 
                 const PRECISENESS: u128 = 10000;
-                let user_reward_rate: u128 = (staked_by_user as u128).checked_mul(PRECISENESS).unwrap()
-                    .checked_div(total_staked as u128).unwrap();
-
-                u64::try_from(
-                    reward_tokens_per_period
-                        .checked_mul(user_reward_rate).unwrap()
-                        .checked_div(PRECISENESS).unwrap()
-                ).unwrap()
+                let user_reward_rate = mul_div(staked_by_user as u128, PRECISENESS, total_staked)?;
+                mul_div(*reward_tokens_per_period, user_reward_rate, PRECISENESS)?
             },
         };
 
-        Ok((reward_amount, full_reward_periods_end_at))
+        Ok((to_u64(reward_amount)?, full_reward_periods_end_at))
     }
 }
 
+/// The rewards a member is able to claim for a `MemberStake` or an `NftStake` at the moment.
+/// Returned by the `get_pending_rewards` instruction as the return data.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PendingRewards {
+    /// The reward accrued since the last claim before the owner's fee is taken.
+    pub gross_reward: u64,
+    /// The reward left unpaid by the previous claims, paid together with the `gross_reward`.
+    pub reward_owed: u64,
+    /// The part of the `gross_reward` and the `reward_owed` accrued to the owner.
+    pub owner_fee: u64,
+    /// The part of the `gross_reward` and the `reward_owed` the member receives.
+    pub net_reward: u64,
+    /// The earliest UNIX time the member is allowed to claim the reward.
+    pub next_claimable_at: u64,
+    /// The end of the last reward period the member has been paid for.
+    pub reward_payed_for: u64,
+}
+
+impl PendingRewards {
+    pub fn calculate(
+        stake_pool: &StakePool,
+        position: &impl RewardPosition,
+        staked_by_user: u64,
+        current_time: u64,
+    ) -> Result<PendingRewards> {
+        let reward_payed_for = position.reward_checkpoint();
+        let next_claimable_at = reward_payed_for.try_add(stake_pool.reward_period)?;
+
+        let gross_reward = if has_accrued(stake_pool, position, staked_by_user, current_time)? {
+            stake_pool.reward_type.calculate(
+                current_time,
+                stake_pool.ends_at,
+                reward_payed_for,
+                staked_by_user,
+                stake_pool.reward_period,
+                stake_pool.total_staked_tokens,
+            )?.0
+        } else {
+            0
+        };
+        let reward_owed = position.reward_owed();
+        let (owner_fee, net_reward) = stake_pool.owner_fee.split(gross_reward.try_add(reward_owed)?)?;
+
+        Ok(PendingRewards {
+            gross_reward,
+            reward_owed,
+            owner_fee,
+            net_reward,
+            next_claimable_at,
+            reward_payed_for,
+        })
+    }
+}
+
+/// Checks a full reward period has passed since the last claim of the position with `staked_by_user` tokens.
+pub fn has_accrued(stake_pool: &StakePool, position: &impl RewardPosition, staked_by_user: u64, current_time: u64) -> Result<bool> {
+    Ok(staked_by_user > 0 && current_time >= position.reward_checkpoint().try_add(stake_pool.reward_period)?)
+}
+
+/// The reward accrued by the position since the last claim and the end of the last period it's paid for.
+/// The owed reward is claimable with nothing accrued since the last claim, even after unstaking.
+pub fn accrued_reward(
+    stake_pool: &StakePool,
+    position: &impl RewardPosition,
+    staked_by_user: u64,
+    current_time: u64,
+) -> Result<(u64, u64)> {
+    let checkpoint = position.reward_checkpoint();
+    if position.reward_owed() > 0 && !has_accrued(stake_pool, position, staked_by_user, current_time)? {
+        return Ok((0, checkpoint));
+    }
+
+    stake_pool.reward_type.calculate(
+        current_time,
+        stake_pool.ends_at,
+        checkpoint,
+        staked_by_user,
+        stake_pool.reward_period,
+        stake_pool.total_staked_tokens,
+    )
+}
+
+/// Settles the claim of the position with `staked_by_user` tokens, paid from the `vault_reward`
+/// as `solvency::payment` allows: moves the checkpoint and records the reward left owed.
+/// Returns the reward paid now before the owner's fee, `None` if there is nothing to claim yet.
+pub fn settle_claim(
+    stake_pool: &StakePool,
+    position: &mut impl RewardPosition,
+    staked_by_user: u64,
+    current_time: u64,
+    vault_reward: u64,
+    committed_rewards: u128,
+) -> Result<Option<u64>> {
+    if position.reward_owed() == 0 && !has_accrued(stake_pool, position, staked_by_user, current_time)? {
+        return Ok(None);
+    }
+
+    let (accrued_reward, reward_payed_for) = accrued_reward(stake_pool, position, staked_by_user, current_time)?;
+    let reward_due = accrued_reward.try_add(position.reward_owed())?;
+    let reward_paid = solvency::payment(stake_pool, reward_due, vault_reward, committed_rewards)?;
+
+    position.settle(reward_payed_for, reward_due.try_sub(reward_paid)?);
+
+    Ok(Some(reward_paid))
+}
+
+/// Moves the reward the position with `staked_by_user` tokens has accrued until `current_time` to its reward owed,
+/// so the periods passed are paid at the parameters of the pool they passed with.
+/// Returns the reward owed to the position afterwards.
+pub fn settle_accrued(
+    stake_pool: &StakePool,
+    position: &mut impl RewardPosition,
+    staked_by_user: u64,
+    current_time: u64,
+) -> Result<u64> {
+    if !has_accrued(stake_pool, position, staked_by_user, current_time)? {
+        return Ok(position.reward_owed());
+    }
+
+    let (accrued_reward, reward_payed_for) = accrued_reward(stake_pool, position, staked_by_user, current_time)?;
+    let reward_owed = position.reward_owed().try_add(accrued_reward)?;
+    position.settle(reward_payed_for, reward_owed);
+
+    Ok(reward_owed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::account::{MemberStake, ACCOUNT_VERSION};
+    use crate::fee::OwnerFee;
+    use crate::limits::StakeLimits;
+    use proptest::prelude::*;
+
+    fn any_reward() -> impl Strategy<Value = Reward> {
+        prop_oneof![
+            any::<u128>().prop_map(|data| Reward::Fixed { data }),
+            (0u128..100).prop_map(|data| Reward::Fixed { data }),
+            any::<u128>().prop_map(|data| Reward::Unfixed { data }),
+            (0u128..u64::MAX as u128).prop_map(|data| Reward::Unfixed { data }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn calculate_never_panics(
+            reward in any_reward(),
+            current_time in any::<u64>(),
+            program_ends_at in any::<u64>(),
+            staked_at in any::<u64>(),
+            staked_by_user in any::<u64>(),
+            reward_period in any::<u64>(),
+            total_staked in any::<u128>(),
+        ) {
+            let _ = reward.calculate(
+                current_time,
+                program_ends_at,
+                staked_at,
+                staked_by_user,
+                reward_period,
+                total_staked,
+            );
+        }
+
+        #[test]
+        fn calculate_never_pays_for_the_future(
+            reward in any_reward(),
+            staked_at in 0u64..u32::MAX as u64,
+            elapsed in 0u64..u32::MAX as u64,
+            program_duration in any::<u32>(),
+            staked_by_user in 1u64..u64::MAX,
+            reward_period in 1u64..u32::MAX as u64,
+        ) {
+            let current_time = staked_at + elapsed;
+            let program_ends_at = staked_at + program_duration as u64;
+
+            if let Ok((_, reward_payed_for)) = reward.calculate(
+                current_time,
+                program_ends_at,
+                staked_at,
+                staked_by_user,
+                reward_period,
+                staked_by_user as u128,
+            ) {
+                prop_assert!(reward_payed_for >= staked_at);
+                prop_assert!(reward_payed_for <= current_time);
+            }
+        }
+    }
 
     #[test]
     fn fixed_config_one_reward_period() {
@@ -97,6 +279,27 @@ mod tests {
         assert_eq!(reward_amount, 20); 
     }
 
+    #[test]
+    fn fixed_config_divides_by_the_rate() {
+        // 300 tokens divided by the rate 5 for one reward period
+        let reward_amount = one_reward_period(Reward::new_fixed(5), 300, 300).unwrap();
+        assert_eq!(reward_amount, 60);
+    }
+
+    #[test]
+    fn unfixed_config_pays_one_reward_period() {
+        // 5% of 200 tokens, however many reward periods have passed
+        let reward_amount = two_reward_periods(Reward::new_unfixed(200), 50, 1000).unwrap();
+        assert_eq!(reward_amount, 10);
+    }
+
+    #[test]
+    fn unfixed_config_share_precision() {
+        // 1 token out of 3000 is 0.0333 % of the pool, rounded down to 0.03 %
+        let reward_amount = one_reward_period(Reward::new_unfixed(1_000_000), 1, 3000).unwrap();
+        assert_eq!(reward_amount, 300);
+    }
+
     #[test]
     fn unfixed_config_one_reward_period_multiple_stakers() {
         // 50 tokens staked by user it's 5% of total_staked tokens (1000) 
@@ -115,6 +318,121 @@ mod tests {
         assert_eq!(reward_amount, 200); 
     }
 
+    fn stake_pool(reward: Reward, owner_fee: OwnerFee, total_staked: u128) -> StakePool {
+        StakePool {
+            name: "test".to_owned(),
+            reward_type: reward,
+            started_at: 1000,
+            ends_at: 10_000,
+            total_staked_tokens: total_staked,
+            bump: 255,
+            owner_fee,
+            fee_recipient: Pubkey::default(),
+            vault_fee: Pubkey::default(),
+            unstake_delay: 10,
+            reward_period: 100,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id: 0,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        }
+    }
+
+    fn member_stake(staked_at: u64, reward_payed_for: u64) -> MemberStake {
+        MemberStake {
+            stake_pool: Pubkey::default(),
+            beneficiary: Pubkey::default(),
+            vault_staked: Pubkey::default(),
+            staked_at,
+            reward_payed_for,
+            bump: 255,
+            reward_owed: 0,
+            version: ACCOUNT_VERSION,
+            staked_tokens: 1000,
+            reserved: [[0; 8]; 7],
+        }
+    }
+
+    #[test]
+    fn pending_rewards_before_reward_period() {
+        let pool = stake_pool(Reward::new_fixed(10), OwnerFee::new(1000, None).unwrap(), 1000);
+        let pending = PendingRewards::calculate(&pool, &member_stake(1500, 0), 1000, 1599).unwrap();
+
+        assert_eq!(pending, PendingRewards {
+            next_claimable_at: 1600,
+            reward_payed_for: 1500,
+            ..PendingRewards::default()
+        });
+    }
+
+    #[test]
+    fn pending_rewards_after_two_reward_periods() {
+        let pool = stake_pool(Reward::new_fixed(10), OwnerFee::new(1000, None).unwrap(), 1000);
+        let pending = PendingRewards::calculate(&pool, &member_stake(1500, 0), 1000, 1750).unwrap();
+
+        assert_eq!(pending, PendingRewards {
+            gross_reward: 200,
+            reward_owed: 0,
+            owner_fee: 20,
+            net_reward: 180,
+            next_claimable_at: 1600,
+            reward_payed_for: 1500,
+        });
+    }
+
+    #[test]
+    fn pending_rewards_start_from_the_last_payment() {
+        let pool = stake_pool(Reward::new_unfixed(300), OwnerFee::new(0, None).unwrap(), 1000);
+        let pending = PendingRewards::calculate(&pool, &member_stake(1500, 1700), 500, 1850).unwrap();
+
+        assert_eq!(pending, PendingRewards {
+            gross_reward: 150,
+            reward_owed: 0,
+            owner_fee: 0,
+            net_reward: 150,
+            next_claimable_at: 1800,
+            reward_payed_for: 1700,
+        });
+    }
+
+    #[test]
+    fn pending_rewards_include_the_reward_owed() {
+        let pool = stake_pool(Reward::new_fixed(10), OwnerFee::new(1000, None).unwrap(), 1000);
+        let mut owing = member_stake(1500, 1600);
+        owing.reward_owed = 50;
+
+        // The owed reward is claimable before the next period ends.
+        let pending = PendingRewards::calculate(&pool, &owing, 1000, 1650).unwrap();
+        assert_eq!((pending.gross_reward, pending.reward_owed, pending.owner_fee, pending.net_reward), (0, 50, 5, 45));
+
+        let pending = PendingRewards::calculate(&pool, &owing, 1000, 1700).unwrap();
+        assert_eq!((pending.gross_reward, pending.reward_owed, pending.owner_fee, pending.net_reward), (100, 50, 15, 135));
+    }
+
+    #[test]
+    fn settled_periods_keep_the_old_rate() {
+        let mut pool = stake_pool(Reward::new_unfixed(100), OwnerFee::new(0, None).unwrap(), 2000);
+        let mut position = member_stake(1500, 0);
+        position.reward_owed = 50;
+
+        // Half of the pool for a single period, the period in progress is left to the new rate.
+        assert_eq!(settle_accrued(&pool, &mut position, 1000, 1750).unwrap(), 100);
+        assert_eq!((position.reward_payed_for, position.reward_owed), (1700, 100));
+        assert_eq!(settle_accrued(&pool, &mut position, 1000, 1799).unwrap(), 100);
+
+        pool.reward_type = Reward::new_unfixed(1000);
+        let pending = PendingRewards::calculate(&pool, &position, 1000, 1800).unwrap();
+        assert_eq!((pending.gross_reward, pending.reward_owed), (500, 100));
+    }
+
     fn one_reward_period(reward: Reward, staked_by_user: u64, total_staked: u128) -> Result<u64> {
         let staked_at: u64 = 1652378565;
         let program_ends_at:u64 = 1652378663;
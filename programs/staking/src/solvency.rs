@@ -0,0 +1,361 @@
+use anchor_lang::prelude::*;
+use crate::account::{Factory, StakePool};
+use crate::error::SPError;
+use crate::math::*;
+use crate::reward::Reward;
+
+/// The reward a stake of `staked` tokens in a Fixed pool accrues
+/// for every full reward period from `from` to the end of the pool.
+/// Unfixed pools share what they have, so they never commit anything.
+/// A pool with an invalid rate or period never pays, so it doesn't commit anything either.
+pub fn committed_reward(stake_pool: &StakePool, from: u64, staked: u64) -> Result<u128> {
+    let reward_rate = match stake_pool.reward_type {
+        Reward::Fixed { data } if data > 1 && data < 100 => data,
+        _ => return Ok(0),
+    };
+    if stake_pool.reward_period == 0 {
+        return Ok(0);
+    }
+
+    let periods = stake_pool.ends_at.saturating_sub(from) / stake_pool.reward_period;
+    (staked as u128).try_mul(100)?.try_div(reward_rate)?.try_div(100)?.try_mul(periods as u128)
+}
+
+/// Adds the `commitment` of a new stake to the `committed` rewards
+/// if the `vault_reward` is able to pay all of them.
+pub fn commit(committed: u128, commitment: u128, vault_reward: u64) -> Result<u128> {
+    if commitment == 0 {
+        return Ok(committed);
+    }
+
+    let committed = committed.try_add(commitment)?;
+    require!(committed <= vault_reward as u128, SPError::RewardBudgetExceeded);
+
+    Ok(committed)
+}
+
+/// Removes the `amount` paid or forfeited by a position of the `stake_pool` from the `committed` rewards.
+/// Saturates, as the stakes made before the rewards were tracked haven't committed anything.
+pub fn release(committed: u128, stake_pool: &StakePool, amount: u128) -> u128 {
+    match stake_pool.reward_type {
+        Reward::Fixed { .. } => committed.saturating_sub(amount),
+        Reward::Unfixed { .. } => committed,
+    }
+}
+
+/// The rewards committed in the partition of the `vault_reward` the `stake_pool` pays its rewards from:
+/// its own commitments if it has a manager, the ones of all the pools without a manager otherwise.
+pub fn partition_committed(factory: &Factory, stake_pool: &StakePool) -> u128 {
+    if stake_pool.has_manager() {
+        stake_pool.committed_rewards
+    } else {
+        factory.committed_rewards
+    }
+}
+
+/// Adds the `commitment` of a new stake of the `stake_pool` to its partition
+/// if the `available_rewards` of the partition are able to pay all of its commitments.
+pub fn commit_to_partition(factory: &mut Factory, stake_pool: &mut StakePool, commitment: u128, vault_reward: u64) -> Result<()> {
+    let available = available_rewards(factory, stake_pool, vault_reward);
+    commit(partition_committed(factory, stake_pool), commitment, available)?;
+
+    stake_pool.committed_rewards = stake_pool.committed_rewards.try_add(commitment)?;
+    if !stake_pool.has_manager() {
+        factory.committed_rewards = factory.committed_rewards.try_add(commitment)?;
+    }
+
+    Ok(())
+}
+
+/// Removes the `amount` paid or forfeited by a position of the `stake_pool` from the commitments of its partition.
+pub fn release_from_partition(factory: &mut Factory, stake_pool: &mut StakePool, amount: u128) {
+    if !stake_pool.has_manager() {
+        factory.committed_rewards = release(factory.committed_rewards, stake_pool, amount);
+    }
+    stake_pool.committed_rewards = release(stake_pool.committed_rewards, stake_pool, amount);
+}
+
+/// The reward tokens of the `vault_reward` the `stake_pool` pays its rewards from.
+/// A pool with a manager only pays what was funded for it, the other pools share the rest of the vault,
+/// so neither pays its rewards out of the funds of another.
+pub fn available_rewards(factory: &Factory, stake_pool: &StakePool, vault_reward: u64) -> u64 {
+    if stake_pool.has_manager() {
+        stake_pool.funded_rewards.min(vault_reward)
+    } else {
+        vault_reward.saturating_sub(factory.funded_rewards)
+    }
+}
+
+/// Takes the `paid` reward out of the funded rewards of the `stake_pool` with a manager.
+pub fn spend_funded(factory: &mut Factory, stake_pool: &mut StakePool, paid: u64) -> Result<()> {
+    if stake_pool.has_manager() {
+        stake_pool.funded_rewards = stake_pool.funded_rewards.try_sub(paid)?;
+        factory.funded_rewards = factory.funded_rewards.try_sub(paid)?;
+    }
+
+    Ok(())
+}
+
+/// Sets the `manager` of the `stake_pool`, moving its commitments to the partition it pays from afterwards.
+/// A pool losing its manager returns its funded rewards to the vault shared by the pools without a manager.
+pub fn set_manager(factory: &mut Factory, stake_pool: &mut StakePool, manager: Option<Pubkey>) -> Result<()> {
+    match (stake_pool.has_manager(), manager.is_some()) {
+        (false, true) => {
+            factory.committed_rewards = factory.committed_rewards.saturating_sub(stake_pool.committed_rewards);
+        },
+        (true, false) => {
+            factory.committed_rewards = factory.committed_rewards.try_add(stake_pool.committed_rewards)?;
+            factory.funded_rewards = factory.funded_rewards.try_sub(stake_pool.funded_rewards)?;
+            stake_pool.funded_rewards = 0;
+        },
+        _ => {},
+    }
+    stake_pool.manager = manager;
+
+    Ok(())
+}
+
+/// Keeps for the `stake_pool` getting a manager the shared funds of the `vault_reward` covering the rewards
+/// it owes and has committed, as far as they go, so they are paid from the funds they accrued against.
+pub fn earmark_owed(factory: &mut Factory, stake_pool: &mut StakePool, owed: u64, vault_reward: u64) -> Result<()> {
+    let covered = stake_pool.committed_rewards.max(owed as u128);
+    let earmarked = to_u64(covered.min(available_rewards(factory, stake_pool, vault_reward) as u128))?;
+    stake_pool.funded_rewards = stake_pool.funded_rewards.try_add(earmarked)?;
+    factory.funded_rewards = factory.funded_rewards.try_add(earmarked)?;
+
+    Ok(())
+}
+
+/// The part of the `due` reward paid when the `vault_reward` is short of the `liabilities`.
+/// Every claimer receives the same share of what is due, instead of the first ones taking it all.
+pub fn pro_rata_payment(due: u64, vault_reward: u64, liabilities: u128) -> Result<u64> {
+    if liabilities <= vault_reward as u128 {
+        return Ok(due.min(vault_reward));
+    }
+
+    Ok(to_u64(mul_div(due as u128, vault_reward as u128, liabilities)?)?.min(due))
+}
+
+/// The reward tokens of the `vault_reward` the `stake_pool` is able to pay.
+/// An Unfixed pool only pays what the Fixed pools of its partition haven't `committed`,
+/// so its claims never spend the rewards owed to their stakes.
+pub fn payable_rewards(stake_pool: &StakePool, vault_reward: u64, committed: u128) -> u64 {
+    match stake_pool.reward_type {
+        Reward::Fixed { .. } => vault_reward,
+        Reward::Unfixed { .. } => (vault_reward as u128).saturating_sub(committed) as u64,
+    }
+}
+
+/// The part of the `due` reward the `stake_pool` pays now from the `vault_reward`.
+/// Fails if the `payable_rewards` are short, unless the pool pays pro rata.
+pub fn payment(stake_pool: &StakePool, due: u64, vault_reward: u64, committed: u128) -> Result<u64> {
+    if stake_pool.partial_payments {
+        return pro_rata_payment(due, vault_reward, committed);
+    }
+
+    require!(due <= payable_rewards(stake_pool, vault_reward, committed), SPError::InsufficientAmountOfTokensToClaim);
+    Ok(due)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::ACCOUNT_VERSION;
+    use crate::fee::OwnerFee;
+    use crate::limits::StakeLimits;
+    use proptest::prelude::*;
+
+    fn assert_error(result: Result<()>, error: SPError) {
+        assert_eq!(ProgramError::from(result.unwrap_err()), ProgramError::from(error!(error)));
+    }
+
+    fn stake_pool(reward: Reward) -> StakePool {
+        StakePool {
+            name: "test".to_owned(),
+            reward_type: reward,
+            started_at: 0,
+            ends_at: 1000,
+            total_staked_tokens: 0,
+            bump: 255,
+            owner_fee: OwnerFee::new(0, None).unwrap(),
+            fee_recipient: Pubkey::default(),
+            vault_fee: Pubkey::default(),
+            unstake_delay: 0,
+            reward_period: 100,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id: 0,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        }
+    }
+
+    fn factory(funded_rewards: u64) -> Factory {
+        Factory {
+            bump: 255,
+            owner: Pubkey::default(),
+            reward_token_mint: Pubkey::default(),
+            stake_token_mint: Pubkey::default(),
+            vault_reward: Pubkey::default(),
+            committed_rewards: 0,
+            version: ACCOUNT_VERSION,
+            pool_count: 0,
+            paused: false,
+            timelock_delay: 0,
+            action_count: 0,
+            funded_rewards,
+            reserved: [0; 31],
+        }
+    }
+
+    #[test]
+    fn fixed_pools_commit_the_remaining_periods() {
+        assert_eq!(committed_reward(&stake_pool(Reward::new_fixed(10)), 0, 1000).unwrap(), 1000);
+        assert_eq!(committed_reward(&stake_pool(Reward::new_fixed(10)), 950, 1000).unwrap(), 0);
+        assert_eq!(committed_reward(&stake_pool(Reward::new_fixed(100)), 0, 1000).unwrap(), 0);
+        assert_eq!(committed_reward(&stake_pool(Reward::new_unfixed(500)), 0, 1000).unwrap(), 0);
+    }
+
+    #[test]
+    fn commit_respects_the_budget() {
+        assert_eq!(commit(600, 400, 1000).unwrap(), 1000);
+        assert!(commit(600, 401, 1000).is_err());
+        // Nothing new is committed even if the vault is short already.
+        assert_eq!(commit(2000, 0, 1000).unwrap(), 2000);
+    }
+
+    #[test]
+    fn managed_pools_pay_from_their_funds_only() {
+        let mut factory = factory(300);
+        let mut managed = stake_pool(Reward::new_unfixed(100));
+        managed.manager = Some(Pubkey::new_unique());
+        managed.funded_rewards = 300;
+        let mut shared = stake_pool(Reward::new_unfixed(100));
+
+        assert_eq!(available_rewards(&factory, &managed, 1000), 300);
+        assert_eq!(available_rewards(&factory, &managed, 200), 200);
+        assert_eq!(available_rewards(&factory, &shared, 1000), 700);
+        assert_eq!(available_rewards(&factory, &shared, 200), 0);
+
+        spend_funded(&mut factory, &mut managed, 100).unwrap();
+        assert_eq!((managed.funded_rewards, factory.funded_rewards), (200, 200));
+        spend_funded(&mut factory, &mut shared, 100).unwrap();
+        assert_eq!((shared.funded_rewards, factory.funded_rewards), (0, 200));
+        assert!(spend_funded(&mut factory, &mut managed, 201).is_err());
+    }
+
+    #[test]
+    fn commitments_are_covered_by_their_partition() {
+        let mut factory = factory(600);
+        let mut managed = stake_pool(Reward::new_fixed(10));
+        managed.manager = Some(Pubkey::new_unique());
+        managed.funded_rewards = 600;
+        let mut shared = stake_pool(Reward::new_fixed(10));
+
+        // The vault holds 1000, but 600 of them are the managed pool's own funds.
+        assert_error(commit_to_partition(&mut factory, &mut shared, 500, 1000), SPError::RewardBudgetExceeded);
+        commit_to_partition(&mut factory, &mut shared, 400, 1000).unwrap();
+        assert_error(commit_to_partition(&mut factory, &mut managed, 601, 1000), SPError::RewardBudgetExceeded);
+        commit_to_partition(&mut factory, &mut managed, 600, 1000).unwrap();
+        assert_eq!((factory.committed_rewards, shared.committed_rewards, managed.committed_rewards), (400, 400, 600));
+        assert_eq!((partition_committed(&factory, &shared), partition_committed(&factory, &managed)), (400, 600));
+
+        release_from_partition(&mut factory, &mut managed, 100);
+        release_from_partition(&mut factory, &mut shared, 50);
+        assert_eq!((factory.committed_rewards, shared.committed_rewards, managed.committed_rewards), (350, 350, 500));
+    }
+
+    #[test]
+    fn short_partitions_pay_pro_rata_of_their_commitments() {
+        let factory = Factory { committed_rewards: 800, ..factory(600) };
+        let mut managed = stake_pool(Reward::new_fixed(10));
+        managed.manager = Some(Pubkey::new_unique());
+        (managed.funded_rewards, managed.committed_rewards) = (600, 600);
+        let mut shared = stake_pool(Reward::new_fixed(10));
+        shared.partial_payments = true;
+
+        let available = available_rewards(&factory, &shared, 1000);
+        assert_eq!(payment(&shared, 100, available, partition_committed(&factory, &shared)).unwrap(), 50);
+        let available = available_rewards(&factory, &managed, 1000);
+        assert_eq!(payment(&managed, 100, available, partition_committed(&factory, &managed)).unwrap(), 100);
+    }
+
+    #[test]
+    fn managers_move_the_commitments_between_partitions() {
+        let mut factory = Factory { committed_rewards: 500, ..factory(0) };
+        let mut pool = stake_pool(Reward::new_fixed(10));
+        pool.committed_rewards = 200;
+
+        set_manager(&mut factory, &mut pool, Some(Pubkey::new_unique())).unwrap();
+        assert_eq!(factory.committed_rewards, 300);
+        pool.funded_rewards = 100;
+        factory.funded_rewards = 100;
+        set_manager(&mut factory, &mut pool, Some(Pubkey::new_unique())).unwrap();
+        assert_eq!((factory.committed_rewards, factory.funded_rewards), (300, 100));
+        set_manager(&mut factory, &mut pool, None).unwrap();
+        assert_eq!((factory.committed_rewards, factory.funded_rewards, pool.funded_rewards), (500, 0, 0));
+    }
+
+    #[test]
+    fn pools_getting_a_manager_keep_the_shared_funds_they_owe() {
+        let mut factory = factory(200);
+        let mut unfixed = stake_pool(Reward::new_unfixed(100));
+        earmark_owed(&mut factory, &mut unfixed, 300, 1000).unwrap();
+        assert_eq!((unfixed.funded_rewards, factory.funded_rewards), (300, 500));
+
+        let mut fixed = stake_pool(Reward::new_fixed(10));
+        fixed.committed_rewards = 400;
+        earmark_owed(&mut factory, &mut fixed, 100, 1000).unwrap();
+        assert_eq!((fixed.funded_rewards, factory.funded_rewards), (400, 900));
+
+        // Only as far as the shared funds go.
+        let mut short = stake_pool(Reward::new_unfixed(100));
+        earmark_owed(&mut factory, &mut short, 300, 1000).unwrap();
+        assert_eq!((short.funded_rewards, factory.funded_rewards), (100, 1000));
+    }
+
+    #[test]
+    fn unfixed_pools_pay_what_the_fixed_pools_have_not_committed() {
+        let unfixed = stake_pool(Reward::new_unfixed(100));
+        assert_eq!(payable_rewards(&unfixed, 1000, 800), 200);
+        assert_eq!(payable_rewards(&unfixed, 1000, 1200), 0);
+        assert_eq!(payable_rewards(&stake_pool(Reward::new_fixed(10)), 1000, 800), 1000);
+
+        assert_eq!(payment(&unfixed, 200, 1000, 800).unwrap(), 200);
+        assert_error(payment(&unfixed, 300, 1000, 800).map(|_| ()), SPError::InsufficientAmountOfTokensToClaim);
+    }
+
+    #[test]
+    fn short_vault_pays_pro_rata() {
+        assert_eq!(pro_rata_payment(300, 1000, 1000).unwrap(), 300);
+        assert_eq!(pro_rata_payment(300, 500, 1000).unwrap(), 150);
+        assert_eq!(pro_rata_payment(300, 0, 1000).unwrap(), 0);
+        assert_eq!(pro_rata_payment(300, 200, 0).unwrap(), 200);
+    }
+
+    proptest! {
+        /// What is claimed and what remains committed always add up to the initial commitment.
+        #[test]
+        fn claims_consume_the_commitment(
+            rate in 2u128..100,
+            staked in 1u64..u64::MAX / 100,
+            claimed_at in 100u64..2000,
+        ) {
+            let pool = stake_pool(Reward::new_fixed(rate as u8));
+            let (reward, reward_payed_for) = pool.reward_type
+                .calculate(claimed_at, pool.ends_at, 0, staked, pool.reward_period, staked as u128)
+                .unwrap();
+
+            let remaining = committed_reward(&pool, reward_payed_for, staked).unwrap();
+            let initial = committed_reward(&pool, 0, staked).unwrap();
+            prop_assert_eq!(reward as u128 + remaining, initial);
+        }
+    }
+}
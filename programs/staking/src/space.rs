@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use crate::account::PositionIndex;
+
+/// Reallocates the `account` to `len` bytes if it's shorter, the `payer` topping up its rent.
+pub fn grow<'info>(account: &AccountInfo<'info>, payer: &AccountInfo<'info>, system_program: &AccountInfo<'info>, len: usize) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(len);
+    let lamports = account.lamports();
+    if rent > lamports {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            rent - lamports,
+        )?;
+    }
+
+    if len > account.data_len() {
+        account.realloc(len, false)?;
+    }
+
+    Ok(())
+}
+
+/// Grows the `position_index` to hold its positions, so the index never limits the positions of a member.
+pub fn fit_position_index<'info>(
+    position_index: &Account<'info, PositionIndex>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let len = 8 + PositionIndex::space(position_index.positions.len());
+    grow(&position_index.to_account_info(), payer, system_program, len)
+}
@@ -0,0 +1,370 @@
+use anchor_lang::prelude::*;
+use crate::account::{Factory, FactoryRoles, StakePool};
+use crate::error::SPError;
+use crate::fee::OwnerFee;
+use crate::limits::StakeLimits;
+use crate::math::TryMath;
+use crate::reward::Reward;
+use crate::solvency;
+
+/// The shortest `timelock_delay` of a factory, 1 hour, and the delay of the new factories,
+/// so the queued actions can be reviewed and cancelled before they take effect.
+pub const MIN_TIMELOCK_DELAY: u64 = 60 * 60;
+
+/// The longest `timelock_delay` of a factory, 30 days,
+/// so a mistaken delay can't lock the queued actions for good.
+pub const MAX_TIMELOCK_DELAY: u64 = 30 * 24 * 60 * 60;
+
+/// A sensitive admin action taking effect only once queued for the factory's `timelock_delay`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub enum AdminAction {
+    /// Changes the owner's fee of the pool. Proposed by the admin.
+    SetOwnerFee {
+        stake_pool: Pubkey,
+        owner_fee: OwnerFee,
+    },
+    /// Changes the rate of the pool's reward, keeping its type. Proposed by the admin or the pool's manager.
+    /// The commitments of a Fixed pool are counted at its rate, so its rate only changes with nothing staked.
+    /// The periods passed before the change keep the old rate.
+    SetReward {
+        stake_pool: Pubkey,
+        reward: Reward,
+    },
+    /// Lifts the pause of the stakes and the claims. Proposed by the admin or the pauser.
+    Unpause,
+    /// Makes the `owner` the admin of the factory. Proposed by the admin.
+    TransferOwnership {
+        owner: Pubkey,
+    },
+    /// Changes the `timelock_delay` of the factory. Proposed by the admin.
+    SetTimelockDelay {
+        delay: u64,
+    },
+    /// Changes the wallet that receives the owner's fees of the pool. Proposed by the admin.
+    SetFeeRecipient {
+        stake_pool: Pubkey,
+        fee_recipient: Pubkey,
+    },
+    /// Changes the share of the owner's fee paid to the cranker of the pool's auto-claims, in basis points.
+    /// Proposed by the admin.
+    SetCrankTip {
+        stake_pool: Pubkey,
+        crank_tip_bps: u16,
+    },
+    /// Turns the pro rata partial payments of the Fixed pool on or off. Proposed by the admin.
+    SetPartialPayments {
+        stake_pool: Pubkey,
+        partial_payments: bool,
+    },
+    /// Changes the amounts of tokens the pool accepts. Proposed by the admin or the pool's manager.
+    /// Lowering the caps below the staked tokens only blocks the new stakes.
+    SetStakeLimits {
+        stake_pool: Pubkey,
+        limits: StakeLimits,
+    },
+    /// Assigns the roles of the factory. Proposed by the admin.
+    SetRoles {
+        pool_creator: Pubkey,
+        reward_funder: Pubkey,
+        pauser: Pubkey,
+    },
+    /// Lets the `manager` describe the pool, set its allowlist and stake limits and fund it.
+    /// `None` removes the manager, the rewards funded for the pool left go to the shared rewards of the factory.
+    /// A pool getting a manager keeps the shared rewards covering what it owes and has committed.
+    /// Proposed by the admin or the pool creator.
+    SetPoolManager {
+        stake_pool: Pubkey,
+        manager: Option<Pubkey>,
+    },
+}
+
+impl AdminAction {
+    pub const SPACE: usize = 1 + 32 * 3;
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            AdminAction::SetOwnerFee { owner_fee, .. } => owner_fee.validate(),
+            AdminAction::SetTimelockDelay { delay } => {
+                require!((MIN_TIMELOCK_DELAY..=MAX_TIMELOCK_DELAY).contains(delay), SPError::TimelockDelayWrong);
+                Ok(())
+            },
+            AdminAction::SetCrankTip { crank_tip_bps, .. } => {
+                require!(*crank_tip_bps <= OwnerFee::MAX_BPS, SPError::CrankTipWrong);
+                Ok(())
+            },
+            AdminAction::SetStakeLimits { limits, .. } => limits.validate(),
+            AdminAction::SetReward { .. }
+            | AdminAction::Unpause
+            | AdminAction::TransferOwnership { .. }
+            | AdminAction::SetFeeRecipient { .. }
+            | AdminAction::SetPartialPayments { .. }
+            | AdminAction::SetRoles { .. }
+            | AdminAction::SetPoolManager { .. } => Ok(()),
+        }
+    }
+
+    /// The actions changing what the positions of the pool earn or the funds they are paid from.
+    /// Their execution settles the rewards accrued so far at the parameters of the pool before the change.
+    pub fn settles_rewards(&self) -> bool {
+        matches!(self, AdminAction::SetReward { .. } | AdminAction::SetPoolManager { .. })
+    }
+
+    /// The stake pool the action changes, given as the remaining account on queuing and executing.
+    pub fn stake_pool(&self) -> Option<Pubkey> {
+        match self {
+            AdminAction::SetOwnerFee { stake_pool, .. }
+            | AdminAction::SetReward { stake_pool, .. }
+            | AdminAction::SetFeeRecipient { stake_pool, .. }
+            | AdminAction::SetCrankTip { stake_pool, .. }
+            | AdminAction::SetPartialPayments { stake_pool, .. }
+            | AdminAction::SetStakeLimits { stake_pool, .. }
+            | AdminAction::SetPoolManager { stake_pool, .. } => Some(*stake_pool),
+            AdminAction::Unpause
+            | AdminAction::TransferOwnership { .. }
+            | AdminAction::SetTimelockDelay { .. }
+            | AdminAction::SetRoles { .. } => None,
+        }
+    }
+
+    /// Applies the action to the `factory` and its `roles`, or to the `stake_pool` of the pool actions.
+    pub fn apply(&self, factory: &mut Factory, roles: &mut FactoryRoles, stake_pool: Option<&mut StakePool>) -> Result<()> {
+        self.validate()?;
+        match (self, stake_pool) {
+            (AdminAction::SetOwnerFee { owner_fee, .. }, Some(stake_pool)) => stake_pool.owner_fee = *owner_fee,
+            (AdminAction::SetReward { reward, .. }, Some(stake_pool)) => {
+                match (stake_pool.reward_type, reward) {
+                    (Reward::Fixed { .. }, Reward::Fixed { .. }) => {
+                        require!(stake_pool.total_staked_tokens == 0, SPError::RewardChangeWrong);
+                    },
+                    (Reward::Unfixed { .. }, Reward::Unfixed { .. }) => {},
+                    _ => return err!(SPError::RewardChangeWrong),
+                }
+                stake_pool.reward_type = *reward;
+            },
+            (AdminAction::Unpause, None) => factory.paused = false,
+            (AdminAction::TransferOwnership { owner }, None) => factory.owner = *owner,
+            (AdminAction::SetTimelockDelay { delay }, None) => factory.timelock_delay = *delay,
+            (AdminAction::SetFeeRecipient { fee_recipient, .. }, Some(stake_pool)) => stake_pool.fee_recipient = *fee_recipient,
+            (AdminAction::SetCrankTip { crank_tip_bps, .. }, Some(stake_pool)) => stake_pool.crank_tip_bps = *crank_tip_bps,
+            (AdminAction::SetPartialPayments { partial_payments, .. }, Some(stake_pool)) => {
+                require!(matches!(stake_pool.reward_type, Reward::Fixed { .. }), SPError::PartialPaymentsNotSupported);
+                stake_pool.partial_payments = *partial_payments;
+            },
+            (AdminAction::SetStakeLimits { limits, .. }, Some(stake_pool)) => stake_pool.limits = *limits,
+            (AdminAction::SetRoles { pool_creator, reward_funder, pauser }, None) => {
+                roles.pool_creator = *pool_creator;
+                roles.reward_funder = *reward_funder;
+                roles.pauser = *pauser;
+            },
+            (AdminAction::SetPoolManager { manager, .. }, Some(stake_pool)) => solvency::set_manager(factory, stake_pool, *manager)?,
+            _ => return err!(SPError::ActionStakePoolWrong),
+        }
+        Ok(())
+    }
+}
+
+/// The stake pool of the pool actions, the first of the `remaining_accounts`. `None` for the factory actions.
+pub fn action_stake_pool<'info>(action: &AdminAction, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<Option<Account<'info, StakePool>>> {
+    match action.stake_pool() {
+        Some(key) => {
+            let account = remaining_accounts.first().ok_or(SPError::ActionStakePoolWrong)?;
+            require!(account.key() == key, SPError::ActionStakePoolWrong);
+            Ok(Some(Account::try_from(account)?))
+        },
+        None => Ok(None),
+    }
+}
+
+/// Checks the action queued `now` with the `eta` waits at least the `timelock_delay`.
+pub fn check_eta(now: u64, timelock_delay: u64, eta: u64) -> Result<()> {
+    require!(eta >= now.try_add(timelock_delay)?, SPError::TimelockEtaWrong);
+    Ok(())
+}
+
+#[event]
+pub struct ActionQueued {
+    pub id: u64,
+    pub action: AdminAction,
+    pub proposer: Pubkey,
+    /// The time the action becomes executable.
+    pub eta: u64,
+}
+
+#[event]
+pub struct ActionCancelled {
+    pub id: u64,
+    pub action: AdminAction,
+    pub cancelled_by: Pubkey,
+}
+
+#[event]
+pub struct ActionExecuted {
+    pub id: u64,
+    pub action: AdminAction,
+    pub executed_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::ACCOUNT_VERSION;
+    use crate::limits::StakeLimits;
+
+    fn assert_error(result: Result<()>, error: SPError) {
+        assert_eq!(ProgramError::from(result.unwrap_err()), ProgramError::from(error!(error)));
+    }
+
+    fn factory() -> Factory {
+        Factory {
+            bump: 255,
+            owner: Pubkey::new_unique(),
+            reward_token_mint: Pubkey::new_unique(),
+            stake_token_mint: Pubkey::new_unique(),
+            vault_reward: Pubkey::new_unique(),
+            committed_rewards: 0,
+            version: ACCOUNT_VERSION,
+            pool_count: 0,
+            paused: true,
+            timelock_delay: MIN_TIMELOCK_DELAY,
+            action_count: 0,
+            funded_rewards: 0,
+            reserved: [0; 31],
+        }
+    }
+
+    fn roles() -> FactoryRoles {
+        FactoryRoles {
+            pool_creator: Pubkey::new_unique(),
+            reward_funder: Pubkey::new_unique(),
+            pauser: Pubkey::new_unique(),
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; 64],
+        }
+    }
+
+    fn stake_pool(reward_type: Reward, total_staked_tokens: u128) -> StakePool {
+        StakePool {
+            name: "pool".to_owned(),
+            reward_type,
+            started_at: 0,
+            ends_at: 100,
+            total_staked_tokens,
+            bump: 255,
+            owner_fee: OwnerFee { bps: 0, max_per_claim: None },
+            fee_recipient: Pubkey::new_unique(),
+            vault_fee: Pubkey::new_unique(),
+            unstake_delay: 0,
+            reward_period: 10,
+            allowlist_root: None,
+            limits: StakeLimits::default(),
+            partial_payments: false,
+            crank_tip_bps: 0,
+            nft: None,
+            version: ACCOUNT_VERSION,
+            seed: [0; 32],
+            id: 1,
+            manager: None,
+            funded_rewards: 0,
+            committed_rewards: 0,
+            reserved: [[0; 8]; 4],
+        }
+    }
+
+    #[test]
+    fn the_eta_waits_the_delay() {
+        assert!(check_eta(100, 0, 100).is_ok());
+        assert!(check_eta(100, 50, 150).is_ok());
+        assert_error(check_eta(100, 50, 149), SPError::TimelockEtaWrong);
+        assert!(check_eta(u64::MAX, 1, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn factory_actions_change_the_factory() {
+        let (mut factory, mut roles) = (factory(), roles());
+        let owner = Pubkey::new_unique();
+        AdminAction::Unpause.apply(&mut factory, &mut roles, None).unwrap();
+        AdminAction::TransferOwnership { owner }.apply(&mut factory, &mut roles, None).unwrap();
+        AdminAction::SetTimelockDelay { delay: 7200 }.apply(&mut factory, &mut roles, None).unwrap();
+        assert!(!factory.paused);
+        assert_eq!(factory.owner, owner);
+        assert_eq!(factory.timelock_delay, 7200);
+
+        let too_long = AdminAction::SetTimelockDelay { delay: MAX_TIMELOCK_DELAY + 1 };
+        assert_error(too_long.validate(), SPError::TimelockDelayWrong);
+        let too_short = AdminAction::SetTimelockDelay { delay: MIN_TIMELOCK_DELAY - 1 };
+        assert_error(too_short.validate(), SPError::TimelockDelayWrong);
+        assert_error(too_short.apply(&mut factory, &mut roles, None), SPError::TimelockDelayWrong);
+        let mut pool = stake_pool(Reward::new_unfixed(100), 0);
+        assert_error(AdminAction::Unpause.apply(&mut factory, &mut roles, Some(&mut pool)), SPError::ActionStakePoolWrong);
+    }
+
+    #[test]
+    fn rewards_keep_their_type_and_fixed_rates_change_only_when_empty() {
+        let (mut factory, mut roles) = (factory(), roles());
+        let key = Pubkey::new_unique();
+        let mut unfixed = stake_pool(Reward::new_unfixed(100), 1000);
+        AdminAction::SetReward { stake_pool: key, reward: Reward::new_unfixed(50) }.apply(&mut factory, &mut roles, Some(&mut unfixed)).unwrap();
+        assert!(matches!(unfixed.reward_type, Reward::Unfixed { data: 50 }));
+        let to_fixed = AdminAction::SetReward { stake_pool: key, reward: Reward::new_fixed(10) };
+        assert_error(to_fixed.apply(&mut factory, &mut roles, Some(&mut unfixed)), SPError::RewardChangeWrong);
+
+        let mut fixed = stake_pool(Reward::new_fixed(10), 1000);
+        let rate = AdminAction::SetReward { stake_pool: key, reward: Reward::new_fixed(5) };
+        assert_error(rate.apply(&mut factory, &mut roles, Some(&mut fixed)), SPError::RewardChangeWrong);
+        fixed.total_staked_tokens = 0;
+        rate.apply(&mut factory, &mut roles, Some(&mut fixed)).unwrap();
+        assert!(matches!(fixed.reward_type, Reward::Fixed { data: 5 }));
+        assert_error(rate.apply(&mut factory, &mut roles, None), SPError::ActionStakePoolWrong);
+    }
+
+    #[test]
+    fn owner_fees_are_validated() {
+        let (mut factory, mut roles) = (factory(), roles());
+        let mut pool = stake_pool(Reward::new_unfixed(100), 0);
+        let fee = OwnerFee { bps: 250, max_per_claim: Some(10) };
+        AdminAction::SetOwnerFee { stake_pool: Pubkey::new_unique(), owner_fee: fee }.apply(&mut factory, &mut roles, Some(&mut pool)).unwrap();
+        assert_eq!((pool.owner_fee.bps, pool.owner_fee.max_per_claim), (250, Some(10)));
+
+        let wrong = AdminAction::SetOwnerFee {
+            stake_pool: Pubkey::new_unique(),
+            owner_fee: OwnerFee { bps: OwnerFee::MAX_BPS + 1, max_per_claim: None },
+        };
+        assert_error(wrong.validate(), SPError::OwnerFeeWrong);
+    }
+
+    #[test]
+    fn pool_settings_are_validated() {
+        let (mut factory, mut roles) = (factory(), roles());
+        let key = Pubkey::new_unique();
+        let mut unfixed = stake_pool(Reward::new_unfixed(100), 0);
+        let tip = AdminAction::SetCrankTip { stake_pool: key, crank_tip_bps: OwnerFee::MAX_BPS + 1 };
+        assert_error(tip.apply(&mut factory, &mut roles, Some(&mut unfixed)), SPError::CrankTipWrong);
+        let limits = StakeLimits { max_total_staked: None, max_per_member: Some(10), min_stake_amount: 11 };
+        assert_error(AdminAction::SetStakeLimits { stake_pool: key, limits }.validate(), SPError::StakeLimitsWrong);
+        let partial_payments = AdminAction::SetPartialPayments { stake_pool: key, partial_payments: true };
+        assert_error(partial_payments.apply(&mut factory, &mut roles, Some(&mut unfixed)), SPError::PartialPaymentsNotSupported);
+        let mut fixed = stake_pool(Reward::new_fixed(10), 0);
+        partial_payments.apply(&mut factory, &mut roles, Some(&mut fixed)).unwrap();
+        assert!(fixed.partial_payments);
+    }
+
+    #[test]
+    fn roles_and_managers_are_assigned() {
+        let (mut factory, mut roles) = (factory(), roles());
+        let (pool_creator, reward_funder, pauser) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        AdminAction::SetRoles { pool_creator, reward_funder, pauser }.apply(&mut factory, &mut roles, None).unwrap();
+        assert_eq!((roles.pool_creator, roles.reward_funder, roles.pauser), (pool_creator, reward_funder, pauser));
+
+        let key = Pubkey::new_unique();
+        let manager = Some(Pubkey::new_unique());
+        let mut pool = stake_pool(Reward::new_unfixed(100), 0);
+        AdminAction::SetPoolManager { stake_pool: key, manager }.apply(&mut factory, &mut roles, Some(&mut pool)).unwrap();
+        assert_eq!(pool.manager, manager);
+        (pool.funded_rewards, factory.funded_rewards) = (300, 500);
+        (pool.committed_rewards, factory.committed_rewards) = (100, 400);
+        AdminAction::SetPoolManager { stake_pool: key, manager: None }.apply(&mut factory, &mut roles, Some(&mut pool)).unwrap();
+        assert_eq!((pool.manager, pool.funded_rewards, factory.funded_rewards), (None, 0, 200));
+        assert_eq!(factory.committed_rewards, 500);
+    }
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke;
+use anchor_spl::token_2022::spl_token_2022;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use spl_token_2022::extension::transfer_fee::{instruction::harvest_withheld_tokens_to_mint, TransferFeeAmount};
+use spl_token_2022::state::Account as SplTokenAccount;
+
+/// The transfer fees withheld in the token `account`, charged on the transfers it received.
+/// Only the Token-2022 accounts of the mints with a transfer fee withhold any.
+pub fn withheld_amount(account: &AccountInfo) -> Result<u64> {
+    if account.owner != &spl_token_2022::ID {
+        return Ok(0);
+    }
+    let data = account.try_borrow_data()?;
+    let account = StateWithExtensions::<SplTokenAccount>::unpack(&data)?;
+    Ok(account.get_extension::<TransferFeeAmount>().map_or(0, |fee| u64::from(fee.withheld_amount)))
+}
+
+/// Moves the transfer fees withheld in the `vaults` to their `mint`, as a token account
+/// with the fees withheld can't be closed. Permissionless, so it needs no signature.
+pub fn harvest_withheld_fees<'info>(
+    token_program: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    vaults: &[AccountInfo<'info>],
+) -> Result<()> {
+    let mut withholding = Vec::with_capacity(vaults.len());
+    for vault in vaults {
+        if withheld_amount(vault)? > 0 {
+            withholding.push(vault.clone());
+        }
+    }
+    if withholding.is_empty() {
+        return Ok(());
+    }
+
+    let sources: Vec<&Pubkey> = withholding.iter().map(|vault| vault.key).collect();
+    let instruction = harvest_withheld_tokens_to_mint(token_program.key, mint.key, &sources)?;
+    withholding.insert(0, mint.clone());
+    withholding.push(token_program.clone());
+    invoke(&instruction, &withholding)?;
+
+    Ok(())
+}
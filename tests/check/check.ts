@@ -5,6 +5,8 @@ import {
 } from '@solana/spl-token';
 import { expect } from "chai";
 import { Ctx, Member, MemberStake, MemberUnstakeAll, StakePool } from '../ctx/ctx';
+import { getPendingRewardsRPC } from '../rpc/get-pending-rewards';
+import { auditPoolRPC } from '../rpc/audit-pool';
 
 export namespace Check {
 
@@ -14,6 +16,16 @@ export namespace Check {
         expect(`${factory.rewardTokenMint}`).to.be.eq(`${ctx.PDAS.factory.rewardTokenMint}`);
         expect(`${factory.stakeTokenMint}`).to.be.eq(`${ctx.PDAS.factory.stakeTokenMint}`);
         expect(`${factory.vaultReward}`).to.be.eq(`${ctx.PDAS.factory.vaultReward}`);
+        expect(`${factory.committedRewards}`).to.be.eq(`0`);
+        expect(factory.version).to.be.eq(1);
+        expect(factory.poolCount.toNumber()).to.be.eq(0);
+        expect(factory.paused).to.be.false;
+        expect(factory.timelockDelay.toNumber()).to.be.eq(3600);
+        expect(factory.actionCount.toNumber()).to.be.eq(0);
+        const roles = await ctx.program.account.factoryRoles.fetch(ctx.PDAS.factory.roles);
+        expect(`${roles.poolCreator}`).to.be.eq(`${ctx.owner.publicKey}`);
+        expect(`${roles.rewardFunder}`).to.be.eq(`${ctx.owner.publicKey}`);
+        expect(`${roles.pauser}`).to.be.eq(`${ctx.owner.publicKey}`);
     }
 
     export async function newStakePool(ctx: Ctx, stakePool: StakePool) {
@@ -22,8 +34,16 @@ export namespace Check {
         expect(`${stakePoolAcc.unstakeDelay}`).to.be.eq(`${stakePool.unstakeDelay}`);
         expect(`${stakePoolAcc.rewardType[stakePool.rewardType.name].data}`)
             .to.be.eq(`${stakePool.rewardType.value[stakePool.rewardType.name].data}`);
-        expect(`${stakePoolAcc.ownerInterestPercent}`).to.be.eq(`${stakePool.ownerInterestPercent}`);
+        expect(stakePoolAcc.ownerFee.bps).to.be.eq(stakePool.ownerFee.bps);
+        expect(`${stakePoolAcc.feeRecipient}`).to.be.eq(`${stakePool.feeRecipient}`);
+        expect(`${stakePoolAcc.vaultFee}`).to.be.eq(`${stakePool.vaultFee}`);
         expect(`${stakePoolAcc.rewardPeriod}`).to.be.eq(`${stakePool.rewardPeriod}`);
+        expect(stakePoolAcc.allowlistRoot).to.be.null;
+        expect(stakePoolAcc.nft).to.be.null;
+        expect(stakePoolAcc.version).to.be.eq(1);
+        expect(stakePoolAcc.id.toNumber()).to.be.greaterThan(0);
+        expect(stakePoolAcc.manager).to.be.null;
+        expect(stakePoolAcc.fundedRewards.toNumber()).to.be.eq(0);
         expect(Buffer.from(stakePoolAcc.name).toString().trim()).to.be.eq(stakePool.name);
     }
 
@@ -39,6 +59,12 @@ export namespace Check {
         expect(`${memberAcc.beneficiary}`).to.be.eq(`${member.beneficiary.publicKey}`);
         expect(`${memberAcc.vaultFree}`).to.be.eq(`${member.vaultFree}`);
         expect(memberAcc.bump).to.be.eq(member.bump);
+        expect(memberAcc.delegate).to.be.null;
+        expect(memberAcc.rewardDestination).to.be.null;
+        expect(memberAcc.autoClaim).to.be.false;
+        expect(memberAcc.openPositions).to.be.eq(0);
+        expect(memberAcc.version).to.be.eq(1);
+        expect(memberAcc.pendingUnstakes).to.be.eq(0);
 
         const beneficiaryAccountState = await getTokenAccount(ctx.connection, member.beneficiaryStakeVault);
         const memberVaultFree = await getTokenAccount(ctx.connection, member.vaultFree);
@@ -51,6 +77,9 @@ export namespace Check {
         expect(`${memberAcc.bump}`).to.be.eq(`${member.bump}`);
         expect(`${memberAcc.beneficiary}`).to.be.eq(`${member.beneficiary.publicKey}`);
         expect(`${memberAcc.vaultFree}`).to.be.eq(`${member.vaultFree}`)
+        const positionIndexAcc = await ctx.program.account.positionIndex.fetch(member.positionIndex);
+        expect(`${positionIndexAcc.member}`).to.be.eq(`${member.key}`);
+        expect(positionIndexAcc.positions).to.be.empty;
 
         const memberVaultFree = await getTokenAccount(ctx.connection, member.vaultFree);
         expect(`${memberVaultFree.amount}`).to.be.eq(`${0}`);
@@ -98,7 +127,10 @@ export namespace Check {
     ) {
         const factory = await ctx.program.account.factory.fetch(ctx.PDAS.factory.key);
         const userRewardBefore = (await getTokenAccount(ctx.connection, ctx.PDAS.member.beneficiaryRewardVault)).amount;
-        const ownerFeeBefore = (await getTokenAccount(ctx.connection, ctx.owner.feeRewardVault)).amount;
+        const pending = await getPendingRewardsRPC(ctx, memberStake);
+        expect(Number(pending.netReward)).to.be.above(0);
+        expect(Number(pending.grossReward) + Number(pending.rewardOwed)).to.be.eq(Number(pending.netReward) + Number(pending.ownerFee));
+        const ownerFeeBefore = (await getTokenAccount(ctx.connection, memberStake.stakePool.vaultFee)).amount;
 
         await claimReward(ctx, memberStake);
 
@@ -108,10 +140,33 @@ export namespace Check {
         const factoryRewardVaultAfter = await getTokenAccount(ctx.connection, factory.vaultReward);
         expect(Number(factoryRewardVaultAfter.amount)).to.be.below(Number(ctx.owner.initialRewardTokensAmount));
 
-        const ownerFeeAfter = (await getTokenAccount(ctx.connection, ctx.owner.feeRewardVault)).amount;
+        const ownerFeeAfter = (await getTokenAccount(ctx.connection, memberStake.stakePool.vaultFee)).amount;
         expect(Number(ownerFeeAfter)).to.be.above(Number(ownerFeeBefore));
     }
 
+    export async function harvestFees(
+        ctx: Ctx,
+        stakePool: StakePool,
+        harvestFees: (ctx: Ctx, stakePool: StakePool) => Promise<void>
+    ) {
+        const vaultFeeBefore = (await getTokenAccount(ctx.connection, stakePool.vaultFee)).amount;
+        const recipientBefore = (await getTokenAccount(ctx.connection, ctx.owner.feeRewardVault)).amount;
+
+        await harvestFees(ctx, stakePool);
+
+        const vaultFeeAfter = (await getTokenAccount(ctx.connection, stakePool.vaultFee)).amount;
+        const recipientAfter = (await getTokenAccount(ctx.connection, ctx.owner.feeRewardVault)).amount;
+        expect(Number(vaultFeeAfter)).to.be.eq(0);
+        expect(Number(recipientAfter) - Number(recipientBefore)).to.be.eq(Number(vaultFeeBefore));
+    }
+
+    export async function auditPool(ctx: Ctx, memberStake: MemberStake) {
+        const report = await auditPoolRPC(ctx, memberStake.stakePool, [memberStake]);
+        const stakePoolAcc = await ctx.program.account.stakePool.fetch(memberStake.stakePool.key);
+        expect(report.positions).to.be.eq(1);
+        expect(`${report.stakedInPositions}`).to.be.eq(`${stakePoolAcc.totalStakedTokens}`);
+    }
+
     export async function startUnstakeAll(
         ctx: Ctx,
         memberUnstakeAll: MemberUnstakeAll,
@@ -1,4 +1,5 @@
 import * as anchor from "@project-serum/anchor";
+import { createHash } from "crypto";
 import { Program, BN } from "@project-serum/anchor";
 import { Staking } from "../../target/types/staking";
 import {
@@ -118,6 +119,7 @@ export async function createOwner(ctx: OwnerCtx): Promise<Owner> {
 }
 
 export interface Factory extends CtxPDA {
+    roles: PublicKey,
     vaultReward: PublicKey,
     rewardTokenMint: PublicKey,
     stakeTokenMint: PublicKey,
@@ -135,12 +137,17 @@ export async function createFactory(ctx: FactoryCtx): Promise<Factory> {
         [anchor.utils.bytes.utf8.encode("factory")],
         ctx.program.programId
     );
+    const [roles] = await PublicKey.findProgramAddress(
+        [anchor.utils.bytes.utf8.encode("factory"), anchor.utils.bytes.utf8.encode("roles")],
+        ctx.program.programId
+    );
     const stakeTokenMint = await createMint(ctx.connection, ctx.owner, ctx.owner.publicKey, ctx.owner.publicKey, 9);
     const vaultReward = await getAssociatedTokenAddress(ctx.owner.rewardTokenMint, key, true);
 
     return { 
         key, 
         bump,
+        roles,
         vaultReward, 
         rewardTokenMint: ctx.owner.rewardTokenMint, 
         stakeTokenMint, 
@@ -153,10 +160,16 @@ export interface StakePool extends CtxPDA {
     name: string,
     endedAt: BN, // secs
     rewardPeriod: BN, // secs
-    ownerInterestPercent: number, // %
+    ownerFee: OwnerFee,
+    feeRecipient: PublicKey,
+    vaultFee: PublicKey,
     unstakeDelay: BN, // secs
     rewardType: RewardType,
-    minOwnerReward: number,
+}
+
+export interface OwnerFee {
+    bps: number,
+    maxPerClaim: BN | null,
 }
 
 export interface StakePoolCtx {
@@ -165,22 +178,22 @@ export interface StakePoolCtx {
     reward: RewardType,
     factory: Factory,
     endedAt?: BN,
-    ownerInterestPercent?: number, // %
+    ownerFee?: OwnerFee,
     unstakeDelay?: BN, // secs
     rewardPeriod?: BN, // secs
-    minOwnerReward?: number,
 }
 
 export async function createStakePool(ctx: StakePoolCtx): Promise<StakePool> {
     const [stakePoolPDA, stakePoolBump] = await PublicKey.findProgramAddress(
         [ 
             ctx.factory.key.toBuffer(),
-            Buffer.from(ctx.name),
+            createHash("sha256").update(ctx.name.trim()).digest(),
         ],
         ctx.program.programId
     );
 
     let rewardPeriod = ctx.rewardPeriod || new BN(3);
+    const vaultFee = await getAssociatedTokenAddress(ctx.factory.rewardTokenMint, stakePoolPDA, true);
 
     return {
         key: stakePoolPDA,
@@ -189,10 +202,11 @@ export async function createStakePool(ctx: StakePoolCtx): Promise<StakePool> {
         factory: ctx.factory,
         rewardType: ctx.reward,
         endedAt: ctx.endedAt || new BN(Math.floor(Date.now() / 1000)).add(rewardPeriod.mul(new BN(50))),
-        ownerInterestPercent: ctx.ownerInterestPercent || 1, // %
+        ownerFee: ctx.ownerFee || { bps: 1000, maxPerClaim: null }, // 10 %
+        feeRecipient: ctx.factory.owner.publicKey,
+        vaultFee,
         unstakeDelay: ctx.unstakeDelay || new BN(5), // secs
         rewardPeriod: rewardPeriod, // secs
-        minOwnerReward: ctx.minOwnerReward || 1, // tokens
     }
 }
 
@@ -200,6 +214,7 @@ export interface Member extends CtxPDA {
     beneficiary: Signer,
     beneficiaryStakeVault: PublicKey,
     vaultFree: PublicKey, 
+    positionIndex: PublicKey,
     stakeTokenAmount: BN,
     amountToDeposit: BN,
     amountToStake: {
@@ -242,6 +257,13 @@ export async function createMember(ctx: MemberCtx): Promise<Member> {
         ctx.program.programId
     );
     const vaultFree = await getAssociatedTokenAddress(ctx.factory.stakeTokenMint, memberPDA, true);
+    const [positionIndex] = await PublicKey.findProgramAddress(
+        [
+            memberPDA.toBuffer(),
+            Buffer.from("positions"),
+        ],
+        ctx.program.programId
+    );
     const beneficiaryRewardVault = (await getOrCreateAssociatedTokenAccount(
         ctx.connection,
         beneficiary,
@@ -258,6 +280,7 @@ export async function createMember(ctx: MemberCtx): Promise<Member> {
         beneficiary,
         beneficiaryStakeVault,
         vaultFree,
+        positionIndex,
         stakeTokenAmount,
         amountToDeposit,
         amountToStake: {
@@ -0,0 +1,34 @@
+import * as anchor from "@project-serum/anchor";
+import { Ctx, MemberStake, StakePool } from "../ctx/ctx";
+
+export interface AuditReport {
+    stakePool: anchor.web3.PublicKey,
+    positions: number,
+    totalStakedTokens: anchor.BN,
+    stakedInPositions: anchor.BN,
+    shortVaults: number,
+    accruedRewards: anchor.BN,
+    vaultReward: anchor.BN,
+    availableRewards: anchor.BN,
+    committedRewards: anchor.BN,
+    vaultFee: anchor.BN,
+    auditedAt: anchor.BN,
+}
+
+export async function auditPoolRPC(ctx: Ctx, stakePool: StakePool, positions: MemberStake[]): Promise<AuditReport> {
+    const simulation = await ctx.program.methods.auditPool()
+    .accounts({
+        factory: ctx.PDAS.factory.key,
+        stakePool: stakePool.key,
+        vaultReward: ctx.PDAS.factory.vaultReward,
+        vaultFee: stakePool.vaultFee,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+    })
+    .remainingAccounts(positions.flatMap(memberStake => [
+        { pubkey: memberStake.key, isSigner: false, isWritable: false },
+        { pubkey: memberStake.vaultStaked, isSigner: false, isWritable: false },
+    ]))
+    .simulate();
+
+    return simulation.events.find(event => event.name === "AuditReport").data as AuditReport;
+}
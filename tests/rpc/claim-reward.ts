@@ -6,14 +6,15 @@ export async function claimRewardRPC(ctx: Ctx, memberStake: MemberStake) {
     await ctx.program.methods.claimReward()
     .accounts({
         factory: ctx.PDAS.factory.key,
+        rewardTokenMint: ctx.PDAS.factory.rewardTokenMint,
         stakePool: memberStake.stakePool.key,
         memberStake: memberStake.key,
         member: memberStake.member.key,
-        vaultStaked: memberStake.vaultStaked,
+        authority: memberStake.member.beneficiary.publicKey,
         vaultReward: ctx.PDAS.factory.vaultReward,
         beneficiary: memberStake.member.beneficiary.publicKey,
         beneficiaryRewardVault: memberStake.member.beneficiaryRewardVault,
-        ownerFeeRewardVault: ctx.owner.feeRewardVault,
+        vaultFee: memberStake.stakePool.vaultFee,
         tokenProgram: TOKEN_PROGRAM_ID,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
     })
@@ -10,6 +10,7 @@ export async function createMemberRPC(ctx: Ctx, member: Member) {
         stakeTokenMint: ctx.PDAS.factory.stakeTokenMint,
         beneficiary: member.beneficiary.publicKey,
         member: member.key,
+        positionIndex: member.positionIndex,
         vaultFree: member.vaultFree,
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
@@ -7,8 +7,10 @@ export async function depositRewardRPC(ctx: Ctx, rewardTokensAmount: number) {
     await ctx.program.methods.depositReward(new anchor.BN(rewardTokensAmount))
     .accounts({
         factory: ctx.PDAS.factory.key,
-        owner: ctx.owner.publicKey,
-        vaultOwner: ctx.owner.rewardTokenVault,
+        rewardTokenMint: ctx.PDAS.factory.rewardTokenMint,
+        roles: ctx.PDAS.factory.roles,
+        funder: ctx.owner.publicKey,
+        vaultFunder: ctx.owner.rewardTokenVault,
         vaultReward: ctx.PDAS.factory.vaultReward,
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
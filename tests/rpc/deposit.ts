@@ -6,6 +6,7 @@ export async function depositRPC(ctx: Ctx, member: Member) {
     await ctx.program.methods.deposit(member.amountToDeposit)
     .accounts({
         factory: ctx.PDAS.factory.key,
+        stakeTokenMint: ctx.PDAS.factory.stakeTokenMint,
         beneficiary: member.beneficiary.publicKey,
         beneficiaryTokenAccount: member.beneficiaryStakeVault,
         member: member.key,
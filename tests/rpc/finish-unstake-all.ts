@@ -7,9 +7,11 @@ export async function finishUnstakeAllRPC(ctx: Ctx, memberUnstakeAll: MemberUnst
     await ctx.program.methods.finishUnstakeAll()
     .accounts({
         factory: ctx.PDAS.factory.key,
+        stakeTokenMint: ctx.PDAS.factory.stakeTokenMint,
         stakePool: memberUnstakeAll.stakePool.key,
         beneficiary: memberUnstakeAll.memberStake.member.beneficiary.publicKey,
         member: memberUnstakeAll.member.key,
+        positionIndex: memberUnstakeAll.member.positionIndex,
         vaultFree: memberUnstakeAll.member.vaultFree,
         memberStake: memberUnstakeAll.memberStake.key,
         vaultStaked: memberUnstakeAll.memberStake.vaultStaked,
@@ -0,0 +1,28 @@
+import * as anchor from "@project-serum/anchor";
+import { Ctx, MemberStake } from "../ctx/ctx";
+
+export interface PendingRewards {
+    grossReward: anchor.BN,
+    rewardOwed: anchor.BN,
+    ownerFee: anchor.BN,
+    netReward: anchor.BN,
+    nextClaimableAt: anchor.BN,
+    rewardPayedFor: anchor.BN,
+}
+
+export async function getPendingRewardsRPC(ctx: Ctx, memberStake: MemberStake): Promise<PendingRewards> {
+    const simulation = await ctx.program.methods.getPendingRewards()
+    .accounts({
+        factory: ctx.PDAS.factory.key,
+        stakePool: memberStake.stakePool.key,
+        memberStake: memberStake.key,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+    })
+    .simulate();
+
+    const prefix = `Program return: ${ctx.program.programId} `;
+    const returnLog = simulation.raw.find(log => log.startsWith(prefix));
+    const returnData = Buffer.from(returnLog.slice(prefix.length), "base64");
+
+    return ctx.program.coder.types.decode("PendingRewards", returnData);
+}
@@ -0,0 +1,17 @@
+import { TOKEN_PROGRAM_ID } from '@solana/spl-token';
+import { Ctx, StakePool } from "../ctx/ctx";
+
+export async function harvestFeesRPC(ctx: Ctx, stakePool: StakePool) {
+    await ctx.program.methods.harvestFees()
+    .accounts({
+        factory: ctx.PDAS.factory.key,
+        rewardTokenMint: ctx.PDAS.factory.rewardTokenMint,
+        stakePool: stakePool.key,
+        authority: ctx.owner.publicKey,
+        vaultFee: stakePool.vaultFee,
+        feeRecipientVault: ctx.owner.feeRewardVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .signers([ctx.owner])
+    .rpc();
+}
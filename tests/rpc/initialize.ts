@@ -12,6 +12,7 @@ export async function initializeRPC(ctx: Ctx) {
     await ctx.program.methods.initialize(ctx.owner.publicKey)
     .accounts({
         factory: ctx.PDAS.factory.key,
+        roles: ctx.PDAS.factory.roles,
         rewardTokenMint: ctx.PDAS.factory.rewardTokenMint,
         stakeTokenMint: ctx.PDAS.factory.stakeTokenMint,
         vaultReward: ctx.PDAS.factory.vaultReward,
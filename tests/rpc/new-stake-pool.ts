@@ -1,24 +1,46 @@
 import * as anchor from "@project-serum/anchor";
 import {
+    PublicKey,
     SystemProgram,
 } from '@solana/web3.js';
+import { ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID } from '@solana/spl-token';
 import { Ctx, StakePool } from "../ctx/ctx";
 
 export async function newStakePoolRPC(ctx: Ctx, stakePool: StakePool) {
+    // The pool is registered under the next id of the factory's registry.
+    const factory = await ctx.program.account.factory.fetch(ctx.PDAS.factory.key);
+    const [poolEntry] = await PublicKey.findProgramAddress(
+        [
+            ctx.PDAS.factory.key.toBuffer(),
+            Buffer.from("pool"),
+            factory.poolCount.addn(1).toArrayLike(Buffer, "le", 8),
+        ],
+        ctx.program.programId
+    );
+
     await ctx.program.methods.newStakePool(
         stakePool.name,
         stakePool.rewardType.value as any,
         stakePool.endedAt,
-        stakePool.minOwnerReward,
-        stakePool.ownerInterestPercent, // %
+        stakePool.ownerFee,
+        stakePool.feeRecipient,
         stakePool.unstakeDelay, // secs
         stakePool.rewardPeriod,
+        null, // open to everyone
+        { maxTotalStaked: null, maxPerMember: null, minStakeAmount: new anchor.BN(0) },
     )
     .accounts({
         factory: ctx.PDAS.factory.key,
+        roles: ctx.PDAS.factory.roles,
         stakePool: stakePool.key,
-        owner: ctx.owner.publicKey,
+        poolEntry,
+        rewardTokenMint: ctx.PDAS.factory.rewardTokenMint,
+        vaultFee: stakePool.vaultFee,
+        creator: ctx.owner.publicKey,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         systemProgram: SystemProgram.programId,
     })
     .signers([ctx.owner])
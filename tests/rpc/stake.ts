@@ -6,13 +6,16 @@ import { TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID } from '@solana/spl-token
 import { Ctx, StakePool, Member, MemberStake } from "../ctx/ctx";
 
 export async function stakeRPC(ctx: Ctx, stakePool: StakePool, member: Member, memberStake: MemberStake) {
-    await ctx.program.methods.stake(memberStake.amountToStake)
+    await ctx.program.methods.stake(memberStake.amountToStake, [])
     .accounts({
         factory: ctx.PDAS.factory.key,
         stakeTokenMint: ctx.PDAS.factory.stakeTokenMint,
+        vaultReward: ctx.PDAS.factory.vaultReward,
         stakePool: stakePool.key,
         beneficiary: member.beneficiary.publicKey,
         member: member.key,
+        positionIndex: member.positionIndex,
+        authority: member.beneficiary.publicKey,
         vaultFree: member.vaultFree,
         memberStake: memberStake.key,
         vaultStaked: memberStake.vaultStaked,
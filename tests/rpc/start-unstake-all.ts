@@ -11,6 +11,7 @@ export async function startUnstakeAllRPC(ctx: Ctx, memberUnstakeAll: MemberUnsta
         stakePool: memberUnstakeAll.stakePool.key,
         beneficiary: memberUnstakeAll.memberStake.member.beneficiary.publicKey,
         member: memberUnstakeAll.member.key,
+        positionIndex: memberUnstakeAll.member.positionIndex,
         memberStake: memberUnstakeAll.memberStake.key,
         vaultStaked: memberUnstakeAll.memberStake.vaultStaked,
         memberPendingUnstake: memberUnstakeAll.key,
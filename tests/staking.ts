@@ -12,6 +12,7 @@ import { Check } from "./check/check";
 import { sleepTill } from "./helpers/general";
 import { finishUnstakeAllRPC } from "./rpc/finish-unstake-all";
 import { withdrawalAllRPC } from "./rpc/withdraw-all";
+import { harvestFeesRPC } from "./rpc/harvest-fees";
 import { RewardName } from "./types/reward";
 
 describe("staking", () => {
@@ -64,6 +65,8 @@ async function stakeSuite (ctx: Ctx, reward: RewardName) {
     const rewardPeriod = Number(ctx.PDAS[reward].stakePool.rewardPeriod);
     await sleepTill((stakedAt + rewardPeriod + rewardPeriod * 0.5) * 1000);
     await Check.claimReward(ctx, ctx.PDAS[reward].memberStake, claimRewardRPC);
+    await Check.harvestFees(ctx, ctx.PDAS[reward].stakePool, harvestFeesRPC);
+    await Check.auditPool(ctx, ctx.PDAS[reward].memberStake);
 };
 
 async function unstakeSuite (ctx: Ctx, reward: RewardName) {